@@ -1,5 +1,9 @@
 use libax::fs::{self, File};
 use libax::io::{self, prelude::*};
+#[cfg(feature = "multitask")]
+use libax::thread::TaskInfo;
+#[cfg(feature = "multitask")]
+use libax::time::{Duration, Instant};
 use libax::{string::String, vec::Vec};
 
 macro_rules! print_err {
@@ -29,8 +33,12 @@ const CMD_TABLE: &[(&str, CmdHandler)] = &[
     ("help", do_help),
     ("ls", do_ls),
     ("mkdir", do_mkdir),
+    #[cfg(feature = "multitask")]
+    ("ps", do_ps),
     ("pwd", do_pwd),
     ("rm", do_rm),
+    #[cfg(feature = "multitask")]
+    ("top", do_top),
     ("uname", do_uname),
 ];
 
@@ -231,6 +239,107 @@ fn do_uname(_args: &str) {
     );
 }
 
+#[cfg(feature = "multitask")]
+fn stack_str(info: &TaskInfo) -> String {
+    match info.stack_high_water_mark {
+        _ if info.stack_size == 0 => String::from("-"),
+        Some(used) => libax::format!("{}K/{}K", used.div_ceil(1024), info.stack_size / 1024),
+        None => libax::format!("{}K", info.stack_size / 1024),
+    }
+}
+
+#[cfg(feature = "multitask")]
+fn do_ps(_args: &str) {
+    println!(
+        "{:>5} {:<16} {} {:>3} {:>4} {:>12} {:>8} {:>11}",
+        "TID", "NAME", "S", "CPU", "PRIO", "TIME", "SWITCHES", "STACK"
+    );
+    for t in libax::thread::tasks() {
+        println!(
+            "{:>5} {:<16} {} {:>3} {:>4} {:>12.3?} {:>8} {:>11}",
+            t.id.as_u64(),
+            t.name,
+            t.state,
+            t.cpu_id,
+            t.priority,
+            t.runtime,
+            t.context_switches,
+            stack_str(&t),
+        );
+    }
+}
+
+#[cfg(feature = "multitask")]
+fn do_top(args: &str) {
+    let mut delay = Duration::from_secs(1);
+    let mut iterations = 1;
+    let mut args = args.split_whitespace();
+    while let Some(arg) = args.next() {
+        let value = args.next().and_then(|v| v.parse::<u64>().ok());
+        match (arg, value) {
+            ("-d", Some(secs)) if secs > 0 => delay = Duration::from_secs(secs),
+            ("-n", Some(n)) => iterations = n,
+            _ => {
+                print_err!("top", "usage: top [-d SECS] [-n ITERATIONS]");
+                return;
+            }
+        }
+    }
+
+    let mut prev: Vec<TaskInfo> = libax::thread::tasks().collect();
+    let mut prev_time = Instant::now();
+    for _ in 0..iterations {
+        libax::thread::sleep(delay);
+        let now = Instant::now();
+        let elapsed = now.duration_since(prev_time).as_nanos().max(1);
+
+        // (CPU usage in 0.1%, task info)
+        let mut tasks: Vec<(u128, TaskInfo)> = libax::thread::tasks()
+            .map(|t| {
+                let prev_runtime = prev
+                    .iter()
+                    .find(|p| p.id == t.id)
+                    .map_or(Duration::ZERO, |p| p.runtime);
+                let delta = t.runtime.saturating_sub(prev_runtime).as_nanos();
+                (delta * 1000 / elapsed, t)
+            })
+            .collect();
+        tasks.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.id.as_u64().cmp(&b.1.id.as_u64())));
+
+        let count = |state| tasks.iter().filter(|(_, t)| t.state == state).count();
+        println!(
+            "Tasks: {} total, {} running, {} ready, {} sleeping, {} exited",
+            tasks.len(),
+            count('R'),
+            count('r'),
+            count('S'),
+            count('Z'),
+        );
+        println!(
+            "{:>5} {:<16} {} {:>3} {:>4} {:>6} {:>12} {:>11}",
+            "TID", "NAME", "S", "CPU", "PRIO", "%CPU", "TIME", "STACK"
+        );
+        for (usage, t) in tasks.iter() {
+            println!(
+                "{:>5} {:<16} {} {:>3} {:>4} {:>4}.{} {:>12.3?} {:>11}",
+                t.id.as_u64(),
+                t.name,
+                t.state,
+                t.cpu_id,
+                t.priority,
+                usage / 10,
+                usage % 10,
+                t.runtime,
+                stack_str(t),
+            );
+        }
+        println!();
+
+        prev = tasks.into_iter().map(|(_, t)| t).collect();
+        prev_time = now;
+    }
+}
+
 fn do_help(_args: &str) {
     println!("Available commands:");
     for (name, _) in CMD_TABLE {
@@ -38,10 +38,33 @@ fn test_btree_map() {
     println!("test_btree_map() OK!");
 }
 
+#[cfg(feature = "leak-check")]
+fn test_leak_detection() {
+    use libax::alloc_trace::{dump_leaks, start_leak_check};
+
+    start_leak_check();
+    let leaked = alloc::boxed::Box::leak(alloc::vec![0u8; 100].into_boxed_slice());
+    assert_eq!(dump_leaks(), 1);
+    // Give it back, to not disturb the later checks.
+    drop(unsafe { alloc::boxed::Box::from_raw(leaked) });
+    assert_eq!(dump_leaks(), 0);
+    println!("test_leak_detection() OK!");
+}
+
 #[no_mangle]
 fn main() {
     println!("Running memory tests...");
+    #[cfg(feature = "leak-check")]
+    libax::alloc_trace::start_leak_check();
+
     test_vec();
     test_btree_map();
+
+    #[cfg(feature = "leak-check")]
+    {
+        assert_eq!(libax::alloc_trace::dump_leaks(), 0, "memory leaked");
+        libax::alloc_trace::dump_usage();
+        test_leak_detection();
+    }
     println!("Memory tests run OK!");
 }
@@ -10,6 +10,7 @@ use alloc::vec::Vec;
 use core::sync::atomic::{AtomicUsize, Ordering};
 use core::time::Duration;
 use libax::sync::WaitQueue;
+use libax::time::Instant;
 use libax::{rand, thread};
 
 const NUM_DATA: usize = 2_000_000;
@@ -46,6 +47,7 @@ fn main() {
     let timeout = WaitQueue::new().wait_timeout(Duration::from_millis(500));
     assert!(timeout);
 
+    let start = Instant::now();
     let mut tasks = Vec::with_capacity(NUM_TASKS);
     for i in 0..NUM_TASKS {
         let vec = vec.clone();
@@ -70,6 +72,7 @@ fn main() {
 
     let actual = tasks.into_iter().map(|t| t.join().unwrap()).sum();
     println!("sum = {}", actual);
+    println!("elapsed: {:?}", start.elapsed());
     assert_eq!(expect, actual);
 
     println!("Parallel summation tests run OK!");
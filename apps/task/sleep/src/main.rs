@@ -14,6 +14,7 @@ static FINISHED_TASKS: AtomicUsize = AtomicUsize::new(0);
 
 #[no_mangle]
 fn main() {
+    let start = Instant::now();
     println!("Hello, main task!");
     let now = Instant::now();
     thread::sleep(Duration::from_secs(1));
@@ -46,5 +47,10 @@ fn main() {
     while FINISHED_TASKS.load(Ordering::Relaxed) < NUM_TASKS {
         thread::sleep(Duration::from_millis(10));
     }
+    println!(
+        "{} timer ticks in {:?}",
+        libax::time::timer_ticks(),
+        Instant::now().duration_since(start)
+    );
     println!("Sleep tests run OK!");
 }
@@ -0,0 +1,88 @@
+//! Compares the byte allocators under a few allocation patterns.
+//!
+//! Run with `cargo bench -p allocator`.
+
+use std::alloc::{alloc, dealloc, Layout};
+
+use allocator::{
+    BuddyByteAllocator, ByteAllocator, FirstFitByteAllocator, SlabByteAllocator, TlsfByteAllocator,
+};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+const POOL_SIZE: usize = 1 << 24; // 16 MiB
+const POOL_ALIGN: usize = 4096;
+
+/// Allocates then frees `n` blocks of the same size.
+fn alloc_free_fixed(a: &mut impl ByteAllocator, n: usize, size: usize) {
+    let mut blocks = Vec::with_capacity(n);
+    for _ in 0..n {
+        blocks.push(a.alloc(size, 8).unwrap());
+    }
+    for pos in blocks {
+        a.dealloc(pos, size, 8);
+    }
+}
+
+/// Allocates and frees blocks of random sizes and alignments in random order.
+fn alloc_free_random(a: &mut impl ByteAllocator, ops: usize) {
+    let mut rng = SmallRng::seed_from_u64(0xdead_beef);
+    let mut blocks = Vec::new();
+    for _ in 0..ops {
+        if blocks.is_empty() || rng.gen_bool(0.55) {
+            let size = if rng.gen_ratio(1, 16) {
+                rng.gen_range(4096..32768)
+            } else {
+                rng.gen_range(8..512)
+            };
+            let align = 1 << rng.gen_range(3..7);
+            if let Ok(pos) = a.alloc(size, align) {
+                blocks.push((pos, size, align));
+            }
+        } else {
+            let (pos, size, align) = blocks.swap_remove(rng.gen_range(0..blocks.len()));
+            a.dealloc(pos, size, align);
+        }
+    }
+    for (pos, size, align) in blocks {
+        a.dealloc(pos, size, align);
+    }
+}
+
+fn bench_allocator<A: ByteAllocator>(c: &mut Criterion, name: &str, new: fn() -> A) {
+    let layout = Layout::from_size_align(POOL_SIZE, POOL_ALIGN).unwrap();
+    let pool = unsafe { alloc(layout) } as usize;
+    let mut a = new();
+    a.init(pool, POOL_SIZE);
+
+    let mut group = c.benchmark_group("fixed-64B");
+    group.bench_function(BenchmarkId::from_parameter(name), |b| {
+        b.iter(|| alloc_free_fixed(black_box(&mut a), 1000, 64))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("fixed-4KiB");
+    group.bench_function(BenchmarkId::from_parameter(name), |b| {
+        b.iter(|| alloc_free_fixed(black_box(&mut a), 1000, 4096))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("random");
+    group.bench_function(BenchmarkId::from_parameter(name), |b| {
+        b.iter(|| alloc_free_random(black_box(&mut a), 10000))
+    });
+    group.finish();
+
+    drop(a);
+    unsafe { dealloc(pool as *mut u8, layout) };
+}
+
+fn byte_allocators(c: &mut Criterion) {
+    bench_allocator(c, "buddy", BuddyByteAllocator::new);
+    bench_allocator(c, "slab", SlabByteAllocator::new);
+    bench_allocator(c, "tlsf", TlsfByteAllocator::new);
+    bench_allocator(c, "first-fit", FirstFitByteAllocator::new);
+}
+
+criterion_group!(benches, byte_allocators);
+criterion_main!(benches);
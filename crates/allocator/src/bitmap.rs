@@ -73,7 +73,8 @@ impl<const PAGE_SIZE: usize> PageAllocator for BitmapPageAllocator<PAGE_SIZE> {
     fn dealloc_pages(&mut self, pos: usize, num_pages: usize) {
         // TODO: not decrease `used_pages` if deallocation failed
         self.used_pages -= num_pages;
-        self.inner.dealloc((pos - self.base) / PAGE_SIZE)
+        let start = (pos - self.base) / PAGE_SIZE;
+        self.inner.insert(start..start + num_pages)
     }
 
     fn total_pages(&self) -> usize {
@@ -0,0 +1,313 @@
+//! Buddy memory allocation in page-granularity.
+//!
+//! Free pages are grouped into blocks of `2^order` pages aligned to their
+//! size, and each order has a doubly linked free list whose nodes are stored
+//! in the free blocks themselves. Each memory region reserves a few pages at
+//! its beginning for the metadata, one byte per page, which records whether a
+//! page is the head of a free block and its order, so the buddy of a block
+//! can be checked and merged in constant time.
+
+use core::ptr::null_mut;
+
+use crate::{align_down, align_up, AllocError, AllocResult, BaseAllocator, PageAllocator};
+
+/// The maximum order of a block, i.e., a block contains at most `2^MAX_ORDER`
+/// pages.
+const MAX_ORDER: usize = 20;
+
+/// The maximum number of memory regions.
+const MAX_REGIONS: usize = 16;
+
+/// Set in the metadata of the head page of a free block, with the order in
+/// the lower bits.
+const META_FREE: u8 = 0x80;
+
+/// The node of a free list, at the start of a free block.
+struct FreeNode {
+    prev: *mut FreeNode,
+    next: *mut FreeNode,
+}
+
+/// A contiguous memory region added by `init` or `add_memory`.
+#[derive(Clone, Copy)]
+struct Region {
+    /// The first usable page frame number.
+    start_pfn: usize,
+    /// The end page frame number (exclusive).
+    end_pfn: usize,
+    /// The metadata of each usable page.
+    meta: *mut u8,
+}
+
+impl Region {
+    const EMPTY: Self = Self {
+        start_pfn: 0,
+        end_pfn: 0,
+        meta: null_mut(),
+    };
+
+    const fn contains(&self, pfn: usize) -> bool {
+        pfn >= self.start_pfn && pfn < self.end_pfn
+    }
+
+    fn meta(&self, pfn: usize) -> u8 {
+        unsafe { *self.meta.add(pfn - self.start_pfn) }
+    }
+
+    fn set_meta(&self, pfn: usize, meta: u8) {
+        unsafe { *self.meta.add(pfn - self.start_pfn) = meta }
+    }
+}
+
+/// A page-granularity memory allocator based on the buddy system.
+///
+/// Allocations and deallocations take `O(log n)` time. Other sizes than a
+/// power of two are rounded up to find a block, which is then split down so
+/// that only the needed pages are taken from it.
+///
+/// It supports up to 16 discontiguous memory regions, kept sorted by address
+/// to find the region of a page by binary search. It writes its metadata to
+/// the memory, so the memory must be accessible.
+///
+/// The `PAGE_SIZE` must be a power of two.
+pub struct BuddyPageAllocator<const PAGE_SIZE: usize> {
+    free_lists: [*mut FreeNode; MAX_ORDER + 1],
+    free_blocks: [usize; MAX_ORDER + 1],
+    regions: [Region; MAX_REGIONS],
+    num_regions: usize,
+    total_pages: usize,
+    used_pages: usize,
+}
+
+unsafe impl<const PAGE_SIZE: usize> Send for BuddyPageAllocator<PAGE_SIZE> {}
+
+impl<const PAGE_SIZE: usize> BuddyPageAllocator<PAGE_SIZE> {
+    /// The maximum order of a block, i.e., a block contains at most
+    /// `2^MAX_ORDER` pages.
+    pub const MAX_ORDER: usize = MAX_ORDER;
+
+    /// Creates a new empty `BuddyPageAllocator`.
+    pub const fn new() -> Self {
+        Self {
+            free_lists: [null_mut(); MAX_ORDER + 1],
+            free_blocks: [0; MAX_ORDER + 1],
+            regions: [Region::EMPTY; MAX_REGIONS],
+            num_regions: 0,
+            total_pages: 0,
+            used_pages: 0,
+        }
+    }
+
+    /// Returns the number of free blocks of `2^order` pages.
+    pub fn free_blocks(&self, order: usize) -> usize {
+        self.free_blocks.get(order).copied().unwrap_or(0)
+    }
+
+    /// Returns the number of pages in the largest free block, which is the
+    /// maximum number of contiguous pages that can be allocated now.
+    pub fn largest_free_pages(&self) -> usize {
+        (0..=MAX_ORDER)
+            .rev()
+            .find(|&order| self.free_blocks[order] > 0)
+            .map_or(0, |order| 1 << order)
+    }
+
+    /// Returns the fragmentation of the free memory for allocations of
+    /// `num_pages` contiguous pages, in percent.
+    ///
+    /// It is the ratio of free pages that are in blocks too small for such an
+    /// allocation. `0` means all free pages can be used, and `100` means the
+    /// allocation will fail although there may be enough free pages.
+    pub fn fragmentation(&self, num_pages: usize) -> usize {
+        let free_pages = self.available_pages();
+        if free_pages == 0 {
+            return 0;
+        }
+        let order = Self::order_of(num_pages.max(1));
+        let unusable_pages: usize = (0..order.min(MAX_ORDER + 1))
+            .map(|order| self.free_blocks[order] << order)
+            .sum();
+        unusable_pages * 100 / free_pages
+    }
+
+    /// Returns the smallest order whose block can hold `num_pages` pages.
+    const fn order_of(num_pages: usize) -> usize {
+        num_pages.next_power_of_two().trailing_zeros() as usize
+    }
+
+    fn region_of(&self, pfn: usize) -> Option<&Region> {
+        let regions = &self.regions[..self.num_regions];
+        let idx = regions.partition_point(|r| r.start_pfn <= pfn);
+        regions[..idx].last().filter(|r| r.contains(pfn))
+    }
+
+    /// Pushes the free block at `pfn` to the free list of `order`.
+    fn push_block(&mut self, region: &Region, pfn: usize, order: usize) {
+        let node = (pfn * PAGE_SIZE) as *mut FreeNode;
+        let head = self.free_lists[order];
+        unsafe {
+            node.write(FreeNode {
+                prev: null_mut(),
+                next: head,
+            });
+            if !head.is_null() {
+                (*head).prev = node;
+            }
+        }
+        self.free_lists[order] = node;
+        self.free_blocks[order] += 1;
+        region.set_meta(pfn, META_FREE | order as u8);
+    }
+
+    /// Removes the free block at `pfn` from the free list of `order`.
+    fn remove_block(&mut self, region: &Region, pfn: usize, order: usize) {
+        let node = (pfn * PAGE_SIZE) as *mut FreeNode;
+        unsafe {
+            let FreeNode { prev, next } = node.read();
+            if prev.is_null() {
+                self.free_lists[order] = next;
+            } else {
+                (*prev).next = next;
+            }
+            if !next.is_null() {
+                (*next).prev = prev;
+            }
+        }
+        self.free_blocks[order] -= 1;
+        region.set_meta(pfn, 0);
+    }
+
+    /// Frees the block at `pfn` of `order`, and merges it with its buddies.
+    fn free_block(&mut self, region: &Region, mut pfn: usize, mut order: usize) {
+        debug_assert!(
+            region.meta(pfn) & META_FREE == 0,
+            "deallocate free pages at {:#x}",
+            pfn * PAGE_SIZE
+        );
+        while order < MAX_ORDER {
+            let buddy = pfn ^ (1 << order);
+            if !region.contains(buddy) || region.meta(buddy) != META_FREE | order as u8 {
+                break;
+            }
+            self.remove_block(region, buddy, order);
+            pfn = pfn.min(buddy);
+            order += 1;
+        }
+        self.push_block(region, pfn, order);
+    }
+
+    /// Frees the pages in `[start_pfn, end_pfn)`, by splitting them into
+    /// largest aligned blocks.
+    fn free_range(&mut self, region: &Region, start_pfn: usize, end_pfn: usize) {
+        let mut pfn = start_pfn;
+        while pfn < end_pfn {
+            let mut order = (pfn.trailing_zeros() as usize).min(MAX_ORDER);
+            while pfn + (1 << order) > end_pfn {
+                order -= 1;
+            }
+            self.free_block(region, pfn, order);
+            pfn += 1 << order;
+        }
+    }
+}
+
+impl<const PAGE_SIZE: usize> BaseAllocator for BuddyPageAllocator<PAGE_SIZE> {
+    fn init(&mut self, start: usize, size: usize) {
+        *self = Self::new();
+        self.add_memory(start, size).unwrap();
+    }
+
+    fn add_memory(&mut self, start: usize, size: usize) -> AllocResult {
+        assert!(PAGE_SIZE.is_power_of_two());
+        let end_pfn = align_down(start + size, PAGE_SIZE) / PAGE_SIZE;
+        let start_pfn = align_up(start, PAGE_SIZE) / PAGE_SIZE;
+        if start_pfn >= end_pfn {
+            return Err(AllocError::InvalidParam);
+        }
+        // Regions do not overlap, so they are sorted by both ends.
+        let idx = self.regions[..self.num_regions].partition_point(|r| r.end_pfn <= start_pfn);
+        if idx < self.num_regions && self.regions[idx].meta as usize / PAGE_SIZE < end_pfn {
+            return Err(AllocError::MemoryOverlap);
+        }
+        if self.num_regions == MAX_REGIONS {
+            return Err(AllocError::NoMemory); // too many regions
+        }
+
+        // Reserve the metadata pages at the beginning of the region.
+        let num_pages = end_pfn - start_pfn;
+        let meta_pages = (num_pages + PAGE_SIZE) / (PAGE_SIZE + 1);
+        if meta_pages >= num_pages {
+            return Err(AllocError::InvalidParam);
+        }
+        let region = Region {
+            start_pfn: start_pfn + meta_pages,
+            end_pfn,
+            meta: (start_pfn * PAGE_SIZE) as *mut u8,
+        };
+        unsafe { core::ptr::write_bytes(region.meta, 0, num_pages - meta_pages) };
+        self.regions.copy_within(idx..self.num_regions, idx + 1);
+        self.regions[idx] = region;
+        self.num_regions += 1;
+        self.total_pages += num_pages - meta_pages;
+        self.free_range(&region, region.start_pfn, region.end_pfn);
+        Ok(())
+    }
+}
+
+impl<const PAGE_SIZE: usize> PageAllocator for BuddyPageAllocator<PAGE_SIZE> {
+    const PAGE_SIZE: usize = PAGE_SIZE;
+
+    fn alloc_pages(&mut self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
+        if !align_pow2.is_power_of_two() || align_pow2 < PAGE_SIZE || num_pages == 0 {
+            return Err(AllocError::InvalidParam);
+        }
+        let order = Self::order_of(num_pages).max(Self::order_of(align_pow2 / PAGE_SIZE));
+        let found = (order..=MAX_ORDER).find(|&o| !self.free_lists[o].is_null());
+        let Some(mut block_order) = found else {
+            return Err(AllocError::NoMemory);
+        };
+
+        let pfn = self.free_lists[block_order] as usize / PAGE_SIZE;
+        let region = *self.region_of(pfn).unwrap();
+        self.remove_block(&region, pfn, block_order);
+        // Split the block down: give back the upper half if the rest of the
+        // pages fit in the lower half, or else take the whole lower half and
+        // continue with the upper half.
+        let (mut split_pfn, mut rest) = (pfn, num_pages);
+        while rest < 1 << block_order {
+            block_order -= 1;
+            let half = 1 << block_order;
+            if rest > half {
+                split_pfn += half;
+                rest -= half;
+            } else {
+                self.push_block(&region, split_pfn + half, block_order);
+            }
+        }
+
+        self.used_pages += num_pages;
+        Ok(pfn * PAGE_SIZE)
+    }
+
+    fn dealloc_pages(&mut self, pos: usize, num_pages: usize) {
+        let pfn = pos / PAGE_SIZE;
+        let Some(&region) = self.region_of(pfn) else {
+            debug_assert!(false, "deallocate pages out of range at {:#x}", pos);
+            return;
+        };
+        self.used_pages -= num_pages;
+        self.free_range(&region, pfn, pfn + num_pages);
+    }
+
+    fn total_pages(&self) -> usize {
+        self.total_pages
+    }
+
+    fn used_pages(&self) -> usize {
+        self.used_pages
+    }
+
+    fn available_pages(&self) -> usize {
+        self.total_pages - self.used_pages
+    }
+}
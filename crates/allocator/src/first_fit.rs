@@ -0,0 +1,241 @@
+//! First-fit memory allocation.
+//!
+//! The free blocks are kept in a singly linked list sorted by address, whose
+//! nodes are stored in the free blocks themselves. An allocation takes the
+//! first block that fits, and adjacent free blocks are merged on
+//! deallocation. It has no metadata other than the list head, so it suits
+//! small memory, but the allocation time is linear in the number of free
+//! blocks.
+
+use core::mem::size_of;
+use core::ptr::null_mut;
+
+use crate::{align_down, align_up, AllocError, AllocResult, BaseAllocator, ByteAllocator};
+
+/// The header of a free block, at the start of the block.
+struct FreeBlock {
+    size: usize,
+    next: *mut FreeBlock,
+}
+
+/// The allocation unit. Every block is aligned to it, and large enough to
+/// hold a [`FreeBlock`].
+const UNIT: usize = size_of::<FreeBlock>();
+
+/// A byte-granularity memory allocator that uses the first-fit strategy on a
+/// linked list of free blocks.
+pub struct FirstFitByteAllocator {
+    head: *mut FreeBlock,
+    total_bytes: usize,
+    used_bytes: usize,
+}
+
+unsafe impl Send for FirstFitByteAllocator {}
+
+impl FirstFitByteAllocator {
+    /// Creates a new empty `FirstFitByteAllocator`.
+    pub const fn new() -> Self {
+        Self {
+            head: null_mut(),
+            total_bytes: 0,
+            used_bytes: 0,
+        }
+    }
+
+    /// Rounds up the size of a request to the allocation unit.
+    const fn block_size(size: usize) -> usize {
+        align_up(if size == 0 { 1 } else { size }, UNIT)
+    }
+
+    /// Inserts the free block `[start, start + size)` into the list, and
+    /// merges it with the adjacent blocks.
+    ///
+    /// # Safety
+    ///
+    /// The block must be valid memory that is not used by others, and both
+    /// `start` and `size` must be aligned to [`UNIT`].
+    unsafe fn insert_block(&mut self, start: usize, size: usize) -> AllocResult {
+        let mut prev: *mut FreeBlock = null_mut();
+        let mut next = self.head;
+        while !next.is_null() && (next as usize) < start {
+            prev = next;
+            next = (*next).next;
+        }
+        if (!prev.is_null() && prev as usize + (*prev).size > start)
+            || (!next.is_null() && start + size > next as usize)
+        {
+            return Err(AllocError::MemoryOverlap);
+        }
+
+        let block = if !prev.is_null() && prev as usize + (*prev).size == start {
+            (*prev).size += size;
+            prev
+        } else {
+            let block = start as *mut FreeBlock;
+            block.write(FreeBlock { size, next });
+            if prev.is_null() {
+                self.head = block;
+            } else {
+                (*prev).next = block;
+            }
+            block
+        };
+        if !next.is_null() && block as usize + (*block).size == next as usize {
+            (*block).size += (*next).size;
+            (*block).next = (*next).next;
+        }
+        Ok(())
+    }
+
+    /// Takes `[pos, pos + size)` out of the free block `curr`, whose previous
+    /// block in the list is `prev`.
+    ///
+    /// The block is split into the part before `pos` (if any), the taken
+    /// part, and the part after it (if any).
+    ///
+    /// # Safety
+    ///
+    /// `[pos, pos + size)` must be inside `curr`, and both `pos` and `size`
+    /// must be aligned to [`UNIT`].
+    unsafe fn take_from_block(
+        &mut self,
+        prev: *mut FreeBlock,
+        curr: *mut FreeBlock,
+        pos: usize,
+        size: usize,
+    ) {
+        let start = curr as usize;
+        let end = start + (*curr).size;
+        let mut next = (*curr).next;
+        if pos + size < end {
+            let rest = (pos + size) as *mut FreeBlock;
+            rest.write(FreeBlock {
+                size: end - pos - size,
+                next,
+            });
+            next = rest;
+        }
+        if pos > start {
+            (*curr).size = pos - start;
+            (*curr).next = next;
+        } else if prev.is_null() {
+            self.head = next;
+        } else {
+            (*prev).next = next;
+        }
+    }
+}
+
+impl BaseAllocator for FirstFitByteAllocator {
+    fn init(&mut self, start: usize, size: usize) {
+        *self = Self::new();
+        self.add_memory(start, size).unwrap();
+    }
+
+    fn add_memory(&mut self, start: usize, size: usize) -> AllocResult {
+        let end = align_down(start + size, UNIT);
+        let start = align_up(start, UNIT);
+        if start >= end {
+            return Err(AllocError::InvalidParam);
+        }
+        unsafe { self.insert_block(start, end - start)? };
+        self.total_bytes += end - start;
+        Ok(())
+    }
+}
+
+impl ByteAllocator for FirstFitByteAllocator {
+    fn alloc(&mut self, size: usize, align_pow2: usize) -> AllocResult<usize> {
+        if !align_pow2.is_power_of_two() {
+            return Err(AllocError::InvalidParam);
+        }
+        let size = Self::block_size(size);
+        let align = align_pow2.max(UNIT);
+
+        let mut prev: *mut FreeBlock = null_mut();
+        let mut curr = self.head;
+        unsafe {
+            while !curr.is_null() {
+                let start = curr as usize;
+                let end = start + (*curr).size;
+                let pos = align_up(start, align);
+                if pos + size <= end {
+                    self.take_from_block(prev, curr, pos, size);
+                    self.used_bytes += size;
+                    return Ok(pos);
+                }
+                prev = curr;
+                curr = (*curr).next;
+            }
+        }
+        Err(AllocError::NoMemory)
+    }
+
+    fn dealloc(&mut self, pos: usize, size: usize, _align_pow2: usize) {
+        let size = Self::block_size(size);
+        let res = unsafe { self.insert_block(pos, size) };
+        debug_assert!(res.is_ok(), "deallocate an unallocated block at {:#x}", pos);
+        self.used_bytes -= size;
+    }
+
+    fn total_bytes(&self) -> usize {
+        self.total_bytes
+    }
+
+    fn used_bytes(&self) -> usize {
+        self.used_bytes
+    }
+
+    fn available_bytes(&self) -> usize {
+        self.total_bytes - self.used_bytes
+    }
+}
+
+impl FirstFitByteAllocator {
+    /// Returns an iterator over the free blocks, as `(start, size)` pairs
+    /// sorted by address.
+    pub fn free_blocks(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let mut curr = self.head;
+        core::iter::from_fn(move || {
+            if curr.is_null() {
+                return None;
+            }
+            let block = curr;
+            let size = unsafe {
+                curr = (*block).next;
+                (*block).size
+            };
+            Some((block as usize, size))
+        })
+    }
+
+    /// Removes a free memory region from the allocator, so that it can be
+    /// given back to where it came from.
+    ///
+    /// Both `start` and `size` must be aligned to the allocation unit, which
+    /// is two words. It fails with [`AllocError::MemoryInUse`] if any byte of
+    /// the region is allocated.
+    pub fn remove_memory(&mut self, start: usize, size: usize) -> AllocResult {
+        if size == 0 || align_down(start, UNIT) != start || align_down(size, UNIT) != size {
+            return Err(AllocError::InvalidParam);
+        }
+        let mut prev: *mut FreeBlock = null_mut();
+        let mut curr = self.head;
+        unsafe {
+            while !curr.is_null() && curr as usize + (*curr).size <= start {
+                prev = curr;
+                curr = (*curr).next;
+            }
+            // The region must be inside a single free block.
+            if curr.is_null()
+                || curr as usize > start
+                || curr as usize + (*curr).size < start + size
+            {
+                return Err(AllocError::MemoryInUse);
+            }
+            self.take_from_block(prev, curr, start, size);
+        }
+        self.total_bytes -= size;
+        Ok(())
+    }
+}
@@ -3,9 +3,10 @@
 //! There are three types of allocators:
 //!
 //! - [`ByteAllocator`]: Byte-granularity memory allocator. (e.g.,
-//!   [`BuddyByteAllocator`], [`SlabByteAllocator`])
+//!   [`BuddyByteAllocator`], [`SlabByteAllocator`], [`TlsfByteAllocator`],
+//!   [`FirstFitByteAllocator`])
 //! - [`PageAllocator`]: Page-granularity memory allocator. (e.g.,
-//!   [`BitmapPageAllocator`])
+//!   [`BitmapPageAllocator`], [`BuddyPageAllocator`])
 //! - [`IdAllocator`]: Used to allocate unique IDs.
 
 #![no_std]
@@ -13,11 +14,17 @@
 
 mod bitmap;
 mod buddy;
+mod buddy_page;
+mod first_fit;
 mod slab;
+mod tlsf;
 
 pub use bitmap::BitmapPageAllocator;
 pub use buddy::BuddyByteAllocator;
+pub use buddy_page::BuddyPageAllocator;
+pub use first_fit::FirstFitByteAllocator;
 pub use slab::SlabByteAllocator;
+pub use tlsf::TlsfByteAllocator;
 
 /// The error type used for allocation.
 #[derive(Debug)]
@@ -30,6 +37,8 @@ pub enum AllocError {
     NoMemory,
     /// Deallocate an unallocated memory region.
     NotAllocated,
+    /// Remove a memory region that is (partially) allocated.
+    MemoryInUse,
 }
 
 /// A [`Result`] type with [`AllocError`] as the error type.
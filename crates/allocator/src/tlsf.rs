@@ -0,0 +1,79 @@
+//! TLSF (Two-Level Segregated Fit) memory allocation.
+//!
+//! Both allocation and deallocation complete in constant time, which makes it
+//! suitable for real-time systems.
+
+use core::alloc::Layout;
+use core::ptr::NonNull;
+use rlsf::Tlsf;
+
+use crate::{AllocError, AllocResult, BaseAllocator, ByteAllocator};
+
+/// A byte-granularity memory allocator based on the [TLSF] algorithm, with
+/// bounded response time.
+///
+/// [TLSF]: http://www.gii.upv.es/tlsf/
+pub struct TlsfByteAllocator {
+    // The maximum block size is `(GRANULARITY << 28) - GRANULARITY`, where
+    // `GRANULARITY` is 32 bytes on 64-bit platforms. Larger memory regions
+    // are split into multiple blocks.
+    inner: Tlsf<'static, u32, u32, 28, 32>,
+    total_bytes: usize,
+    used_bytes: usize,
+}
+
+impl TlsfByteAllocator {
+    /// Creates a new empty `TlsfByteAllocator`.
+    pub const fn new() -> Self {
+        Self {
+            inner: Tlsf::new(),
+            total_bytes: 0,
+            used_bytes: 0,
+        }
+    }
+}
+
+impl BaseAllocator for TlsfByteAllocator {
+    fn init(&mut self, start: usize, size: usize) {
+        *self = Self::new();
+        self.add_memory(start, size).unwrap();
+    }
+
+    fn add_memory(&mut self, start: usize, size: usize) -> AllocResult {
+        let block = NonNull::new(core::ptr::slice_from_raw_parts_mut(start as *mut u8, size))
+            .ok_or(AllocError::InvalidParam)?;
+        let size =
+            unsafe { self.inner.insert_free_block_ptr(block) }.ok_or(AllocError::InvalidParam)?;
+        self.total_bytes += size.get();
+        Ok(())
+    }
+}
+
+impl ByteAllocator for TlsfByteAllocator {
+    fn alloc(&mut self, size: usize, align_pow2: usize) -> AllocResult<usize> {
+        let layout = Layout::from_size_align(size, align_pow2).unwrap();
+        let ptr = self.inner.allocate(layout).ok_or(AllocError::NoMemory)?;
+        self.used_bytes += size;
+        Ok(ptr.as_ptr() as usize)
+    }
+
+    fn dealloc(&mut self, pos: usize, size: usize, align_pow2: usize) {
+        unsafe {
+            self.inner
+                .deallocate(NonNull::new_unchecked(pos as *mut u8), align_pow2)
+        }
+        self.used_bytes -= size;
+    }
+
+    fn total_bytes(&self) -> usize {
+        self.total_bytes
+    }
+
+    fn used_bytes(&self) -> usize {
+        self.used_bytes
+    }
+
+    fn available_bytes(&self) -> usize {
+        self.total_bytes - self.used_bytes
+    }
+}
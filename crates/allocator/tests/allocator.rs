@@ -0,0 +1,266 @@
+use std::alloc::{alloc, dealloc, Layout};
+
+use allocator::{
+    AllocError, BaseAllocator, BuddyByteAllocator, BuddyPageAllocator, ByteAllocator,
+    FirstFitByteAllocator, PageAllocator, SlabByteAllocator, TlsfByteAllocator,
+};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+const POOL_SIZE: usize = 1 << 22; // 4 MiB
+const POOL_ALIGN: usize = 4096;
+const PAGE_SIZE: usize = 4096;
+
+struct Pool(*mut u8, Layout);
+
+impl Pool {
+    fn new(size: usize) -> Self {
+        Self::with_align(size, POOL_ALIGN)
+    }
+
+    fn with_align(size: usize, align: usize) -> Self {
+        let layout = Layout::from_size_align(size, align).unwrap();
+        let ptr = unsafe { alloc(layout) };
+        assert!(!ptr.is_null());
+        Self(ptr, layout)
+    }
+
+    fn start(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        unsafe { dealloc(self.0, self.1) }
+    }
+}
+
+/// Allocates and deallocates randomly, and checks that the allocated blocks
+/// are aligned, inside the pools, and do not overlap (by filling them with
+/// patterns).
+///
+/// If `reclaim` is `true`, also checks that all the memory can be used again
+/// after everything is deallocated. (The slab allocator never gives back the
+/// memory of its slabs.)
+fn random_alloc_dealloc<A: ByteAllocator>(mut a: A, reclaim: bool) {
+    let pools = [Pool::new(POOL_SIZE), Pool::new(POOL_SIZE)];
+    a.init(pools[0].start(), POOL_SIZE);
+    a.add_memory(pools[1].start(), POOL_SIZE).unwrap();
+    let in_pools = |pos: usize, size: usize| {
+        pools
+            .iter()
+            .any(|p| pos >= p.start() && pos + size <= p.start() + POOL_SIZE)
+    };
+
+    let mut rng = SmallRng::seed_from_u64(0x1234);
+    let mut blocks: Vec<(usize, usize, usize, u8)> = Vec::new();
+    for i in 0..20000 {
+        if blocks.is_empty() || rng.gen_bool(0.6) {
+            let size = match rng.gen_range(0..10) {
+                0 => rng.gen_range(4096..65536),
+                _ => rng.gen_range(1..512),
+            };
+            let align = 1 << rng.gen_range(0..7);
+            let Ok(pos) = a.alloc(size, align) else {
+                continue;
+            };
+            assert_eq!(pos % align, 0);
+            assert!(in_pools(pos, size));
+            let pattern = i as u8;
+            unsafe { core::ptr::write_bytes(pos as *mut u8, pattern, size) };
+            blocks.push((pos, size, align, pattern));
+        } else {
+            let (pos, size, align, pattern) = blocks.swap_remove(rng.gen_range(0..blocks.len()));
+            let data = unsafe { core::slice::from_raw_parts(pos as *const u8, size) };
+            assert!(data.iter().all(|&b| b == pattern));
+            a.dealloc(pos, size, align);
+        }
+    }
+    for (pos, size, align, _) in blocks.drain(..) {
+        a.dealloc(pos, size, align);
+    }
+
+    if reclaim {
+        assert_eq!(a.used_bytes(), 0);
+        let pos = a.alloc(POOL_SIZE / 2, 4096).unwrap();
+        a.dealloc(pos, POOL_SIZE / 2, 4096);
+    }
+    drop(a); // it may access the pools on drop
+}
+
+#[test]
+fn test_buddy() {
+    random_alloc_dealloc(BuddyByteAllocator::new(), true);
+}
+
+#[test]
+fn test_slab() {
+    random_alloc_dealloc(SlabByteAllocator::new(), false);
+}
+
+#[test]
+fn test_tlsf() {
+    random_alloc_dealloc(TlsfByteAllocator::new(), true);
+}
+
+#[test]
+fn test_first_fit() {
+    random_alloc_dealloc(FirstFitByteAllocator::new(), true);
+}
+
+#[test]
+fn test_first_fit_merge() {
+    let pool = Pool::new(4096);
+    let mut a = FirstFitByteAllocator::new();
+    a.init(pool.start(), 4096);
+    assert_eq!(a.total_bytes(), 4096);
+
+    let blocks: Vec<_> = (0..4).map(|_| a.alloc(1024, 8).unwrap()).collect();
+    assert!(a.alloc(1, 1).is_err());
+    a.dealloc(blocks[0], 1024, 8);
+    a.dealloc(blocks[2], 1024, 8);
+    assert!(a.alloc(2048, 8).is_err());
+    a.dealloc(blocks[1], 1024, 8);
+    let pos = a.alloc(2048, 8).unwrap();
+    assert_eq!(pos, pool.start());
+    a.dealloc(pos, 2048, 8);
+    a.dealloc(blocks[3], 1024, 8);
+    assert_eq!(a.used_bytes(), 0);
+    assert_eq!(a.alloc(4096, 4096).unwrap(), pool.start());
+}
+
+#[test]
+fn test_buddy_pages() {
+    let pools = [Pool::new(POOL_SIZE), Pool::new(POOL_SIZE)];
+    let mut a = BuddyPageAllocator::<PAGE_SIZE>::new();
+    a.init(pools[0].start(), POOL_SIZE);
+    a.add_memory(pools[1].start(), POOL_SIZE).unwrap();
+    assert!(a
+        .add_memory(pools[1].start() + PAGE_SIZE, PAGE_SIZE)
+        .is_err());
+    let total_pages = a.total_pages();
+    let in_pools = |pos: usize, size: usize| {
+        pools
+            .iter()
+            .any(|p| pos >= p.start() && pos + size <= p.start() + POOL_SIZE)
+    };
+
+    let mut rng = SmallRng::seed_from_u64(0x5678);
+    let mut blocks: Vec<(usize, usize, u8)> = Vec::new();
+    for i in 0..10000 {
+        if blocks.is_empty() || rng.gen_bool(0.55) {
+            let num_pages = match rng.gen_range(0..10) {
+                0 => rng.gen_range(16..128),
+                _ => rng.gen_range(1..8),
+            };
+            let align = PAGE_SIZE << rng.gen_range(0..4);
+            let Ok(pos) = a.alloc_pages(num_pages, align) else {
+                continue;
+            };
+            let size = num_pages * PAGE_SIZE;
+            assert_eq!(pos % align, 0);
+            assert!(in_pools(pos, size));
+            let pattern = i as u8;
+            unsafe { core::ptr::write_bytes(pos as *mut u8, pattern, size) };
+            blocks.push((pos, num_pages, pattern));
+        } else {
+            let (pos, num_pages, pattern) = blocks.swap_remove(rng.gen_range(0..blocks.len()));
+            let data =
+                unsafe { core::slice::from_raw_parts(pos as *const u8, num_pages * PAGE_SIZE) };
+            assert!(data.iter().all(|&b| b == pattern));
+            a.dealloc_pages(pos, num_pages);
+        }
+    }
+    for (pos, num_pages, _) in blocks.drain(..) {
+        a.dealloc_pages(pos, num_pages);
+    }
+
+    assert_eq!(a.used_pages(), 0);
+    assert_eq!(a.available_pages(), total_pages);
+    let num_pages = a.largest_free_pages();
+    assert!(num_pages >= POOL_SIZE / PAGE_SIZE / 2);
+    let pos = a.alloc_pages(num_pages, PAGE_SIZE).unwrap();
+    a.dealloc_pages(pos, num_pages);
+}
+
+#[test]
+fn test_buddy_pages_merge() {
+    // 64 usable pages aligned to 64 pages, with one metadata page before.
+    let pool = Pool::with_align(128 * PAGE_SIZE, 64 * PAGE_SIZE);
+    let start = pool.start() + 64 * PAGE_SIZE;
+    let mut a = BuddyPageAllocator::<PAGE_SIZE>::new();
+    a.init(start - PAGE_SIZE, 65 * PAGE_SIZE);
+    assert_eq!(a.total_pages(), 64);
+    assert_eq!(a.free_blocks(6), 1);
+    assert_eq!(a.fragmentation(64), 0);
+
+    let pos = a.alloc_pages(1, PAGE_SIZE).unwrap();
+    assert_eq!(pos, start);
+    assert!((0..6).all(|order| a.free_blocks(order) == 1));
+    assert_eq!(a.largest_free_pages(), 32);
+    assert_eq!(a.fragmentation(64), 100);
+    assert!(a.alloc_pages(64, PAGE_SIZE).is_err());
+    a.dealloc_pages(pos, 1);
+    assert_eq!(a.free_blocks(6), 1);
+
+    // The unused tail page of a 3-page allocation is given back.
+    let pos = a.alloc_pages(3, PAGE_SIZE).unwrap();
+    assert_eq!(pos, start);
+    assert_eq!(a.used_pages(), 3);
+    assert_eq!(a.free_blocks(0), 1);
+    assert_eq!(a.alloc_pages(1, PAGE_SIZE).unwrap(), start + 3 * PAGE_SIZE);
+    a.dealloc_pages(start + 3 * PAGE_SIZE, 1);
+    a.dealloc_pages(pos, 3);
+    assert_eq!(a.used_pages(), 0);
+    assert_eq!(a.free_blocks(6), 1);
+
+    // A 5-page allocation takes a 4-page and a 1-page block.
+    let pos = a.alloc_pages(5, PAGE_SIZE).unwrap();
+    assert_eq!(pos, start);
+    assert!([0, 1, 3, 4, 5]
+        .iter()
+        .all(|&order| a.free_blocks(order) == 1));
+    assert_eq!(a.free_blocks(2), 0);
+    a.dealloc_pages(pos, 5);
+    assert_eq!(a.free_blocks(6), 1);
+
+    let pos = a.alloc_pages(1, 16 * PAGE_SIZE).unwrap();
+    assert_eq!(pos % (16 * PAGE_SIZE), 0);
+    a.dealloc_pages(pos, 1);
+    assert_eq!(a.alloc_pages(64, 64 * PAGE_SIZE).unwrap(), start);
+}
+
+#[test]
+fn test_first_fit_remove() {
+    let pool = Pool::new(4 * PAGE_SIZE);
+    let mut a = FirstFitByteAllocator::new();
+    a.init(pool.start(), 2 * PAGE_SIZE);
+    a.add_memory(pool.start() + 2 * PAGE_SIZE, 2 * PAGE_SIZE)
+        .unwrap();
+
+    let pos = a.alloc(16, 8).unwrap();
+    assert_eq!(pos, pool.start());
+    assert!(matches!(
+        a.remove_memory(pool.start(), PAGE_SIZE),
+        Err(AllocError::MemoryInUse)
+    ));
+    // Remove a page from the middle of a merged free block.
+    a.remove_memory(pool.start() + 2 * PAGE_SIZE, PAGE_SIZE)
+        .unwrap();
+    assert_eq!(a.total_bytes(), 3 * PAGE_SIZE);
+    let free_blocks: Vec<_> = a.free_blocks().collect();
+    assert_eq!(
+        free_blocks,
+        [
+            (pool.start() + 16, 2 * PAGE_SIZE - 16),
+            (pool.start() + 3 * PAGE_SIZE, PAGE_SIZE)
+        ]
+    );
+    assert!(a.alloc(2 * PAGE_SIZE, 8).is_err());
+    assert_eq!(
+        a.alloc(PAGE_SIZE, PAGE_SIZE).unwrap(),
+        pool.start() + PAGE_SIZE
+    );
+    assert_eq!(a.alloc(PAGE_SIZE, 8).unwrap(), pool.start() + 3 * PAGE_SIZE);
+    assert!(a.alloc(PAGE_SIZE, 8).is_err());
+}
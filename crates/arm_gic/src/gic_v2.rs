@@ -4,7 +4,7 @@
 
 use core::ptr::NonNull;
 
-use crate::{TriggerMode, GIC_MAX_IRQ, SPI_RANGE};
+use crate::{TriggerMode, GIC_MAX_IRQ, SGI_RANGE, SPI_RANGE};
 use tock_registers::interfaces::{Readable, Writeable};
 use tock_registers::register_structs;
 use tock_registers::registers::{ReadOnly, ReadWrite, WriteOnly};
@@ -177,6 +177,15 @@ impl GicDistributor {
         }
     }
 
+    /// Sends an SGI to the given CPU interface.
+    pub fn send_sgi(&mut self, sgi_id: usize, cpu_if: usize) {
+        if !SGI_RANGE.contains(&sgi_id) || cpu_if >= 8 {
+            return;
+        }
+        // TargetListFilter = 0b00: forward to the CPU interfaces in the list
+        self.regs().SGIR.set(((1 << cpu_if) << 16 | sgi_id) as u32);
+    }
+
     /// Initializes the GIC distributor.
     ///
     /// It disables all interrupts, sets the target of all SPIs to CPU 0,
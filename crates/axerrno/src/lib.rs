@@ -38,6 +38,8 @@ pub enum AxError {
     ConnectionRefused,
     /// A non-empty directory was specified where an empty directory was expected.
     DirectoryNotEmpty,
+    /// The operation was interrupted, e.g., the waiting task was killed.
+    Interrupted,
     /// Data not valid for the operation were encountered.
     ///
     /// Unlike [`InvalidInput`], this typically means that the operation
@@ -69,6 +71,8 @@ pub enum AxError {
     ResourceBusy,
     /// The underlying storage (typically, a filesystem) is full.
     StorageFull,
+    /// The I/O operation's timeout expired, causing it to be canceled.
+    TimedOut,
     /// An error returned when an operation could not be completed because an
     /// "end of file" was reached prematurely.
     UnexpectedEof,
@@ -202,6 +206,7 @@ impl From<AxError> for LinuxError {
             BadAddress | BadState => LinuxError::EFAULT,
             ConnectionRefused => LinuxError::ECONNREFUSED,
             DirectoryNotEmpty => LinuxError::ENOTEMPTY,
+            Interrupted => LinuxError::EINTR,
             InvalidInput | InvalidData => LinuxError::EINVAL,
             Io => LinuxError::EIO,
             IsADirectory => LinuxError::EISDIR,
@@ -212,6 +217,7 @@ impl From<AxError> for LinuxError {
             PermissionDenied => LinuxError::EACCES,
             ResourceBusy => LinuxError::EBUSY,
             StorageFull => LinuxError::ENOSPC,
+            TimedOut => LinuxError::ETIMEDOUT,
             Unsupported => LinuxError::ENOSYS,
             UnexpectedEof | WriteZero => LinuxError::EIO,
             WouldBlock => LinuxError::EAGAIN,
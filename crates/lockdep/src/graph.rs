@@ -0,0 +1,307 @@
+//! The lock dependency graph, stored in fixed-size tables.
+
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{ClassName, Site, Trace};
+
+/// The maximum number of lock classes in the graph.
+const MAX_CLASSES: usize = 512;
+/// The maximum number of dependencies in the graph.
+const MAX_DEPS: usize = 2048;
+/// The maximum number of dependencies of a chain in the report.
+pub const MAX_CHAIN_LEN: usize = 8;
+
+/// The number of buckets of the class hash table.
+const NR_BUCKETS: usize = MAX_CLASSES * 2;
+
+const WORDS: usize = MAX_CLASSES / 64;
+
+type Bitmap = [u64; WORDS];
+
+/// Lock `to` was acquired at `to_site` while holding lock `from`, which was
+/// acquired at `from_site`. `trace` is the call stack when `to` was acquired.
+#[derive(Clone, Copy)]
+struct Dep {
+    from: u16,
+    to: u16,
+    from_site: Option<Site>,
+    to_site: Option<Site>,
+    trace: Trace,
+}
+
+/// A dependency in a [`Chain`], with the names of the lock classes.
+pub struct Link {
+    pub from: ClassName,
+    pub to: ClassName,
+    pub from_site: Option<Site>,
+    pub to_site: Option<Site>,
+    pub trace: Trace,
+}
+
+/// A path in the dependency graph, in the order of acquisition.
+///
+/// Only the class IDs are saved to keep it small, as it is on the stack of
+/// the locking path. Use [`Graph::link`] to get the details.
+pub struct Chain {
+    pub nodes: [u16; MAX_CHAIN_LEN + 1],
+    /// The number of dependencies, the first `len + 1` nodes are valid.
+    pub len: usize,
+    /// Whether the path is longer than [`MAX_CHAIN_LEN`].
+    pub truncated: bool,
+}
+
+/// The error of [`Graph::add_dep`].
+pub enum DepError {
+    /// The new dependency closes a cycle, the chain is the existing path in
+    /// the reverse direction.
+    Cycle(Chain),
+    /// Too many dependencies.
+    Full,
+}
+
+pub struct Graph {
+    classes: [ClassName; MAX_CLASSES],
+    nr_classes: usize,
+    /// Hash table from the class keys and names to the class IDs plus one,
+    /// with linear probing. Zero means an empty bucket.
+    buckets: [u16; NR_BUCKETS],
+    /// `after[a]` has bit `b` set if class `b` depends on class `a`.
+    after: [Bitmap; MAX_CLASSES],
+    deps: [Dep; MAX_DEPS],
+    nr_deps: usize,
+    // scratch space of the path search.
+    visited: Bitmap,
+    prev: [u16; MAX_CLASSES],
+    stack: [u16; MAX_CLASSES],
+}
+
+impl Graph {
+    const fn new() -> Self {
+        const EMPTY: Dep = Dep {
+            from: 0,
+            to: 0,
+            from_site: None,
+            to_site: None,
+            trace: [0; crate::TRACE_DEPTH],
+        };
+        Self {
+            classes: [ClassName {
+                name: "",
+                key: None,
+            }; MAX_CLASSES],
+            nr_classes: 0,
+            buckets: [0; NR_BUCKETS],
+            after: [[0; WORDS]; MAX_CLASSES],
+            deps: [EMPTY; MAX_DEPS],
+            nr_deps: 0,
+            visited: [0; WORDS],
+            prev: [0; MAX_CLASSES],
+            stack: [0; MAX_CLASSES],
+        }
+    }
+
+    /// Returns the ID of the class with the given key and name, and registers
+    /// the class if it is new. Returns `None` if the table is full.
+    pub fn class(&mut self, key: Option<Site>, name: &'static str) -> Option<u16> {
+        let this = ClassName { name, key };
+        let mut i = this.hash() % NR_BUCKETS;
+        loop {
+            match self.buckets[i] {
+                0 => break,
+                id if self.classes[id as usize - 1].same(&this) => return Some(id - 1),
+                _ => i = (i + 1) % NR_BUCKETS,
+            }
+        }
+        if self.nr_classes == MAX_CLASSES {
+            return None;
+        }
+        let id = self.nr_classes as u16;
+        self.classes[id as usize] = this;
+        self.nr_classes += 1;
+        self.buckets[i] = id + 1;
+        Some(id)
+    }
+
+    /// Whether class `to` already depends on class `from` directly.
+    pub fn has_dep(&self, from: u16, to: u16) -> bool {
+        test_bit(&self.after[from as usize], to)
+    }
+
+    /// Records that class `to` is acquired while holding class `from`.
+    pub fn add_dep(
+        &mut self,
+        from: u16,
+        to: u16,
+        from_site: Site,
+        to_site: Site,
+        trace: &Trace,
+    ) -> Result<(), DepError> {
+        if self.has_dep(from, to) {
+            return Ok(());
+        }
+        if self.search(to, from) {
+            return Err(DepError::Cycle(self.chain(to, from)));
+        }
+        if self.nr_deps == MAX_DEPS {
+            return Err(DepError::Full);
+        }
+        self.after[from as usize][to as usize / 64] |= 1 << (to % 64);
+        self.deps[self.nr_deps] = Dep {
+            from,
+            to,
+            from_site: Some(from_site),
+            to_site: Some(to_site),
+            trace: *trace,
+        };
+        self.nr_deps += 1;
+        Ok(())
+    }
+
+    /// Depth-first search for a path from `src` to `dst`, the path is left in
+    /// `prev`.
+    fn search(&mut self, src: u16, dst: u16) -> bool {
+        self.visited = [0; WORDS];
+        self.visited[src as usize / 64] |= 1 << (src % 64);
+        self.stack[0] = src;
+        let mut top = 1;
+        while top > 0 {
+            top -= 1;
+            let node = self.stack[top];
+            if node == dst {
+                return true;
+            }
+            for w in 0..WORDS {
+                let mut bits = self.after[node as usize][w] & !self.visited[w];
+                self.visited[w] |= bits;
+                while bits != 0 {
+                    let next = (w * 64) as u16 + bits.trailing_zeros() as u16;
+                    bits &= bits - 1;
+                    self.prev[next as usize] = node;
+                    self.stack[top] = next;
+                    top += 1;
+                }
+            }
+        }
+        false
+    }
+
+    /// Collects the path found by [`search`](Self::search).
+    fn chain(&mut self, src: u16, dst: u16) -> Chain {
+        // walk back from `dst`, the nodes are saved in `stack` reversely.
+        let mut len = 0;
+        let mut node = dst;
+        while node != src {
+            self.stack[len] = node;
+            len += 1;
+            node = self.prev[node as usize];
+        }
+        self.stack[len] = src;
+
+        let mut chain = Chain {
+            nodes: [0; MAX_CHAIN_LEN + 1],
+            len: len.min(MAX_CHAIN_LEN),
+            truncated: len > MAX_CHAIN_LEN,
+        };
+        for (i, node) in chain.nodes.iter_mut().take(chain.len + 1).enumerate() {
+            *node = self.stack[len - i];
+        }
+        chain
+    }
+
+    /// Returns the details of the dependency between two classes.
+    pub fn link(&self, from: u16, to: u16) -> Link {
+        let dep = self.deps[..self.nr_deps]
+            .iter()
+            .find(|dep| dep.from == from && dep.to == to);
+        Link {
+            from: self.classes[from as usize],
+            to: self.classes[to as usize],
+            from_site: dep.and_then(|dep| dep.from_site),
+            to_site: dep.and_then(|dep| dep.to_site),
+            trace: dep.map_or([0; crate::TRACE_DEPTH], |dep| dep.trace),
+        }
+    }
+}
+
+impl ClassName {
+    /// Whether the two are the same class.
+    fn same(&self, other: &Self) -> bool {
+        self.key == other.key && self.name == other.name
+    }
+
+    /// The FNV-1a hash of the class key and name.
+    fn hash(&self) -> usize {
+        let (file, pos) = match self.key {
+            Some(key) => (key.file(), (key.line() as u64) << 32 | key.column() as u64),
+            None => ("", 0),
+        };
+        self.name
+            .bytes()
+            .chain(file.bytes())
+            .chain(pos.to_le_bytes())
+            .fold(0xcbf2_9ce4_8422_2325_u64, |h, b| {
+                (h ^ b as u64).wrapping_mul(0x100_0000_01b3)
+            }) as usize
+    }
+}
+
+fn test_bit(bitmap: &Bitmap, bit: u16) -> bool {
+    bitmap[bit as usize / 64] & (1 << (bit % 64)) != 0
+}
+
+/// The spin lock that protects the graph.
+///
+/// It can not be a `spinlock` lock, which is checked by ourselves. Local IRQs
+/// must be disabled while holding it.
+pub struct GraphLock {
+    locked: AtomicBool,
+    graph: UnsafeCell<Graph>,
+}
+
+unsafe impl Sync for GraphLock {}
+
+pub struct GraphGuard<'a>(&'a GraphLock);
+
+impl GraphLock {
+    pub const fn new() -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            graph: UnsafeCell::new(Graph::new()),
+        }
+    }
+
+    pub fn lock(&self) -> GraphGuard<'_> {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            while self.locked.load(Ordering::Relaxed) {
+                core::hint::spin_loop();
+            }
+        }
+        GraphGuard(self)
+    }
+}
+
+impl Deref for GraphGuard<'_> {
+    type Target = Graph;
+
+    fn deref(&self) -> &Graph {
+        unsafe { &*self.0.graph.get() }
+    }
+}
+
+impl DerefMut for GraphGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Graph {
+        unsafe { &mut *self.0.graph.get() }
+    }
+}
+
+impl Drop for GraphGuard<'_> {
+    fn drop(&mut self) {
+        self.0.locked.store(false, Ordering::Release);
+    }
+}
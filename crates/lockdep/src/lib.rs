@@ -0,0 +1,508 @@
+//! A runtime lock dependency validator, which detects potential deadlocks
+//! before they happen.
+//!
+//! Each lock embeds a [`LockClass`], and reports its acquisitions and
+//! releases by [`lock_acquire`] and [`lock_release`]. The locks are grouped
+//! into classes by the source locations where they are created (see
+//! [`LockClass::new`]) and their names, which are usually the type names of
+//! the locks, so that the order learned from some instances applies to all
+//! the others of the same type created at the same place, e.g., the locks of
+//! all the wait queues. The validator then:
+//!
+//! - records the locks held by each context in a [`HeldLocks`] stack, in the
+//!   order they are acquired. A context is a task, or the IRQ handlers of a
+//!   CPU, which is decided by the [`LockdepIf`] implementation;
+//! - records the acquisition order in a global dependency graph of the
+//!   classes: class `B` depends on class `A` if a lock of `B` is acquired
+//!   while holding a lock of `A`;
+//! - reports an acquisition that closes a cycle in the graph, i.e., two
+//!   classes are acquired in the inverse orders in different places, which
+//!   may deadlock even if it has not happened yet;
+//! - reports acquiring a lock instance that is already held, and sleeping
+//!   (see [`check_sleep`]) while holding a spin lock.
+//!
+//! Nesting two instances of the same class (e.g., the run queues of two CPUs)
+//! is not checked, as their order can not be told apart by the class.
+//!
+//! The reports are printed at the error level of the [`log`] crate, with the
+//! source locations where the involved locks were acquired, and the call
+//! stacks of the current acquisition and of the ones recorded in the graph.
+//!
+//! A class joins the graph when a lock of it is first nested with other
+//! locks, and stays there forever; the class ID is cached in the
+//! [`LockClass`] of each instance. The graph is stored in fixed-size tables,
+//! as the memory allocator itself is protected by a lock. If the tables are
+//! full, the validator prints a warning and turns itself off.
+//!
+//! The crate user must implement the [`LockdepIf`] trait using
+//! [`crate_interface::impl_interface`] to provide the held lock stack of the
+//! current context, and the call stack unwinding.
+
+#![cfg_attr(not(test), no_std)]
+
+mod graph;
+
+#[cfg(test)]
+mod tests;
+
+use core::panic::Location;
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use kernel_guard::IrqSave;
+use log::{error, warn};
+
+use self::graph::{Chain, DepError, GraphLock};
+
+type Site = &'static Location<'static>;
+
+/// The maximum number of locks that a context can hold at the same time.
+pub const MAX_LOCK_DEPTH: usize = 32;
+
+/// The maximum number of return addresses in a saved call stack.
+pub const TRACE_DEPTH: usize = 8;
+
+/// A saved call stack, the unused entries are zeros.
+type Trace = [usize; TRACE_DEPTH];
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+static NR_REPORTS: AtomicUsize = AtomicUsize::new(0);
+static GRAPH: GraphLock = GraphLock::new();
+
+/// Low-level interfaces that must be implemented by the crate user.
+#[crate_interface::def_interface]
+pub trait LockdepIf {
+    /// Returns the locks held by the current context, or `None` if it is not
+    /// available yet (e.g., during early boot).
+    ///
+    /// It is called with local IRQs disabled, the returned stack must not be
+    /// accessed by other contexts.
+    fn held_locks() -> Option<NonNull<HeldLocks>>;
+
+    /// Fills `callers` with the return addresses on the current stack, from
+    /// the innermost one.
+    ///
+    /// Returns the number of return addresses filled, which may be zero if
+    /// unwinding is not supported.
+    fn backtrace(callers: &mut [usize]) -> usize;
+
+    /// Looks up the symbol that contains the code address `addr`.
+    ///
+    /// Returns the symbol name and the offset of `addr` from the symbol
+    /// start, or `None` if it is not found.
+    fn lookup_symbol(addr: usize) -> Option<(&'static str, usize)>;
+}
+
+/// The kind of a lock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockKind {
+    /// A spin lock, the holder must not sleep.
+    Spin,
+    /// A sleeping lock, e.g., a mutex.
+    ///
+    /// Acquiring it may sleep, so the lock should call [`check_sleep`]
+    /// before, which reports it if a spin lock is held.
+    Sleep,
+}
+
+/// The lockdep state that must be embedded in each lock instance.
+///
+/// It identifies the instance, and caches the ID of its class, which is
+/// decided by the source location where the lock is created, and the lock
+/// name passed to [`lock_acquire`].
+///
+/// An all-zero `LockClass` is also valid (e.g., a lock statically initialized
+/// by C code), whose class is decided by the lock name only.
+pub struct LockClass {
+    /// The caller of [`new`](Self::new), which is the key of the class.
+    key: Option<Site>,
+    /// The class ID in the graph plus one, or zero if not looked up yet.
+    id: AtomicUsize,
+}
+
+impl LockClass {
+    /// Creates the lockdep state of a new lock.
+    ///
+    /// The class is keyed on the caller, so the constructor of the lock
+    /// should also be `#[track_caller]`, otherwise all the locks of that type
+    /// are in the same class. So are the ones of the same type created by
+    /// the same call to a `#[track_caller]` constructor, as they share the
+    /// same caller.
+    #[track_caller]
+    pub const fn new() -> Self {
+        Self {
+            key: Some(Location::caller()),
+            id: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the class ID in the graph, looks it up by the key and `name`
+    /// on the first call.
+    fn id(&self, graph: &mut graph::Graph, name: &'static str) -> Option<u16> {
+        match self.id.load(Ordering::Acquire) {
+            0 => {
+                let id = graph.class(self.key, name)?;
+                self.id.store(id as usize + 1, Ordering::Release);
+                Some(id)
+            }
+            id => Some((id - 1) as u16),
+        }
+    }
+}
+
+impl Default for LockClass {
+    #[track_caller]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy)]
+struct HeldLock {
+    class: *const LockClass,
+    kind: LockKind,
+    name: &'static str,
+    site: Site,
+}
+
+impl HeldLock {
+    fn class_name(&self) -> ClassName {
+        ClassName {
+            name: self.name,
+            // Safety: the held locks are alive, and the class is not null
+            // except for the pseudo lock of `check_sleep`.
+            key: unsafe { (*self.class).key },
+        }
+    }
+}
+
+/// The name of a lock class with its key, in the form of
+/// `<name> (created at <key>)`, or just `<name>` if there is no key.
+#[derive(Clone, Copy)]
+struct ClassName {
+    name: &'static str,
+    key: Option<Site>,
+}
+
+impl core::fmt::Display for ClassName {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self.key {
+            Some(key) => write!(f, "{} (created at {})", self.name, key),
+            None => f.write_str(self.name),
+        }
+    }
+}
+
+/// The locks held by a context, in the order they are acquired.
+pub struct HeldLocks {
+    locks: [Option<HeldLock>; MAX_LOCK_DEPTH],
+    depth: usize,
+    /// Non-zero while the validator is running in this context, the locks
+    /// acquired by itself (e.g., to print reports) are not checked.
+    recursion: usize,
+}
+
+// Safety: the stack is only accessed by its own context.
+unsafe impl Send for HeldLocks {}
+unsafe impl Sync for HeldLocks {}
+
+impl HeldLocks {
+    /// Creates an empty stack.
+    pub const fn new() -> Self {
+        Self {
+            locks: [None; MAX_LOCK_DEPTH],
+            depth: 0,
+            recursion: 0,
+        }
+    }
+
+    /// Returns the number of locks held.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &HeldLock> {
+        self.locks[..self.depth].iter().flatten()
+    }
+}
+
+impl Default for HeldLocks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+enum Report {
+    Recursive(HeldLock),
+    Inversion(HeldLock, Chain),
+    SleepInAtomic,
+}
+
+/// Enters the validator in the current context, returns the held lock stack,
+/// or `None` if the validator is off or already running in this context.
+///
+/// Local IRQs must be disabled.
+fn enter() -> Option<NonNull<HeldLocks>> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let held = crate_interface::call_interface!(LockdepIf::held_locks)?.as_ptr();
+    // Safety: the stack is only accessed by the current context, and a nested
+    // call only reads `recursion` and returns.
+    unsafe {
+        if (*held).recursion > 0 {
+            return None;
+        }
+        (*held).recursion += 1;
+        NonNull::new(held)
+    }
+}
+
+fn leave(held: NonNull<HeldLocks>) {
+    unsafe { (*held.as_ptr()).recursion -= 1 };
+}
+
+fn disable(reason: &str) {
+    if ENABLED.swap(false, Ordering::Relaxed) {
+        warn!("lockdep: {}, turning off the validator", reason);
+    }
+}
+
+/// Reports that the current context is acquiring a lock.
+///
+/// It should be called before the lock is actually acquired, so that a
+/// deadlock is reported before it happens, with `name` being the name of
+/// the lock class in the reports (usually the type name of the lock) and
+/// `site` being the caller of the lock method. A successful `trylock` never
+/// deadlocks, so it is not checked, but the locks acquired while holding it
+/// are.
+pub fn lock_acquire(
+    class: &LockClass,
+    kind: LockKind,
+    name: &'static str,
+    site: &'static Location<'static>,
+    trylock: bool,
+) {
+    let _guard = IrqSave::new();
+    let Some(held) = enter() else { return };
+    let this = HeldLock {
+        class,
+        kind,
+        name,
+        site,
+    };
+    // Safety: see `enter`.
+    let report = if trylock {
+        None
+    } else {
+        check_acquire(unsafe { held.as_ref() }, &this)
+    };
+    if let Some(report) = report {
+        print_report(unsafe { held.as_ref() }, &this, report);
+    }
+
+    let locks = unsafe { &mut *held.as_ptr() };
+    if locks.depth == MAX_LOCK_DEPTH {
+        disable("too many locks held");
+    } else {
+        locks.locks[locks.depth] = Some(this);
+        locks.depth += 1;
+    }
+    leave(held);
+}
+
+/// Reports that the current context has released a lock.
+///
+/// Locks may be released in any order. It is ignored if the lock is not in
+/// the held lock stack, e.g., it was acquired before the validator can track
+/// the current context.
+pub fn lock_release(class: &LockClass) {
+    let _guard = IrqSave::new();
+    let Some(held) = enter() else { return };
+    // Safety: see `enter`.
+    let locks = unsafe { &mut *held.as_ptr() };
+    let depth = locks.depth;
+    if let Some(i) = locks.locks[..depth]
+        .iter()
+        .rposition(|l| l.is_some_and(|l| core::ptr::eq(l.class, class)))
+    {
+        locks.locks.copy_within(i + 1..depth, i);
+        locks.locks[depth - 1] = None;
+        locks.depth -= 1;
+    }
+    leave(held);
+}
+
+/// Checks that the current context does not hold any spin lock, should be
+/// called before it may sleep.
+#[track_caller]
+pub fn check_sleep() {
+    let site = Location::caller();
+    let _guard = IrqSave::new();
+    let Some(held) = enter() else { return };
+    // Safety: see `enter`.
+    let locks = unsafe { held.as_ref() };
+    if locks.iter().any(|l| l.kind == LockKind::Spin) {
+        print_report(
+            locks,
+            &HeldLock {
+                class: core::ptr::null(),
+                kind: LockKind::Sleep,
+                name: "<sleep>",
+                site,
+            },
+            Report::SleepInAtomic,
+        );
+    }
+    leave(held);
+}
+
+/// Returns the number of problems reported so far.
+pub fn nr_reports() -> usize {
+    NR_REPORTS.load(Ordering::Relaxed)
+}
+
+fn check_acquire(held: &HeldLocks, this: &HeldLock) -> Option<Report> {
+    if let Some(prev) = held.iter().find(|l| core::ptr::eq(l.class, this.class)) {
+        return Some(Report::Recursive(*prev));
+    }
+    if held.depth == 0 {
+        return None;
+    }
+
+    let mut graph = GRAPH.lock();
+    let Some(to) = (unsafe { &*this.class }).id(&mut graph, this.name) else {
+        drop(graph);
+        disable("too many lock classes");
+        return None;
+    };
+    let mut trace = None;
+    for prev in held.iter() {
+        // Safety: the held locks are alive.
+        let Some(from) = (unsafe { &*prev.class }).id(&mut graph, prev.name) else {
+            drop(graph);
+            disable("too many lock classes");
+            return None;
+        };
+        if from == to || graph.has_dep(from, to) {
+            continue;
+        }
+        let trace = trace.get_or_insert_with(backtrace);
+        match graph.add_dep(from, to, prev.site, this.site, trace) {
+            Ok(()) => {}
+            Err(DepError::Cycle(chain)) => return Some(Report::Inversion(*prev, chain)),
+            Err(DepError::Full) => {
+                drop(graph);
+                disable("too many lock dependencies");
+                return None;
+            }
+        }
+    }
+    None
+}
+
+/// Saves the current call stack.
+fn backtrace() -> Trace {
+    let mut trace = [0; TRACE_DEPTH];
+    let buf: &mut [usize] = &mut trace;
+    crate_interface::call_interface!(LockdepIf::backtrace, buf);
+    trace
+}
+
+fn print_trace(trace: &[usize], indent: &str) {
+    for (i, &ra) in trace.iter().take_while(|&&ra| ra != 0).enumerate() {
+        error!("{}#{:<2} {}", indent, i, Symbolized(ra));
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn print_report(held: &HeldLocks, this: &HeldLock, report: Report) {
+    NR_REPORTS.fetch_add(1, Ordering::Relaxed);
+    match &report {
+        Report::Recursive(_) => error!("lockdep: possible recursive locking detected"),
+        Report::Inversion(..) => {
+            error!("lockdep: possible circular locking dependency detected")
+        }
+        Report::SleepInAtomic => error!("lockdep: sleeping while holding a spin lock"),
+    }
+    if this.class.is_null() {
+        error!("  sleeping at {}", this.site);
+    } else {
+        error!(
+            "  acquiring {} ({:?}) at {}",
+            this.class_name(),
+            this.kind,
+            this.site
+        );
+    }
+    print_trace(&backtrace(), "    ");
+    error!("  while holding:");
+    for l in held.iter() {
+        error!(
+            "    {} ({:?}) acquired at {}",
+            l.class_name(),
+            l.kind,
+            l.site
+        );
+    }
+    match report {
+        Report::Recursive(prev) => {
+            error!(
+                "  which already holds the same lock acquired at {}",
+                prev.site
+            );
+        }
+        Report::Inversion(prev, chain) => {
+            error!(
+                "  but {} was acquired before {} through:",
+                this.class_name(),
+                prev.class_name()
+            );
+            for pair in chain.nodes[..=chain.len].windows(2) {
+                let link = GRAPH.lock().link(pair[0], pair[1]);
+                error!(
+                    "    {} acquired at {}, then {} acquired at {}",
+                    link.from,
+                    OptSite(link.from_site),
+                    link.to,
+                    OptSite(link.to_site)
+                );
+                print_trace(&link.trace, "      ");
+            }
+            if chain.truncated {
+                error!("    ...");
+            }
+        }
+        Report::SleepInAtomic => {}
+    }
+}
+
+struct OptSite(Option<Site>);
+
+impl core::fmt::Display for OptSite {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self.0 {
+            Some(site) => site.fmt(f),
+            None => f.write_str("<unknown>"),
+        }
+    }
+}
+
+/// A return address with its symbol, in the form of `<address> <symbol>+<offset>`.
+struct Symbolized(usize);
+
+impl core::fmt::Display for Symbolized {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{:#018x}", self.0)?;
+        // Look up the call instruction rather than the next one, which may
+        // belong to another function if the call does not return.
+        let addr = self.0.wrapping_sub(1);
+        if let Some((name, offset)) =
+            crate_interface::call_interface!(LockdepIf::lookup_symbol, addr)
+        {
+            write!(f, " {}+{:#x}", name, offset + 1)?;
+        }
+        Ok(())
+    }
+}
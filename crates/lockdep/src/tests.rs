@@ -0,0 +1,234 @@
+use core::cell::UnsafeCell;
+use core::panic::Location;
+use core::ptr::NonNull;
+use core::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+use crate::*;
+
+static SERIAL: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    static HELD: UnsafeCell<HeldLocks> = const { UnsafeCell::new(HeldLocks::new()) };
+}
+
+struct LockdepIfImpl;
+
+#[crate_interface::impl_interface]
+impl LockdepIf for LockdepIfImpl {
+    fn held_locks() -> Option<NonNull<HeldLocks>> {
+        HELD.with(|held| NonNull::new(held.get()))
+    }
+
+    fn backtrace(_callers: &mut [usize]) -> usize {
+        0
+    }
+
+    fn lookup_symbol(_addr: usize) -> Option<(&'static str, usize)> {
+        None
+    }
+}
+
+struct Lock {
+    class: LockClass,
+    kind: LockKind,
+    name: &'static str,
+}
+
+struct Guard<'a>(&'a Lock);
+
+impl Lock {
+    /// Creates a lock of the class keyed on the caller, which is named
+    /// `name` in the reports.
+    #[track_caller]
+    fn new(kind: LockKind, name: &'static str) -> Self {
+        Self {
+            class: LockClass::new(),
+            kind,
+            name,
+        }
+    }
+
+    #[track_caller]
+    fn lock(&self) -> Guard<'_> {
+        lock_acquire(&self.class, self.kind, self.name, Location::caller(), false);
+        Guard(self)
+    }
+
+    #[track_caller]
+    fn try_lock(&self) -> Guard<'_> {
+        lock_acquire(&self.class, self.kind, self.name, Location::caller(), true);
+        Guard(self)
+    }
+}
+
+impl Drop for Guard<'_> {
+    fn drop(&mut self) {
+        lock_release(&self.0.class);
+    }
+}
+
+fn depth() -> usize {
+    HELD.with(|held| unsafe { (*held.get()).depth() })
+}
+
+/// Runs `f` and returns the number of reports it produces.
+fn reports(f: impl FnOnce()) -> usize {
+    let _serial = SERIAL.lock().unwrap();
+    let n = nr_reports();
+    f();
+    assert_eq!(depth(), 0);
+    nr_reports() - n
+}
+
+#[test]
+fn test_inversion() {
+    let a = Lock::new(LockKind::Spin, "inversion_a");
+    let b = Lock::new(LockKind::Spin, "inversion_b");
+    let c = Lock::new(LockKind::Spin, "inversion_c");
+    let n = reports(|| {
+        let ga = a.lock();
+        let gb = b.lock();
+        drop((gb, ga));
+        let _gb = b.lock();
+        let _ga = a.lock(); // B -> A
+    });
+    assert_eq!(n, 1);
+
+    let n = reports(|| {
+        let gc = c.lock();
+        let ga = a.lock();
+        drop((ga, gc));
+        let _gb = b.lock();
+        let _gc = c.lock(); // B -> C -> A -> B
+    });
+    assert_eq!(n, 1);
+}
+
+#[test]
+fn test_order_kept() {
+    let a = Lock::new(LockKind::Sleep, "order_a");
+    let b = Lock::new(LockKind::Spin, "order_b");
+    let n = reports(|| {
+        for _ in 0..3 {
+            let ga = a.lock();
+            let gb = b.lock();
+            // released in the same order as acquired.
+            drop(ga);
+            drop(gb);
+        }
+        check_sleep();
+    });
+    assert_eq!(n, 0);
+}
+
+#[test]
+fn test_recursive() {
+    let a = Lock::new(LockKind::Spin, "recursive");
+    let n = reports(|| {
+        let _g1 = a.lock();
+        let _g2 = a.lock();
+    });
+    assert_eq!(n, 1);
+}
+
+#[test]
+fn test_trylock() {
+    let a = Lock::new(LockKind::Spin, "trylock_a");
+    let b = Lock::new(LockKind::Spin, "trylock_b");
+    let n = reports(|| {
+        let ga = a.lock();
+        let gb = b.lock();
+        drop((gb, ga));
+        let _gb = b.lock();
+        let _ga = a.try_lock(); // never spins
+    });
+    assert_eq!(n, 0);
+}
+
+#[test]
+fn test_sleep() {
+    let spin = Lock::new(LockKind::Spin, "sleep_spin");
+    let sleep = Lock::new(LockKind::Sleep, "sleep_sleep");
+    let n = reports(|| {
+        let _g = sleep.lock();
+        check_sleep();
+    });
+    assert_eq!(n, 0);
+    let n = reports(|| {
+        let _g = spin.lock();
+        check_sleep();
+    });
+    assert_eq!(n, 1);
+}
+
+#[test]
+fn test_same_class() {
+    // created at the same places
+    let [a1, a2] = [0; 2].map(|_| Lock::new(LockKind::Spin, "same_class_a"));
+    let [b1, b2] = [0; 2].map(|_| Lock::new(LockKind::Spin, "same_class_b"));
+    let n = reports(|| {
+        // different instances of the same class
+        let _ga1 = a1.lock();
+        let _ga2 = a2.lock();
+    });
+    assert_eq!(n, 0);
+    let n = reports(|| {
+        let ga1 = a1.lock();
+        let gb1 = b1.lock();
+        drop((gb1, ga1));
+        // learned from the other instances
+        let _gb2 = b2.lock();
+        let _ga2 = a2.lock();
+    });
+    assert_eq!(n, 1);
+}
+
+#[test]
+fn test_same_name() {
+    // the same name, but created at different places
+    let a = Lock::new(LockKind::Spin, "same_name");
+    let b = Lock::new(LockKind::Spin, "same_name");
+    let n = reports(|| {
+        let ga = a.lock();
+        let gb = b.lock();
+        drop((gb, ga));
+        let _gb = b.lock();
+        let _ga = a.lock(); // B -> A
+    });
+    assert_eq!(n, 1);
+}
+
+#[test]
+fn test_zeroed() {
+    // statically initialized by C code, grouped by the names
+    let zeroed = |name| Lock {
+        class: unsafe { core::mem::zeroed() },
+        kind: LockKind::Spin,
+        name,
+    };
+    let [a1, a2] = ["zeroed_a"; 2].map(zeroed);
+    let b = zeroed("zeroed_b");
+    let n = reports(|| {
+        let ga1 = a1.lock();
+        let gb = b.lock();
+        drop((gb, ga1));
+        let _gb = b.lock();
+        let _ga2 = a2.lock(); // B -> A
+    });
+    assert_eq!(n, 1);
+}
+
+#[test]
+fn test_recycle() {
+    let n = reports(|| {
+        for _ in 0..10000 {
+            let a = Lock::new(LockKind::Spin, "recycle_a");
+            let b = Lock::new(LockKind::Spin, "recycle_b");
+            let _ga = a.lock();
+            let _gb = b.lock();
+        }
+    });
+    assert_eq!(n, 0);
+    assert!(ENABLED.load(Ordering::Relaxed));
+}
@@ -0,0 +1,254 @@
+//! Dynamically allocated per-CPU data.
+//!
+//! A fixed-size region is reserved in the per-CPU data area of each CPU, and
+//! [`PerCpu`] allocates its instances at the same offset in the region on all
+//! CPUs. So it can be accessed on the current CPU through the thread pointer
+//! register, just like the statically defined per-CPU data.
+
+use core::alloc::Layout;
+use core::marker::PhantomData;
+
+use spin::Mutex;
+
+/// To use `percpu::*` in macro expansion.
+#[allow(unused_imports)]
+use crate as percpu;
+use crate::{def_percpu, percpu_area_num};
+
+/// The size of the region for dynamically allocated per-CPU data, on each
+/// CPU.
+pub const DYNAMIC_AREA_SIZE: usize = 0x2000;
+
+/// The maximum alignment of dynamically allocated per-CPU data.
+const DYNAMIC_AREA_ALIGN: usize = 64;
+
+/// The allocation unit in bytes.
+const UNIT_SIZE: usize = 16;
+
+const NR_UNITS: usize = DYNAMIC_AREA_SIZE / UNIT_SIZE;
+
+#[repr(C, align(64))]
+struct DynamicArea([u8; DYNAMIC_AREA_SIZE]);
+
+#[def_percpu]
+static DYNAMIC_AREA: DynamicArea = DynamicArea([0; DYNAMIC_AREA_SIZE]);
+
+static ALLOCATOR: Mutex<UnitAllocator> = Mutex::new(UnitAllocator::new());
+
+/// A first-fit allocator of the units in the dynamic region.
+struct UnitAllocator {
+    used: [u64; NR_UNITS / 64],
+}
+
+impl UnitAllocator {
+    const fn new() -> Self {
+        Self {
+            used: [0; NR_UNITS / 64],
+        }
+    }
+
+    fn nr_units(layout: Layout) -> usize {
+        layout.size().max(1).div_ceil(UNIT_SIZE)
+    }
+
+    fn is_free(&self, start: usize, n: usize) -> bool {
+        (start..start + n).all(|i| self.used[i / 64] & (1 << (i % 64)) == 0)
+    }
+
+    fn set_used(&mut self, start: usize, n: usize, used: bool) {
+        for i in start..start + n {
+            if used {
+                self.used[i / 64] |= 1 << (i % 64);
+            } else {
+                self.used[i / 64] &= !(1 << (i % 64));
+            }
+        }
+    }
+
+    /// Returns the offset in the dynamic region.
+    fn alloc(&mut self, layout: Layout) -> Option<usize> {
+        if layout.align() > DYNAMIC_AREA_ALIGN {
+            return None;
+        }
+        let n = Self::nr_units(layout);
+        let step = layout.align().div_ceil(UNIT_SIZE);
+        let start = (0..=NR_UNITS.checked_sub(n)?)
+            .step_by(step)
+            .find(|&start| self.is_free(start, n))?;
+        self.set_used(start, n, true);
+        Some(start * UNIT_SIZE)
+    }
+
+    fn dealloc(&mut self, offset: usize, layout: Layout) {
+        self.set_used(offset / UNIT_SIZE, Self::nr_units(layout), false);
+    }
+}
+
+/// Per-CPU data allocated at runtime.
+///
+/// It has an instance of `T` on each CPU, which are dropped together with
+/// it. The safe accessors only return shared references, so the instances
+/// should be modified through interior mutability, e.g., atomics.
+///
+/// The instances are allocated from a region of [`DYNAMIC_AREA_SIZE`] bytes
+/// in each per-CPU data area, so it must be created after [`init`], and `T`
+/// must not be aligned to more than 64 bytes.
+///
+/// [`init`]: crate::init
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::sync::atomic::{AtomicUsize, Ordering};
+/// use percpu::PerCpu;
+///
+/// percpu::init(4);
+/// percpu::set_local_thread_pointer(0);
+///
+/// let rx_packets = PerCpu::<AtomicUsize>::new();
+/// rx_packets.current_ref().fetch_add(1, Ordering::Relaxed);
+/// let total: usize = rx_packets.iter().map(|c| c.load(Ordering::Relaxed)).sum();
+/// assert_eq!(total, 1);
+/// ```
+pub struct PerCpu<T> {
+    /// The offset in the dynamic region.
+    offset: usize,
+    _marker: PhantomData<T>,
+}
+
+unsafe impl<T: Send> Send for PerCpu<T> {}
+unsafe impl<T: Sync> Sync for PerCpu<T> {}
+
+impl<T: Default> PerCpu<T> {
+    /// Allocates per-CPU data, with the default value on each CPU.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the dynamic region is exhausted.
+    pub fn new() -> Self {
+        Self::from_fn(|_| T::default())
+    }
+}
+
+impl<T: Default> Default for PerCpu<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> PerCpu<T> {
+    /// Allocates per-CPU data, the instance on each CPU is initialized with
+    /// `f(cpu_id)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the dynamic region is exhausted.
+    pub fn from_fn<F: FnMut(usize) -> T>(f: F) -> Self {
+        Self::try_from_fn(f).expect("dynamic per-CPU data area exhausted")
+    }
+
+    /// Like [`from_fn`](Self::from_fn), but returns `None` if the dynamic
+    /// region is exhausted.
+    pub fn try_from_fn<F: FnMut(usize) -> T>(mut f: F) -> Option<Self> {
+        assert!(
+            percpu_area_num() > 0,
+            "per-CPU data areas are not initialized"
+        );
+        let offset = ALLOCATOR.lock().alloc(Layout::new::<T>())?;
+        let this = Self {
+            offset,
+            _marker: PhantomData,
+        };
+        for cpu_id in 0..percpu_area_num() {
+            // Safety: the instance is allocated and not initialized yet.
+            unsafe { (this.remote_ptr(cpu_id) as *mut T).write(f(cpu_id)) };
+        }
+        Some(this)
+    }
+
+    /// Returns the raw pointer of the instance on the current CPU.
+    ///
+    /// # Safety
+    ///
+    /// Caller must ensure that preemption is disabled on the current CPU, if
+    /// the pointer is used on the same CPU.
+    #[inline]
+    pub unsafe fn current_ptr(&self) -> *const T {
+        (DYNAMIC_AREA.current_ptr() as usize + self.offset) as *const T
+    }
+
+    /// Returns the raw pointer of the instance on the given CPU.
+    ///
+    /// # Safety
+    ///
+    /// Caller must ensure that `cpu_id` is less than the number of per-CPU
+    /// data areas.
+    #[inline]
+    pub unsafe fn remote_ptr(&self, cpu_id: usize) -> *const T {
+        (DYNAMIC_AREA.remote_ptr(cpu_id) as usize + self.offset) as *const T
+    }
+
+    /// Returns the mutable reference of the instance on the current CPU.
+    ///
+    /// # Safety
+    ///
+    /// Caller must ensure that preemption is disabled on the current CPU, and
+    /// the instance is not accessed by others at the same time.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn current_ref_mut_raw(&self) -> &mut T {
+        &mut *(self.current_ptr() as *mut T)
+    }
+
+    /// Returns the mutable reference of the instance on the given CPU.
+    ///
+    /// # Safety
+    ///
+    /// Caller must ensure that `cpu_id` is valid, and the instance is not
+    /// accessed by others at the same time.
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn remote_ref_mut_raw(&self, cpu_id: usize) -> &mut T {
+        &mut *(self.remote_ptr(cpu_id) as *mut T)
+    }
+}
+
+impl<T: Sync> PerCpu<T> {
+    /// Returns the reference of the instance on the current CPU.
+    ///
+    /// The current task may be migrated to another CPU after it returns, so
+    /// the instance may belong to another CPU then. Disable preemption to
+    /// avoid it if it matters.
+    #[inline]
+    pub fn current_ref(&self) -> &T {
+        // Safety: the instance is alive as long as `self`, and can be shared.
+        unsafe { &*self.current_ptr() }
+    }
+
+    /// Returns the reference of the instance on the given CPU.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cpu_id` is not less than the number of per-CPU data areas.
+    pub fn remote_ref(&self, cpu_id: usize) -> &T {
+        assert!(cpu_id < percpu_area_num(), "invalid CPU ID {}", cpu_id);
+        // Safety: the instance is alive as long as `self`, and can be shared.
+        unsafe { &*self.remote_ptr(cpu_id) }
+    }
+
+    /// Returns an iterator over the instances on all CPUs, in the order of
+    /// CPU IDs.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        (0..percpu_area_num()).map(|cpu_id| self.remote_ref(cpu_id))
+    }
+}
+
+impl<T> Drop for PerCpu<T> {
+    fn drop(&mut self) {
+        for cpu_id in 0..percpu_area_num() {
+            // Safety: the instances are initialized, and no longer used.
+            unsafe { core::ptr::drop_in_place(self.remote_ptr(cpu_id) as *mut T) };
+        }
+        ALLOCATOR.lock().dealloc(self.offset, Layout::new::<T>());
+    }
+}
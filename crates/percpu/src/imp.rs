@@ -1,3 +1,5 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 const fn align_up(val: usize) -> usize {
     const PAGE_SIZE: usize = 0x1000;
     (val + PAGE_SIZE - 1) & !(PAGE_SIZE - 1)
@@ -6,6 +8,14 @@ const fn align_up(val: usize) -> usize {
 #[cfg(not(target_os = "none"))]
 static PERCPU_AREA_BASE: spin::once::Once<usize> = spin::once::Once::new();
 
+static PERCPU_AREA_NUM: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the number of per-CPU data areas, i.e., `max_cpu_num` passed to
+/// [`init`], or `0` if it has not been initialized.
+pub fn percpu_area_num() -> usize {
+    PERCPU_AREA_NUM.load(Ordering::Acquire)
+}
+
 /// Returns the per-CPU data area size for each CPUs.
 #[doc(cfg(not(feature = "sp-naive")))]
 pub fn percpu_area_size() -> usize {
@@ -55,6 +65,7 @@ pub fn init(max_cpu_num: usize) {
             core::ptr::copy_nonoverlapping(base as *const u8, secondary_base as *mut u8, size);
         }
     }
+    PERCPU_AREA_NUM.store(max_cpu_num, Ordering::Release);
 }
 
 /// Read the architecture-specific thread pointer register on the current CPU.
@@ -113,9 +124,8 @@ pub fn set_local_thread_pointer(cpu_id: usize) {
     }
 }
 
-/// To use `percpu::__priv::NoPreemptGuard::new()` in macro expansion.
+/// To use `percpu::*` in macro expansion.
 #[allow(unused_imports)]
-#[cfg(feature = "preempt")]
 use crate as percpu;
 
 /// On x86, we use `gs:SELF_PTR` to store the address of the per-CPU data area base.
@@ -27,6 +27,10 @@
 //! println!("{}", CPU_ID.read_current()); // prints "1"
 //! ```
 //!
+//! Per-CPU data can also be allocated at runtime by `PerCpu` (with the
+//! `dynamic` feature), and the data on other CPUs can be accessed by the
+//! `remote_*` methods, e.g., to aggregate per-CPU statistics.
+//!
 //! # Cargo Features
 //!
 //! - `sp-naive`: For **single-core** use. In this case, each per-CPU data is
@@ -35,6 +39,8 @@
 //! - `preempt`: For **preemptible** system use. In this case, we need to disable
 //!    preemption when accessing per-CPU data. Otherwise, the data may be corrupted
 //!    when it's being accessing and the current thread happens to be preempted.
+//! - `dynamic`: Allocate per-CPU data at runtime by `PerCpu`. It reserves a
+//!   region of `DYNAMIC_AREA_SIZE` bytes in each per-CPU data area.
 
 #![cfg_attr(target_os = "none", no_std)]
 #![feature(doc_cfg)]
@@ -44,6 +50,12 @@ extern crate percpu_macros;
 #[cfg_attr(feature = "sp-naive", path = "naive.rs")]
 mod imp;
 
+#[cfg(feature = "dynamic")]
+mod dynamic;
+
+#[cfg(feature = "dynamic")]
+#[doc(cfg(feature = "dynamic"))]
+pub use self::dynamic::{PerCpu, DYNAMIC_AREA_SIZE};
 pub use self::imp::*;
 pub use percpu_macros::def_percpu;
 
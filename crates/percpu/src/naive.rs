@@ -1,6 +1,11 @@
 /// No effect for "sp-naive" use.
 pub fn init(_max_cpu_num: usize) {}
 
+/// Always returns `1` for "sp-naive" use.
+pub fn percpu_area_num() -> usize {
+    1
+}
+
 /// Always returns `0` for "sp-naive" use.
 pub fn get_local_thread_pointer() -> usize {
     0
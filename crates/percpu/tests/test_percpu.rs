@@ -30,6 +30,14 @@ struct Struct {
 #[def_percpu]
 static STRUCT: Struct = Struct { foo: 0, bar: 0 };
 
+/// Initializes the per-CPU data areas only once, as the tests run in parallel.
+#[cfg(target_os = "linux")]
+fn init_percpu() {
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(|| init(4));
+    set_local_thread_pointer(0);
+}
+
 #[cfg(target_os = "linux")]
 #[test]
 fn test_percpu() {
@@ -40,8 +48,7 @@ fn test_percpu() {
 
     #[cfg(not(feature = "sp-naive"))]
     let base = {
-        init(4);
-        set_local_thread_pointer(0);
+        init_percpu();
 
         let base = get_local_thread_pointer();
         println!("per-CPU area base = {:#x}", base);
@@ -99,4 +106,71 @@ fn test_percpu() {
         assert_eq!(s.foo, 0x2333);
         assert_eq!(s.bar, 100);
     });
+
+    #[cfg(not(feature = "sp-naive"))]
+    unsafe {
+        for cpu_id in 1..4 {
+            let base = percpu_area_base(cpu_id);
+            assert_eq!(base + U64.offset(), U64.remote_ptr(cpu_id) as usize);
+            assert_eq!(base + STRUCT.offset(), STRUCT.remote_ptr(cpu_id) as usize);
+            *U64.remote_ref_mut_raw(cpu_id) = cpu_id as u64;
+            STRUCT.remote_ref_mut_raw(cpu_id).foo = cpu_id;
+        }
+        assert_eq!(U64.read_current(), 0xa2ce_a2ce_a2ce_a2ce);
+
+        set_local_thread_pointer(2);
+        assert_eq!(U64.read_current(), 2);
+        STRUCT.with_current(|s| assert_eq!(s.foo, 2));
+        set_local_thread_pointer(0);
+        assert_eq!(*U64.remote_ref_raw(0), 0xa2ce_a2ce_a2ce_a2ce);
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "dynamic"))]
+#[test]
+fn test_percpu_dynamic() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    init_percpu();
+    let cpu_num = percpu_area_num();
+    println!("cpu num = {}", cpu_num);
+
+    let counters = PerCpu::from_fn(|cpu_id| AtomicUsize::new(cpu_id * 10));
+    counters.current_ref().fetch_add(1, Ordering::Relaxed);
+    assert_eq!(counters.remote_ref(0).load(Ordering::Relaxed), 1);
+    let sum: usize = counters.iter().map(|c| c.load(Ordering::Relaxed)).sum();
+    assert_eq!(
+        sum,
+        (0..cpu_num).map(|cpu_id| cpu_id * 10).sum::<usize>() + 1
+    );
+
+    #[cfg(not(feature = "sp-naive"))]
+    {
+        set_local_thread_pointer(3);
+        assert_eq!(counters.current_ref().load(Ordering::Relaxed), 30);
+        unsafe { *counters.current_ref_mut_raw().get_mut() = 33 };
+        set_local_thread_pointer(0);
+        assert_eq!(counters.remote_ref(3).load(Ordering::Relaxed), 33);
+    }
+
+    // the instances on all CPUs are dropped.
+    struct Counted(Arc<AtomicUsize>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let drops = Arc::new(AtomicUsize::new(0));
+    drop(PerCpu::from_fn(|_| Counted(drops.clone())));
+    assert_eq!(drops.load(Ordering::Relaxed), cpu_num);
+
+    // exhausts the dynamic area, and frees it.
+    let blocks: Vec<_> = std::iter::from_fn(|| PerCpu::try_from_fn(|_| [0u8; 1024])).collect();
+    assert!(!blocks.is_empty() && blocks.len() < DYNAMIC_AREA_SIZE / 1024);
+    drop(blocks);
+    assert!(PerCpu::try_from_fn(|_| [0u8; 1024]).is_some());
+    assert_eq!(counters.remote_ref(0).load(Ordering::Relaxed), 1);
 }
@@ -1,12 +1,12 @@
 use alloc::{collections::BTreeMap, sync::Arc};
+use core::marker::PhantomData;
 use core::ops::Deref;
 use core::sync::atomic::{AtomicIsize, Ordering};
 
 use crate::BaseScheduler;
 
-/// task for CFS
-pub struct CFSTask<T> {
-    inner: T,
+/// Per-task states of the [`CFScheduler`].
+pub struct CFSEntity {
     init_vruntime: AtomicIsize,
     delta: AtomicIsize,
     nice: AtomicIsize,
@@ -28,11 +28,16 @@ const NICE2WEIGHT_NEG: [isize; NICE_RANGE_NEG + 1] = [
     29154, 36291, 46273, 56483, 71755, 88761,
 ];
 
-impl<T> CFSTask<T> {
+/// Types that can be scheduled by the [`CFScheduler`].
+pub trait HasCFSEntity {
+    /// Returns the states used by the [`CFScheduler`].
+    fn cfs_entity(&self) -> &CFSEntity;
+}
+
+impl CFSEntity {
     /// new with default values
-    pub const fn new(inner: T) -> Self {
+    pub const fn new() -> Self {
         Self {
-            inner,
             init_vruntime: AtomicIsize::new(0_isize),
             delta: AtomicIsize::new(0_isize),
             nice: AtomicIsize::new(0_isize),
@@ -40,6 +45,11 @@ impl<T> CFSTask<T> {
         }
     }
 
+    /// Returns the nice value.
+    pub fn nice(&self) -> isize {
+        self.nice.load(Ordering::Acquire)
+    }
+
     fn get_weight(&self) -> isize {
         let nice = self.nice.load(Ordering::Acquire);
         if nice >= 0 {
@@ -83,6 +93,22 @@ impl<T> CFSTask<T> {
     fn task_tick(&self) {
         self.delta.fetch_add(1, Ordering::Release);
     }
+}
+
+/// task for CFS
+pub struct CFSTask<T> {
+    inner: T,
+    entity: CFSEntity,
+}
+
+impl<T> CFSTask<T> {
+    /// new with default values
+    pub const fn new(inner: T) -> Self {
+        Self {
+            inner,
+            entity: CFSEntity::new(),
+        }
+    }
 
     /// Returns a reference to the inner task struct.
     pub const fn inner(&self) -> &T {
@@ -90,6 +116,12 @@ impl<T> CFSTask<T> {
     }
 }
 
+impl<T> HasCFSEntity for CFSTask<T> {
+    fn cfs_entity(&self) -> &CFSEntity {
+        &self.entity
+    }
+}
+
 impl<T> Deref for CFSTask<T> {
     type Target = T;
     fn deref(&self) -> &Self::Target {
@@ -99,30 +131,40 @@ impl<T> Deref for CFSTask<T> {
 
 /// A simple [Completely Fair Scheduler][1] (CFS).
 ///
+/// The scheduled entity is [`CFSTask`] by default, or any type that
+/// implements [`HasCFSEntity`] (`E`).
+///
 /// [1]: https://en.wikipedia.org/wiki/Completely_Fair_Scheduler
-pub struct CFScheduler<T> {
-    ready_queue: BTreeMap<(isize, isize), Arc<CFSTask<T>>>, // (vruntime, taskid)
+pub struct CFScheduler<T, E = CFSTask<T>> {
+    ready_queue: BTreeMap<(isize, isize), Arc<E>>, // (vruntime, taskid)
     min_vruntime: Option<AtomicIsize>,
     id_pool: AtomicIsize,
+    _phantom: PhantomData<T>,
 }
 
-impl<T> CFScheduler<T> {
+impl<T, E: HasCFSEntity> CFScheduler<T, E> {
     /// Creates a new empty [`CFScheduler`].
     pub const fn new() -> Self {
         Self {
             ready_queue: BTreeMap::new(),
             min_vruntime: None,
             id_pool: AtomicIsize::new(0_isize),
+            _phantom: PhantomData,
         }
     }
     /// get the name of scheduler
     pub fn scheduler_name() -> &'static str {
         "Completely Fair"
     }
+
+    /// Returns whether there is no ready task.
+    pub fn is_empty(&self) -> bool {
+        self.ready_queue.is_empty()
+    }
 }
 
-impl<T> BaseScheduler for CFScheduler<T> {
-    type SchedItem = Arc<CFSTask<T>>;
+impl<T, E: HasCFSEntity> BaseScheduler for CFScheduler<T, E> {
+    type SchedItem = Arc<E>;
 
     fn init(&mut self) {}
 
@@ -132,8 +174,8 @@ impl<T> BaseScheduler for CFScheduler<T> {
         }
         let vruntime = self.min_vruntime.as_mut().unwrap().load(Ordering::Acquire);
         let taskid = self.id_pool.fetch_add(1, Ordering::Release);
-        task.set_vruntime(vruntime);
-        task.set_id(taskid);
+        task.cfs_entity().set_vruntime(vruntime);
+        task.cfs_entity().set_id(taskid);
         self.ready_queue.insert((vruntime, taskid), task);
         if let Some(((min_vruntime, _), _)) = self.ready_queue.first_key_value() {
             self.min_vruntime = Some(AtomicIsize::new(*min_vruntime));
@@ -145,7 +187,7 @@ impl<T> BaseScheduler for CFScheduler<T> {
     fn remove_task(&mut self, task: &Self::SchedItem) -> Option<Self::SchedItem> {
         if let Some((_, tmp)) = self
             .ready_queue
-            .remove_entry(&(task.clone().get_vruntime(), task.clone().get_id()))
+            .remove_entry(&(task.cfs_entity().get_vruntime(), task.cfs_entity().get_id()))
         {
             if let Some(((min_vruntime, _), _)) = self.ready_queue.first_key_value() {
                 self.min_vruntime = Some(AtomicIsize::new(*min_vruntime));
@@ -168,20 +210,35 @@ impl<T> BaseScheduler for CFScheduler<T> {
 
     fn put_prev_task(&mut self, prev: Self::SchedItem, _preempt: bool) {
         let taskid = self.id_pool.fetch_add(1, Ordering::Release);
-        prev.set_id(taskid);
+        prev.cfs_entity().set_id(taskid);
         self.ready_queue
-            .insert((prev.clone().get_vruntime(), taskid), prev);
+            .insert((prev.cfs_entity().get_vruntime(), taskid), prev);
+    }
+
+    fn steal_task<F>(&mut self, mut filter: F) -> Option<Self::SchedItem>
+    where
+        F: FnMut(&Self::SchedItem) -> bool,
+    {
+        let key = *self.ready_queue.iter().find(|(_, t)| filter(t))?.0;
+        let task = self.ready_queue.remove(&key);
+        if let Some(((min_vruntime, _), _)) = self.ready_queue.first_key_value() {
+            self.min_vruntime = Some(AtomicIsize::new(*min_vruntime));
+        } else {
+            self.min_vruntime = None;
+        }
+        task
     }
 
     fn task_tick(&mut self, current: &Self::SchedItem) -> bool {
-        current.task_tick();
+        current.cfs_entity().task_tick();
         self.min_vruntime.is_none()
-            || current.get_vruntime() > self.min_vruntime.as_mut().unwrap().load(Ordering::Acquire)
+            || current.cfs_entity().get_vruntime()
+                > self.min_vruntime.as_mut().unwrap().load(Ordering::Acquire)
     }
 
     fn set_priority(&mut self, task: &Self::SchedItem, prio: isize) -> bool {
         if (-20..=19).contains(&prio) {
-            task.set_priority(prio);
+            task.cfs_entity().set_priority(prio);
             true
         } else {
             false
@@ -0,0 +1,251 @@
+use alloc::sync::Arc;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+use linked_list::{Adapter, Links};
+
+use crate::{BaseScheduler, CFScheduler, FifoScheduler, PrioScheduler};
+use crate::{CFSEntity, HasCFSEntity, HasPrioEntity, PrioEntity};
+
+/// The scheduling policy of a task, which decides the scheduling class it
+/// belongs to in the [`ClassScheduler`].
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SchedPolicy {
+    /// Real-time tasks with fixed priorities. They always run before tasks of
+    /// other classes.
+    RealTime = 0,
+    /// Normal tasks that share the CPU time fairly.
+    Normal = 1,
+    /// Background tasks that run only when no other task is ready.
+    Idle = 2,
+}
+
+impl From<u8> for SchedPolicy {
+    #[inline]
+    fn from(policy: u8) -> Self {
+        match policy {
+            0 => Self::RealTime,
+            1 => Self::Normal,
+            2 => Self::Idle,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// A task wrapper for the [`ClassScheduler`].
+///
+/// It contains the scheduling policy, and the states used by the scheduler of
+/// each class.
+pub struct ClassTask<T, const MAX_TIME_SLICE: usize> {
+    inner: T,
+    policy: AtomicU8,
+    queued: AtomicBool,
+    rt: PrioEntity<MAX_TIME_SLICE>,
+    fair: CFSEntity,
+    links: Links<Self>,
+}
+
+unsafe impl<T, const S: usize> Adapter for ClassTask<T, S> {
+    type EntryType = Self;
+
+    #[inline]
+    fn to_links(t: &Self) -> &Links<Self> {
+        &t.links
+    }
+}
+
+impl<T, const S: usize> ClassTask<T, S> {
+    /// Creates a new [`ClassTask`] from the inner task struct, with the
+    /// [`SchedPolicy::Normal`] policy.
+    pub const fn new(inner: T) -> Self {
+        Self {
+            inner,
+            policy: AtomicU8::new(SchedPolicy::Normal as u8),
+            queued: AtomicBool::new(false),
+            rt: PrioEntity::new(),
+            fair: CFSEntity::new(),
+            links: Links::new(),
+        }
+    }
+
+    /// Returns the scheduling policy of the task.
+    pub fn policy(&self) -> SchedPolicy {
+        self.policy.load(Ordering::Acquire).into()
+    }
+
+    /// Returns the priority in the class of the current policy, i.e., the
+    /// fixed priority of a real-time task, or the nice value of a normal task.
+    pub fn prio(&self) -> isize {
+        match self.policy() {
+            SchedPolicy::RealTime => self.rt.prio() as isize,
+            SchedPolicy::Normal => self.fair.nice(),
+            SchedPolicy::Idle => 0,
+        }
+    }
+
+    /// Returns a reference to the inner task struct.
+    pub const fn inner(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T, const S: usize> HasPrioEntity<S> for ClassTask<T, S> {
+    fn prio_entity(&self) -> &PrioEntity<S> {
+        &self.rt
+    }
+}
+
+impl<T, const S: usize> HasCFSEntity for ClassTask<T, S> {
+    fn cfs_entity(&self) -> &CFSEntity {
+        &self.fair
+    }
+}
+
+impl<T, const S: usize> Deref for ClassTask<T, S> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+/// A hierarchical scheduler with multiple scheduling classes, like the
+/// scheduling classes in Linux.
+///
+/// Tasks are put into one of the following classes according to their
+/// [`SchedPolicy`]. Each class is managed by its own scheduler, and a class
+/// runs only when all classes above it have no ready task:
+///
+/// 1. [`SchedPolicy::RealTime`]: the [`PrioScheduler`]. The priority ranges
+///    from 0 (highest) to 99 (lowest).
+/// 2. [`SchedPolicy::Normal`]: the [`CFScheduler`]. The priority is the nice
+///    value, ranging from -20 to 19.
+/// 3. [`SchedPolicy::Idle`]: the [`FifoScheduler`].
+pub struct ClassScheduler<T, const MAX_TIME_SLICE: usize> {
+    rt: PrioScheduler<T, MAX_TIME_SLICE, ClassTask<T, MAX_TIME_SLICE>>,
+    fair: CFScheduler<T, ClassTask<T, MAX_TIME_SLICE>>,
+    idle: FifoScheduler<T, ClassTask<T, MAX_TIME_SLICE>>,
+}
+
+impl<T, const S: usize> ClassScheduler<T, S> {
+    /// Creates a new empty [`ClassScheduler`].
+    pub const fn new() -> Self {
+        Self {
+            rt: PrioScheduler::new(),
+            fair: CFScheduler::new(),
+            idle: FifoScheduler::new(),
+        }
+    }
+    /// get the name of scheduler
+    pub fn scheduler_name() -> &'static str {
+        "Multi-class"
+    }
+
+    /// Changes the scheduling policy of the task. If the task is ready, it is
+    /// moved to the scheduler of the new class.
+    pub fn set_policy(&mut self, task: &Arc<ClassTask<T, S>>, policy: SchedPolicy) {
+        if task.policy() != policy {
+            let removed = self.remove_task(task);
+            task.policy.store(policy as u8, Ordering::Release);
+            if let Some(task) = removed {
+                self.add_task(task);
+            }
+        }
+    }
+}
+
+impl<T, const S: usize> BaseScheduler for ClassScheduler<T, S> {
+    type SchedItem = Arc<ClassTask<T, S>>;
+
+    fn init(&mut self) {
+        self.rt.init();
+        self.fair.init();
+        self.idle.init();
+    }
+
+    fn add_task(&mut self, task: Self::SchedItem) {
+        task.queued.store(true, Ordering::Release);
+        match task.policy() {
+            SchedPolicy::RealTime => self.rt.add_task(task),
+            SchedPolicy::Normal => self.fair.add_task(task),
+            SchedPolicy::Idle => self.idle.add_task(task),
+        }
+    }
+
+    fn remove_task(&mut self, task: &Self::SchedItem) -> Option<Self::SchedItem> {
+        if !task.queued.load(Ordering::Acquire) {
+            return None;
+        }
+        let removed = match task.policy() {
+            SchedPolicy::RealTime => self.rt.remove_task(task),
+            SchedPolicy::Normal => self.fair.remove_task(task),
+            SchedPolicy::Idle => self.idle.remove_task(task),
+        };
+        if removed.is_some() {
+            task.queued.store(false, Ordering::Release);
+        }
+        removed
+    }
+
+    fn pick_next_task(&mut self) -> Option<Self::SchedItem> {
+        let next = self
+            .rt
+            .pick_next_task()
+            .or_else(|| self.fair.pick_next_task())
+            .or_else(|| self.idle.pick_next_task());
+        if let Some(task) = &next {
+            task.queued.store(false, Ordering::Release);
+        }
+        next
+    }
+
+    fn put_prev_task(&mut self, prev: Self::SchedItem, preempt: bool) {
+        prev.queued.store(true, Ordering::Release);
+        match prev.policy() {
+            SchedPolicy::RealTime => self.rt.put_prev_task(prev, preempt),
+            SchedPolicy::Normal => self.fair.put_prev_task(prev, preempt),
+            SchedPolicy::Idle => self.idle.put_prev_task(prev, preempt),
+        }
+    }
+
+    fn steal_task<F>(&mut self, mut filter: F) -> Option<Self::SchedItem>
+    where
+        F: FnMut(&Self::SchedItem) -> bool,
+    {
+        let task = self
+            .rt
+            .steal_task(&mut filter)
+            .or_else(|| self.fair.steal_task(&mut filter))
+            .or_else(|| self.idle.steal_task(&mut filter));
+        if let Some(task) = &task {
+            task.queued.store(false, Ordering::Release);
+        }
+        task
+    }
+
+    fn task_tick(&mut self, current: &Self::SchedItem) -> bool {
+        // Also reschedule if a task of a higher class is ready.
+        match current.policy() {
+            SchedPolicy::RealTime => self.rt.task_tick(current),
+            SchedPolicy::Normal => self.fair.task_tick(current) || !self.rt.is_empty(),
+            SchedPolicy::Idle => !self.rt.is_empty() || !self.fair.is_empty(),
+        }
+    }
+
+    fn need_preempt(&self, current: &Self::SchedItem) -> bool {
+        match current.policy() {
+            SchedPolicy::RealTime => self.rt.need_preempt(current),
+            SchedPolicy::Normal => !self.rt.is_empty(),
+            SchedPolicy::Idle => !self.rt.is_empty() || !self.fair.is_empty(),
+        }
+    }
+
+    fn set_priority(&mut self, task: &Self::SchedItem, prio: isize) -> bool {
+        match task.policy() {
+            SchedPolicy::RealTime => self.rt.set_priority(task, prio),
+            SchedPolicy::Normal => self.fair.set_priority(task, prio),
+            SchedPolicy::Idle => false,
+        }
+    }
+}
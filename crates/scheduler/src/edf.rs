@@ -0,0 +1,247 @@
+use alloc::{collections::BTreeMap, sync::Arc};
+use core::ops::Deref;
+use core::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+
+use crate::BaseScheduler;
+
+/// The absolute deadline of tasks without real-time parameters. They run only
+/// when no real-time task is ready.
+const NO_DEADLINE: u64 = u64::MAX;
+
+/// A task wrapper for the [`EDFScheduler`].
+///
+/// A real-time task is described by three parameters, all measured in timer
+/// ticks: it needs to run for `runtime` ticks in every `period`, and the
+/// execution must be finished within `deadline` ticks after the period begins
+/// (`runtime <= deadline <= period`).
+///
+/// A task created by [`EDFTask::new`] has no real-time parameters and is
+/// scheduled in the background.
+pub struct EDFTask<T> {
+    inner: T,
+    runtime: AtomicU64,
+    deadline: AtomicU64,
+    period: AtomicU64,
+    /// Absolute deadline of the current period.
+    abs_deadline: AtomicU64,
+    /// Remaining runtime in the current period.
+    budget: AtomicI64,
+    /// Sequence number to keep the FIFO order among tasks with equal deadlines.
+    seq: AtomicU64,
+}
+
+impl<T> EDFTask<T> {
+    /// Creates a new [`EDFTask`] from the inner task struct, without real-time
+    /// parameters.
+    pub const fn new(inner: T) -> Self {
+        Self {
+            inner,
+            runtime: AtomicU64::new(0),
+            deadline: AtomicU64::new(0),
+            period: AtomicU64::new(0),
+            abs_deadline: AtomicU64::new(NO_DEADLINE),
+            budget: AtomicI64::new(0),
+            seq: AtomicU64::new(0),
+        }
+    }
+
+    /// Creates a new real-time [`EDFTask`] with the given parameters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the parameters are invalid (see [`EDFTask::set_params`]).
+    pub fn new_periodic(inner: T, runtime: u64, deadline: u64, period: u64) -> Self {
+        let task = Self::new(inner);
+        assert!(task.set_params(runtime, deadline, period));
+        task
+    }
+
+    /// Sets the real-time parameters of the task. They take effect from the
+    /// next period. Setting all of them to 0 makes it a background task.
+    ///
+    /// Returns `false` if the parameters are invalid, i.e., not satisfy
+    /// `0 < runtime <= deadline <= period`.
+    pub fn set_params(&self, runtime: u64, deadline: u64, period: u64) -> bool {
+        let clear = runtime == 0 && deadline == 0 && period == 0;
+        if !clear && !(0 < runtime && runtime <= deadline && deadline <= period) {
+            return false;
+        }
+        self.runtime.store(runtime, Ordering::Release);
+        self.deadline.store(deadline, Ordering::Release);
+        self.period.store(period, Ordering::Release);
+        true
+    }
+
+    /// Returns the real-time parameters `(runtime, deadline, period)` of the
+    /// task.
+    pub fn params(&self) -> (u64, u64, u64) {
+        (
+            self.runtime.load(Ordering::Acquire),
+            self.deadline.load(Ordering::Acquire),
+            self.period.load(Ordering::Acquire),
+        )
+    }
+
+    /// Returns the absolute deadline of the current period, or [`None`] if the
+    /// task has no real-time parameters.
+    pub fn abs_deadline(&self) -> Option<u64> {
+        match self.abs_deadline.load(Ordering::Acquire) {
+            NO_DEADLINE => None,
+            d => Some(d),
+        }
+    }
+
+    fn is_realtime(&self) -> bool {
+        self.runtime.load(Ordering::Acquire) > 0
+    }
+
+    fn key(&self) -> (u64, u64) {
+        (
+            self.abs_deadline.load(Ordering::Acquire),
+            self.seq.load(Ordering::Acquire),
+        )
+    }
+
+    /// Starts a new period at `now`.
+    fn start_period(&self, now: u64) {
+        let (runtime, deadline, _) = self.params();
+        if runtime > 0 {
+            self.abs_deadline.store(now + deadline, Ordering::Release);
+        } else {
+            self.abs_deadline.store(NO_DEADLINE, Ordering::Release);
+        }
+        self.budget.store(runtime as i64, Ordering::Release);
+    }
+
+    /// Moves to the next period after the budget is used up.
+    fn next_period(&self, now: u64) {
+        let (runtime, _, period) = self.params();
+        let abs_deadline = self.abs_deadline.load(Ordering::Acquire);
+        if runtime == 0 || abs_deadline == NO_DEADLINE || abs_deadline + period <= now {
+            self.start_period(now);
+        } else {
+            self.abs_deadline
+                .store(abs_deadline + period, Ordering::Release);
+            self.budget.store(runtime as i64, Ordering::Release);
+        }
+    }
+
+    /// Returns a reference to the inner task struct.
+    pub const fn inner(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T> Deref for EDFTask<T> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+/// An [Earliest Deadline First][1] (EDF) scheduler.
+///
+/// The ready task with the earliest absolute deadline always runs first. When
+/// a real-time task has used up its runtime in the current period, its deadline
+/// is postponed to the next period, so that it can not starve other tasks
+/// (as the constant bandwidth server). Tasks without real-time parameters are
+/// scheduled in round-robin after all real-time tasks.
+///
+/// Time is measured in timer ticks observed by [`BaseScheduler::task_tick`].
+///
+/// [1]: https://en.wikipedia.org/wiki/Earliest_deadline_first_scheduling
+pub struct EDFScheduler<T> {
+    ready_queue: BTreeMap<(u64, u64), Arc<EDFTask<T>>>, // (abs_deadline, seq)
+    clock: u64,
+    next_seq: u64,
+}
+
+impl<T> EDFScheduler<T> {
+    /// Creates a new empty [`EDFScheduler`].
+    pub const fn new() -> Self {
+        Self {
+            ready_queue: BTreeMap::new(),
+            clock: 0,
+            next_seq: 0,
+        }
+    }
+    /// get the name of scheduler
+    pub fn scheduler_name() -> &'static str {
+        "Earliest Deadline First"
+    }
+
+    fn insert(&mut self, task: Arc<EDFTask<T>>) {
+        task.seq.store(self.next_seq, Ordering::Release);
+        self.next_seq += 1;
+        self.ready_queue.insert(task.key(), task);
+    }
+}
+
+impl<T> BaseScheduler for EDFScheduler<T> {
+    type SchedItem = Arc<EDFTask<T>>;
+
+    fn init(&mut self) {}
+
+    fn add_task(&mut self, task: Self::SchedItem) {
+        // A new or woken up task starts a new period if the deadline of the
+        // last one has passed, or its parameters are changed.
+        let abs_deadline = task.abs_deadline.load(Ordering::Acquire);
+        if task.is_realtime() != (abs_deadline != NO_DEADLINE) || abs_deadline <= self.clock {
+            task.start_period(self.clock);
+        }
+        self.insert(task);
+    }
+
+    fn remove_task(&mut self, task: &Self::SchedItem) -> Option<Self::SchedItem> {
+        self.ready_queue.remove(&task.key())
+    }
+
+    fn pick_next_task(&mut self) -> Option<Self::SchedItem> {
+        self.ready_queue.pop_first().map(|(_, task)| task)
+    }
+
+    fn put_prev_task(&mut self, prev: Self::SchedItem, _preempt: bool) {
+        if prev.budget.load(Ordering::Acquire) <= 0
+            || prev.is_realtime() != prev.abs_deadline().is_some()
+        {
+            prev.next_period(self.clock);
+        }
+        self.insert(prev);
+    }
+
+    fn steal_task<F>(&mut self, mut filter: F) -> Option<Self::SchedItem>
+    where
+        F: FnMut(&Self::SchedItem) -> bool,
+    {
+        let key = *self.ready_queue.iter().find(|(_, t)| filter(t))?.0;
+        self.ready_queue.remove(&key)
+    }
+
+    fn task_tick(&mut self, current: &Self::SchedItem) -> bool {
+        self.clock += 1;
+        if current.is_realtime() {
+            let old_budget = current.budget.fetch_sub(1, Ordering::Release);
+            old_budget <= 1
+                || self
+                    .ready_queue
+                    .first_key_value()
+                    .is_some_and(|(&(d, _), _)| d < current.key().0)
+        } else {
+            // background tasks run in round-robin, one tick each.
+            !self.ready_queue.is_empty()
+        }
+    }
+
+    fn need_preempt(&self, current: &Self::SchedItem) -> bool {
+        // background tasks have the latest deadline.
+        let deadline = current.key().0;
+        self.ready_queue
+            .first_key_value()
+            .is_some_and(|(&(d, _), _)| d < deadline)
+    }
+
+    fn set_priority(&mut self, _task: &Self::SchedItem, _prio: isize) -> bool {
+        false
+    }
+}
@@ -1,4 +1,6 @@
 use alloc::sync::Arc;
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
 use core::ops::Deref;
 
 use linked_list::{Adapter, Links, List};
@@ -53,26 +55,34 @@ impl<T> Deref for FifoTask<T> {
 ///
 /// As it's a cooperative scheduler, it does nothing when the timer tick occurs.
 ///
-/// It internally uses a linked list as the ready queue.
-pub struct FifoScheduler<T> {
-    ready_queue: List<Arc<FifoTask<T>>>,
+/// It internally uses a linked list as the ready queue. The scheduled entity
+/// is [`FifoTask`] by default, or any type that has its own list links (`E`).
+pub struct FifoScheduler<T, E: Adapter<EntryType = E> = FifoTask<T>> {
+    ready_queue: List<Arc<E>>,
+    _phantom: PhantomData<T>,
 }
 
-impl<T> FifoScheduler<T> {
+impl<T, E: Adapter<EntryType = E>> FifoScheduler<T, E> {
     /// Creates a new empty [`FifoScheduler`].
     pub const fn new() -> Self {
         Self {
             ready_queue: List::new(),
+            _phantom: PhantomData,
         }
     }
     /// get the name of scheduler
     pub fn scheduler_name() -> &'static str {
         "FIFO"
     }
+
+    /// Returns whether there is no ready task.
+    pub fn is_empty(&self) -> bool {
+        self.ready_queue.is_empty()
+    }
 }
 
-impl<T> BaseScheduler for FifoScheduler<T> {
-    type SchedItem = Arc<FifoTask<T>>;
+impl<T, E: Adapter<EntryType = E>> BaseScheduler for FifoScheduler<T, E> {
+    type SchedItem = Arc<E>;
 
     fn init(&mut self) {}
 
@@ -92,6 +102,24 @@ impl<T> BaseScheduler for FifoScheduler<T> {
         self.ready_queue.push_back(prev);
     }
 
+    fn steal_task<F>(&mut self, mut filter: F) -> Option<Self::SchedItem>
+    where
+        F: FnMut(&Self::SchedItem) -> bool,
+    {
+        let mut cursor = self.ready_queue.cursor_front();
+        let found = loop {
+            let entry = cursor.current()?;
+            // Safety: the entries in the list are held by `Arc`s, and the
+            // reference count is not changed.
+            let task = ManuallyDrop::new(unsafe { Arc::from_raw(entry as *const E) });
+            if filter(&task) {
+                break task;
+            }
+            cursor.move_next();
+        };
+        unsafe { self.ready_queue.remove(&found) }
+    }
+
     fn task_tick(&mut self, _current: &Self::SchedItem) -> bool {
         false // no reschedule
     }
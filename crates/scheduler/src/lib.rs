@@ -5,12 +5,19 @@
 //! - [`FifoScheduler`]: FIFO (First-In-First-Out) scheduler (cooperative).
 //! - [`RRScheduler`]: Round-robin scheduler (preemptive).
 //! - [`CFScheduler`]: Completely Fair Scheduler (preemptive).
+//! - [`PrioScheduler`]: Fixed-priority scheduler (preemptive).
+//! - [`EDFScheduler`]: Earliest Deadline First scheduler (preemptive).
+//! - [`ClassScheduler`]: Multi-class scheduler that combines the real-time,
+//!   fair and idle classes (preemptive).
 
 #![cfg_attr(not(test), no_std)]
 #![feature(const_mut_refs)]
 
 mod cfs;
+mod class;
+mod edf;
 mod fifo;
+mod prio;
 mod round_robin;
 
 #[cfg(test)]
@@ -18,8 +25,13 @@ mod tests;
 
 extern crate alloc;
 
-pub use cfs::{CFSTask, CFScheduler};
+pub use cfs::{CFSEntity, CFSTask, CFScheduler, HasCFSEntity};
+pub use class::{ClassScheduler, ClassTask, SchedPolicy};
+pub use edf::{EDFScheduler, EDFTask};
 pub use fifo::{FifoScheduler, FifoTask};
+pub use prio::{
+    HasPrioEntity, PrioEntity, PrioScheduler, PrioTask, RtPolicy, DEFAULT_PRIO, NUM_PRIO,
+};
 pub use round_robin::{RRScheduler, RRTask};
 
 /// The base scheduler trait that all schedulers should implement.
@@ -58,12 +70,34 @@ pub trait BaseScheduler {
     /// ready queue.
     fn put_prev_task(&mut self, prev: Self::SchedItem, preempt: bool);
 
+    /// Removes the first task that satisfies `filter`, in the order they
+    /// would be picked by [`pick_next_task`], and returns it. The order of
+    /// the other tasks is not changed.
+    ///
+    /// It's used to move a ready task to another CPU (e.g., work stealing),
+    /// where `filter` checks whether the task is allowed to run there.
+    ///
+    /// [`pick_next_task`]: BaseScheduler::pick_next_task
+    fn steal_task<F>(&mut self, filter: F) -> Option<Self::SchedItem>
+    where
+        F: FnMut(&Self::SchedItem) -> bool;
+
     /// Advances the scheduler state at each timer tick. Returns `true` if
     /// re-scheduling is required.
     ///
     /// `current` is the current running task.
     fn task_tick(&mut self, current: &Self::SchedItem) -> bool;
 
+    /// Returns `true` if a ready task in the scheduler should preempt the
+    /// `current` running task right away, e.g., it has a higher priority.
+    ///
+    /// It's checked when a task becomes ready, so that it need not wait for
+    /// the next timer tick. Schedulers without priorities never preempt in
+    /// this case, which is the default.
+    fn need_preempt(&self, _current: &Self::SchedItem) -> bool {
+        false
+    }
+
     /// set priority for a task
     fn set_priority(&mut self, task: &Self::SchedItem, prio: isize) -> bool;
 }
@@ -0,0 +1,306 @@
+use alloc::{collections::VecDeque, sync::Arc};
+use core::marker::PhantomData;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicIsize, AtomicU8, AtomicUsize, Ordering};
+
+use crate::BaseScheduler;
+
+/// Number of priority levels of the [`PrioScheduler`].
+pub const NUM_PRIO: usize = 100;
+
+/// Default priority of a new task of the [`PrioScheduler`].
+pub const DEFAULT_PRIO: usize = NUM_PRIO / 2;
+
+/// How the [`PrioScheduler`] schedules the tasks with the same priority.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum RtPolicy {
+    /// The task runs until it blocks, yields, or is preempted by a higher
+    /// priority task. It has no time slice.
+    Fifo = 0,
+    /// The task runs for a time slice, then it is put to the back of its
+    /// ready queue.
+    RoundRobin = 1,
+}
+
+impl From<u8> for RtPolicy {
+    #[inline]
+    fn from(policy: u8) -> Self {
+        match policy {
+            0 => Self::Fifo,
+            1 => Self::RoundRobin,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Per-task states of the [`PrioScheduler`], i.e., a priority, a policy and a
+/// time slice counter. The time slice is used to round-robin among tasks with
+/// the same priority under the [`RtPolicy::RoundRobin`] policy.
+pub struct PrioEntity<const MAX_TIME_SLICE: usize> {
+    prio: AtomicUsize,
+    policy: AtomicU8,
+    time_slice: AtomicIsize,
+}
+
+/// Types that can be scheduled by the [`PrioScheduler`].
+pub trait HasPrioEntity<const MAX_TIME_SLICE: usize> {
+    /// Returns the states used by the [`PrioScheduler`].
+    fn prio_entity(&self) -> &PrioEntity<MAX_TIME_SLICE>;
+}
+
+impl<const S: usize> PrioEntity<S> {
+    /// Creates a new [`PrioEntity`] with the default priority (50) and the
+    /// [`RtPolicy::RoundRobin`] policy.
+    pub const fn new() -> Self {
+        Self {
+            prio: AtomicUsize::new(DEFAULT_PRIO),
+            policy: AtomicU8::new(RtPolicy::RoundRobin as u8),
+            time_slice: AtomicIsize::new(S as isize),
+        }
+    }
+
+    /// Returns the priority. The smaller the value, the higher the priority.
+    pub fn prio(&self) -> usize {
+        self.prio.load(Ordering::Acquire)
+    }
+
+    fn set_prio(&self, prio: usize) {
+        self.prio.store(prio, Ordering::Release);
+    }
+
+    /// Returns the policy among the tasks with the same priority.
+    pub fn policy(&self) -> RtPolicy {
+        self.policy.load(Ordering::Acquire).into()
+    }
+
+    fn set_policy(&self, policy: RtPolicy) {
+        self.policy.store(policy as u8, Ordering::Release);
+    }
+
+    fn time_slice(&self) -> isize {
+        self.time_slice.load(Ordering::Acquire)
+    }
+
+    fn reset_time_slice(&self) {
+        self.time_slice.store(S as isize, Ordering::Release);
+    }
+}
+
+/// A task wrapper for the [`PrioScheduler`].
+///
+/// It adds a [`PrioEntity`] to the inner task struct.
+pub struct PrioTask<T, const MAX_TIME_SLICE: usize> {
+    inner: T,
+    entity: PrioEntity<MAX_TIME_SLICE>,
+}
+
+impl<T, const S: usize> PrioTask<T, S> {
+    /// Creates a new [`PrioTask`] from the inner task struct, with the default
+    /// priority (50) and the [`RtPolicy::RoundRobin`] policy.
+    pub const fn new(inner: T) -> Self {
+        Self {
+            inner,
+            entity: PrioEntity::new(),
+        }
+    }
+
+    /// Returns the priority of the task. The smaller the value, the higher the
+    /// priority.
+    pub fn prio(&self) -> usize {
+        self.entity.prio()
+    }
+
+    /// Returns the policy among the tasks with the same priority.
+    pub fn policy(&self) -> RtPolicy {
+        self.entity.policy()
+    }
+
+    /// Returns a reference to the inner task struct.
+    pub const fn inner(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T, const S: usize> HasPrioEntity<S> for PrioTask<T, S> {
+    fn prio_entity(&self) -> &PrioEntity<S> {
+        &self.entity
+    }
+}
+
+impl<T, const S: usize> Deref for PrioTask<T, S> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+/// A fixed-priority preemptive scheduler.
+///
+/// There are [`NUM_PRIO`] (100) priority levels, 0 is the highest and 99 is
+/// the lowest. Each level has its own ready queue, and a bitmap records which
+/// levels are non-empty, so the highest priority ready task is found in O(1)
+/// time.
+///
+/// A task is always preempted by a higher priority ready task. Tasks with the
+/// same priority are scheduled by their [`RtPolicy`]: in round-robin like the
+/// [`RRScheduler`], or in first-in-first-out order without time slices.
+///
+/// The scheduled entity is [`PrioTask`] by default, or any type that
+/// implements [`HasPrioEntity`] (`E`).
+///
+/// [`RRScheduler`]: crate::RRScheduler
+pub struct PrioScheduler<T, const MAX_TIME_SLICE: usize, E = PrioTask<T, MAX_TIME_SLICE>> {
+    ready_queues: [VecDeque<Arc<E>>; NUM_PRIO],
+    bitmap: u128,
+    _phantom: PhantomData<T>,
+}
+
+impl<T, const S: usize, E: HasPrioEntity<S>> PrioScheduler<T, S, E> {
+    const EMPTY_QUEUE: VecDeque<Arc<E>> = VecDeque::new();
+
+    /// Creates a new empty [`PrioScheduler`].
+    pub const fn new() -> Self {
+        Self {
+            ready_queues: [Self::EMPTY_QUEUE; NUM_PRIO],
+            bitmap: 0,
+            _phantom: PhantomData,
+        }
+    }
+    /// get the name of scheduler
+    pub fn scheduler_name() -> &'static str {
+        "Fixed-priority"
+    }
+
+    /// Returns the highest priority among the ready tasks.
+    fn highest_prio(&self) -> Option<usize> {
+        if self.bitmap == 0 {
+            None
+        } else {
+            Some(self.bitmap.trailing_zeros() as usize)
+        }
+    }
+
+    /// Changes the policy of the task among the tasks with the same priority.
+    /// A round-robin task starts with a full time slice.
+    pub fn set_policy(&mut self, task: &Arc<E>, policy: RtPolicy) {
+        let entity = task.prio_entity();
+        entity.set_policy(policy);
+        entity.reset_time_slice();
+    }
+
+    /// Returns whether there is no ready task.
+    pub fn is_empty(&self) -> bool {
+        self.bitmap == 0
+    }
+
+    fn push(&mut self, task: Arc<E>, front: bool) {
+        let prio = task.prio_entity().prio();
+        if front {
+            self.ready_queues[prio].push_front(task);
+        } else {
+            self.ready_queues[prio].push_back(task);
+        }
+        self.bitmap |= 1 << prio;
+    }
+
+    fn remove_at(&mut self, prio: usize, task: &Arc<E>) -> Option<Arc<E>> {
+        let queue = &mut self.ready_queues[prio];
+        let removed = queue
+            .iter()
+            .position(|t| Arc::ptr_eq(t, task))
+            .and_then(|idx| queue.remove(idx));
+        if queue.is_empty() {
+            self.bitmap &= !(1 << prio);
+        }
+        removed
+    }
+}
+
+impl<T, const S: usize, E: HasPrioEntity<S>> BaseScheduler for PrioScheduler<T, S, E> {
+    type SchedItem = Arc<E>;
+
+    fn init(&mut self) {}
+
+    fn add_task(&mut self, task: Self::SchedItem) {
+        self.push(task, false);
+    }
+
+    fn remove_task(&mut self, task: &Self::SchedItem) -> Option<Self::SchedItem> {
+        self.remove_at(task.prio_entity().prio(), task)
+    }
+
+    fn pick_next_task(&mut self) -> Option<Self::SchedItem> {
+        let prio = self.highest_prio()?;
+        let queue = &mut self.ready_queues[prio];
+        let task = queue.pop_front();
+        if queue.is_empty() {
+            self.bitmap &= !(1 << prio);
+        }
+        task
+    }
+
+    fn put_prev_task(&mut self, prev: Self::SchedItem, preempt: bool) {
+        let entity = prev.prio_entity();
+        // A preempted task keeps its place at the front of its ready queue,
+        // unless its time slice is used up.
+        let keep_place = entity.policy() == RtPolicy::Fifo || entity.time_slice() > 0;
+        if preempt && keep_place {
+            self.push(prev, true);
+        } else {
+            entity.reset_time_slice();
+            self.push(prev, false);
+        }
+    }
+
+    fn steal_task<F>(&mut self, mut filter: F) -> Option<Self::SchedItem>
+    where
+        F: FnMut(&Self::SchedItem) -> bool,
+    {
+        let mut bitmap = self.bitmap;
+        while bitmap != 0 {
+            let prio = bitmap.trailing_zeros() as usize;
+            bitmap &= bitmap - 1;
+            let queue = &mut self.ready_queues[prio];
+            if let Some(idx) = queue.iter().position(&mut filter) {
+                let task = queue.remove(idx);
+                if queue.is_empty() {
+                    self.bitmap &= !(1 << prio);
+                }
+                return task;
+            }
+        }
+        None
+    }
+
+    fn task_tick(&mut self, current: &Self::SchedItem) -> bool {
+        let entity = current.prio_entity();
+        let expired = match entity.policy() {
+            RtPolicy::Fifo => false,
+            RtPolicy::RoundRobin => entity.time_slice.fetch_sub(1, Ordering::Release) <= 1,
+        };
+        expired || self.highest_prio().is_some_and(|p| p < entity.prio())
+    }
+
+    fn need_preempt(&self, current: &Self::SchedItem) -> bool {
+        let prio = current.prio_entity().prio();
+        self.highest_prio().is_some_and(|p| p < prio)
+    }
+
+    fn set_priority(&mut self, task: &Self::SchedItem, prio: isize) -> bool {
+        if !(0..NUM_PRIO as isize).contains(&prio) {
+            return false;
+        }
+        let old_prio = task.prio_entity().prio();
+        if old_prio != prio as usize {
+            // Move it to the new ready queue if it is ready.
+            let removed = self.remove_at(old_prio, task);
+            task.prio_entity().set_prio(prio as usize);
+            if let Some(task) = removed {
+                self.push(task, false);
+            }
+        }
+        true
+    }
+}
@@ -28,6 +28,26 @@ macro_rules! def_test_sched {
                 assert_eq!(n, NUM_TASKS);
             }
 
+            #[test]
+            fn test_steal() {
+                const NUM_TASKS: usize = 11;
+
+                let mut scheduler = <$scheduler>::new();
+                for i in 0..NUM_TASKS {
+                    scheduler.add_task(Arc::new(<$task>::new(i)));
+                }
+
+                let stolen = scheduler.steal_task(|t| *t.inner() % 2 == 1).unwrap();
+                assert_eq!(*stolen.inner(), 1);
+                assert!(scheduler.steal_task(|t| *t.inner() >= NUM_TASKS).is_none());
+
+                // the order of the other tasks is kept.
+                for i in (0..NUM_TASKS).filter(|&i| i != 1) {
+                    assert_eq!(*scheduler.pick_next_task().unwrap().inner(), i);
+                }
+                assert!(scheduler.pick_next_task().is_none());
+            }
+
             #[test]
             fn bench_yield() {
                 const NUM_TASKS: usize = 1_000_000;
@@ -82,3 +102,224 @@ macro_rules! def_test_sched {
 def_test_sched!(fifo, FifoScheduler::<usize>, FifoTask::<usize>);
 def_test_sched!(rr, RRScheduler::<usize, 5>, RRTask::<usize, 5>);
 def_test_sched!(cfs, CFScheduler::<usize>, CFSTask::<usize>);
+def_test_sched!(prio, PrioScheduler::<usize, 5>, PrioTask::<usize, 5>);
+def_test_sched!(edf, EDFScheduler::<usize>, EDFTask::<usize>);
+
+mod prio_rt {
+    use crate::*;
+    use alloc::sync::Arc;
+
+    #[test]
+    fn test_strict_priority() {
+        let mut scheduler = PrioScheduler::<usize, 5>::new();
+        let tasks: Vec<_> = (0..NUM_PRIO).map(|i| Arc::new(PrioTask::new(i))).collect();
+        for t in tasks.iter().rev() {
+            assert!(scheduler.set_priority(t, *t.inner() as isize));
+            scheduler.add_task(t.clone());
+        }
+        assert!(!scheduler.set_priority(&tasks[0], -1));
+        assert!(!scheduler.set_priority(&tasks[0], NUM_PRIO as isize));
+
+        for i in 0..NUM_PRIO {
+            assert_eq!(*scheduler.pick_next_task().unwrap().inner(), i);
+        }
+        assert!(scheduler.pick_next_task().is_none());
+    }
+
+    #[test]
+    fn test_preempt_by_higher_priority() {
+        let mut scheduler = PrioScheduler::<usize, 5>::new();
+        let low = Arc::new(PrioTask::new(0));
+        let high = Arc::new(PrioTask::new(1));
+        scheduler.set_priority(&low, 20);
+        scheduler.set_priority(&high, 10);
+
+        scheduler.add_task(low.clone());
+        let curr = scheduler.pick_next_task().unwrap();
+        assert!(Arc::ptr_eq(&curr, &low));
+        assert!(!scheduler.task_tick(&curr));
+        scheduler.add_task(Arc::new(PrioTask::new(2)));
+        assert!(!scheduler.need_preempt(&curr)); // lower priority
+
+        scheduler.add_task(high.clone());
+        assert!(scheduler.need_preempt(&curr));
+        assert!(scheduler.task_tick(&curr));
+        scheduler.put_prev_task(curr, true);
+        assert!(Arc::ptr_eq(&scheduler.pick_next_task().unwrap(), &high));
+        assert!(Arc::ptr_eq(&scheduler.pick_next_task().unwrap(), &low));
+    }
+
+    #[test]
+    fn test_change_priority_in_queue() {
+        let mut scheduler = PrioScheduler::<usize, 5>::new();
+        let tasks: Vec<_> = (0..3).map(|i| Arc::new(PrioTask::new(i))).collect();
+        for t in &tasks {
+            scheduler.add_task(t.clone());
+        }
+        assert!(scheduler.set_priority(&tasks[2], 0));
+        assert_eq!(*scheduler.pick_next_task().unwrap().inner(), 2);
+        assert!(scheduler.remove_task(&tasks[1]).is_some());
+        assert_eq!(*scheduler.pick_next_task().unwrap().inner(), 0);
+        assert!(scheduler.pick_next_task().is_none());
+    }
+
+    #[test]
+    fn test_fifo_policy() {
+        let mut scheduler = PrioScheduler::<usize, 5>::new();
+        let fifo = Arc::new(PrioTask::new(0));
+        let rr = Arc::new(PrioTask::new(1));
+        scheduler.set_policy(&fifo, RtPolicy::Fifo);
+        assert_eq!(fifo.policy(), RtPolicy::Fifo);
+        assert_eq!(rr.policy(), RtPolicy::RoundRobin);
+        scheduler.add_task(fifo.clone());
+        scheduler.add_task(rr.clone());
+
+        // A FIFO task is not preempted by tasks with the same priority.
+        let curr = scheduler.pick_next_task().unwrap();
+        assert!(Arc::ptr_eq(&curr, &fifo));
+        for _ in 0..20 {
+            assert!(!scheduler.task_tick(&curr));
+        }
+        scheduler.put_prev_task(curr, true);
+        assert!(Arc::ptr_eq(&scheduler.pick_next_task().unwrap(), &fifo));
+
+        // But it goes to the back of the ready queue after it yields.
+        scheduler.put_prev_task(fifo.clone(), false);
+        let curr = scheduler.pick_next_task().unwrap();
+        assert!(Arc::ptr_eq(&curr, &rr));
+        assert!(!(0..4).any(|_| scheduler.task_tick(&curr)));
+        assert!(scheduler.task_tick(&curr));
+        scheduler.put_prev_task(curr, true);
+        assert!(Arc::ptr_eq(&scheduler.pick_next_task().unwrap(), &fifo));
+    }
+}
+
+mod edf_rt {
+    use crate::*;
+    use alloc::sync::Arc;
+
+    #[test]
+    fn test_earliest_deadline_first() {
+        let mut scheduler = EDFScheduler::<usize>::new();
+        let background = Arc::new(EDFTask::new(0));
+        scheduler.add_task(background);
+        for (i, deadline) in [(1, 30), (2, 10), (3, 20)] {
+            scheduler.add_task(Arc::new(EDFTask::new_periodic(i, 5, deadline, 50)));
+        }
+        let order: Vec<_> = core::iter::from_fn(|| scheduler.pick_next_task())
+            .map(|t| *t.inner())
+            .collect();
+        assert_eq!(order, [2, 3, 1, 0]);
+    }
+
+    #[test]
+    fn test_preempt_by_earlier_deadline() {
+        let mut scheduler = EDFScheduler::<usize>::new();
+        scheduler.add_task(Arc::new(EDFTask::new_periodic(1, 5, 20, 50)));
+        let curr = scheduler.pick_next_task().unwrap();
+        scheduler.add_task(Arc::new(EDFTask::new_periodic(2, 5, 30, 50)));
+        assert!(!scheduler.need_preempt(&curr));
+        scheduler.add_task(Arc::new(EDFTask::new_periodic(3, 5, 10, 50)));
+        assert!(scheduler.need_preempt(&curr));
+    }
+
+    #[test]
+    fn test_budget_exhausted() {
+        let mut scheduler = EDFScheduler::<usize>::new();
+        let t1 = Arc::new(EDFTask::new_periodic(1, 2, 10, 10));
+        let t2 = Arc::new(EDFTask::new_periodic(2, 5, 15, 15));
+        scheduler.add_task(t1.clone());
+        scheduler.add_task(t2.clone());
+
+        let curr = scheduler.pick_next_task().unwrap();
+        assert!(Arc::ptr_eq(&curr, &t1));
+        assert_eq!(curr.abs_deadline(), Some(10));
+        assert!(!scheduler.task_tick(&curr));
+        assert!(scheduler.task_tick(&curr)); // runtime used up
+        scheduler.put_prev_task(curr, true);
+        assert_eq!(t1.abs_deadline(), Some(20));
+
+        let curr = scheduler.pick_next_task().unwrap();
+        assert!(Arc::ptr_eq(&curr, &t2));
+        scheduler.put_prev_task(curr, false);
+        assert!(Arc::ptr_eq(&scheduler.pick_next_task().unwrap(), &t2));
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        let t = EDFTask::new(0);
+        assert!(!t.set_params(5, 3, 10));
+        assert!(!t.set_params(5, 10, 8));
+        assert!(!t.set_params(0, 10, 10));
+        assert!(t.set_params(3, 5, 10));
+        assert_eq!(t.params(), (3, 5, 10));
+        assert!(t.set_params(0, 0, 0));
+    }
+}
+
+def_test_sched!(class, ClassScheduler::<usize, 5>, ClassTask::<usize, 5>);
+
+mod class_rt {
+    use crate::*;
+    use alloc::sync::Arc;
+
+    #[test]
+    fn test_class_order() {
+        let mut scheduler = ClassScheduler::<usize, 5>::new();
+        let policies = [
+            SchedPolicy::Idle,
+            SchedPolicy::Normal,
+            SchedPolicy::RealTime,
+        ];
+        let tasks: Vec<_> = (0..3).map(|i| Arc::new(ClassTask::new(i))).collect();
+        for (t, policy) in tasks.iter().zip(policies) {
+            scheduler.set_policy(t, policy);
+            scheduler.add_task(t.clone());
+        }
+        for i in (0..3).rev() {
+            let next = scheduler.pick_next_task().unwrap();
+            assert_eq!(*next.inner(), i);
+        }
+        assert!(scheduler.pick_next_task().is_none());
+    }
+
+    #[test]
+    fn test_preempt_by_higher_class() {
+        let mut scheduler = ClassScheduler::<usize, 5>::new();
+        let normal = Arc::new(ClassTask::new(0));
+        let rt = Arc::new(ClassTask::new(1));
+        scheduler.set_policy(&rt, SchedPolicy::RealTime);
+        assert!(scheduler.set_priority(&rt, 10));
+        assert!(!scheduler.set_priority(&rt, 100));
+        assert!(scheduler.set_priority(&normal, -5));
+
+        scheduler.add_task(rt.clone());
+        scheduler.put_prev_task(normal.clone(), false);
+        let curr = scheduler.pick_next_task().unwrap();
+        assert!(Arc::ptr_eq(&curr, &rt));
+        let curr = scheduler.pick_next_task().unwrap();
+        assert!(Arc::ptr_eq(&curr, &normal));
+
+        scheduler.add_task(rt.clone());
+        assert!(scheduler.need_preempt(&curr));
+        assert!(scheduler.task_tick(&curr));
+    }
+
+    #[test]
+    fn test_change_policy_in_queue() {
+        let mut scheduler = ClassScheduler::<usize, 5>::new();
+        let tasks: Vec<_> = (0..3).map(|i| Arc::new(ClassTask::new(i))).collect();
+        for t in &tasks {
+            scheduler.add_task(t.clone());
+        }
+        scheduler.set_policy(&tasks[0], SchedPolicy::Idle);
+        scheduler.set_policy(&tasks[2], SchedPolicy::RealTime);
+        assert_eq!(tasks[2].policy(), SchedPolicy::RealTime);
+
+        let order: Vec<_> = core::iter::from_fn(|| scheduler.pick_next_task())
+            .map(|t| *t.inner())
+            .collect();
+        assert_eq!(order, [2, 1, 0]);
+        assert!(scheduler.remove_task(&tasks[1]).is_none());
+    }
+}
@@ -14,6 +14,8 @@ use core::ops::{Deref, DerefMut};
 use core::sync::atomic::{AtomicBool, Ordering};
 
 use kernel_guard::BaseGuard;
+#[cfg(feature = "lockdep")]
+use lockdep::{LockClass, LockKind};
 
 /// A [spin lock](https://en.m.wikipedia.org/wiki/Spinlock) providing mutually
 /// exclusive access to data.
@@ -24,10 +26,16 @@ use kernel_guard::BaseGuard;
 ///
 /// For single-core environment (without the "smp" feature), we remove the lock
 /// state, CPU can always get the lock if we follow the proper guard in use.
+///
+/// With the "lockdep" feature, the acquisitions are checked by the `lockdep`
+/// validator, and the locks created at the same place are in the same lock
+/// class.
 pub struct BaseSpinLock<G: BaseGuard, T: ?Sized> {
     _phantom: PhantomData<G>,
     #[cfg(feature = "smp")]
     lock: AtomicBool,
+    #[cfg(feature = "lockdep")]
+    class: LockClass,
     data: UnsafeCell<T>,
 }
 
@@ -40,6 +48,8 @@ pub struct BaseSpinLockGuard<'a, G: BaseGuard, T: ?Sized + 'a> {
     data: *mut T,
     #[cfg(feature = "smp")]
     lock: &'a AtomicBool,
+    #[cfg(feature = "lockdep")]
+    class: &'a LockClass,
 }
 
 // Same unsafe impls as `std::sync::Mutex`
@@ -49,12 +59,15 @@ unsafe impl<G: BaseGuard, T: ?Sized + Send> Send for BaseSpinLock<G, T> {}
 impl<G: BaseGuard, T> BaseSpinLock<G, T> {
     /// Creates a new [`BaseSpinLock`] wrapping the supplied data.
     #[inline(always)]
+    #[cfg_attr(feature = "lockdep", track_caller)]
     pub const fn new(data: T) -> Self {
         Self {
             _phantom: PhantomData,
             data: UnsafeCell::new(data),
             #[cfg(feature = "smp")]
             lock: AtomicBool::new(false),
+            #[cfg(feature = "lockdep")]
+            class: LockClass::new(),
         }
     }
 
@@ -74,8 +87,11 @@ impl<G: BaseGuard, T: ?Sized> BaseSpinLock<G, T> {
     /// The returned value may be dereferenced for data access
     /// and the lock will be dropped when the guard falls out of scope.
     #[inline(always)]
+    #[cfg_attr(feature = "lockdep", track_caller)]
     pub fn lock(&self) -> BaseSpinLockGuard<G, T> {
         let irq_state = G::acquire();
+        #[cfg(feature = "lockdep")]
+        self.lockdep_acquire(false);
         #[cfg(feature = "smp")]
         {
             // Can fail to lock even if the spinlock is not locked. May be more efficient than `try_lock`
@@ -97,6 +113,8 @@ impl<G: BaseGuard, T: ?Sized> BaseSpinLock<G, T> {
             data: unsafe { &mut *self.data.get() },
             #[cfg(feature = "smp")]
             lock: &self.lock,
+            #[cfg(feature = "lockdep")]
+            class: &self.class,
         }
     }
 
@@ -119,6 +137,7 @@ impl<G: BaseGuard, T: ?Sized> BaseSpinLock<G, T> {
 
     /// Try to lock this [`BaseSpinLock`], returning a lock guard if successful.
     #[inline(always)]
+    #[cfg_attr(feature = "lockdep", track_caller)]
     pub fn try_lock(&self) -> Option<BaseSpinLockGuard<G, T>> {
         let irq_state = G::acquire();
 
@@ -136,12 +155,16 @@ impl<G: BaseGuard, T: ?Sized> BaseSpinLock<G, T> {
         }
 
         if is_unlocked {
+            #[cfg(feature = "lockdep")]
+            self.lockdep_acquire(true);
             Some(BaseSpinLockGuard {
                 _phantom: &PhantomData,
                 irq_state,
                 data: unsafe { &mut *self.data.get() },
                 #[cfg(feature = "smp")]
                 lock: &self.lock,
+                #[cfg(feature = "lockdep")]
+                class: &self.class,
             })
         } else {
             None
@@ -157,6 +180,8 @@ impl<G: BaseGuard, T: ?Sized> BaseSpinLock<G, T> {
     /// lock to FFI that doesn't know how to deal with RAII.
     #[inline(always)]
     pub unsafe fn force_unlock(&self) {
+        #[cfg(feature = "lockdep")]
+        lockdep::lock_release(&self.class);
         #[cfg(feature = "smp")]
         self.lock.store(false, Ordering::Release);
     }
@@ -172,10 +197,23 @@ impl<G: BaseGuard, T: ?Sized> BaseSpinLock<G, T> {
         // there's no need to lock the inner mutex.
         unsafe { &mut *self.data.get() }
     }
+
+    #[cfg(feature = "lockdep")]
+    #[track_caller]
+    fn lockdep_acquire(&self, trylock: bool) {
+        lockdep::lock_acquire(
+            &self.class,
+            LockKind::Spin,
+            core::any::type_name::<BaseSpinLock<G, T>>(),
+            core::panic::Location::caller(),
+            trylock,
+        );
+    }
 }
 
 impl<G: BaseGuard, T: ?Sized + Default> Default for BaseSpinLock<G, T> {
     #[inline(always)]
+    #[cfg_attr(feature = "lockdep", track_caller)]
     fn default() -> Self {
         Self::new(Default::default())
     }
@@ -192,6 +230,46 @@ impl<G: BaseGuard, T: ?Sized + fmt::Debug> fmt::Debug for BaseSpinLock<G, T> {
     }
 }
 
+impl<'a, G: BaseGuard, T: ?Sized> BaseSpinLockGuard<'a, G, T> {
+    /// Temporarily unlocks the lock to execute the given function, then locks
+    /// it again.
+    ///
+    /// This is safe because `&mut` guarantees that there exist no other
+    /// references to the data protected by the lock.
+    #[cfg_attr(feature = "lockdep", track_caller)]
+    pub fn unlocked<F, U>(guard: &mut Self, f: F) -> U
+    where
+        F: FnOnce() -> U,
+    {
+        #[cfg(feature = "lockdep")]
+        lockdep::lock_release(guard.class);
+        #[cfg(feature = "smp")]
+        guard.lock.store(false, Ordering::Release);
+        G::release(guard.irq_state);
+        let ret = f();
+        guard.irq_state = G::acquire();
+        #[cfg(feature = "lockdep")]
+        lockdep::lock_acquire(
+            guard.class,
+            LockKind::Spin,
+            core::any::type_name::<BaseSpinLock<G, T>>(),
+            core::panic::Location::caller(),
+            false,
+        );
+        #[cfg(feature = "smp")]
+        while guard
+            .lock
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            while guard.lock.load(Ordering::Relaxed) {
+                core::hint::spin_loop();
+            }
+        }
+        ret
+    }
+}
+
 impl<'a, G: BaseGuard, T: ?Sized> Deref for BaseSpinLockGuard<'a, G, T> {
     type Target = T;
     #[inline(always)]
@@ -220,6 +298,8 @@ impl<'a, G: BaseGuard, T: ?Sized> Drop for BaseSpinLockGuard<'a, G, T> {
     /// created from.
     #[inline(always)]
     fn drop(&mut self) {
+        #[cfg(feature = "lockdep")]
+        lockdep::lock_release(self.class);
         #[cfg(feature = "smp")]
         self.lock.store(false, Ordering::Release);
         G::release(self.irq_state);
@@ -245,6 +325,18 @@ mod tests {
         drop(m.lock());
     }
 
+    #[test]
+    fn unlocked() {
+        let m = SpinMutex::<_>::new(1);
+        let mut g = m.lock();
+        crate::SpinRawGuard::unlocked(&mut g, || {
+            *m.lock() += 1;
+        });
+        *g += 1;
+        drop(g);
+        assert_eq!(*m.lock(), 3);
+    }
+
     #[test]
     #[cfg(feature = "smp")]
     fn lots_and_lots() {
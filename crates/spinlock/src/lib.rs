@@ -7,6 +7,9 @@
 //!   environment (without this feature), the lock state is unnecessary and
 //!   optimized out. CPU can always get the lock if we follow the proper guard
 //!   in use. By default, this feature is disabled.
+//! - `lockdep`: Check the acquisitions of the locks by the `lockdep`
+//!   validator, to report potential deadlocks. The `LockdepIf` trait must be
+//!   implemented (e.g., by `axtask`). By default, this feature is disabled.
 
 #![cfg_attr(not(test), no_std)]
 
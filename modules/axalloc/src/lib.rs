@@ -4,6 +4,59 @@
 //! [`core::alloc::GlobalAlloc`]. A static global variable of type
 //! [`GlobalAllocator`] is defined with the `#[global_allocator]` attribute, to
 //! be registered as the standard library’s default allocator.
+//!
+//! # Cargo Features
+//!
+//! The byte allocator used by [`GlobalAllocator`] is selected by one of the
+//! following features. If more than one is enabled, the first one in the
+//! list below takes effect.
+//!
+//! - `alloc-tlsf`: Use [`TlsfByteAllocator`], with bounded allocation time
+//!   for real-time workloads.
+//! - `alloc-buddy`: Use [`BuddyByteAllocator`].
+//! - `alloc-first-fit`: Use [`FirstFitByteAllocator`], which has little
+//!   metadata overhead, for small memory.
+//! - `alloc-slab`: Use [`SlabByteAllocator`]. This is the default.
+//!
+//! Similarly, the page allocator is selected by one of the following features:
+//!
+//! - `page-alloc-buddy`: Use [`BuddyPageAllocator`], which is faster for large
+//!   contiguous allocations and fragments less. It stores its metadata in the
+//!   managed memory.
+//! - `page-alloc-bitmap`: Use [`BitmapPageAllocator`]. This is the default.
+//!
+//! # Memory Zones
+//!
+//! The physical memory is divided into zones by the DMA address limits of the
+//! platform (see [`MemZone`]), each managed by its own page allocator.
+//! Allocations in a zone can also be served from the lower zones, and the
+//! heap memory is allocated from the [`MemZone::Normal`] zone first, so the
+//! low memory is kept for the devices that need it (see
+//! [`GlobalAllocator::alloc_pages_in`]).
+//!
+//! # Heap Shrinking
+//!
+//! With the `heap-reclaim` feature, the free pages in the heap memory
+//! allocated from the page allocator are given back, either explicitly by
+//! [`reclaim`], or automatically by the next page allocation after a large
+//! deallocation leaves too much free memory in the heap. It requires the
+//! first-fit byte allocator, which can remove free memory, so the feature
+//! enables `alloc-first-fit`, and conflicts with `alloc-tlsf` and
+//! `alloc-buddy`.
+//!
+//! # Allocation Tracing
+//!
+//! With the `alloc-trace` feature, each live allocation through the global
+//! allocator is recorded with its size, task and callers, to find memory
+//! leaks (see [`dump_leaks`] and [`dump_usage`]). It requires the upper layer
+//! to implement [`AllocTraceIf`]. There is no overhead without the feature.
+//!
+//! [`TlsfByteAllocator`]: allocator::TlsfByteAllocator
+//! [`BuddyByteAllocator`]: allocator::BuddyByteAllocator
+//! [`FirstFitByteAllocator`]: allocator::FirstFitByteAllocator
+//! [`SlabByteAllocator`]: allocator::SlabByteAllocator
+//! [`BuddyPageAllocator`]: allocator::BuddyPageAllocator
+//! [`BitmapPageAllocator`]: allocator::BitmapPageAllocator
 
 #![no_std]
 
@@ -11,17 +64,141 @@
 extern crate log;
 extern crate alloc;
 
+#[cfg(all(
+    feature = "heap-reclaim",
+    any(feature = "alloc-tlsf", feature = "alloc-buddy")
+))]
+compile_error!("the `heap-reclaim` feature requires the first-fit byte allocator");
+
 mod page;
+#[cfg(feature = "alloc-trace")]
+mod trace;
+mod zone;
 
-use allocator::{AllocResult, BaseAllocator, ByteAllocator, PageAllocator};
-use allocator::{BitmapPageAllocator, SlabByteAllocator};
+use allocator::{AllocError, AllocResult, BaseAllocator, ByteAllocator};
 use core::alloc::{GlobalAlloc, Layout};
+#[cfg(feature = "heap-reclaim")]
+use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "heap-reclaim")]
+use memory_addr::{align_down, align_up};
 use spinlock::SpinNoIrq;
 
+use self::zone::ZoneAllocator;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "alloc-tlsf")] {
+        use allocator::TlsfByteAllocator as DefaultByteAllocator;
+    } else if #[cfg(feature = "alloc-buddy")] {
+        use allocator::BuddyByteAllocator as DefaultByteAllocator;
+    } else if #[cfg(feature = "alloc-first-fit")] {
+        use allocator::FirstFitByteAllocator as DefaultByteAllocator;
+    } else {
+        use allocator::SlabByteAllocator as DefaultByteAllocator;
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "page-alloc-buddy")] {
+        use allocator::BuddyPageAllocator as DefaultPageAllocator;
+    } else {
+        use allocator::BitmapPageAllocator as DefaultPageAllocator;
+    }
+}
+
 const PAGE_SIZE: usize = 0x1000;
 const MIN_HEAP_SIZE: usize = 0x8000; // 32 K
+#[cfg(feature = "heap-reclaim")]
+const MAX_HEAP_CHUNKS: usize = 64;
+#[cfg(feature = "heap-reclaim")]
+const HEAP_RECLAIM_WATERMARK: usize = 0x40_0000; // 4 M
 
 pub use page::GlobalPage;
+#[cfg(feature = "alloc-trace")]
+pub use trace::{dump_leaks, dump_usage, start_leak_check, task_usage, AllocTraceIf, AllocUsage};
+pub use zone::MemZone;
+
+/// The heap memory allocated from the page allocator, as sorted and disjoint
+/// `[start, end)` chunks, whose free pages can be given back.
+#[cfg(feature = "heap-reclaim")]
+struct HeapChunks {
+    chunks: [(usize, usize); MAX_HEAP_CHUNKS],
+    len: usize,
+}
+
+#[cfg(feature = "heap-reclaim")]
+impl HeapChunks {
+    const fn new() -> Self {
+        Self {
+            chunks: [(0, 0); MAX_HEAP_CHUNKS],
+            len: 0,
+        }
+    }
+
+    /// Returns the index of the first chunk that ends after `pos`.
+    fn index_after(&self, pos: usize) -> usize {
+        self.chunks[..self.len].partition_point(|&(_, end)| end <= pos)
+    }
+
+    fn insert_at(&mut self, idx: usize, chunk: (usize, usize)) -> bool {
+        if self.len == MAX_HEAP_CHUNKS {
+            return false;
+        }
+        self.chunks.copy_within(idx..self.len, idx + 1);
+        self.chunks[idx] = chunk;
+        self.len += 1;
+        true
+    }
+
+    fn remove_at(&mut self, idx: usize) {
+        self.chunks.copy_within(idx + 1..self.len, idx);
+        self.len -= 1;
+    }
+
+    /// Returns the first part of `[start, end)` that is in a chunk.
+    fn first_overlap(&self, start: usize, end: usize) -> Option<(usize, usize)> {
+        let &(chunk_start, chunk_end) = self.chunks[..self.len].get(self.index_after(start))?;
+        let (start, end) = (start.max(chunk_start), end.min(chunk_end));
+        (start < end).then_some((start, end))
+    }
+
+    /// Records the chunk `[start, end)`, merged with the adjacent ones.
+    /// Returns `false` if there is no space.
+    fn insert(&mut self, start: usize, end: usize) -> bool {
+        let idx = self.index_after(start.saturating_sub(1));
+        let merge_prev = idx < self.len && self.chunks[idx].1 == start;
+        let next = if merge_prev { idx + 1 } else { idx };
+        let merge_next = next < self.len && self.chunks[next].0 == end;
+        match (merge_prev, merge_next) {
+            (true, true) => {
+                self.chunks[idx].1 = self.chunks[next].1;
+                self.remove_at(next);
+            }
+            (true, false) => self.chunks[idx].1 = end,
+            (false, true) => self.chunks[next].0 = start,
+            (false, false) => return self.insert_at(idx, (start, end)),
+        }
+        true
+    }
+
+    /// Removes `[start, end)`, which must be inside a chunk. Returns `false`
+    /// if there is no space to split the chunk.
+    fn remove(&mut self, start: usize, end: usize) -> bool {
+        let idx = self.index_after(start);
+        let (chunk_start, chunk_end) = self.chunks[idx];
+        match (chunk_start < start, end < chunk_end) {
+            (true, true) => {
+                if !self.insert_at(idx + 1, (end, chunk_end)) {
+                    return false;
+                }
+                self.chunks[idx].1 = start;
+            }
+            (true, false) => self.chunks[idx].1 = start,
+            (false, true) => self.chunks[idx].0 = end,
+            (false, false) => self.remove_at(idx),
+        }
+        true
+    }
+}
 
 /// The global allocator used by ArceOS.
 ///
@@ -30,35 +207,89 @@ pub use page::GlobalPage;
 /// there is no memory, asks the page allocator for more memory and adds it to
 /// the byte allocator.
 ///
-/// Currently, [`SlabByteAllocator`] is used as the byte allocator, while
-/// [`BitmapPageAllocator`] is used as the page allocator.
+/// With the `heap-reclaim` feature, the heap memory added from the page
+/// allocator can be given back when it becomes free again (see
+/// [`GlobalAllocator::reclaim`]).
+///
+/// There is a page allocator for each memory zone (see [`MemZone`]), and the
+/// pages are given back to the zone whose memory regions contain them.
+///
+/// Both the byte allocator and the page allocator are selected by cargo
+/// features (see the [crate-level documentation](crate)).
+///
+/// [`PageAllocator`]: allocator::PageAllocator
 pub struct GlobalAllocator {
-    balloc: SpinNoIrq<SlabByteAllocator>,
-    palloc: SpinNoIrq<BitmapPageAllocator<PAGE_SIZE>>,
+    balloc: SpinNoIrq<DefaultByteAllocator>,
+    palloc: [SpinNoIrq<ZoneAllocator>; MemZone::COUNT],
+    /// Always locked after `balloc`.
+    #[cfg(feature = "heap-reclaim")]
+    heap_chunks: SpinNoIrq<HeapChunks>,
+    /// Whether there is too much free memory in the heap, which is reclaimed
+    /// by the next page allocation.
+    #[cfg(feature = "heap-reclaim")]
+    reclaim_pending: AtomicBool,
 }
 
 impl GlobalAllocator {
     /// Creates an empty [`GlobalAllocator`].
     pub const fn new() -> Self {
         Self {
-            balloc: SpinNoIrq::new(SlabByteAllocator::new()),
-            palloc: SpinNoIrq::new(BitmapPageAllocator::new()),
+            balloc: SpinNoIrq::new(DefaultByteAllocator::new()),
+            palloc: [
+                SpinNoIrq::new(ZoneAllocator::new()),
+                SpinNoIrq::new(ZoneAllocator::new()),
+                SpinNoIrq::new(ZoneAllocator::new()),
+            ],
+            #[cfg(feature = "heap-reclaim")]
+            heap_chunks: SpinNoIrq::new(HeapChunks::new()),
+            #[cfg(feature = "heap-reclaim")]
+            reclaim_pending: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns the name of the byte allocator.
+    pub const fn name(&self) -> &'static str {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "alloc-tlsf")] {
+                "TLSF"
+            } else if #[cfg(feature = "alloc-buddy")] {
+                "buddy"
+            } else if #[cfg(feature = "alloc-first-fit")] {
+                "first-fit"
+            } else {
+                "slab"
+            }
         }
     }
 
-    /// Initializes the allocator with the given region.
+    /// Returns the name of the page allocator.
+    pub const fn page_allocator_name(&self) -> &'static str {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "page-alloc-buddy")] {
+                "buddy"
+            } else {
+                "bitmap"
+            }
+        }
+    }
+
+    /// Initializes the allocator with the given region in the memory `zone`.
     ///
-    /// It firstly adds the whole region to the page allocator, then allocates
-    /// a small region (32 KB) to initialize the byte allocator. Therefore,
-    /// the given region must be larger than 32 KB.
-    pub fn init(&self, start_vaddr: usize, size: usize) {
+    /// It firstly adds the whole region to the page allocator of the zone,
+    /// then allocates a small region (32 KB) to initialize the byte allocator.
+    /// Therefore, the given region must be larger than 32 KB.
+    pub fn init(&self, zone: MemZone, start_vaddr: usize, size: usize) {
         assert!(size > MIN_HEAP_SIZE);
         let init_heap_size = MIN_HEAP_SIZE;
-        self.palloc.lock().init(start_vaddr, size);
+        self.add_zone_memory(zone, start_vaddr, size).unwrap();
         let heap_ptr = self
-            .alloc_pages(init_heap_size / PAGE_SIZE, PAGE_SIZE)
+            .alloc_zone_pages(MemZone::Normal, init_heap_size / PAGE_SIZE, PAGE_SIZE)
             .unwrap();
         self.balloc.lock().init(heap_ptr, init_heap_size);
+        #[cfg(feature = "heap-reclaim")]
+        self.heap_chunks
+            .lock()
+            .insert(heap_ptr, heap_ptr + init_heap_size);
     }
 
     /// Add the given region to the allocator.
@@ -68,6 +299,17 @@ impl GlobalAllocator {
         self.balloc.lock().add_memory(start_vaddr, size)
     }
 
+    /// Add the given region to the page allocator of the memory `zone`.
+    ///
+    /// The region must be in the physical address range of the zone. It may
+    /// fail if the page allocator does not support multiple regions (e.g.,
+    /// the bitmap allocator), and the zone already has one.
+    pub fn add_zone_memory(&self, zone: MemZone, start_vaddr: usize, size: usize) -> AllocResult {
+        self.palloc[zone as usize]
+            .lock()
+            .add_memory(start_vaddr, size)
+    }
+
     /// Allocate arbitrary number of bytes. Returns the left bound of the
     /// allocated region.
     ///
@@ -86,36 +328,151 @@ impl GlobalAllocator {
             } else {
                 let old_size = balloc.total_bytes();
                 let expand_size = old_size.max(size).next_power_of_two().max(PAGE_SIZE);
-                let heap_ptr = self.alloc_pages(expand_size / PAGE_SIZE, PAGE_SIZE)?;
+                let heap_ptr =
+                    self.alloc_zone_pages(MemZone::Normal, expand_size / PAGE_SIZE, PAGE_SIZE)?;
                 debug!(
                     "expand heap memory: [{:#x}, {:#x})",
                     heap_ptr,
                     heap_ptr + expand_size
                 );
                 balloc.add_memory(heap_ptr, expand_size)?;
+                #[cfg(feature = "heap-reclaim")]
+                if !self
+                    .heap_chunks
+                    .lock()
+                    .insert(heap_ptr, heap_ptr + expand_size)
+                {
+                    warn!(
+                        "too many heap chunks, [{:#x}, {:#x}) cannot be reclaimed",
+                        heap_ptr,
+                        heap_ptr + expand_size
+                    );
+                }
             }
         }
     }
 
     /// Gives back the allocated region to the byte allocator.
     ///
+    /// With the `heap-reclaim` feature, if the region is not smaller than a
+    /// page, and the free memory in the heap exceeds both the used memory and
+    /// a watermark (4 MB) after that, the free heap memory is given back to
+    /// the page allocator by the next page allocation (see [`reclaim`]).
+    ///
     /// The region should be allocated by [`alloc`], and `align_pow2` should be
     /// the same as the one used in [`alloc`]. Otherwise, the behavior is
     /// undefined.
     ///
     /// [`alloc`]: GlobalAllocator::alloc
+    /// [`reclaim`]: GlobalAllocator::reclaim
     pub fn dealloc(&self, pos: usize, size: usize, align_pow2: usize) {
-        self.balloc.lock().dealloc(pos, size, align_pow2)
+        let mut balloc = self.balloc.lock();
+        balloc.dealloc(pos, size, align_pow2);
+        // Scanning the free blocks is too slow with the lock held here.
+        #[cfg(feature = "heap-reclaim")]
+        if size >= PAGE_SIZE
+            && balloc.available_bytes() > balloc.used_bytes().max(HEAP_RECLAIM_WATERMARK)
+        {
+            self.reclaim_pending.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Gives back the free heap memory to the page allocator. Returns the
+    /// number of pages reclaimed.
+    ///
+    /// Only the memory that was allocated from the page allocator, by
+    /// [`init`] or by [`alloc`] when the heap was out of memory, is reclaimed.
+    /// It is given back in whole free pages.
+    ///
+    /// [`init`]: GlobalAllocator::init
+    /// [`alloc`]: GlobalAllocator::alloc
+    #[cfg(feature = "heap-reclaim")]
+    pub fn reclaim(&self) -> usize {
+        let mut balloc = self.balloc.lock();
+        let mut heap_chunks = self.heap_chunks.lock();
+        self.reclaim_pending.store(false, Ordering::Relaxed);
+        let mut num_pages = 0;
+        // The free blocks are sorted by address, so the free pages are found
+        // in order, and the search resumes from the end of the last ones.
+        let mut cursor = 0;
+        loop {
+            let found = balloc.free_blocks().find_map(|(pos, size)| {
+                let start = align_up(pos.max(cursor), PAGE_SIZE);
+                let end = align_down(pos + size, PAGE_SIZE);
+                heap_chunks.first_overlap(start, end)
+            });
+            let Some((start, end)) = found else {
+                break;
+            };
+            cursor = end;
+            if !heap_chunks.remove(start, end) {
+                continue; // too many chunks to split one
+            }
+            balloc.remove_memory(start, end - start).unwrap();
+            debug!("shrink heap memory: [{:#x}, {:#x})", start, end);
+            self.dealloc_pages(start, (end - start) / PAGE_SIZE);
+            num_pages += (end - start) / PAGE_SIZE;
+        }
+        num_pages
     }
 
     /// Allocates contiguous pages.
     ///
-    /// It allocates `num_pages` pages from the page allocator.
+    /// It allocates `num_pages` pages from the page allocator, in any memory
+    /// zone. It's the same as [`alloc_pages_in`] with [`MemZone::Normal`].
     ///
     /// `align_pow2` must be a power of 2, and the returned region bound will be
     /// aligned to it.
+    ///
+    /// [`alloc_pages_in`]: GlobalAllocator::alloc_pages_in
     pub fn alloc_pages(&self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
-        self.palloc.lock().alloc_pages(num_pages, align_pow2)
+        self.alloc_pages_in(MemZone::Normal, num_pages, align_pow2)
+    }
+
+    /// Allocates contiguous pages in the memory `zone` or lower zones.
+    ///
+    /// It firstly tries the page allocator of `zone`, then the lower zones
+    /// one by one if there is no memory, so that the memory with stricter
+    /// address limits is used last.
+    ///
+    /// With the `heap-reclaim` feature, it gives back the free heap memory to
+    /// the page allocator first, if a deallocation has left too much of it
+    /// (see [`dealloc`]).
+    ///
+    /// `align_pow2` must be a power of 2, and the returned region bound will be
+    /// aligned to it.
+    ///
+    /// [`dealloc`]: GlobalAllocator::dealloc
+    pub fn alloc_pages_in(
+        &self,
+        zone: MemZone,
+        num_pages: usize,
+        align_pow2: usize,
+    ) -> AllocResult<usize> {
+        #[cfg(feature = "heap-reclaim")]
+        if self.reclaim_pending.load(Ordering::Relaxed) {
+            let num_pages = self.reclaim();
+            debug!("reclaimed {} pages from the heap", num_pages);
+        }
+        self.alloc_zone_pages(zone, num_pages, align_pow2)
+    }
+
+    /// Allocates contiguous pages in the memory `zone` or lower zones, without
+    /// reclaiming the heap memory, so that it can be called with `balloc`
+    /// locked.
+    fn alloc_zone_pages(
+        &self,
+        zone: MemZone,
+        num_pages: usize,
+        align_pow2: usize,
+    ) -> AllocResult<usize> {
+        for palloc in self.palloc[..=zone as usize].iter().rev() {
+            match palloc.lock().alloc_pages(num_pages, align_pow2) {
+                Err(AllocError::NoMemory) => continue,
+                res => return res,
+            }
+        }
+        Err(AllocError::NoMemory)
     }
 
     /// Gives back the allocated pages starts from `pos` to the page allocator.
@@ -126,7 +483,13 @@ impl GlobalAllocator {
     ///
     /// [`alloc_pages`]: GlobalAllocator::alloc_pages
     pub fn dealloc_pages(&self, pos: usize, num_pages: usize) {
-        self.palloc.lock().dealloc_pages(pos, num_pages)
+        for palloc in &self.palloc {
+            let mut palloc = palloc.lock();
+            if palloc.contains(pos) {
+                return palloc.dealloc_pages(pos, num_pages);
+            }
+        }
+        error!("deallocate pages out of all memory zones at {:#x}", pos);
     }
 
     /// Returns the number of allocated bytes in the byte allocator.
@@ -141,18 +504,28 @@ impl GlobalAllocator {
 
     /// Returns the number of allocated pages in the page allocator.
     pub fn used_pages(&self) -> usize {
-        self.palloc.lock().used_pages()
+        self.palloc.iter().map(|p| p.lock().used_pages()).sum()
     }
 
     /// Returns the number of available pages in the page allocator.
     pub fn available_pages(&self) -> usize {
-        self.palloc.lock().available_pages()
+        self.palloc.iter().map(|p| p.lock().available_pages()).sum()
+    }
+
+    /// Returns the number of available pages in the page allocator of the
+    /// memory `zone`.
+    pub fn zone_available_pages(&self, zone: MemZone) -> usize {
+        self.palloc[zone as usize].lock().available_pages()
     }
 }
 
 unsafe impl GlobalAlloc for GlobalAllocator {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        if let Ok(ptr) = GlobalAllocator::alloc(self, layout.size(), layout.align()) {
+        #[cfg(feature = "alloc-trace")]
+        let res = trace::alloc(self, layout);
+        #[cfg(not(feature = "alloc-trace"))]
+        let res = GlobalAllocator::alloc(self, layout.size(), layout.align());
+        if let Ok(ptr) = res {
             ptr as _
         } else {
             alloc::alloc::handle_alloc_error(layout)
@@ -160,7 +533,10 @@ unsafe impl GlobalAlloc for GlobalAllocator {
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-        GlobalAllocator::dealloc(self, ptr as _, layout.size(), layout.align())
+        #[cfg(feature = "alloc-trace")]
+        trace::dealloc(self, ptr as _, layout);
+        #[cfg(not(feature = "alloc-trace"))]
+        GlobalAllocator::dealloc(self, ptr as _, layout.size(), layout.align());
     }
 }
 
@@ -172,7 +548,8 @@ pub fn global_allocator() -> &'static GlobalAllocator {
     &GLOBAL_ALLOCATOR
 }
 
-/// Initializes the global allocator with the given memory region.
+/// Initializes the global allocator with the given memory region in the
+/// memory `zone`.
 ///
 /// Note that the memory region bounds are just numbers, and the allocator
 /// does not actually access the region. Users should ensure that the region
@@ -180,13 +557,25 @@ pub fn global_allocator() -> &'static GlobalAllocator {
 /// valid.
 ///
 /// This function should be called only once, and before any allocation.
-pub fn global_init(start_vaddr: usize, size: usize) {
+pub fn global_init(zone: MemZone, start_vaddr: usize, size: usize) {
     debug!(
-        "initialize global allocator at: [{:#x}, {:#x})",
+        "initialize global allocator at: [{:#x}, {:#x}) in zone {}",
         start_vaddr,
-        start_vaddr + size
+        start_vaddr + size,
+        zone.name()
     );
-    GLOBAL_ALLOCATOR.init(start_vaddr, size);
+    GLOBAL_ALLOCATOR.init(zone, start_vaddr, size);
+}
+
+/// Gives back the free heap memory of the global allocator to its page
+/// allocator. Returns the number of pages reclaimed.
+///
+/// See [`GlobalAllocator::reclaim`] for details.
+#[cfg(feature = "heap-reclaim")]
+pub fn reclaim() -> usize {
+    let num_pages = GLOBAL_ALLOCATOR.reclaim();
+    debug!("reclaimed {} pages from the heap", num_pages);
+    num_pages
 }
 
 /// Add the given memory region to the global allocator.
@@ -203,3 +592,20 @@ pub fn global_add_memory(start_vaddr: usize, size: usize) -> AllocResult {
     );
     GLOBAL_ALLOCATOR.add_memory(start_vaddr, size)
 }
+
+/// Add the given memory region to the page allocator of the memory `zone` in
+/// the global allocator.
+///
+/// Users should ensure that the region is valid and not being used by others,
+/// and is in the physical address range of the zone.
+///
+/// See [`GlobalAllocator::add_zone_memory`] for details.
+pub fn global_add_zone_memory(zone: MemZone, start_vaddr: usize, size: usize) -> AllocResult {
+    debug!(
+        "add a memory region to zone {} of global allocator: [{:#x}, {:#x})",
+        zone.name(),
+        start_vaddr,
+        start_vaddr + size
+    );
+    GLOBAL_ALLOCATOR.add_zone_memory(zone, start_vaddr, size)
+}
@@ -2,7 +2,7 @@ use allocator::AllocError;
 use axerrno::{AxError, AxResult};
 use memory_addr::{PhysAddr, VirtAddr};
 
-use crate::{global_allocator, PAGE_SIZE};
+use crate::{global_allocator, MemZone, PAGE_SIZE};
 
 /// A RAII wrapper of contiguous 4K-sized pages.
 ///
@@ -43,6 +43,18 @@ impl GlobalPage {
             .map_err(alloc_err_to_ax_err)
     }
 
+    /// Allocate contiguous 4K-sized pages below 4 GB in physical memory, for
+    /// devices that can only do 32-bit DMA.
+    pub fn alloc_contiguous_dma32(num_pages: usize, align_pow2: usize) -> AxResult<Self> {
+        global_allocator()
+            .alloc_pages_in(MemZone::Dma32, num_pages, align_pow2)
+            .map(|vaddr| Self {
+                start_vaddr: vaddr.into(),
+                num_pages,
+            })
+            .map_err(alloc_err_to_ax_err)
+    }
+
     /// Get the start virtual address of this page.
     pub fn start_vaddr(&self) -> VirtAddr {
         self.start_vaddr
@@ -104,5 +116,6 @@ const fn alloc_err_to_ax_err(e: AllocError) -> AxError {
             AxError::InvalidInput
         }
         AllocError::NoMemory => AxError::NoMemory,
+        AllocError::MemoryInUse => AxError::ResourceBusy,
     }
 }
@@ -0,0 +1,342 @@
+//! Memory allocation tracing, to find memory leaks.
+//!
+//! Each allocation through [`GlobalAlloc`] is followed by a trailer, which
+//! records its size, the ID of the task that allocates it and the return
+//! addresses on the stack. The trailer is placed after the user data rather
+//! than before it, so that it does not waste the padding of a large
+//! alignment. The trailers of live allocations are linked in a list, which
+//! can be inspected by [`dump_leaks`], [`dump_usage`] and [`task_usage`].
+//! Allocations by [`GlobalAllocator::alloc`] and
+//! [`GlobalAllocator::alloc_pages`] directly are not traced.
+//!
+//! The task ID, the stack and the symbols are provided by the upper layer
+//! through [`AllocTraceIf`].
+//!
+//! [`GlobalAlloc`]: core::alloc::GlobalAlloc
+
+use core::alloc::Layout;
+use core::fmt;
+use core::ptr::null_mut;
+
+use allocator::{AllocError, AllocResult};
+use axlog::ax_println;
+use crate_interface::call_interface;
+use spinlock::SpinNoIrq;
+
+use crate::GlobalAllocator;
+
+/// The number of return addresses recorded for each allocation.
+const TRACE_DEPTH: usize = 8;
+
+/// The number of records copied out of the list at a time for printing.
+const DUMP_BATCH: usize = 16;
+
+/// The maximum number of tasks shown separately by [`dump_usage`].
+const MAX_USAGE_TASKS: usize = 32;
+
+/// Extern interfaces that must be implemented in other crates when the
+/// `alloc-trace` feature is enabled.
+#[crate_interface::def_interface]
+pub trait AllocTraceIf {
+    /// Gets current task ID.
+    ///
+    /// Returns [`None`] if there is no task (e.g., during initialization).
+    fn current_task_id() -> Option<u64>;
+
+    /// Fills `callers` with the return addresses on the current stack,
+    /// innermost first, by walking the frame pointers.
+    ///
+    /// Returns the number of return addresses filled.
+    fn backtrace(callers: &mut [usize]) -> usize;
+
+    /// Writes the code address `addr` with its symbol, for printing the
+    /// callers of an allocation.
+    fn fmt_caller(addr: usize, f: &mut fmt::Formatter) -> fmt::Result;
+}
+
+/// The number and total size of live allocations.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AllocUsage {
+    /// The number of live allocations.
+    pub count: usize,
+    /// The total size (in bytes) of live allocations, without the trace
+    /// trailers.
+    pub bytes: usize,
+}
+
+impl AllocUsage {
+    fn add(&mut self, size: usize) {
+        self.count += 1;
+        self.bytes += size;
+    }
+}
+
+/// The information recorded for each traced allocation.
+#[derive(Clone, Copy)]
+struct AllocRecord {
+    /// The sequence number of the allocation.
+    seq: u64,
+    task_id: Option<u64>,
+    /// The address returned to the user.
+    ptr: usize,
+    size: usize,
+    /// The innermost return addresses, which may be in the allocator itself.
+    callers: [usize; TRACE_DEPTH],
+}
+
+impl AllocRecord {
+    const EMPTY: Self = Self {
+        seq: 0,
+        task_id: None,
+        ptr: 0,
+        size: 0,
+        callers: [0; TRACE_DEPTH],
+    };
+}
+
+/// The trailer after each traced allocation.
+struct TraceTrailer {
+    prev: *mut TraceTrailer,
+    next: *mut TraceTrailer,
+    record: AllocRecord,
+}
+
+/// The list of live allocations.
+struct TraceList {
+    head: *mut TraceTrailer,
+    next_seq: u64,
+    /// The sequence number when the last leak check started.
+    checkpoint: u64,
+}
+
+unsafe impl Send for TraceList {}
+
+impl TraceList {
+    const fn new() -> Self {
+        Self {
+            head: null_mut(),
+            next_seq: 0,
+            checkpoint: 0,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `trailer` must be valid and not in the list.
+    unsafe fn push(&mut self, trailer: *mut TraceTrailer) {
+        (*trailer).prev = null_mut();
+        (*trailer).next = self.head;
+        if !self.head.is_null() {
+            (*self.head).prev = trailer;
+        }
+        self.head = trailer;
+    }
+
+    /// # Safety
+    ///
+    /// `trailer` must be in the list.
+    unsafe fn remove(&mut self, trailer: *mut TraceTrailer) {
+        let TraceTrailer { prev, next, .. } = *trailer;
+        if prev.is_null() {
+            self.head = next;
+        } else {
+            (*prev).next = next;
+        }
+        if !next.is_null() {
+            (*next).prev = prev;
+        }
+    }
+
+    /// Iterates over the records of live allocations, from the newest one.
+    fn iter(&self) -> impl Iterator<Item = &AllocRecord> {
+        let mut curr = self.head;
+        core::iter::from_fn(move || {
+            // Safety: the trailers in the list are valid while it is locked.
+            let trailer = unsafe { curr.as_ref()? };
+            curr = trailer.next;
+            Some(&trailer.record)
+        })
+    }
+}
+
+static TRACE: SpinNoIrq<TraceList> = SpinNoIrq::new(TraceList::new());
+
+/// Returns the layout with the trailer appended, and the offset of the
+/// trailer in it.
+fn layout_with_trailer(layout: Layout) -> AllocResult<(Layout, usize)> {
+    layout
+        .extend(Layout::new::<TraceTrailer>())
+        .map_err(|_| AllocError::InvalidParam)
+}
+
+/// Allocates memory with a trace trailer, and records it in the list.
+pub(crate) fn alloc(a: &GlobalAllocator, layout: Layout) -> AllocResult<usize> {
+    let (outer, offset) = layout_with_trailer(layout)?;
+    let mut callers = [0; TRACE_DEPTH];
+    let buf: &mut [usize] = &mut callers;
+    call_interface!(AllocTraceIf::backtrace, buf);
+    let task_id = call_interface!(AllocTraceIf::current_task_id);
+
+    let pos = a.alloc(outer.size(), outer.align())?;
+    let trailer = (pos + offset) as *mut TraceTrailer;
+    let mut trace = TRACE.lock();
+    unsafe {
+        trailer.write(TraceTrailer {
+            prev: null_mut(),
+            next: null_mut(),
+            record: AllocRecord {
+                seq: trace.next_seq,
+                task_id,
+                ptr: pos,
+                size: layout.size(),
+                callers,
+            },
+        });
+        trace.push(trailer);
+    }
+    trace.next_seq += 1;
+    Ok(pos)
+}
+
+/// Removes the allocation at `ptr` from the list, and deallocates it with its
+/// trace trailer.
+pub(crate) fn dealloc(a: &GlobalAllocator, ptr: usize, layout: Layout) {
+    let (outer, offset) = layout_with_trailer(layout).unwrap();
+    unsafe { TRACE.lock().remove((ptr + offset) as *mut TraceTrailer) };
+    a.dealloc(ptr, outer.size(), outer.align());
+}
+
+struct Caller(usize);
+
+impl fmt::Display for Caller {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        call_interface!(AllocTraceIf::fmt_caller, self.0, f)
+    }
+}
+
+struct TaskName(Option<u64>);
+
+impl fmt::Display for TaskName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(id) => write!(f, "task {}", id),
+            None => f.write_str("no task"),
+        }
+    }
+}
+
+/// Starts a leak check, so that [`dump_leaks`] only reports the allocations
+/// made after this call.
+pub fn start_leak_check() {
+    let mut trace = TRACE.lock();
+    trace.checkpoint = trace.next_seq;
+}
+
+/// Prints the allocations that are made after the last [`start_leak_check`]
+/// (or since boot) and are still alive, with their sizes, tasks and callers.
+///
+/// Returns the number of such allocations.
+pub fn dump_leaks() -> usize {
+    let checkpoint = TRACE.lock().checkpoint;
+    let mut leaks = AllocUsage::default();
+    // The records are copied out in batches, from the newest one, and
+    // printed without holding the lock.
+    let mut batch = [AllocRecord::EMPTY; DUMP_BATCH];
+    let mut last_seq = u64::MAX;
+    loop {
+        let mut len = 0;
+        for record in TRACE
+            .lock()
+            .iter()
+            .filter(|r| r.seq >= checkpoint && r.seq < last_seq)
+            .take(DUMP_BATCH)
+        {
+            batch[len] = *record;
+            len += 1;
+        }
+        if len == 0 {
+            break;
+        }
+        for record in &batch[..len] {
+            if leaks.count == 0 {
+                ax_println!("Live allocations since #{}:", checkpoint);
+            }
+            ax_println!(
+                "  #{} {:#x}: {} bytes, {}, callers:",
+                record.seq,
+                record.ptr,
+                record.size,
+                TaskName(record.task_id),
+            );
+            for &ra in record.callers.iter().take_while(|&&ra| ra != 0) {
+                ax_println!("    {}", Caller(ra));
+            }
+            leaks.add(record.size);
+        }
+        last_seq = batch[len - 1].seq;
+    }
+    ax_println!(
+        "{} leaked allocations, {} bytes in total.",
+        leaks.count,
+        leaks.bytes
+    );
+    leaks.count
+}
+
+/// Returns the live allocations made by the given task, or made without a
+/// task if `task_id` is [`None`].
+pub fn task_usage(task_id: Option<u64>) -> AllocUsage {
+    let mut usage = AllocUsage::default();
+    for record in TRACE.lock().iter().filter(|r| r.task_id == task_id) {
+        usage.add(record.size);
+    }
+    usage
+}
+
+/// Prints the live allocations of each task.
+///
+/// Only the first 32 tasks found are shown separately, the others are summed
+/// up together.
+pub fn dump_usage() {
+    let mut tasks = [(None, AllocUsage::default()); MAX_USAGE_TASKS];
+    let mut num_tasks = 0;
+    let mut others = AllocUsage::default();
+    let mut total = AllocUsage::default();
+    // Sum up under the lock, and print after releasing it.
+    for record in TRACE.lock().iter() {
+        total.add(record.size);
+        if let Some((_, usage)) = tasks[..num_tasks]
+            .iter_mut()
+            .find(|(id, _)| *id == record.task_id)
+        {
+            usage.add(record.size);
+        } else if num_tasks < MAX_USAGE_TASKS {
+            tasks[num_tasks] = (record.task_id, AllocUsage::default());
+            tasks[num_tasks].1.add(record.size);
+            num_tasks += 1;
+        } else {
+            others.add(record.size);
+        }
+    }
+
+    ax_println!(
+        "Live allocations: {}, {} bytes in total.",
+        total.count,
+        total.bytes
+    );
+    for (id, usage) in &tasks[..num_tasks] {
+        ax_println!(
+            "  {}: {} allocations, {} bytes",
+            TaskName(*id),
+            usage.count,
+            usage.bytes
+        );
+    }
+    if others.count > 0 {
+        ax_println!(
+            "  other tasks: {} allocations, {} bytes",
+            others.count,
+            others.bytes
+        );
+    }
+}
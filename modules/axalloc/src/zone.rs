@@ -0,0 +1,102 @@
+//! Physical memory zones.
+
+use allocator::{AllocError, AllocResult, BaseAllocator, PageAllocator};
+
+use crate::{DefaultPageAllocator, PAGE_SIZE};
+
+/// The maximum number of memory regions in a zone.
+const MAX_ZONE_REGIONS: usize = 16;
+
+/// A memory zone, i.e., a range of physical memory that suits some kinds of
+/// page allocations.
+///
+/// The address limits of the zones depend on the platform, which marks its
+/// memory regions with their zones. An allocation in a zone can also be
+/// served from the lower zones, as their memory satisfies stricter address
+/// limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MemZone {
+    /// Physical memory for legacy devices (e.g., ISA DMA, below 16 MB).
+    Dma = 0,
+    /// Physical memory for devices that can only do 32-bit DMA (below 4 GB).
+    Dma32 = 1,
+    /// All the other physical memory.
+    Normal = 2,
+}
+
+impl MemZone {
+    /// The number of zones.
+    pub const COUNT: usize = 3;
+
+    /// All zones, from the lowest one.
+    pub const ALL: [Self; Self::COUNT] = [Self::Dma, Self::Dma32, Self::Normal];
+
+    /// Returns the name of the zone.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Dma => "DMA",
+            Self::Dma32 => "DMA32",
+            Self::Normal => "Normal",
+        }
+    }
+}
+
+/// The page allocator of a zone.
+pub(crate) struct ZoneAllocator {
+    inner: DefaultPageAllocator<PAGE_SIZE>,
+    /// The virtual address ranges of the memory regions in the zone.
+    regions: [(usize, usize); MAX_ZONE_REGIONS],
+    num_regions: usize,
+}
+
+impl ZoneAllocator {
+    pub const fn new() -> Self {
+        Self {
+            inner: DefaultPageAllocator::new(),
+            regions: [(0, 0); MAX_ZONE_REGIONS],
+            num_regions: 0,
+        }
+    }
+
+    const fn is_empty(&self) -> bool {
+        self.num_regions == 0
+    }
+
+    pub fn contains(&self, vaddr: usize) -> bool {
+        self.regions[..self.num_regions]
+            .iter()
+            .any(|&(start, end)| vaddr >= start && vaddr < end)
+    }
+
+    pub fn add_memory(&mut self, start: usize, size: usize) -> AllocResult {
+        if self.is_empty() {
+            self.inner.init(start, size);
+        } else if self.num_regions == MAX_ZONE_REGIONS {
+            return Err(AllocError::NoMemory); // too many regions
+        } else {
+            self.inner.add_memory(start, size)?;
+        }
+        self.regions[self.num_regions] = (start, start + size);
+        self.num_regions += 1;
+        Ok(())
+    }
+
+    pub fn alloc_pages(&mut self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
+        if self.is_empty() {
+            return Err(AllocError::NoMemory);
+        }
+        self.inner.alloc_pages(num_pages, align_pow2)
+    }
+
+    pub fn dealloc_pages(&mut self, pos: usize, num_pages: usize) {
+        self.inner.dealloc_pages(pos, num_pages)
+    }
+
+    pub fn used_pages(&self) -> usize {
+        self.inner.used_pages()
+    }
+
+    pub fn available_pages(&self) -> usize {
+        self.inner.available_pages()
+    }
+}
@@ -0,0 +1,58 @@
+use std::alloc::{alloc, dealloc, Layout};
+
+use axalloc::{GlobalAllocator, MemZone};
+
+const PAGE_SIZE: usize = 0x1000;
+const POOL_SIZE: usize = 0x100_0000;
+
+#[test]
+fn test_reclaim() {
+    let layout = Layout::from_size_align(POOL_SIZE, PAGE_SIZE).unwrap();
+    let pool = unsafe { alloc(layout) } as usize;
+    // Too large for the stack.
+    static A: GlobalAllocator = GlobalAllocator::new();
+    let a = &A;
+    a.init(MemZone::Normal, pool, POOL_SIZE);
+    let total_pages = a.available_pages() + a.used_pages();
+
+    // Expand the heap, then give all of it back, including the initial heap.
+    let pos = a.alloc(0x10000, 8).unwrap();
+    assert!(a.used_pages() > 0x10000 / PAGE_SIZE);
+    a.dealloc(pos, 0x10000, 8);
+    let used_pages = a.used_pages();
+    assert_eq!(a.reclaim(), used_pages);
+    assert_eq!(a.used_pages(), 0);
+    assert_eq!(a.available_pages(), total_pages);
+
+    // Only whole free pages are given back.
+    let small = a.alloc(16, 8).unwrap();
+    let blocks: Vec<_> = (0..4).map(|_| a.alloc(0x2000, 8).unwrap()).collect();
+    a.dealloc(blocks[1], 0x2000, 8);
+    a.dealloc(blocks[2], 0x2000, 8);
+    let used_pages = a.used_pages();
+    let reclaimed = a.reclaim();
+    assert!(reclaimed >= 3);
+    assert_eq!(a.used_pages(), used_pages - reclaimed);
+    assert_eq!(a.reclaim(), 0);
+
+    // The reclaimed pages can be used by the heap again.
+    let pos = a.alloc(0x4000, 8).unwrap();
+    unsafe { core::ptr::write_bytes(pos as *mut u8, 0xff, 0x4000) };
+    a.dealloc(pos, 0x4000, 8);
+    a.dealloc(small, 16, 8);
+    a.dealloc(blocks[0], 0x2000, 8);
+    a.dealloc(blocks[3], 0x2000, 8);
+    assert_eq!(a.used_bytes(), 0);
+    a.reclaim();
+    assert_eq!(a.used_pages(), 0);
+
+    // A large deallocation leaves the free memory to the next page allocation.
+    let pos = a.alloc(0x50_0000, 8).unwrap();
+    a.dealloc(pos, 0x50_0000, 8);
+    assert!(a.used_pages() > 0x50_0000 / PAGE_SIZE);
+    let page = a.alloc_pages(1, PAGE_SIZE).unwrap();
+    assert_eq!(a.used_pages(), 1);
+    a.dealloc_pages(page, 1);
+
+    unsafe { dealloc(pool as *mut u8, layout) };
+}
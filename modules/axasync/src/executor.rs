@@ -0,0 +1,93 @@
+use alloc::sync::Arc;
+use core::future::Future;
+use core::pin::{pin, Pin};
+use core::task::{Context, Poll, Waker};
+
+use spinlock::SpinNoIrq;
+
+use crate::waker::TaskWaker;
+
+/// Runs a future to completion on the current task.
+///
+/// The current task is blocked while the future is pending, until the waker
+/// passed to the future is woken.
+pub fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = pin!(fut);
+    let task_waker = Arc::new(TaskWaker::new(axtask::current().as_task_ref().clone()));
+    let waker = Waker::from(task_waker.clone());
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = fut.as_mut().poll(&mut cx) {
+            return output;
+        }
+        task_waker.wait();
+    }
+}
+
+/// Spawns a new task to run the given future, returns a [`JoinHandle`] for
+/// its output.
+///
+/// # Examples
+///
+/// ```no_run
+/// let handle = axasync::spawn(async { 42 });
+/// assert_eq!(handle.join(), 42);
+/// ```
+pub fn spawn<F>(fut: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let state = Arc::new(SpinNoIrq::new(JoinState {
+        output: None,
+        waker: None,
+    }));
+    let state2 = state.clone();
+    axtask::spawn(move || {
+        let output = block_on(fut);
+        let waker = {
+            let mut state = state2.lock();
+            state.output = Some(output);
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    });
+    JoinHandle { state }
+}
+
+struct JoinState<T> {
+    output: Option<T>,
+    waker: Option<Waker>,
+}
+
+/// A handle to the future spawned by [`spawn`].
+///
+/// It is a future that resolves to the output of the spawned one. The
+/// spawned future keeps running if the handle is dropped.
+pub struct JoinHandle<T> {
+    state: Arc<SpinNoIrq<JoinState<T>>>,
+}
+
+impl<T> JoinHandle<T> {
+    /// Blocks the current task until the spawned future completes, and
+    /// returns its output.
+    pub fn join(self) -> T {
+        block_on(self)
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.state.lock();
+        if let Some(output) = state.output.take() {
+            Poll::Ready(output)
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! [ArceOS](https://github.com/rcore-os/arceos) async runtime.
+//!
+//! It runs futures on top of [axtask] tasks, rather than having a separate
+//! scheduler for them:
+//!
+//! - [`block_on`]: Runs a future to completion on the current task. The task
+//!   sleeps in a [`WaitQueue`](axtask::WaitQueue) while the future is pending,
+//!   and is woken up by the [`Waker`](core::task::Waker) of the future.
+//! - [`spawn`]: Runs a future on a new task, and returns a [`JoinHandle`]
+//!   which can be awaited or joined for its output.
+//! - [`sleep`], [`sleep_until`] and [`timeout`]: Async timers on top of the
+//!   kernel timers in [`axtask::timer`].
+//! - [`yield_now`]: Yields once to the executor.
+//!
+//! # Cargo Features
+//!
+//! - `irq`: Enable the async timers, which need timer interrupts.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! let handle = axasync::spawn(async { 1 + 1 });
+//! let result = axasync::block_on(async move { handle.await * 2 });
+//! assert_eq!(result, 4);
+//! ```
+
+#![cfg_attr(not(test), no_std)]
+#![feature(doc_cfg)]
+
+extern crate alloc;
+
+mod executor;
+mod waker;
+
+#[cfg(feature = "irq")]
+mod time;
+
+#[cfg(test)]
+mod tests;
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+pub use self::executor::{block_on, spawn, JoinHandle};
+
+#[cfg(feature = "irq")]
+#[doc(cfg(feature = "irq"))]
+pub use self::time::{sleep, sleep_until, timeout, Elapsed, Sleep};
+
+/// Yields once to the executor, so that the future is polled again later.
+///
+/// Other tasks get a chance to run before that, as the current task is
+/// yielded when the future is pending in [`block_on`].
+pub async fn yield_now() {
+    struct YieldNow(bool);
+
+    impl Future for YieldNow {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    YieldNow(false).await
+}
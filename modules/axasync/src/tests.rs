@@ -0,0 +1,64 @@
+use core::future::poll_fn;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::task::{Poll, Waker};
+use std::sync::{Mutex as StdMutex, Once as StdOnce};
+
+use crate::{block_on, spawn, yield_now};
+
+static INIT: StdOnce = StdOnce::new();
+static SERIAL: StdMutex<()> = StdMutex::new(());
+
+#[test]
+fn test_spawn_join() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    let handles: Vec<_> = (0..10)
+        .map(|i| {
+            spawn(async move {
+                yield_now().await;
+                i * 2
+            })
+        })
+        .collect();
+    let sum = block_on(async move {
+        let mut sum = 0;
+        for h in handles {
+            sum += h.await;
+        }
+        sum
+    });
+    assert_eq!(sum, 90);
+    assert_eq!(spawn(async { 42 }).join(), 42);
+}
+
+#[test]
+fn test_wake_from_other_task() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    static READY: AtomicBool = AtomicBool::new(false);
+    static POLLS: AtomicUsize = AtomicUsize::new(0);
+    static WAKER: StdMutex<Option<Waker>> = StdMutex::new(None);
+
+    axtask::spawn(|| {
+        // wait until the future is pending
+        while WAKER.lock().unwrap().is_none() {
+            axtask::yield_now();
+        }
+        READY.store(true, Ordering::Release);
+        WAKER.lock().unwrap().take().unwrap().wake();
+    });
+
+    block_on(poll_fn(|cx| {
+        POLLS.fetch_add(1, Ordering::Relaxed);
+        if READY.load(Ordering::Acquire) {
+            Poll::Ready(())
+        } else {
+            *WAKER.lock().unwrap() = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }));
+    // only polled again after being woken up
+    assert_eq!(POLLS.load(Ordering::Relaxed), 2);
+}
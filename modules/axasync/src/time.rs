@@ -0,0 +1,97 @@
+use alloc::sync::Arc;
+use core::future::{poll_fn, Future};
+use core::pin::{pin, Pin};
+use core::task::{Context, Poll, Waker};
+use core::{fmt, time::Duration};
+
+use axhal::time::{current_time, TimeValue};
+use axtask::timer::{Timer, TimerHandle};
+use spinlock::SpinNoIrq;
+
+/// Returns a future that completes after `dur` has elapsed.
+pub fn sleep(dur: Duration) -> Sleep {
+    sleep_until(current_time() + dur)
+}
+
+/// Returns a future that completes at the given `deadline`.
+pub fn sleep_until(deadline: TimeValue) -> Sleep {
+    Sleep {
+        deadline,
+        timer: None,
+    }
+}
+
+/// Runs the future with a time limit.
+///
+/// Returns [`Err(Elapsed)`](Elapsed) if the future does not complete in
+/// `dur`, and the future is dropped.
+pub async fn timeout<F: Future>(dur: Duration, fut: F) -> Result<F::Output, Elapsed> {
+    let mut fut = pin!(fut);
+    let mut sleep = sleep(dur);
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = fut.as_mut().poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        Pin::new(&mut sleep).poll(cx).map(|_| Err(Elapsed))
+    })
+    .await
+}
+
+/// The error returned by [`timeout`] when the time limit is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elapsed;
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("deadline has elapsed")
+    }
+}
+
+/// A future returned by [`sleep`] and [`sleep_until`].
+///
+/// A kernel timer is set to wake it up at the first time it is polled, and
+/// is cancelled if the future is dropped before the deadline.
+pub struct Sleep {
+    deadline: TimeValue,
+    timer: Option<(TimerHandle, Arc<SpinNoIrq<Option<Waker>>>)>,
+}
+
+impl Sleep {
+    /// Returns the deadline of the sleep.
+    pub fn deadline(&self) -> TimeValue {
+        self.deadline
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if current_time() >= self.deadline {
+            return Poll::Ready(());
+        }
+        match &self.timer {
+            Some((_, waker)) => *waker.lock() = Some(cx.waker().clone()),
+            None => {
+                let waker = Arc::new(SpinNoIrq::new(Some(cx.waker().clone())));
+                let waker2 = waker.clone();
+                let handle = Timer::oneshot(self.deadline, move |_| {
+                    let waker = waker2.lock().take();
+                    if let Some(waker) = waker {
+                        waker.wake();
+                    }
+                });
+                self.timer = Some((handle, waker));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some((handle, _)) = &self.timer {
+            handle.cancel();
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use alloc::{sync::Arc, task::Wake};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use axtask::{AxTaskRef, WaitQueue};
+
+/// A waker that wakes up the task running [`block_on`](crate::block_on).
+///
+/// The task sleeps in its own wait queue while the future is pending. A wake
+/// up before the task goes to sleep is recorded by the `woken` flag, so it
+/// will not be lost.
+pub(crate) struct TaskWaker {
+    task: AxTaskRef,
+    woken: AtomicBool,
+    wq: WaitQueue,
+}
+
+impl TaskWaker {
+    pub fn new(task: AxTaskRef) -> Self {
+        Self {
+            task,
+            woken: AtomicBool::new(false),
+            wq: WaitQueue::new(),
+        }
+    }
+
+    /// Blocks the current task until it is woken up.
+    ///
+    /// If it has been woken up already, it only yields the CPU, so that a
+    /// future that keeps waking itself does not starve other tasks.
+    pub fn wait(&self) {
+        if self.woken.swap(false, Ordering::AcqRel) {
+            axtask::yield_now();
+        } else {
+            self.wq
+                .wait_until(|| self.woken.swap(false, Ordering::AcqRel));
+        }
+    }
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::Release);
+        self.wq.notify_task(true, &self.task);
+    }
+}
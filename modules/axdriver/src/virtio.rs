@@ -1,7 +1,7 @@
 use core::marker::PhantomData;
 use core::ptr::NonNull;
 
-use axalloc::global_allocator;
+use axalloc::{global_allocator, MemZone};
 use axhal::mem::{phys_to_virt, virt_to_phys};
 use cfg_if::cfg_if;
 use driver_common::{BaseDriverOps, DevResult, DeviceType};
@@ -141,11 +141,13 @@ pub struct VirtIoHalImpl;
 
 unsafe impl VirtIoHal for VirtIoHalImpl {
     fn dma_alloc(pages: usize, _direction: BufferDirection) -> (PhysAddr, NonNull<u8>) {
-        let vaddr = if let Ok(vaddr) = global_allocator().alloc_pages(pages, 0x1000) {
-            vaddr
-        } else {
-            return (0, NonNull::dangling());
-        };
+        // Legacy virtio devices take 32-bit page frame numbers of the queues.
+        let vaddr =
+            if let Ok(vaddr) = global_allocator().alloc_pages_in(MemZone::Dma32, pages, 0x1000) {
+                vaddr
+            } else {
+                return (0, NonNull::dangling());
+            };
         let paddr = virt_to_phys(vaddr.into());
         let ptr = NonNull::new(vaddr as _).unwrap();
         (paddr.as_usize(), ptr)
@@ -1,5 +1,9 @@
 use std::io::Result;
 
+/// The size of the space reserved for the kernel symbol table with the `ksyms`
+/// feature, which is filled after linking.
+const KSYMS_SIZE: usize = 0x4_0000;
+
 fn main() {
     let arch = std::env::var("CARGO_CFG_TARGET_ARCH").unwrap();
     let platform = std::env::var("PLATFORM").unwrap_or("dummy".to_string());
@@ -22,6 +26,13 @@ fn gen_linker_script(arch: &str, platform: &str) -> Result<()> {
         &format!("{:#x}", axconfig::KERNEL_BASE_VADDR),
     );
     let ld_content = ld_content.replace("%SMP%", &format!("{}", axconfig::SMP));
+    // Without the `ksyms` feature, the `.ksyms` section is empty and discarded.
+    let ksyms = if std::env::var_os("CARGO_FEATURE_KSYMS").is_some() {
+        format!("BYTE(0)\n        . = sksyms + {:#x};", KSYMS_SIZE)
+    } else {
+        String::new()
+    };
+    let ld_content = ld_content.replace("%KSYMS%", &ksyms);
 
     std::fs::write(fname, ld_content)?;
     Ok(())
@@ -42,6 +42,14 @@ pub fn halt() {
     aarch64_cpu::asm::wfi(); // should never return
 }
 
+/// Reads the frame pointer register (`x29`).
+#[inline(always)]
+pub fn read_frame_pointer() -> usize {
+    let fp;
+    unsafe { asm!("mov {}, x29", out(reg) fp) };
+    fp
+}
+
 /// Reads the register that stores the current page table root.
 ///
 /// Returns the physical address of the page table root.
@@ -29,10 +29,8 @@ enum TrapSource {
 
 #[no_mangle]
 fn invalid_exception(tf: &mut TrapFrame, kind: TrapKind, source: TrapSource) {
-    panic!(
-        "Invalid exception {:?} from {:?}:\n{:#x?}",
-        kind, source, tf
-    );
+    crate::backtrace::dump_registers(tf);
+    panic!("Invalid exception {:?} from {:?}", kind, source);
 }
 
 #[no_mangle]
@@ -60,15 +58,17 @@ fn handle_sync_exception(tf: &mut TrapFrame) {
         Some(ESR_EL1::EC::Value::DataAbortCurrentEL)
         | Some(ESR_EL1::EC::Value::InstrAbortCurrentEL) => {
             let iss = esr.read(ESR_EL1::ISS);
+            crate::trap::handle_page_fault_extern((FAR_EL1.get() as usize).into());
+            crate::backtrace::dump_registers(tf);
             panic!(
-                "EL1 Page Fault @ {:#x}, FAR={:#x}, ISS={:#x}:\n{:#x?}",
+                "EL1 Page Fault @ {:#x}, FAR={:#x}, ISS={:#x}",
                 tf.elr,
                 FAR_EL1.get(),
                 iss,
-                tf,
             );
         }
         _ => {
+            crate::backtrace::dump_registers(tf);
             panic!(
                 "Unhandled synchronous exception @ {:#x}: ESR={:#x} (EC {:#08b}, ISS {:#x})",
                 tf.elr,
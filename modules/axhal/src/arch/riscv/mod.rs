@@ -43,6 +43,14 @@ pub fn halt() {
     unsafe { riscv::asm::wfi() } // should never return
 }
 
+/// Reads the frame pointer register (`s0`).
+#[inline(always)]
+pub fn read_frame_pointer() -> usize {
+    let fp;
+    unsafe { core::arch::asm!("mv {}, s0", out(reg) fp) };
+    fp
+}
+
 /// Reads the register that stores the current page table root.
 ///
 /// Returns the physical address of the page table root.
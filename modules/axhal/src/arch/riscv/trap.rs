@@ -1,4 +1,5 @@
 use riscv::register::scause::{self, Exception as E, Trap};
+use riscv::register::stval;
 
 use super::TrapFrame;
 
@@ -15,18 +16,29 @@ fn handle_breakpoint(sepc: &mut usize) {
 }
 
 #[no_mangle]
-fn riscv_trap_handler(tf: &mut TrapFrame, _from_user: bool) {
+fn riscv_trap_handler(tf: &mut TrapFrame, from_user: bool) {
     let scause = scause::read();
     match scause.cause() {
         Trap::Exception(E::Breakpoint) => handle_breakpoint(&mut tf.sepc),
         Trap::Interrupt(_) => crate::trap::handle_irq_extern(scause.bits()),
-        _ => {
+        Trap::Exception(E::LoadPageFault)
+        | Trap::Exception(E::StorePageFault)
+        | Trap::Exception(E::InstructionPageFault)
+            if !from_user =>
+        {
+            let vaddr = stval::read();
+            crate::trap::handle_page_fault_extern(vaddr.into());
+            crate::backtrace::dump_registers(tf);
             panic!(
-                "Unhandled trap {:?} @ {:#x}:\n{:#x?}",
+                "Kernel {:?} @ {:#x}, fault_vaddr={:#x}",
                 scause.cause(),
                 tf.sepc,
-                tf
+                vaddr
             );
         }
+        _ => {
+            crate::backtrace::dump_registers(tf);
+            panic!("Unhandled trap {:?} @ {:#x}", scause.cause(), tf.sepc);
+        }
     }
 }
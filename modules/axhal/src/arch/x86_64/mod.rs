@@ -13,7 +13,7 @@ use x86_64::instructions::interrupts;
 
 pub use self::context::{ExtendedState, FxsaveArea, TaskContext, TrapFrame};
 pub use self::gdt::GdtStruct;
-pub use self::idt::IdtStruct;
+pub use self::idt::{IdtStruct, DOUBLE_FAULT_IST_INDEX};
 pub use x86_64::structures::tss::TaskStateSegment;
 
 /// Allows the current CPU to respond to interrupts.
@@ -55,6 +55,14 @@ pub fn halt() {
     wait_for_irqs(); // should never return
 }
 
+/// Reads the frame pointer register (`rbp`).
+#[inline(always)]
+pub fn read_frame_pointer() -> usize {
+    let fp;
+    unsafe { asm!("mov {}, rbp", out(reg) fp) };
+    fp
+}
+
 /// Reads the register that stores the current page table root.
 ///
 /// Returns the physical address of the page table root.
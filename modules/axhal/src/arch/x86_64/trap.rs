@@ -19,27 +19,33 @@ fn x86_trap_handler(tf: &mut TrapFrame) {
                     tf.error_code,
                 );
             } else {
+                crate::trap::handle_page_fault_extern(unsafe { cr2() }.into());
+                crate::backtrace::dump_registers(tf);
                 panic!(
-                    "Kernel #PF @ {:#x}, fault_vaddr={:#x}, error_code={:#x}:\n{:#x?}",
+                    "Kernel #PF @ {:#x}, fault_vaddr={:#x}, error_code={:#x}",
                     tf.rip,
                     unsafe { cr2() },
                     tf.error_code,
-                    tf,
                 );
             }
         }
+        DOUBLE_FAULT_VECTOR => {
+            // may be caused by a page fault on kernel stack overflow
+            crate::trap::handle_page_fault_extern(unsafe { cr2() }.into());
+            crate::backtrace::dump_registers(tf);
+            panic!("#DF @ {:#x}, fault_vaddr={:#x}", tf.rip, unsafe { cr2() });
+        }
         BREAKPOINT_VECTOR => debug!("#BP @ {:#x} ", tf.rip),
         GENERAL_PROTECTION_FAULT_VECTOR => {
-            panic!(
-                "#GP @ {:#x}, error_code={:#x}:\n{:#x?}",
-                tf.rip, tf.error_code, tf
-            );
+            crate::backtrace::dump_registers(tf);
+            panic!("#GP @ {:#x}, error_code={:#x}", tf.rip, tf.error_code);
         }
         IRQ_VECTOR_START..=IRQ_VECTOR_END => crate::trap::handle_irq_extern(tf.vector as _),
         _ => {
+            crate::backtrace::dump_registers(tf);
             panic!(
-                "Unhandled exception {} (error_code = {:#x}) @ {:#x}:\n{:#x?}",
-                tf.vector, tf.error_code, tf.rip, tf
+                "Unhandled exception {} (error_code = {:#x}) @ {:#x}",
+                tf.vector, tf.error_code, tf.rip
             );
         }
     }
@@ -0,0 +1,206 @@
+//! Stack unwinding and kernel symbol lookup, for diagnostics (e.g., panics).
+//!
+//! The call stack is unwound by walking the frame-pointer chain, so the
+//! kernel must be built with frame pointers (`-C force-frame-pointers=yes`, see
+//! `scripts/make/cargo.mk`).
+//!
+//! With the `ksyms` feature, return addresses are resolved to function names
+//! by the kernel symbol table, which is embedded in the `.ksyms` section of
+//! the kernel image after linking (see `scripts/make/build.mk`). It is a list
+//! of text lines in the form of `<hex address> <name>`, sorted by the
+//! address, and terminated by a NUL byte. If the table is empty or the
+//! feature is disabled, only the raw addresses are printed.
+//!
+//! Everything is printed by [`RawConsole`], as it is mostly used on panics.
+
+use core::fmt::{self, Write};
+
+use crate::arch::TrapFrame;
+use crate::console::RawConsole;
+
+macro_rules! raw_println {
+    ($($arg:tt)*) => {
+        let _ = writeln!(RawConsole, $($arg)*);
+    };
+}
+
+/// The maximum number of frames to unwind.
+const MAX_DEPTH: usize = 64;
+
+/// A stack frame in the frame-pointer chain.
+#[derive(Debug, Clone, Copy)]
+pub struct Frame {
+    /// The frame pointer of the frame.
+    pub fp: usize,
+    /// The return address saved in the frame, i.e., the address in the caller
+    /// right after the call instruction.
+    pub ra: usize,
+}
+
+impl Frame {
+    /// Reads the frame that `fp` points to, and returns it together with the
+    /// frame pointer of the caller.
+    ///
+    /// # Safety
+    ///
+    /// `fp` must point to a valid frame record.
+    unsafe fn read(fp: usize) -> (Self, usize) {
+        cfg_if::cfg_if! {
+            if #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))] {
+                // The frame record is right below the frame pointer:
+                // `[fp - 2 * XLEN] = caller's fp`, `[fp - XLEN] = ra`.
+                let record = (fp as *const usize).sub(2);
+            } else {
+                // x86_64: `[rbp] = caller's rbp`, `[rbp + 8] = return address`.
+                // aarch64: `[x29] = caller's x29`, `[x29 + 8] = x30 (lr)`.
+                let record = fp as *const usize;
+            }
+        }
+        let ra = record.add(1).read();
+        (Self { fp, ra }, record.read())
+    }
+}
+
+/// An iterator over the frames of a call stack, from the innermost one.
+///
+/// The unwinding stops when the frame pointer becomes null, misaligned or
+/// does not go up the stack, or 64 frames are visited.
+pub struct Frames {
+    fp: usize,
+    depth: usize,
+}
+
+impl Frames {
+    /// Starts unwinding from the given frame pointer.
+    ///
+    /// # Safety
+    ///
+    /// `fp` must be the frame pointer of a call stack built with frame
+    /// pointers, and the stack must not be changed during the iteration.
+    pub unsafe fn new(fp: usize) -> Self {
+        Self { fp, depth: 0 }
+    }
+}
+
+impl Iterator for Frames {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        let misaligned = self.fp & (core::mem::align_of::<usize>() - 1) != 0;
+        if self.fp == 0 || misaligned || self.depth >= MAX_DEPTH {
+            return None;
+        }
+        // Safety: guaranteed by the caller of `Frames::new`.
+        let (frame, caller_fp) = unsafe { Frame::read(self.fp) };
+        if frame.ra == 0 {
+            return None;
+        }
+        // The stack grows downwards, so the caller's frame must be higher.
+        self.fp = if caller_fp > self.fp { caller_fp } else { 0 };
+        self.depth += 1;
+        Some(frame)
+    }
+}
+
+/// Returns the embedded kernel symbol table.
+fn ksyms() -> &'static str {
+    #[cfg(target_os = "none")]
+    {
+        extern "C" {
+            fn sksyms();
+            fn eksyms();
+        }
+        let start = sksyms as usize;
+        let len = eksyms as usize - start;
+        // Safety: the section is in the read-only kernel image.
+        let bytes = unsafe { core::slice::from_raw_parts(start as *const u8, len) };
+        let len = bytes.iter().position(|&b| b == 0).unwrap_or(len);
+        core::str::from_utf8(&bytes[..len]).unwrap_or("")
+    }
+    #[cfg(not(target_os = "none"))]
+    ""
+}
+
+/// Looks up the kernel symbol that contains `addr`.
+///
+/// Returns the symbol name and the offset of `addr` from the symbol start,
+/// or `None` if it is not found.
+pub fn lookup_symbol(addr: usize) -> Option<(&'static str, usize)> {
+    let mut found = None;
+    for line in ksyms().lines() {
+        let Some((sym_addr, name)) = line.split_once(' ') else {
+            continue;
+        };
+        let Ok(sym_addr) = usize::from_str_radix(sym_addr, 16) else {
+            continue;
+        };
+        if sym_addr > addr {
+            break;
+        }
+        found = Some((name, addr - sym_addr));
+    }
+    found
+}
+
+/// Symbolizes a code address for printing, in the form of
+/// `<address> <symbol>+<offset>`.
+pub struct Symbolized(pub usize);
+
+impl fmt::Display for Symbolized {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#018x}", self.0)?;
+        // Look up the call instruction rather than the next one, which may
+        // belong to another function if the call does not return.
+        if let Some((name, offset)) = lookup_symbol(self.0.wrapping_sub(1)) {
+            write!(f, " {}+{:#x}", name, offset + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the program counter and the frame pointer of a trapped context.
+fn trapped_pc_fp(tf: &TrapFrame) -> (usize, usize) {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "x86_64")] {
+            (tf.rip as usize, tf.rbp as usize)
+        } else if #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))] {
+            (tf.sepc, tf.regs.s0)
+        } else if #[cfg(target_arch = "aarch64")] {
+            (tf.elr as usize, tf.r[29] as usize)
+        }
+    }
+}
+
+/// Prints the registers of a trapped context, and its backtrace.
+///
+/// It is called before panicking on a fatal trap, as the backtrace printed by
+/// the panic handler does not go beyond the trap handler.
+pub fn dump_registers(tf: &TrapFrame) {
+    let (pc, fp) = trapped_pc_fp(tf);
+    raw_println!(
+        "CPU {} trapped at {}:\n{:#x?}\n  page table root = {:#x}",
+        crate::cpu::this_cpu_id(),
+        Symbolized(pc),
+        tf,
+        crate::arch::read_page_table_root(),
+    );
+    raw_println!("Backtrace of the trapped context:");
+    raw_println!("  #0  {}", Symbolized(pc));
+    // Safety: the trapped stack is alive during the iteration, as it is
+    // below the trap handler.
+    let frames = unsafe { Frames::new(fp) };
+    for (i, frame) in frames.enumerate() {
+        raw_println!("  #{:<2} {}", i + 1, Symbolized(frame.ra));
+    }
+}
+
+/// Prints the backtrace of the caller.
+#[inline(never)]
+pub fn print_backtrace() {
+    raw_println!("Backtrace:");
+    // Safety: the current stack is alive during the iteration.
+    let frames = unsafe { Frames::new(crate::arch::read_frame_pointer()) };
+    for (i, frame) in frames.enumerate() {
+        raw_println!("  #{:<2} {}", i, Symbolized(frame.ra));
+    }
+}
@@ -1,5 +1,8 @@
 //! CPU-related operations.
 
+use core::fmt;
+use core::ops::{BitAnd, BitOr, Not};
+
 #[percpu::def_percpu]
 static CPU_ID: usize = 0;
 
@@ -81,6 +84,7 @@ pub(crate) fn init_primary(cpu_id: usize) {
         CPU_ID.write_current_raw(cpu_id);
         IS_BSP.write_current_raw(true);
     }
+    crate::trap::init_percpu(cpu_id);
 }
 
 #[allow(dead_code)]
@@ -90,4 +94,117 @@ pub(crate) fn init_secondary(cpu_id: usize) {
         CPU_ID.write_current_raw(cpu_id);
         IS_BSP.write_current_raw(false);
     }
+    crate::trap::init_percpu(cpu_id);
+}
+
+/// A set of CPUs, e.g., the CPU affinity of a task, or the targets of a
+/// cross-CPU function call.
+///
+/// Bit `i` of the mask is set if CPU `i` is included. At most `usize::BITS`
+/// CPUs are supported, functions that take a CPU ID panic if it exceeds that.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct CpuMask(usize);
+
+impl CpuMask {
+    /// Creates an empty mask that contains no CPU.
+    pub const fn new() -> Self {
+        Self(0)
+    }
+
+    /// Creates a mask that contains all CPUs (up to [`axconfig::SMP`]).
+    pub const fn full() -> Self {
+        if axconfig::SMP >= usize::BITS as usize {
+            Self(usize::MAX)
+        } else {
+            Self((1 << axconfig::SMP) - 1)
+        }
+    }
+
+    /// Creates a mask that contains only the given CPU.
+    pub const fn one(cpu_id: usize) -> Self {
+        Self(Self::bit(cpu_id))
+    }
+
+    /// Creates a mask from raw bits.
+    pub const fn from_raw(bits: usize) -> Self {
+        Self(bits)
+    }
+
+    /// Returns the raw bits of the mask.
+    pub const fn bits(&self) -> usize {
+        self.0
+    }
+
+    /// Whether the mask contains no CPU.
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether the given CPU is in the mask.
+    pub const fn contains(&self, cpu_id: usize) -> bool {
+        cpu_id < usize::BITS as usize && self.0 & (1 << cpu_id) != 0
+    }
+
+    /// Adds the given CPU to the mask.
+    pub fn set(&mut self, cpu_id: usize) {
+        self.0 |= Self::bit(cpu_id);
+    }
+
+    /// Removes the given CPU from the mask.
+    pub fn clear(&mut self, cpu_id: usize) {
+        self.0 &= !Self::bit(cpu_id);
+    }
+
+    /// Returns the lowest CPU ID in the mask, or [`None`] if it is empty.
+    pub const fn first(&self) -> Option<usize> {
+        if self.0 == 0 {
+            None
+        } else {
+            Some(self.0.trailing_zeros() as usize)
+        }
+    }
+
+    /// Returns an iterator over the CPU IDs in the mask.
+    pub fn iter(&self) -> impl Iterator<Item = usize> {
+        let bits = self.0;
+        (0..usize::BITS as usize).filter(move |i| bits & (1 << i) != 0)
+    }
+
+    const fn bit(cpu_id: usize) -> usize {
+        assert!(cpu_id < usize::BITS as usize, "CPU ID out of range");
+        1 << cpu_id
+    }
+}
+
+impl Default for CpuMask {
+    fn default() -> Self {
+        Self::full()
+    }
+}
+
+impl BitAnd for CpuMask {
+    type Output = Self;
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl BitOr for CpuMask {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl Not for CpuMask {
+    type Output = Self;
+    fn not(self) -> Self {
+        Self(!self.0) & Self::full()
+    }
+}
+
+impl fmt::Debug for CpuMask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
 }
@@ -1,16 +1,126 @@
 //! Interrupt management.
 
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 use handler_table::HandlerTable;
 
 use crate::platform::irq::MAX_IRQ_COUNT;
 
-pub use crate::platform::irq::{dispatch_irq, register_handler, set_enable};
+pub use crate::platform::irq::{register_handler, set_enable};
 
 /// The type if an IRQ handler.
 pub type IrqHandler = handler_table::Handler;
 
 static IRQ_HANDLER_TABLE: HandlerTable<MAX_IRQ_COUNT> = HandlerTable::new();
 
+/// Nesting level of IRQ handlers on this CPU.
+#[percpu::def_percpu]
+static IRQ_NESTING: usize = 0;
+
+/// Whether the current CPU is running an IRQ handler, i.e., inside
+/// [`dispatch_irq`].
+pub fn in_irq() -> bool {
+    let _guard = kernel_guard::IrqSave::new();
+    // Safety: IRQs are disabled.
+    unsafe { IRQ_NESTING.read_current_raw() > 0 }
+}
+
+/// Dispatches the IRQ to its handler, and marks the current CPU as in IRQ
+/// context (see [`in_irq`]) meanwhile.
+///
+/// It is called by the common interrupt handler, with IRQs disabled. The
+/// meaning of `irq_num` is platform-specific, e.g., the `scause` register on
+/// RISC-V.
+pub fn dispatch_irq(irq_num: usize) {
+    // Safety: IRQs are disabled.
+    unsafe {
+        IRQ_NESTING.write_current_raw(IRQ_NESTING.read_current_raw() + 1);
+        crate::platform::irq::dispatch_irq(irq_num);
+        IRQ_NESTING.write_current_raw(IRQ_NESTING.read_current_raw() - 1);
+    }
+}
+
+/// Kinds of inter-processor interrupts (IPIs).
+#[repr(usize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpiKind {
+    /// Asks the target CPU to reschedule.
+    Reschedule = 0,
+    /// Asks the target CPU to run the functions queued for it.
+    CallFunction = 1,
+    /// Asks the target CPU to stop.
+    Stop = 2,
+}
+
+/// The number of IPI kinds.
+pub const NR_IPI_KINDS: usize = 3;
+
+/// The target CPUs of an IPI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpiTarget {
+    /// The CPU with the given ID.
+    Cpu(usize),
+    /// All CPUs except the current one.
+    Others,
+}
+
+static IPI_HANDLER_TABLE: HandlerTable<NR_IPI_KINDS> = HandlerTable::new();
+
+/// Bitmap of the pending IPI kinds on this CPU.
+#[percpu::def_percpu]
+static IPI_PENDING: AtomicUsize = AtomicUsize::new(0);
+
+/// Registers the handler of an IPI kind, which is called in IRQ context on
+/// the target CPU.
+///
+/// It returns `false` if the kind already has a handler.
+pub fn register_ipi_handler(kind: IpiKind, handler: IrqHandler) -> bool {
+    IPI_HANDLER_TABLE.register_handler(kind as usize, handler)
+}
+
+/// Sends an inter-processor interrupt to the target CPUs.
+///
+/// The IPIs of the same kind sent to a CPU before it handles them are merged
+/// into one.
+pub fn send_ipi(target: IpiTarget, kind: IpiKind) {
+    let _guard = kernel_guard::NoPreemptIrqSave::new();
+    match target {
+        IpiTarget::Cpu(cpu_id) => send_ipi_to(cpu_id, kind),
+        IpiTarget::Others => {
+            let this_cpu = crate::cpu::this_cpu_id();
+            for cpu_id in (0..axconfig::SMP).filter(|&id| id != this_cpu) {
+                send_ipi_to(cpu_id, kind);
+            }
+        }
+    }
+}
+
+fn send_ipi_to(cpu_id: usize, kind: IpiKind) {
+    if cpu_id >= axconfig::SMP {
+        warn!("send {:?} IPI to invalid CPU {}", kind, cpu_id);
+        return;
+    }
+    // Safety: the CPU ID is valid, and the pending bits are atomic.
+    unsafe { IPI_PENDING.remote_ref_raw(cpu_id) }.fetch_or(1 << kind as usize, Ordering::Release);
+    crate::platform::irq::send_ipi(cpu_id);
+}
+
+/// Platform-independent IPI dispatching, called by the platform IRQ
+/// dispatcher on the IPI vector.
+#[allow(dead_code)]
+pub(crate) fn dispatch_ipi() {
+    // Safety: IRQs are disabled in IRQ handlers.
+    let mut pending = unsafe { IPI_PENDING.current_ref_raw() }.swap(0, Ordering::Acquire);
+    while pending != 0 {
+        let kind = pending.trailing_zeros() as usize;
+        pending &= pending - 1;
+        trace!("IPI {}", kind);
+        if !IPI_HANDLER_TABLE.handle(kind) {
+            warn!("Unhandled IPI {}", kind);
+        }
+    }
+}
+
 /// Platform-independent IRQ dispatching.
 #[allow(dead_code)]
 pub(crate) fn dispatch_irq_common(irq_num: usize) {
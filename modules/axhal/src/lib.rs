@@ -19,6 +19,8 @@
 //! - `fp_simd`: Enable floating-point and SIMD support.
 //! - `paging`: Enable page table manipulation.
 //! - `irq`: Enable interrupt handling support.
+//! - `ksyms`: Reserve space in the kernel image for the kernel symbol table,
+//!   to symbolize backtraces (see [`backtrace`]).
 //! - `platform-pc-x86`: Specify for use on the corresponding platform.
 //! - `platform-qemu-virt-riscv`: Specify for use on the corresponding platform.
 //! - `platform-qemu-virt-aarch64`: Specify for use on the corresponding platform.
@@ -39,6 +41,7 @@ extern crate log;
 mod platform;
 
 pub mod arch;
+pub mod backtrace;
 pub mod cpu;
 pub mod mem;
 pub mod time;
@@ -60,6 +63,17 @@ pub mod console {
             putchar(*c);
         }
     }
+
+    /// Writes to the console directly, without taking the print lock of
+    /// `axlog`, which may be held by the panicking code.
+    pub struct RawConsole;
+
+    impl core::fmt::Write for RawConsole {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            write_bytes(s.as_bytes());
+            Ok(())
+        }
+    }
 }
 
 /// Miscellaneous operation, e.g. terminate the system.
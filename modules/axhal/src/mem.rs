@@ -7,6 +7,7 @@ pub use memory_addr::{PhysAddr, VirtAddr, PAGE_SIZE_4K};
 
 bitflags::bitflags! {
     /// The flags of a physical memory region.
+    #[derive(Clone, Copy)]
     pub struct MemRegionFlags: usize {
         /// Readable.
         const READ          = 1 << 0;
@@ -20,9 +21,21 @@ bitflags::bitflags! {
         const RESERVED      = 1 << 5;
         /// Free memory for allocation.
         const FREE          = 1 << 6;
+        /// Free memory addressable by legacy devices (e.g., ISA DMA).
+        const DMA           = 1 << 7;
+        /// Free memory addressable by 32-bit DMA.
+        const DMA32         = 1 << 8;
     }
 }
 
+/// The DMA address limits of the platform, as the zone flag and the end
+/// physical address (exclusive) of the zone, from the lowest one.
+const DMA_ZONES: &[(MemRegionFlags, usize)] = &[
+    #[cfg(target_arch = "x86_64")]
+    (MemRegionFlags::DMA, 0x100_0000), // 16 M
+    (MemRegionFlags::DMA32, 0x1_0000_0000), // 4 G
+];
+
 impl fmt::Debug for MemRegionFlags {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         fmt::Debug::fmt(&self.0, f)
@@ -43,8 +56,36 @@ pub struct MemRegion {
 }
 
 /// The iterator over all physical memory regions.
+///
+/// Free memory regions are split at the DMA address limits, and the lower
+/// parts are marked with [`MemRegionFlags::DMA`] or [`MemRegionFlags::DMA32`].
 struct MemRegionIter {
     idx: usize,
+    /// The upper part of the last free region that was split.
+    rest: Option<MemRegion>,
+}
+
+impl MemRegionIter {
+    fn split_free(&mut self, mut region: MemRegion) -> MemRegion {
+        if !region.flags.contains(MemRegionFlags::FREE) {
+            return region;
+        }
+        let start = region.paddr.as_usize();
+        let end = start + region.size;
+        if let Some(&(zone_flag, zone_end)) = DMA_ZONES.iter().find(|&&(_, e)| start < e) {
+            if end > zone_end {
+                self.rest = Some(MemRegion {
+                    paddr: zone_end.into(),
+                    size: end - zone_end,
+                    flags: region.flags,
+                    name: region.name,
+                });
+                region.size = zone_end - start;
+            }
+            region.flags |= zone_flag;
+        }
+        region
+    }
 }
 
 impl Iterator for MemRegionIter {
@@ -52,13 +93,16 @@ impl Iterator for MemRegionIter {
 
     fn next(&mut self) -> Option<Self::Item> {
         use crate::platform::mem::{memory_region_at, memory_regions_num};
-        let ret = if self.idx < memory_regions_num() {
-            memory_region_at(self.idx)
-        } else {
-            None
-        };
-        self.idx += 1;
-        ret
+        let ret = self.rest.take().or_else(|| {
+            let ret = if self.idx < memory_regions_num() {
+                memory_region_at(self.idx)
+            } else {
+                None
+            };
+            self.idx += 1;
+            ret
+        });
+        ret.map(|r| self.split_free(r))
     }
 }
 
@@ -89,8 +133,11 @@ pub const fn phys_to_virt(paddr: PhysAddr) -> VirtAddr {
 }
 
 /// Returns an iterator over all physical memory regions.
+///
+/// Free memory regions are split at the DMA address limits of the platform,
+/// and marked with the flag of the lowest DMA zone they are in, if any.
 pub fn memory_regions() -> impl Iterator<Item = MemRegion> {
-    MemRegionIter { idx: 0 }
+    MemRegionIter { idx: 0, rest: None }
 }
 
 /// Number of common physical memory regions for all platforms.
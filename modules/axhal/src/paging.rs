@@ -1,7 +1,9 @@
 //! Page table manipulation.
 
 use axalloc::global_allocator;
+use lazy_init::LazyInit;
 use page_table::PagingIf;
+use spinlock::SpinNoIrq;
 
 use crate::mem::{phys_to_virt, virt_to_phys, MemRegionFlags, PhysAddr, VirtAddr, PAGE_SIZE_4K};
 
@@ -61,3 +63,87 @@ cfg_if::cfg_if! {
         pub type PageTable = page_table::aarch64::A64PageTable<PagingIfImpl>;
     }
 }
+
+static KERNEL_PAGE_TABLE: LazyInit<SpinNoIrq<PageTable>> = LazyInit::new();
+
+/// Sets the kernel page table, which is shared by all CPUs.
+///
+/// It should be called only once, on the primary CPU.
+pub fn set_kernel_page_table(page_table: PageTable) {
+    KERNEL_PAGE_TABLE.init_by(SpinNoIrq::new(page_table));
+}
+
+/// Returns the physical address of the root of the kernel page table.
+///
+/// # Panics
+///
+/// Panics if the kernel page table is not set by [`set_kernel_page_table`].
+pub fn kernel_page_table_root() -> PhysAddr {
+    KERNEL_PAGE_TABLE.lock().root_paddr()
+}
+
+/// Unmaps the 4K page at `vaddr` from the kernel page table, so that any
+/// access to it triggers a page fault.
+///
+/// It is used to make guard pages. The page must be in the linear mapping of
+/// the physical memory and mapped by a 4K page table entry, it can be
+/// restored by [`unprotect_page`]. Huge pages are not split here, as
+/// replacing a live block mapping breaks the break-before-make rule on
+/// AArch64, so the memory holding guard pages must be mapped with 4K pages
+/// at boot.
+pub fn protect_page(vaddr: VirtAddr) -> PagingResult {
+    let mut page_table = KERNEL_PAGE_TABLE
+        .try_get()
+        .ok_or(PagingError::NotMapped)?
+        .lock();
+    if page_table.query(vaddr)?.2 != PageSize::Size4K {
+        return Err(PagingError::MappedToHugePage);
+    }
+    page_table.unmap(vaddr)?;
+    drop(page_table);
+    flush_tlb_all_cpus(vaddr);
+    Ok(())
+}
+
+/// Maps back the 4K page at `vaddr` unmapped by [`protect_page`].
+pub fn unprotect_page(vaddr: VirtAddr) -> PagingResult {
+    let mut page_table = KERNEL_PAGE_TABLE
+        .try_get()
+        .ok_or(PagingError::NotMapped)?
+        .lock();
+    page_table.map(
+        vaddr,
+        virt_to_phys(vaddr),
+        PageSize::Size4K,
+        MappingFlags::READ | MappingFlags::WRITE,
+    )?;
+    drop(page_table);
+    flush_tlb_all_cpus(vaddr);
+    Ok(())
+}
+
+/// TLB shootdown interface, built on the cross-CPU function calls of the
+/// upper layer.
+///
+/// This trait is defined with the [`#[def_interface]`][1] attribute. Users
+/// should implement it with [`#[impl_interface]`][2] in any other crate.
+///
+/// [1]: crate_interface::def_interface
+/// [2]: crate_interface::impl_interface
+#[cfg(all(feature = "smp", feature = "irq"))]
+#[crate_interface::def_interface]
+pub trait TlbShootdownIf {
+    /// Flushes the TLB entries of `vaddr` on all CPUs, and returns after all
+    /// of them are flushed.
+    fn flush_tlb_all(vaddr: VirtAddr);
+}
+
+/// Flushes the TLB entries of `vaddr` on all CPUs, and waits for them to
+/// finish.
+fn flush_tlb_all_cpus(vaddr: VirtAddr) {
+    // `tlbi vaae1is` on AArch64 is broadcast to all CPUs already.
+    #[cfg(all(feature = "smp", feature = "irq", not(target_arch = "aarch64")))]
+    crate_interface::call_interface!(TlbShootdownIf::flush_tlb_all, vaddr);
+    #[cfg(not(all(feature = "smp", feature = "irq", not(target_arch = "aarch64"))))]
+    crate::arch::flush_tlb(Some(vaddr));
+}
@@ -9,6 +9,9 @@ pub const MAX_IRQ_COUNT: usize = 1024;
 /// The timer IRQ number.
 pub const TIMER_IRQ_NUM: usize = 30; // physical timer, type=PPI, id=14
 
+/// The IRQ number of IPIs (type=SGI, id=1).
+const IPI_IRQ_NUM: usize = 1;
+
 const GICD_BASE: PhysAddr = PhysAddr::from(axconfig::GICD_PADDR);
 const GICC_BASE: PhysAddr = PhysAddr::from(axconfig::GICC_PADDR);
 
@@ -37,7 +40,15 @@ pub fn register_handler(irq_num: usize, handler: IrqHandler) -> bool {
 /// up in the IRQ handler table and calls the corresponding handler. If
 /// necessary, it also acknowledges the interrupt controller after handling.
 pub fn dispatch_irq(_unused: usize) {
-    GICC.handle_irq(|irq_num| crate::irq::dispatch_irq_common(irq_num as _));
+    GICC.handle_irq(|irq_num| match irq_num as usize {
+        IPI_IRQ_NUM => crate::irq::dispatch_ipi(),
+        irq_num => crate::irq::dispatch_irq_common(irq_num),
+    });
+}
+
+/// Sends the IPI SGI to the given CPU.
+pub(crate) fn send_ipi(cpu_id: usize) {
+    GICD.lock().send_sgi(IPI_IRQ_NUM, cpu_id);
 }
 
 /// Initializes GICD, GICC on the primary CPU.
@@ -45,10 +56,12 @@ pub(crate) fn init_primary() {
     info!("Initialize GICv2...");
     GICD.lock().init();
     GICC.init();
+    set_enable(IPI_IRQ_NUM, true);
 }
 
 /// Initializes GICC on secondary CPUs.
 #[cfg(feature = "smp")]
 pub(crate) fn init_secondary() {
     GICC.init();
+    set_enable(IPI_IRQ_NUM, true); // SGIs are banked per CPU
 }
@@ -74,7 +74,7 @@ pub mod irq {
 
     /// Registers an IRQ handler for the given IRQ.
     pub fn register_handler(irq_num: usize, handler: crate::irq::IrqHandler) -> bool {
-        false
+        crate::irq::register_handler_common(irq_num, handler)
     }
 
     /// Dispatches the IRQ.
@@ -82,7 +82,12 @@ pub mod irq {
     /// This function is called by the common interrupt handler. It looks
     /// up in the IRQ handler table and calls the corresponding handler. If
     /// necessary, it also acknowledges the interrupt controller after handling.
-    pub fn dispatch_irq(irq_num: usize) {}
+    pub fn dispatch_irq(irq_num: usize) {
+        crate::irq::dispatch_irq_common(irq_num)
+    }
+
+    /// Sends an IPI to the given CPU.
+    pub(crate) fn send_ipi(cpu_id: usize) {}
 }
 
 /// Initializes the platform devices for the primary CPU.
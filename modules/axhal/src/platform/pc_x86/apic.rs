@@ -14,6 +14,7 @@ pub(super) mod vectors {
     pub const APIC_TIMER_VECTOR: u8 = 0xf0;
     pub const APIC_SPURIOUS_VECTOR: u8 = 0xf1;
     pub const APIC_ERROR_VECTOR: u8 = 0xf2;
+    pub const APIC_IPI_VECTOR: u8 = 0xf3;
 }
 
 /// The maximum number of IRQs.
@@ -59,10 +60,20 @@ pub fn register_handler(vector: usize, handler: crate::irq::IrqHandler) -> bool
 /// necessary, it also acknowledges the interrupt controller after handling.
 #[cfg(feature = "irq")]
 pub fn dispatch_irq(vector: usize) {
-    crate::irq::dispatch_irq_common(vector);
+    if vector == APIC_IPI_VECTOR as usize {
+        crate::irq::dispatch_ipi();
+    } else {
+        crate::irq::dispatch_irq_common(vector);
+    }
     unsafe { local_apic().end_of_interrupt() };
 }
 
+/// Sends the IPI vector to the given CPU, IRQs must be disabled.
+#[cfg(feature = "irq")]
+pub(crate) fn send_ipi(cpu_id: usize) {
+    unsafe { local_apic().send_ipi(APIC_IPI_VECTOR, raw_apic_id(cpu_id as u8)) };
+}
+
 pub(super) fn local_apic<'a>() -> &'a mut LocalApic {
     // It's safe as LAPIC is per-cpu.
     unsafe { LOCAL_APIC.as_mut().unwrap() }
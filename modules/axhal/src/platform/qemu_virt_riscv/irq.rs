@@ -2,13 +2,12 @@
 
 use crate::irq::IrqHandler;
 use lazy_init::LazyInit;
-use riscv::register::sie;
+use riscv::register::{sie, sip};
 
 /// `Interrupt` bit in `scause`
 pub(super) const INTC_IRQ_BASE: usize = 1 << (usize::BITS - 1);
 
 /// Supervisor software interrupt in `scause`
-#[allow(unused)]
 pub(super) const S_SOFT: usize = INTC_IRQ_BASE + 1;
 
 /// Supervisor timer interrupt in `scause`
@@ -26,10 +25,16 @@ pub const MAX_IRQ_COUNT: usize = 1024;
 pub const TIMER_IRQ_NUM: usize = S_TIMER;
 
 macro_rules! with_cause {
-    ($cause: expr, @TIMER => $timer_op: expr, @EXT => $ext_op: expr $(,)?) => {
+    (
+        $cause: expr,
+        @TIMER => $timer_op: expr,
+        @EXT => $ext_op: expr,
+        @IPI => $ipi_op: expr $(,)?
+    ) => {
         match $cause {
             S_TIMER => $timer_op,
             S_EXT => $ext_op,
+            S_SOFT => $ipi_op,
             _ => panic!("invalid trap cause: {:#x}", $cause),
         }
     };
@@ -56,6 +61,7 @@ pub fn register_handler(scause: usize, handler: IrqHandler) -> bool {
             false
         },
         @EXT => crate::irq::register_handler_common(scause & !INTC_IRQ_BASE, handler),
+        @IPI => false, // use `crate::irq::register_ipi_handler` instead
     )
 }
 
@@ -72,9 +78,18 @@ pub fn dispatch_irq(scause: usize) {
             TIMER_HANDLER();
         },
         @EXT => crate::irq::dispatch_irq_common(0), // TODO: get IRQ number from PLIC
+        @IPI => {
+            unsafe { sip::clear_ssoft() };
+            crate::irq::dispatch_ipi();
+        },
     );
 }
 
+/// Sends a supervisor software interrupt to the given hart.
+pub(crate) fn send_ipi(hartid: usize) {
+    sbi_rt::send_ipi(1 << hartid, 0);
+}
+
 pub(super) fn init_percpu() {
     // enable soft interrupts, timer interrupts, and external interrupts
     unsafe {
@@ -2,6 +2,28 @@
 
 use crate_interface::{call_interface, def_interface};
 
+use crate::mem::VirtAddr;
+
+/// The size of the per-CPU stack for handling fatal exceptions.
+const EXCEPTION_STACK_SIZE: usize = 0x4000;
+
+#[repr(C, align(16))]
+struct ExceptionStack([u8; EXCEPTION_STACK_SIZE]);
+
+const EMPTY_EXCEPTION_STACK: ExceptionStack = ExceptionStack([0; EXCEPTION_STACK_SIZE]);
+
+static mut EXCEPTION_STACKS: [ExceptionStack; axconfig::SMP] =
+    [EMPTY_EXCEPTION_STACK; axconfig::SMP];
+
+/// The top of the exception stack of the current CPU.
+///
+/// Kernel page faults (and double faults on x86_64) are handled on this stack
+/// rather than the interrupted one, since they may be caused by a stack
+/// overflow. It is referenced by name in the trap entry code.
+#[no_mangle]
+#[percpu::def_percpu]
+static EXCEPTION_STACK_TOP: usize = 0;
+
 /// Trap handler interface.
 ///
 /// This trait is defined with the [`#[def_interface]`][1] attribute. Users
@@ -13,7 +35,12 @@ use crate_interface::{call_interface, def_interface};
 pub trait TrapHandler {
     /// Handles interrupt requests for the given IRQ number.
     fn handle_irq(irq_num: usize);
-    // more e.g.: handle_page_fault();
+    /// Handles page faults in the kernel at the given virtual address.
+    ///
+    /// Kernel page faults are not recoverable, the trap handler panics after
+    /// it returns. It gives a chance to report the cause (e.g., stack
+    /// overflows) first.
+    fn handle_page_fault(vaddr: VirtAddr);
 }
 
 /// Call the external IRQ handler.
@@ -21,3 +48,22 @@ pub trait TrapHandler {
 pub(crate) fn handle_irq_extern(irq_num: usize) {
     call_interface!(TrapHandler::handle_irq, irq_num);
 }
+
+/// Call the external kernel page fault handler.
+#[allow(dead_code)]
+pub(crate) fn handle_page_fault_extern(vaddr: VirtAddr) {
+    call_interface!(TrapHandler::handle_page_fault, vaddr)
+}
+
+/// Sets up the exception stack of the current CPU.
+pub(crate) fn init_percpu(cpu_id: usize) {
+    let stack = unsafe { core::ptr::addr_of!(EXCEPTION_STACKS[cpu_id]) };
+    let top = stack as usize + EXCEPTION_STACK_SIZE;
+    unsafe { EXCEPTION_STACK_TOP.write_current_raw(top) };
+}
+
+/// Returns the top of the exception stack of the current CPU.
+#[allow(dead_code)]
+pub(crate) fn exception_stack_top() -> usize {
+    EXCEPTION_STACK_TOP.read_current()
+}
@@ -16,6 +16,12 @@
 //!
 //! - `smoltcp`: Use [smoltcp] as the underlying network stack. This is enabled
 //!   by default.
+//! - `async`: Enable the async variants of socket operations, such as
+//!   `TcpSocket::recv_async`. They register wakers to the sockets rather than
+//!   polling, and can be run by an async executor like `axasync`. It also
+//!   enables the `multitask` and `irq` features of `axtask`, as the interfaces
+//!   are polled by a background task, which sleeps until the next timer of the
+//!   interfaces.
 //!
 //! [smoltcp]: https://github.com/smoltcp-rs/smoltcp
 
@@ -1,5 +1,6 @@
 use alloc::{boxed::Box, collections::VecDeque};
 use core::ops::{Deref, DerefMut};
+use core::task::Waker;
 
 use axerrno::{ax_err, AxError, AxResult};
 use axsync::Mutex;
@@ -13,12 +14,18 @@ const PORT_NUM: usize = 65536;
 
 struct ListenTableEntry {
     syn_queue: VecDeque<SocketHandle>,
+    /// The waker of the pending async `accept`, woken up when a new socket
+    /// joins the SYN queue.
+    #[cfg(feature = "async")]
+    waker: Option<Waker>,
 }
 
 impl ListenTableEntry {
     pub fn new() -> Self {
         Self {
             syn_queue: VecDeque::with_capacity(LISTEN_QUEUE_SIZE),
+            #[cfg(feature = "async")]
+            waker: None,
         }
     }
 }
@@ -72,7 +79,7 @@ impl ListenTable {
     pub fn can_accept(&self, port: u16) -> AxResult<bool> {
         if let Some(entry) = self.tcp[port as usize].lock().deref() {
             if entry.syn_queue.iter().any(|&handle| {
-                let (connected, _) = get_socket_info(handle);
+                let (connected, _) = get_socket_info(handle, None);
                 connected
             }) {
                 Ok(true)
@@ -84,15 +91,28 @@ impl ListenTable {
         }
     }
 
-    pub fn accept(&self, port: u16) -> AxResult<(SocketHandle, Option<SocketAddr>)> {
+    /// Takes a connected socket from the SYN queue.
+    ///
+    /// If no socket is connected and `waker` is given, it will be woken up
+    /// when any socket in the SYN queue changes its state, or a new socket
+    /// joins the queue. The waker of the previous call is replaced.
+    pub fn accept(
+        &self,
+        port: u16,
+        waker: Option<&Waker>,
+    ) -> AxResult<(SocketHandle, Option<SocketAddr>)> {
         if let Some(entry) = self.tcp[port as usize].lock().deref_mut() {
+            #[cfg(feature = "async")]
+            {
+                entry.waker = waker.cloned();
+            }
             let syn_queue = &mut entry.syn_queue;
             if let Some(&handle) = syn_queue.front() {
                 // In most cases, the order in which sockets establish connections
                 // is the same as the order in which they join the SYN queue. That
                 // is, the front of the queue connects first. At this point, we can
                 // use `pop_front` to speed up queue deletion.
-                let (connected, peer_addr) = get_socket_info(handle);
+                let (connected, peer_addr) = get_socket_info(handle, waker);
                 if connected {
                     syn_queue.pop_front();
                     return Ok((handle, peer_addr));
@@ -106,7 +126,7 @@ impl ListenTable {
                     .enumerate()
                     .skip(1)
                     .find_map(|(idx, &handle)| {
-                        let (connected, peer_addr) = get_socket_info(handle);
+                        let (connected, peer_addr) = get_socket_info(handle, waker);
                         if connected {
                             Some((idx, peer_addr))
                         } else {
@@ -146,17 +166,28 @@ impl ListenTable {
                     handle, src, dst
                 );
                 entry.syn_queue.push_back(handle);
+                // the pending `accept` registers its waker to the new socket
+                // when it is polled again.
+                #[cfg(feature = "async")]
+                if let Some(waker) = entry.waker.take() {
+                    waker.wake();
+                }
             }
         }
     }
 }
 
-fn get_socket_info(handle: SocketHandle) -> (bool, Option<SocketAddr>) {
-    let (connected, peer_addr) = SOCKET_SET.with_socket::<tcp::Socket, _, _>(handle, |socket| {
-        (
-            !matches!(socket.state(), State::Listen | State::SynReceived),
-            socket.remote_endpoint(),
-        )
-    });
-    (connected, peer_addr)
+/// Returns whether the socket is connected and its remote address.
+///
+/// If it is not connected and `waker` is given, registers the waker to be
+/// woken up on the state change.
+fn get_socket_info(handle: SocketHandle, _waker: Option<&Waker>) -> (bool, Option<SocketAddr>) {
+    SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+        let connected = !matches!(socket.state(), State::Listen | State::SynReceived);
+        #[cfg(feature = "async")]
+        if let (false, Some(waker)) = (connected, _waker) {
+            socket.register_recv_waker(waker);
+        }
+        (connected, socket.remote_endpoint())
+    })
 }
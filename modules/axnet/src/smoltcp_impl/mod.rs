@@ -7,6 +7,10 @@ use alloc::{boxed::Box, collections::VecDeque, vec};
 use core::borrow::BorrowMut;
 use core::cell::RefCell;
 use core::ops::DerefMut;
+#[cfg(feature = "async")]
+use core::task::{Poll, Waker};
+
+use axerrno::{AxError, AxResult};
 
 use axdriver::prelude::*;
 use axhal::time::{current_time_nanos, NANOS_PER_MICROS};
@@ -48,6 +52,9 @@ const NET_BUF_POOL_SIZE: usize = 128;
 
 const STANDARD_MTU: usize = 1500;
 
+#[cfg(feature = "async")]
+const ASYNC_RX_POLL_INTERVAL: core::time::Duration = core::time::Duration::from_millis(10);
+
 static NET_BUF_POOL: LazyInit<NetBufferPool> = LazyInit::new();
 
 static LISTEN_TABLE: LazyInit<ListenTable> = LazyInit::new();
@@ -127,6 +134,11 @@ impl<'a> SocketSetWrapper<'a> {
         ETH0.poll(&self.0);
     }
 
+    #[cfg(feature = "async")]
+    pub fn poll_delay(&self) -> Option<core::time::Duration> {
+        ETH0.poll_delay(&self.0)
+    }
+
     pub fn remove(&self, handle: SocketHandle) {
         self.0.lock().remove(handle);
         debug!("socket {}: destroyed", handle);
@@ -185,6 +197,18 @@ impl InterfaceWrapper {
         let mut sockets = sockets.lock();
         iface.poll(timestamp, dev.deref_mut(), &mut sockets);
     }
+
+    /// Returns how long to wait before the next poll for the pending timers
+    /// (e.g., TCP retransmissions), or `None` if there are no timers.
+    #[cfg(feature = "async")]
+    pub fn poll_delay(&self, sockets: &Mutex<SocketSet>) -> Option<core::time::Duration> {
+        let timestamp = Self::current_time();
+        let mut iface = self.iface.lock();
+        let sockets = sockets.lock();
+        iface
+            .poll_delay(timestamp, &sockets)
+            .map(|d| core::time::Duration::from_micros(d.total_micros()))
+    }
 }
 
 impl DeviceWrapper {
@@ -291,6 +315,113 @@ impl<'a> TxToken for AxNetTxToken<'a> {
     }
 }
 
+/// Retries the socket operation `f` until it does not return
+/// [`Err(WouldBlock)`](AxError::WouldBlock), or returns it immediately if
+/// `nonblock` is true.
+fn poll_blocking<T, F>(nonblock: bool, mut f: F) -> AxResult<T>
+where
+    F: FnMut() -> AxResult<T>,
+{
+    loop {
+        SOCKET_SET.poll_interfaces();
+        match f() {
+            Err(AxError::WouldBlock) if !nonblock => axtask::yield_now(),
+            res => return res,
+        }
+    }
+}
+
+/// Runs the socket operation `f` asynchronously.
+///
+/// `f` should register the waker to the socket before it returns
+/// [`Err(WouldBlock)`](AxError::WouldBlock), while holding the socket set.
+/// The future is pending then, until the waker is woken up by the interface
+/// polling.
+#[cfg(feature = "async")]
+async fn poll_async<T, F>(mut f: F) -> AxResult<T>
+where
+    F: FnMut(&Waker) -> AxResult<T>,
+{
+    let _pending = async_poller::PendingOp::new();
+    core::future::poll_fn(|cx| {
+        SOCKET_SET.poll_interfaces();
+        match f(cx.waker()) {
+            Err(AxError::WouldBlock) => {
+                async_poller::wake();
+                Poll::Pending
+            }
+            res => Poll::Ready(res),
+        }
+    })
+    .await
+}
+
+/// A background task that polls the interfaces to wake up the pending async
+/// operations.
+///
+/// The poller sleeps until it is woken up by a new pending operation, or the
+/// next timer of the interface expires. The NICs are not interrupt-driven yet,
+/// so while some operations are pending, it also polls the interfaces every
+/// [`ASYNC_RX_POLL_INTERVAL`] to receive packets.
+#[cfg(feature = "async")]
+mod async_poller {
+    use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    use axtask::WaitQueue;
+
+    use super::{ASYNC_RX_POLL_INTERVAL, SOCKET_SET};
+
+    static STARTED: AtomicBool = AtomicBool::new(false);
+    static POLL_REQUESTED: AtomicBool = AtomicBool::new(false);
+    static PENDING_OPS: AtomicUsize = AtomicUsize::new(0);
+    static POLLER_WQ: WaitQueue = WaitQueue::new();
+
+    /// Counts an async operation as pending until it is dropped.
+    pub struct PendingOp;
+
+    impl PendingOp {
+        pub fn new() -> Self {
+            PENDING_OPS.fetch_add(1, Ordering::AcqRel);
+            Self
+        }
+    }
+
+    impl Drop for PendingOp {
+        fn drop(&mut self) {
+            PENDING_OPS.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+
+    /// Wakes up the poller, starts it if not yet.
+    pub fn wake() {
+        if !STARTED.swap(true, Ordering::AcqRel) {
+            axtask::spawn(poller);
+        }
+        POLL_REQUESTED.store(true, Ordering::Release);
+        POLLER_WQ.notify_one(false);
+    }
+
+    fn poller() {
+        let requested = || POLL_REQUESTED.load(Ordering::Acquire);
+        loop {
+            POLL_REQUESTED.store(false, Ordering::Release);
+            SOCKET_SET.poll_interfaces();
+            let delay = SOCKET_SET.poll_delay();
+            let timeout = if PENDING_OPS.load(Ordering::Acquire) > 0 {
+                Some(delay.map_or(ASYNC_RX_POLL_INTERVAL, |d| d.min(ASYNC_RX_POLL_INTERVAL)))
+            } else {
+                delay
+            };
+            match timeout {
+                Some(dur) => {
+                    POLLER_WQ.wait_timeout_until(dur, requested);
+                }
+                None => POLLER_WQ.wait_until(requested),
+            }
+        }
+    }
+}
+
 fn snoop_tcp_packet(buf: &[u8]) -> Result<(), smoltcp::wire::Error> {
     use crate::SocketAddr;
     use smoltcp::wire::{EthernetFrame, IpProtocol, Ipv4Packet, TcpPacket};
@@ -1,3 +1,5 @@
+use core::task::Waker;
+
 use axerrno::{ax_err, ax_err_type, AxError, AxResult};
 use axio::PollState;
 use axsync::Mutex;
@@ -6,7 +8,9 @@ use smoltcp::iface::SocketHandle;
 use smoltcp::socket::tcp::{self, ConnectError, RecvError, State};
 use smoltcp::wire::IpAddress;
 
-use super::{SocketSetWrapper, ETH0, LISTEN_TABLE, SOCKET_SET};
+#[cfg(feature = "async")]
+use super::poll_async;
+use super::{poll_blocking, SocketSetWrapper, ETH0, LISTEN_TABLE, SOCKET_SET};
 use crate::SocketAddr;
 
 /// A TCP socket that provides POSIX-like APIs.
@@ -75,6 +79,19 @@ impl TcpSocket {
     ///
     /// The local port is generated automatically.
     pub fn connect(&mut self, addr: SocketAddr) -> AxResult {
+        let (handle, local_addr, peer_addr) = self.connect_start(addr)?;
+        poll_blocking(false, || self.poll_connect(handle, None))?;
+        self.local_addr = local_addr;
+        self.peer_addr = peer_addr;
+        Ok(())
+    }
+
+    /// Starts connecting to the given address and port, returns the socket
+    /// handle, the local and the remote address.
+    fn connect_start(
+        &self,
+        addr: SocketAddr,
+    ) -> AxResult<(SocketHandle, Option<SocketAddr>, Option<SocketAddr>)> {
         let handle = if self.is_listening() {
             return ax_err!(AlreadyExists, "socket connect() failed: already connected");
         } else {
@@ -98,22 +115,26 @@ impl TcpSocket {
                     })?;
                 Ok((socket.local_endpoint(), socket.remote_endpoint()))
             })?;
+        Ok((handle, local_addr, peer_addr))
+    }
 
-        loop {
-            SOCKET_SET.poll_interfaces();
-            let (state, may_recv) = SOCKET_SET.with_socket::<tcp::Socket, _, _>(handle, |socket| {
-                (socket.state(), socket.may_recv())
-            });
-            if may_recv || state == State::Established {
-                self.local_addr = local_addr;
-                self.peer_addr = peer_addr;
-                return Ok(());
+    /// Checks whether the connection is established, returns
+    /// [`Err(WouldBlock)`](AxError::WouldBlock) if it is still in progress.
+    fn poll_connect(&self, handle: SocketHandle, _waker: Option<&Waker>) -> AxResult {
+        SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+            let state = socket.state();
+            if socket.may_recv() || state == State::Established {
+                Ok(())
             } else if state == State::SynSent {
-                axtask::yield_now();
+                #[cfg(feature = "async")]
+                if let Some(waker) = _waker {
+                    socket.register_send_waker(waker);
+                }
+                Err(AxError::WouldBlock)
             } else {
-                return ax_err!(ConnectionRefused, "socket connect() failed");
+                ax_err!(ConnectionRefused, "socket connect() failed")
             }
-        }
+        })
     }
 
     /// Binds an unbound socket to the given address and port.
@@ -168,37 +189,29 @@ impl TcpSocket {
     ///
     /// It's must be called after [`bind`](Self::bind) and [`listen`](Self::listen).
     pub fn accept(&mut self) -> AxResult<TcpSocket> {
+        let local_port = self.listen_port()?;
+        poll_blocking(self.nonblock, || self.poll_accept(local_port, None))
+    }
+
+    fn poll_accept(&self, local_port: u16, waker: Option<&Waker>) -> AxResult<TcpSocket> {
+        let (handle, peer_addr) = LISTEN_TABLE.accept(local_port, waker)?;
+        debug!("socket accepted a new connection {}", peer_addr.unwrap());
+        Ok(TcpSocket {
+            handle: Some(handle),
+            local_addr: self.local_addr,
+            peer_addr,
+            nonblock: false,
+        })
+    }
+
+    fn listen_port(&self) -> AxResult<u16> {
         if !self.is_listening() {
             return ax_err!(InvalidInput, "socket accept() failed: not listen");
         }
-
-        let local_port = self
+        Ok(self
             .local_addr
             .ok_or_else(|| ax_err_type!(InvalidInput, "socket accept() failed: no address bound"))?
-            .port;
-
-        loop {
-            SOCKET_SET.poll_interfaces();
-            match LISTEN_TABLE.accept(local_port) {
-                Ok((handle, peer_addr)) => {
-                    debug!("socket accepted a new connection {}", peer_addr.unwrap());
-                    return Ok(TcpSocket {
-                        handle: Some(handle),
-                        local_addr: self.local_addr,
-                        peer_addr,
-                        nonblock: false,
-                    });
-                }
-                Err(AxError::WouldBlock) => {
-                    if self.nonblock {
-                        return Err(AxError::WouldBlock);
-                    } else {
-                        axtask::yield_now()
-                    }
-                }
-                Err(e) => return Err(e),
-            }
-        }
+            .port)
     }
 
     /// Close the connection.
@@ -224,41 +237,34 @@ impl TcpSocket {
         let handle = self
             .handle
             .ok_or_else(|| ax_err_type!(NotConnected, "socket recv() failed"))?;
-        loop {
-            SOCKET_SET.poll_interfaces();
-            match SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
-                if !socket.is_open() {
-                    // not connected
-                    ax_err!(NotConnected, "socket recv() failed")
-                } else if !socket.may_recv() {
-                    // connection closed
-                    Ok(0)
-                } else if socket.can_recv() {
-                    // data available
-                    // TODO: use sxocket.recv(|buf| {...})
-                    match socket.recv_slice(buf) {
-                        Ok(len) => Ok(len),
-                        Err(RecvError::Finished) => Ok(0),
-                        Err(_) => ax_err!(ConnectionRefused, "socket recv() failed"),
-                    }
-                } else {
-                    // no more data
-                    Err(AxError::WouldBlock)
-                }
-            }) {
-                Ok(n) => {
-                    return Ok(n);
+        poll_blocking(self.nonblock, || Self::poll_recv(handle, buf, None))
+    }
+
+    fn poll_recv(handle: SocketHandle, buf: &mut [u8], _waker: Option<&Waker>) -> AxResult<usize> {
+        SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+            if !socket.is_open() {
+                // not connected
+                ax_err!(NotConnected, "socket recv() failed")
+            } else if !socket.may_recv() {
+                // connection closed
+                Ok(0)
+            } else if socket.can_recv() {
+                // data available
+                // TODO: use sxocket.recv(|buf| {...})
+                match socket.recv_slice(buf) {
+                    Ok(len) => Ok(len),
+                    Err(RecvError::Finished) => Ok(0),
+                    Err(_) => ax_err!(ConnectionRefused, "socket recv() failed"),
                 }
-                Err(AxError::WouldBlock) => {
-                    if self.nonblock {
-                        return Err(AxError::WouldBlock);
-                    } else {
-                        axtask::yield_now()
-                    }
+            } else {
+                // no more data
+                #[cfg(feature = "async")]
+                if let Some(waker) = _waker {
+                    socket.register_recv_waker(waker);
                 }
-                Err(e) => return Err(e),
+                Err(AxError::WouldBlock)
             }
-        }
+        })
     }
 
     /// Transmits data in the given buffer.
@@ -266,37 +272,30 @@ impl TcpSocket {
         let handle = self
             .handle
             .ok_or_else(|| ax_err_type!(NotConnected, "socket send() failed"))?;
-        loop {
-            SOCKET_SET.poll_interfaces();
-            match SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
-                if !socket.is_open() || !socket.may_send() {
-                    // not connected
-                    ax_err!(NotConnected, "socket send() failed")
-                } else if socket.can_send() {
-                    // connected, and the tx buffer is not full
-                    // TODO: use socket.send(|buf| {...})
-                    let len = socket
-                        .send_slice(buf)
-                        .map_err(|_| ax_err_type!(ConnectionRefused, "socket send() failed"))?;
-                    Ok(len)
-                } else {
-                    // tx buffer is full
-                    Err(AxError::WouldBlock)
-                }
-            }) {
-                Ok(n) => {
-                    return Ok(n);
-                }
-                Err(AxError::WouldBlock) => {
-                    if self.nonblock {
-                        return Err(AxError::WouldBlock);
-                    } else {
-                        axtask::yield_now()
-                    }
+        poll_blocking(self.nonblock, || Self::poll_send(handle, buf, None))
+    }
+
+    fn poll_send(handle: SocketHandle, buf: &[u8], _waker: Option<&Waker>) -> AxResult<usize> {
+        SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+            if !socket.is_open() || !socket.may_send() {
+                // not connected
+                ax_err!(NotConnected, "socket send() failed")
+            } else if socket.can_send() {
+                // connected, and the tx buffer is not full
+                // TODO: use socket.send(|buf| {...})
+                let len = socket
+                    .send_slice(buf)
+                    .map_err(|_| ax_err_type!(ConnectionRefused, "socket send() failed"))?;
+                Ok(len)
+            } else {
+                // tx buffer is full
+                #[cfg(feature = "async")]
+                if let Some(waker) = _waker {
+                    socket.register_send_waker(waker);
                 }
-                Err(e) => return Err(e),
+                Err(AxError::WouldBlock)
             }
-        }
+        })
     }
 
     /// Detect whether the socket needs to receive/can send.
@@ -328,6 +327,47 @@ impl TcpSocket {
     }
 }
 
+/// Async operations, which register wakers and return pending futures rather
+/// than blocking the current task. The nonblocking mode does not affect them.
+#[cfg(feature = "async")]
+impl TcpSocket {
+    /// Connects to the given address and port asynchronously.
+    ///
+    /// See [`connect`](Self::connect) for details.
+    pub async fn connect_async(&mut self, addr: SocketAddr) -> AxResult {
+        let (handle, local_addr, peer_addr) = self.connect_start(addr)?;
+        poll_async(|waker| self.poll_connect(handle, Some(waker))).await?;
+        self.local_addr = local_addr;
+        self.peer_addr = peer_addr;
+        Ok(())
+    }
+
+    /// Accepts a new connection asynchronously.
+    ///
+    /// See [`accept`](Self::accept) for details.
+    pub async fn accept_async(&mut self) -> AxResult<TcpSocket> {
+        let local_port = self.listen_port()?;
+        poll_async(|waker| self.poll_accept(local_port, Some(waker))).await
+    }
+
+    /// Receives data from the socket asynchronously, stores it in the given
+    /// buffer.
+    pub async fn recv_async(&self, buf: &mut [u8]) -> AxResult<usize> {
+        let handle = self
+            .handle
+            .ok_or_else(|| ax_err_type!(NotConnected, "socket recv() failed"))?;
+        poll_async(|waker| Self::poll_recv(handle, buf, Some(waker))).await
+    }
+
+    /// Transmits data in the given buffer asynchronously.
+    pub async fn send_async(&self, buf: &[u8]) -> AxResult<usize> {
+        let handle = self
+            .handle
+            .ok_or_else(|| ax_err_type!(NotConnected, "socket send() failed"))?;
+        poll_async(|waker| Self::poll_send(handle, buf, Some(waker))).await
+    }
+}
+
 impl Drop for TcpSocket {
     fn drop(&mut self) {
         self.shutdown().ok();
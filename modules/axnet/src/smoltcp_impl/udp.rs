@@ -1,3 +1,5 @@
+use core::task::Waker;
+
 use axerrno::{ax_err, ax_err_type, AxError, AxResult};
 use axio::PollState;
 use axsync::Mutex;
@@ -5,7 +7,9 @@ use axsync::Mutex;
 use smoltcp::iface::SocketHandle;
 use smoltcp::socket::udp::{self, BindError, SendError};
 
-use super::{SocketSetWrapper, ETH0, SOCKET_SET};
+#[cfg(feature = "async")]
+use super::poll_async;
+use super::{poll_blocking, SocketSetWrapper, ETH0, SOCKET_SET};
 use crate::SocketAddr;
 
 /// A UDP socket that provides POSIX-like APIs.
@@ -83,72 +87,73 @@ impl UdpSocket {
 
     /// Transmits data in the given buffer to the given address.
     pub fn send_to(&self, buf: &[u8], addr: SocketAddr) -> AxResult<usize> {
-        loop {
-            SOCKET_SET.poll_interfaces();
-            match SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-                if !socket.is_open() {
-                    // not bound
-                    ax_err!(NotConnected, "socket send() failed")
-                } else if socket.can_send() {
-                    // TODO: size
-                    socket.send_slice(buf, addr).map_err(|e| match e {
-                        SendError::BufferFull => AxError::WouldBlock,
-                        SendError::Unaddressable => {
-                            ax_err_type!(ConnectionRefused, "socket send() failed")
-                        }
-                    })?;
-                    Ok(buf.len())
-                } else {
-                    // tx buffer is full
-                    Err(AxError::WouldBlock)
-                }
-            }) {
-                Ok(n) => {
-                    return Ok(n);
-                }
-                Err(AxError::WouldBlock) => {
-                    if self.nonblock {
-                        return Err(AxError::WouldBlock);
-                    } else {
-                        axtask::yield_now()
+        poll_blocking(self.nonblock, || self.poll_send_to(buf, addr, None))
+    }
+
+    fn poll_send_to(
+        &self,
+        buf: &[u8],
+        addr: SocketAddr,
+        _waker: Option<&Waker>,
+    ) -> AxResult<usize> {
+        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
+            let res = if !socket.is_open() {
+                // not bound
+                ax_err!(NotConnected, "socket send() failed")
+            } else if socket.can_send() {
+                // TODO: size
+                match socket.send_slice(buf, addr) {
+                    Ok(_) => Ok(buf.len()),
+                    Err(SendError::BufferFull) => Err(AxError::WouldBlock),
+                    Err(SendError::Unaddressable) => {
+                        ax_err!(ConnectionRefused, "socket send() failed")
                     }
                 }
-                Err(e) => return Err(e),
+            } else {
+                // tx buffer is full
+                Err(AxError::WouldBlock)
+            };
+            #[cfg(feature = "async")]
+            if let (Err(AxError::WouldBlock), Some(waker)) = (&res, _waker) {
+                socket.register_send_waker(waker);
             }
-        }
+            res
+        })
     }
 
     fn recv_impl<F, T>(&self, mut op: F, err: &str) -> AxResult<T>
     where
         F: FnMut(&mut udp::Socket) -> AxResult<T>,
     {
-        loop {
-            SOCKET_SET.poll_interfaces();
-            match SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-                if !socket.is_open() {
-                    // not connected
-                    ax_err!(NotConnected, err)
-                } else if socket.can_recv() {
-                    // data available
-                    op(socket)
+        poll_blocking(self.nonblock, || self.poll_recv(&mut op, err, None))
+    }
+
+    fn poll_recv<F, T>(&self, op: &mut F, err: &str, _waker: Option<&Waker>) -> AxResult<T>
+    where
+        F: FnMut(&mut udp::Socket) -> AxResult<T>,
+    {
+        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
+            let res = if !socket.is_open() {
+                // not connected
+                ax_err!(NotConnected, err)
+            } else if socket.can_recv() {
+                // data available
+                op(socket)
+            } else {
+                // no more data
+                Err(AxError::WouldBlock)
+            };
+            #[cfg(feature = "async")]
+            if let (Err(AxError::WouldBlock), Some(waker)) = (&res, _waker) {
+                if socket.can_recv() {
+                    // the received packets are filtered out, try again
+                    waker.wake_by_ref();
                 } else {
-                    // no more data
-                    Err(AxError::WouldBlock)
+                    socket.register_recv_waker(waker);
                 }
-            }) {
-                Ok(x) => {
-                    return Ok(x);
-                }
-                Err(AxError::WouldBlock) => {
-                    if self.nonblock {
-                        return Err(AxError::WouldBlock);
-                    } else {
-                        axtask::yield_now()
-                    }
-                }
-                Err(e) => return Err(e),
             }
-        }
+            res
+        })
     }
 
     /// Receives data from the socket, stores it in the given buffer.
@@ -244,6 +249,43 @@ impl UdpSocket {
     }
 }
 
+/// Async operations, which register wakers and return pending futures rather
+/// than blocking the current task. The nonblocking mode does not affect them.
+#[cfg(feature = "async")]
+impl UdpSocket {
+    /// Transmits data in the given buffer to the given address asynchronously.
+    pub async fn send_to_async(&self, buf: &[u8], addr: SocketAddr) -> AxResult<usize> {
+        poll_async(|waker| self.poll_send_to(buf, addr, Some(waker))).await
+    }
+
+    /// Receives data from the socket asynchronously, stores it in the given
+    /// buffer.
+    pub async fn recv_from_async(&self, buf: &mut [u8]) -> AxResult<(usize, SocketAddr)> {
+        let mut op = |socket: &mut udp::Socket| match socket.recv_slice(buf) {
+            Ok((len, meta)) => Ok((len, meta.endpoint)),
+            Err(_) => Err(AxError::WouldBlock),
+        };
+        poll_async(|waker| self.poll_recv(&mut op, "socket recv_from() failed", Some(waker))).await
+    }
+
+    /// Transmits data in the given buffer to the remote address to which it
+    /// is connected asynchronously.
+    pub async fn send_async(&self, buf: &[u8]) -> AxResult<usize> {
+        self.send_to_async(buf, self.peer_addr()?).await
+    }
+
+    /// Receives data asynchronously from the remote address to which it is
+    /// connected.
+    pub async fn recv_async(&self, buf: &mut [u8]) -> AxResult<usize> {
+        let peer_addr = self.peer_addr()?;
+        let mut op = |socket: &mut udp::Socket| match socket.recv_slice(buf) {
+            Ok((len, meta)) if meta.endpoint == peer_addr => Ok(len),
+            _ => Err(AxError::WouldBlock),
+        };
+        poll_async(|waker| self.poll_recv(&mut op, "socket recv() failed", Some(waker))).await
+    }
+}
+
 impl Drop for UdpSocket {
     fn drop(&mut self) {
         self.shutdown().ok();
@@ -0,0 +1,107 @@
+//! Cross-CPU function calls through inter-processor interrupts.
+
+use alloc::{boxed::Box, collections::VecDeque, sync::Arc};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use axhal::cpu::CpuMask;
+use axhal::irq::{IpiKind, IpiTarget};
+use kernel_guard::{IrqSave, NoPreempt};
+use spinlock::SpinNoIrq;
+
+struct CallData {
+    func: Box<dyn Fn() + Send + Sync>,
+    /// The number of CPUs that have not finished the call.
+    pending: AtomicUsize,
+}
+
+/// The calls queued for this CPU.
+#[percpu::def_percpu]
+static CALL_QUEUE: SpinNoIrq<VecDeque<Arc<CallData>>> = SpinNoIrq::new(VecDeque::new());
+
+/// Calls `f` on the CPUs in `cpu_mask`.
+///
+/// `f` is called in the IPI handler on the other CPUs, so it must not block.
+/// If the current CPU is in `cpu_mask`, `f` is also called on it directly,
+/// with IRQs disabled.
+///
+/// If `wait` is `true`, it returns after `f` has returned on all the CPUs.
+/// In this case, it must be called with IRQs enabled, otherwise it may
+/// deadlock with another CPU that is calling it at the same time.
+///
+/// # Panics
+///
+/// Panics if the secondary CPUs have not been initialized yet.
+pub fn smp_call_function<F>(cpu_mask: CpuMask, f: F, wait: bool)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    assert!(super::is_init_ok(), "secondary CPUs are not initialized");
+    debug_assert!(!wait || axhal::arch::irqs_enabled());
+
+    let cpu_mask = cpu_mask & CpuMask::full();
+    let data = Arc::new(CallData {
+        func: Box::new(f),
+        pending: AtomicUsize::new(cpu_mask.iter().count()),
+    });
+
+    let guard = NoPreempt::new();
+    let this_cpu = axhal::cpu::this_cpu_id();
+    for cpu_id in cpu_mask.iter().filter(|&id| id != this_cpu) {
+        // Safety: the CPU ID is valid, and the queue is protected by its lock.
+        unsafe { CALL_QUEUE.remote_ref_raw(cpu_id) }
+            .lock()
+            .push_back(data.clone());
+        axhal::irq::send_ipi(IpiTarget::Cpu(cpu_id), IpiKind::CallFunction);
+    }
+    if cpu_mask.contains(this_cpu) {
+        let _irq_guard = IrqSave::new();
+        run_call(&data);
+    }
+    drop(guard);
+
+    if wait {
+        while data.pending.load(Ordering::Acquire) != 0 {
+            core::hint::spin_loop();
+        }
+    }
+}
+
+fn run_call(data: &CallData) {
+    (data.func)();
+    data.pending.fetch_sub(1, Ordering::Release);
+}
+
+fn handle_call_function() {
+    loop {
+        // Safety: IRQs are disabled in IPI handlers.
+        let Some(data) = (unsafe { CALL_QUEUE.current_ref_raw() }).lock().pop_front() else {
+            break;
+        };
+        run_call(&data);
+    }
+}
+
+#[cfg(feature = "paging")]
+struct TlbShootdownIfImpl;
+
+#[cfg(feature = "paging")]
+#[crate_interface::impl_interface]
+impl axhal::paging::TlbShootdownIf for TlbShootdownIfImpl {
+    fn flush_tlb_all(vaddr: axhal::mem::VirtAddr) {
+        if super::is_init_ok() {
+            smp_call_function(
+                CpuMask::full(),
+                move || axhal::arch::flush_tlb(Some(vaddr)),
+                true,
+            );
+        } else {
+            // The other CPUs flush their whole TLBs after all CPUs are
+            // initialized.
+            axhal::arch::flush_tlb(Some(vaddr));
+        }
+    }
+}
+
+pub(crate) fn init() {
+    axhal::irq::register_ipi_handler(IpiKind::CallFunction, handle_call_function);
+}
@@ -1,7 +1,48 @@
+use core::fmt::Write;
 use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use axhal::console::RawConsole;
+
+/// The ID of the CPU that is handling a panic, or `usize::MAX` if none.
+static PANIC_CPU: AtomicUsize = AtomicUsize::new(usize::MAX);
 
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
-    error!("{}", info);
+    axhal::arch::disable_irqs();
+    let cpu_id = axhal::cpu::this_cpu_id();
+    if let Err(panic_cpu) =
+        PANIC_CPU.compare_exchange(usize::MAX, cpu_id, Ordering::AcqRel, Ordering::Acquire)
+    {
+        if panic_cpu == cpu_id {
+            // Panicked again while handling the panic, e.g., when unwinding a
+            // corrupted stack. Do as little as possible.
+            let _ = writeln!(RawConsole, "\nnested panic on CPU {}: {}", cpu_id, info);
+            axhal::misc::terminate();
+        }
+        // Another CPU is handling its panic, which will terminate the system.
+        #[cfg(feature = "smp")]
+        crate::mp::stop_this_cpu();
+        #[cfg(not(feature = "smp"))]
+        loop {
+            axhal::arch::halt();
+        }
+    }
+
+    #[cfg(all(feature = "smp", feature = "irq"))]
+    let all_stopped = !crate::is_init_ok() || crate::mp::stop_other_cpus();
+
+    // The print lock may be held by the panicking code, or by the stopped
+    // CPUs, so only write to the console directly from now on.
+    let _ = writeln!(RawConsole, "\nCPU {} {}", cpu_id, info);
+    #[cfg(all(feature = "smp", feature = "irq"))]
+    if !all_stopped {
+        let _ = writeln!(RawConsole, "some CPUs did not respond to the stop request");
+    }
+    axhal::backtrace::print_backtrace();
+
+    #[cfg(feature = "panic-monitor")]
+    crate::monitor::run();
+
     axhal::misc::terminate()
 }
@@ -6,13 +6,24 @@
 //! # Cargo Features
 //!
 //! - `alloc`: Enable global memory allocator.
-//! - `paging`: Enable page table manipulation support.
+//! - `paging`: Enable page table manipulation support. With `multitask`, task
+//!   stacks are also protected by guard pages.
 //! - `irq`: Enable interrupt handling support.
 //! - `multitask`: Enable multi-threading support.
-//! - `smp`: Enable SMP (symmetric multiprocessing) support.
+//! - `tickless`: Stop the periodic timer tick when the CPU is idle. It also
+//!   enables the `irq` and `multitask` features.
+//! - `smp`: Enable SMP (symmetric multiprocessing) support. With `irq` and
+//!   `alloc`, functions can be called on other CPUs by [`smp_call_function`].
+//!   With `irq`, a panic also stops the other CPUs.
 //! - `fs`: Enable filesystem support.
 //! - `net`: Enable networking support.
 //! - `display`: Enable graphics support.
+//! - `alloc-trace`: Record live allocations of the global allocator with
+//!   their tasks and callers, to find memory leaks. It also enables the
+//!   `alloc` feature.
+//! - `panic-monitor`: Enter a monitor shell on the console after a panic,
+//!   instead of terminating the system immediately. It also embeds the kernel
+//!   symbol table to look up the addresses (the `ksyms` feature of `axhal`).
 //!
 //! All the features are optional and disabled by default.
 
@@ -22,6 +33,9 @@
 #[macro_use]
 extern crate axlog;
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 #[cfg(all(target_os = "none", not(test)))]
 mod lang_items;
 mod trap;
@@ -29,9 +43,18 @@ mod trap;
 #[cfg(feature = "smp")]
 mod mp;
 
+#[cfg(all(target_os = "none", not(test), feature = "panic-monitor"))]
+mod monitor;
+
 #[cfg(feature = "smp")]
 pub use self::mp::rust_main_secondary;
 
+#[cfg(all(feature = "smp", feature = "irq", feature = "alloc"))]
+mod ipi;
+
+#[cfg(all(feature = "smp", feature = "irq", feature = "alloc"))]
+pub use self::ipi::smp_call_function;
+
 const LOGO: &str = r#"
        d8888                            .d88888b.   .d8888b.
       d88888                           d88P" "Y88b d88P  Y88b
@@ -84,6 +107,32 @@ impl axlog::LogIf for LogIfImpl {
     }
 }
 
+#[cfg(feature = "alloc-trace")]
+struct AllocTraceIfImpl;
+
+#[cfg(feature = "alloc-trace")]
+#[crate_interface::impl_interface]
+impl axalloc::AllocTraceIf for AllocTraceIfImpl {
+    fn current_task_id() -> Option<u64> {
+        <LogIfImpl as axlog::LogIf>::current_task_id()
+    }
+
+    fn backtrace(callers: &mut [usize]) -> usize {
+        // Safety: the current stack is alive during the iteration.
+        let frames = unsafe { axhal::backtrace::Frames::new(axhal::arch::read_frame_pointer()) };
+        let mut depth = 0;
+        for (ra, frame) in callers.iter_mut().zip(frames) {
+            *ra = frame.ra;
+            depth += 1;
+        }
+        depth
+    }
+
+    fn fmt_caller(addr: usize, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{}", axhal::backtrace::Symbolized(addr))
+    }
+}
+
 use core::sync::atomic::{AtomicUsize, Ordering};
 
 static INITED_CPUS: AtomicUsize = AtomicUsize::new(0);
@@ -138,6 +187,11 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
     #[cfg(feature = "alloc")]
     {
         info!("Initialize global memory allocator...");
+        info!("  use {} allocator.", axalloc::global_allocator().name());
+        info!(
+            "  use {} page allocator.",
+            axalloc::global_allocator().page_allocator_name()
+        );
         init_allocator();
     }
 
@@ -186,7 +240,13 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
     while !is_init_ok() {
         core::hint::spin_loop();
     }
+    // Drop the stale TLB entries of the pages protected by other CPUs during
+    // the initialization.
+    #[cfg(all(feature = "smp", feature = "paging"))]
+    axhal::arch::flush_tlb(None);
 
+    #[cfg(feature = "multitask")]
+    axtask::set_system_running();
     unsafe { main() };
 
     #[cfg(feature = "multitask")]
@@ -200,53 +260,91 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
 
 #[cfg(feature = "alloc")]
 fn init_allocator() {
+    use axalloc::MemZone;
     use axhal::mem::{memory_regions, phys_to_virt, MemRegionFlags};
 
+    // Free memory regions with their zones. They are already split at the
+    // zone boundaries by the platform.
+    let zone_regions = || {
+        memory_regions()
+            .filter(|r| r.flags.contains(MemRegionFlags::FREE))
+            .map(|r| {
+                let zone = if r.flags.contains(MemRegionFlags::DMA) {
+                    MemZone::Dma
+                } else if r.flags.contains(MemRegionFlags::DMA32) {
+                    MemZone::Dma32
+                } else {
+                    MemZone::Normal
+                };
+                (zone, r.paddr, r.size)
+            })
+    };
+
     let mut max_region_size = 0;
     let mut max_region_paddr = 0.into();
-    for r in memory_regions() {
-        if r.flags.contains(MemRegionFlags::FREE) && r.size > max_region_size {
-            max_region_size = r.size;
-            max_region_paddr = r.paddr;
+    for (_, paddr, size) in zone_regions() {
+        if size > max_region_size {
+            max_region_size = size;
+            max_region_paddr = paddr;
         }
     }
-    for r in memory_regions() {
-        if r.flags.contains(MemRegionFlags::FREE) && r.paddr == max_region_paddr {
-            axalloc::global_init(phys_to_virt(r.paddr).as_usize(), r.size);
+    for (zone, paddr, size) in zone_regions() {
+        if paddr == max_region_paddr {
+            axalloc::global_init(zone, phys_to_virt(paddr).as_usize(), size);
             break;
         }
     }
-    for r in memory_regions() {
-        if r.flags.contains(MemRegionFlags::FREE) && r.paddr != max_region_paddr {
-            axalloc::global_add_memory(phys_to_virt(r.paddr).as_usize(), r.size)
-                .expect("add heap memory region failed");
+    for (zone, paddr, size) in zone_regions() {
+        if paddr != max_region_paddr {
+            let vaddr = phys_to_virt(paddr).as_usize();
+            // The page allocator may not support more regions in the zone,
+            // add it to the heap instead.
+            if let Err(e) = axalloc::global_add_zone_memory(zone, vaddr, size) {
+                warn!(
+                    "cannot add [{:#x}, {:#x}) to zone {} ({:?}), add it to the heap instead",
+                    paddr.as_usize(),
+                    paddr.as_usize() + size,
+                    zone.name(),
+                    e
+                );
+                axalloc::global_add_memory(vaddr, size).expect("add heap memory region failed");
+            }
         }
     }
+    for zone in MemZone::ALL {
+        debug!(
+            "  zone {}: {} pages available",
+            zone.name(),
+            axalloc::global_allocator().zone_available_pages(zone)
+        );
+    }
 }
 
 #[cfg(feature = "paging")]
 fn remap_kernel_memory() -> Result<(), axhal::paging::PagingError> {
-    use axhal::mem::{memory_regions, phys_to_virt};
-    use axhal::paging::PageTable;
-    use lazy_init::LazyInit;
-
-    static KERNEL_PAGE_TABLE: LazyInit<PageTable> = LazyInit::new();
+    use axhal::mem::{memory_regions, phys_to_virt, MemRegionFlags};
+    use axhal::paging::{kernel_page_table_root, set_kernel_page_table, PageTable};
 
     if axhal::cpu::this_cpu_is_bsp() {
         let mut kernel_page_table = PageTable::try_new()?;
         for r in memory_regions() {
+            // Free memory holds the task stacks, it is mapped with 4K pages so
+            // that their guard pages can be unmapped without splitting a live
+            // huge page.
+            let allow_huge =
+                !(cfg!(feature = "multitask") && r.flags.contains(MemRegionFlags::FREE));
             kernel_page_table.map_region(
                 phys_to_virt(r.paddr),
                 r.paddr,
                 r.size,
                 r.flags.into(),
-                true,
+                allow_huge,
             )?;
         }
-        KERNEL_PAGE_TABLE.init_by(kernel_page_table);
+        set_kernel_page_table(kernel_page_table);
     }
 
-    unsafe { axhal::arch::write_page_table_root(KERNEL_PAGE_TABLE.root_paddr()) };
+    unsafe { axhal::arch::write_page_table_root(kernel_page_table_root()) };
     Ok(())
 }
 
@@ -269,15 +367,65 @@ fn init_interrupt() {
             deadline = now_ns + PERIODIC_INTERVAL_NANOS;
         }
         unsafe { NEXT_DEADLINE.write_current_raw(deadline + PERIODIC_INTERVAL_NANOS) };
+        #[cfg(feature = "tickless")]
+        if axtask::is_cpu_idle() {
+            deadline = idle_deadline(now_ns);
+        }
         axhal::time::set_oneshot_timer(deadline);
     }
 
+    #[cfg(feature = "tickless")]
+    struct TickIfImpl;
+
+    /// Restarts the periodic tick when a task is switched in on an idle CPU.
+    /// The timer is programmed at the earlier one of the next tick and the
+    /// next timer event.
+    #[cfg(feature = "tickless")]
+    #[crate_interface::impl_interface]
+    impl axtask::TickIf for TickIfImpl {
+        fn restart_tick() {
+            let now_ns = axhal::time::current_time_nanos();
+            let tick = now_ns + PERIODIC_INTERVAL_NANOS;
+            let deadline =
+                axtask::next_timer_deadline().map_or(tick, |d| (d.as_nanos() as u64).min(tick));
+            // `NEXT_DEADLINE` is the tick after the programmed one.
+            let next_tick = if deadline < tick {
+                tick
+            } else {
+                tick + PERIODIC_INTERVAL_NANOS
+            };
+            // Safety: IRQs are disabled by the caller.
+            unsafe { NEXT_DEADLINE.write_current_raw(next_tick) };
+            axhal::time::set_oneshot_timer(deadline);
+        }
+    }
+
+    /// Stops the periodic tick on an idle CPU, it only wakes up for the next
+    /// timer event. The tick is restarted by `axtask` when a task is switched
+    /// in.
+    #[cfg(feature = "tickless")]
+    fn idle_deadline(now_ns: u64) -> u64 {
+        // A task woken up by a remote CPU is noticed by the reschedule IPI
+        // (see `axtask`), so only the timer events need to be waited for.
+        const MAX_IDLE_NANOS: u64 = axhal::time::NANOS_PER_SEC;
+        let max_deadline = now_ns + MAX_IDLE_NANOS;
+        axtask::next_timer_deadline()
+            .map_or(max_deadline, |d| (d.as_nanos() as u64).min(max_deadline))
+    }
+
     axhal::irq::register_handler(TIMER_IRQ_NUM, || {
-        update_timer();
+        // Handle the tick first, so that `update_timer` can see the tasks woken
+        // up by the timer events.
         #[cfg(feature = "multitask")]
         axtask::on_timer_tick();
+        update_timer();
     });
 
+    #[cfg(feature = "smp")]
+    self::mp::init_stop_ipi();
+    #[cfg(all(feature = "smp", feature = "alloc"))]
+    self::ipi::init();
+
     // Enable IRQs before starting app
     axhal::arch::enable_irqs();
 }
@@ -0,0 +1,151 @@
+//! A minimal monitor shell on the console, to inspect the system after a
+//! panic.
+//!
+//! The console is polled with IRQs disabled, and the other CPUs should have
+//! been stopped. It writes to the console directly, as the print lock may be
+//! held by the panicking code.
+
+use core::fmt::Write;
+
+use axhal::backtrace::lookup_symbol;
+use axhal::console::{getchar, putchar, RawConsole};
+
+macro_rules! print {
+    ($($arg:tt)*) => {
+        let _ = write!(RawConsole, $($arg)*);
+    };
+}
+
+macro_rules! println {
+    ($($arg:tt)*) => {
+        let _ = writeln!(RawConsole, $($arg)*);
+    };
+}
+
+const LF: u8 = b'\n';
+const CR: u8 = b'\r';
+const DL: u8 = b'\x7f';
+const BS: u8 = b'\x08';
+const SPACE: u8 = b' ';
+
+const MAX_CMD_LEN: usize = 128;
+
+/// Returns `true` to leave the monitor.
+type CmdHandler = fn(&str) -> bool;
+
+const CMD_TABLE: &[(&str, CmdHandler, &str)] = &[
+    ("bt", do_bt, "print the backtrace of this CPU"),
+    ("exit", do_exit, "terminate the system"),
+    ("help", do_help, "print this message"),
+    ("mem", do_mem, "<addr> [count]: dump memory words"),
+    ("sym", do_sym, "<addr>: look up a code address"),
+];
+
+fn do_bt(_args: &str) -> bool {
+    axhal::backtrace::print_backtrace();
+    false
+}
+
+fn do_exit(_args: &str) -> bool {
+    true
+}
+
+fn do_help(_args: &str) -> bool {
+    println!("Available commands:");
+    for (name, _, help) in CMD_TABLE {
+        println!("  {:<6} {}", name, help);
+    }
+    false
+}
+
+fn do_mem(args: &str) -> bool {
+    let mut args = args.split_whitespace();
+    let Some(addr) = args.next().and_then(parse_addr) else {
+        println!("mem: invalid address");
+        return false;
+    };
+    let count = args.next().and_then(|n| n.parse().ok()).unwrap_or(8);
+    let addr = addr & !(core::mem::size_of::<usize>() - 1);
+    for i in 0..count {
+        let ptr = (addr as *const usize).wrapping_add(i);
+        // Safety: not guaranteed, the user is responsible for the address.
+        println!("{:#018x}: {:#018x}", ptr as usize, unsafe {
+            ptr.read_volatile()
+        });
+    }
+    false
+}
+
+fn do_sym(args: &str) -> bool {
+    match parse_addr(args) {
+        Some(addr) => match lookup_symbol(addr) {
+            Some((name, offset)) => println!("{:#x}: {}+{:#x}", addr, name, offset),
+            None => println!("sym: symbol not found"),
+        },
+        None => println!("sym: invalid address"),
+    }
+    false
+}
+
+fn parse_addr(s: &str) -> Option<usize> {
+    let s = s.trim();
+    usize::from_str_radix(s.strip_prefix("0x").unwrap_or(s), 16).ok()
+}
+
+/// Runs a command line, returns `true` to leave the monitor.
+fn run_cmd(line: &str) -> bool {
+    let line = line.trim();
+    let (cmd, args) = line
+        .find(char::is_whitespace)
+        .map_or((line, ""), |n| (&line[..n], line[n + 1..].trim()));
+    if cmd.is_empty() {
+        return false;
+    }
+    for (name, func, _) in CMD_TABLE {
+        if cmd == *name {
+            return func(args);
+        }
+    }
+    println!("{}: command not found", cmd);
+    false
+}
+
+/// Runs the monitor until the `exit` command.
+pub(crate) fn run() {
+    println!("\nEntered the kernel monitor, type \"help\" for available commands.");
+    let mut buf = [0; MAX_CMD_LEN];
+    let mut cursor = 0;
+    print!("monitor> ");
+    loop {
+        let Some(c) = getchar() else {
+            core::hint::spin_loop();
+            continue;
+        };
+        match c {
+            CR | LF => {
+                println!();
+                // The buffer only contains printable ASCII characters.
+                let line = core::str::from_utf8(&buf[..cursor]).unwrap_or("");
+                if run_cmd(line) {
+                    return;
+                }
+                cursor = 0;
+                print!("monitor> ");
+            }
+            BS | DL => {
+                if cursor > 0 {
+                    axhal::console::write_bytes(&[BS, SPACE, BS]);
+                    cursor -= 1;
+                }
+            }
+            0..=31 | 128.. => {}
+            c => {
+                if cursor < MAX_CMD_LEN {
+                    putchar(c);
+                    buf[cursor] = c;
+                    cursor += 1;
+                }
+            }
+        }
+    }
+}
@@ -48,6 +48,10 @@ pub extern "C" fn rust_main_secondary(cpu_id: usize) -> ! {
     while !super::is_init_ok() {
         core::hint::spin_loop();
     }
+    // Drop the stale TLB entries of the pages protected by other CPUs during
+    // the initialization.
+    #[cfg(feature = "paging")]
+    axhal::arch::flush_tlb(None);
 
     #[cfg(feature = "irq")]
     axhal::arch::enable_irqs();
@@ -59,3 +63,40 @@ pub extern "C" fn rust_main_secondary(cpu_id: usize) -> ! {
         axhal::arch::wait_for_irqs();
     }
 }
+
+/// The number of CPUs that have been stopped by [`stop_this_cpu`].
+static STOPPED_CPUS: AtomicUsize = AtomicUsize::new(0);
+
+/// Stops the current CPU forever, with IRQs disabled.
+pub(crate) fn stop_this_cpu() -> ! {
+    axhal::arch::disable_irqs();
+    STOPPED_CPUS.fetch_add(1, Ordering::Release);
+    loop {
+        axhal::arch::halt();
+    }
+}
+
+/// Stops all the other CPUs through IPIs, and waits for them to stop.
+///
+/// Returns `false` if some CPUs do not stop in time, e.g., they are spinning
+/// with IRQs disabled.
+#[cfg(feature = "irq")]
+pub(crate) fn stop_other_cpus() -> bool {
+    use axhal::irq::{IpiKind, IpiTarget};
+    const STOP_TIMEOUT: core::time::Duration = core::time::Duration::from_secs(1);
+
+    axhal::irq::send_ipi(IpiTarget::Others, IpiKind::Stop);
+    let deadline = axhal::time::current_time() + STOP_TIMEOUT;
+    while STOPPED_CPUS.load(Ordering::Acquire) < SMP - 1 {
+        if axhal::time::current_time() >= deadline {
+            return false;
+        }
+        core::hint::spin_loop();
+    }
+    true
+}
+
+#[cfg(feature = "irq")]
+pub(crate) fn init_stop_ipi() {
+    axhal::irq::register_ipi_handler(axhal::irq::IpiKind::Stop, || stop_this_cpu());
+}
@@ -7,7 +7,15 @@ impl axhal::trap::TrapHandler for TrapHandlerImpl {
         {
             let guard = kernel_guard::NoPreempt::new();
             axhal::irq::dispatch_irq(_irq_num);
+            // run the pending softirqs after the IRQ is acknowledged.
+            #[cfg(feature = "multitask")]
+            axtask::softirq::handle_pending();
             drop(guard); // rescheduling may occur when preemption is re-enabled.
         }
     }
+
+    fn handle_page_fault(_vaddr: axhal::mem::VirtAddr) {
+        #[cfg(all(feature = "multitask", feature = "paging"))]
+        axtask::check_stack_guard(_vaddr);
+    }
 }
@@ -0,0 +1,89 @@
+//! A barrier to synchronize a group of tasks.
+
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use spinlock::SpinNoIrq;
+
+use crate::wait_queue::WaitQueue;
+
+/// A barrier enables multiple tasks to synchronize the beginning of some
+/// computation, similar to
+/// [`std::sync::Barrier`](https://doc.rust-lang.org/std/sync/struct.Barrier.html).
+///
+/// If the `multitask` feature is not enabled, the waiting task spins instead
+/// of sleeping.
+pub struct Barrier {
+    num_tasks: usize,
+    /// The number of tasks arrived in the current generation.
+    count: SpinNoIrq<usize>,
+    generation: AtomicUsize,
+    wq: WaitQueue,
+}
+
+/// A [`BarrierWaitResult`] is returned by [`Barrier::wait`] when all tasks in
+/// the [`Barrier`] have rendezvoused.
+pub struct BarrierWaitResult(bool);
+
+impl BarrierWaitResult {
+    /// Returns `true` if this task is the "leader task" for the call to
+    /// [`Barrier::wait`].
+    ///
+    /// Only one task will have `true` returned from their result, all other
+    /// tasks will have `false` returned.
+    pub fn is_leader(&self) -> bool {
+        self.0
+    }
+}
+
+impl Barrier {
+    /// Creates a new barrier that can block a given number of tasks.
+    ///
+    /// A barrier will block `n`-1 tasks which call [`Barrier::wait`] and then
+    /// wake up all tasks at once when the `n`th task calls it.
+    #[cfg_attr(feature = "lockdep", track_caller)]
+    pub const fn new(n: usize) -> Self {
+        Self {
+            num_tasks: n,
+            count: SpinNoIrq::new(0),
+            generation: AtomicUsize::new(0),
+            wq: WaitQueue::new(),
+        }
+    }
+
+    /// Blocks the current task until all tasks have rendezvoused here.
+    ///
+    /// Barriers are re-usable after all tasks have rendezvoused once, and can
+    /// be used continuously.
+    pub fn wait(&self) -> BarrierWaitResult {
+        let mut count = self.count.lock();
+        let generation = self.generation.load(Ordering::Acquire);
+        *count += 1;
+        if *count < self.num_tasks {
+            drop(count);
+            self.wq
+                .wait_until(|| self.generation.load(Ordering::Acquire) != generation);
+            BarrierWaitResult(false)
+        } else {
+            *count = 0;
+            self.generation.fetch_add(1, Ordering::Release);
+            drop(count);
+            self.wq.notify_all();
+            BarrierWaitResult(true)
+        }
+    }
+}
+
+impl fmt::Debug for Barrier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Barrier").finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for BarrierWaitResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BarrierWaitResult")
+            .field("is_leader", &self.is_leader())
+            .finish()
+    }
+}
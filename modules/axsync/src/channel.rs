@@ -0,0 +1,496 @@
+//! The lock-free queues and the common parts of the channels.
+
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::sync::atomic::{fence, AtomicPtr, AtomicUsize, Ordering};
+
+use axhal::time::TimeValue;
+use spinlock::SpinNoIrq;
+
+use crate::mpsc::{RecvTimeoutError, SendTimeoutError, TryRecvError, TrySendError};
+use crate::wait_queue::{WaitQueue, WaitResult};
+
+/// A lock-free queue that holds the messages of a channel.
+pub(crate) trait Queue {
+    /// The type of the messages.
+    type Item;
+
+    /// Whether the queue has a limited capacity.
+    const BOUNDED: bool;
+
+    /// Pushes a message, returns it back if the queue is full.
+    fn push(&self, value: Self::Item) -> Result<(), Self::Item>;
+
+    /// Whether a message may be pushed without blocking.
+    fn can_push(&self) -> bool;
+
+    /// Pops a message, returns `None` if the queue is empty.
+    ///
+    /// # Safety
+    ///
+    /// For single-consumer queues, it must not be called concurrently with
+    /// itself or [`can_pop`](Self::can_pop).
+    unsafe fn pop(&self) -> Option<Self::Item>;
+
+    /// Whether a message may be popped without blocking.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`pop`](Self::pop).
+    unsafe fn can_pop(&self) -> bool;
+}
+
+struct Node<T> {
+    value: T,
+    next: *mut Node<T>,
+}
+
+/// An unbounded multi-producer single-consumer queue.
+///
+/// Producers push nodes onto a lock-free stack. The consumer takes the whole
+/// stack at once, and reverses it into its private list in the FIFO order.
+pub(crate) struct ListQueue<T> {
+    /// Nodes pushed by the producers, the latest one first.
+    stack: AtomicPtr<Node<T>>,
+    /// Nodes taken by the consumer, the earliest one first.
+    local: UnsafeCell<*mut Node<T>>,
+}
+
+unsafe impl<T: Send> Send for ListQueue<T> {}
+unsafe impl<T: Send> Sync for ListQueue<T> {}
+
+impl<T> ListQueue<T> {
+    pub const fn new() -> Self {
+        Self {
+            stack: AtomicPtr::new(ptr::null_mut()),
+            local: UnsafeCell::new(ptr::null_mut()),
+        }
+    }
+}
+
+impl<T> Queue for ListQueue<T> {
+    type Item = T;
+    const BOUNDED: bool = false;
+
+    fn push(&self, value: T) -> Result<(), T> {
+        let node = Box::into_raw(Box::new(Node {
+            value,
+            next: ptr::null_mut(),
+        }));
+        let mut head = self.stack.load(Ordering::Relaxed);
+        loop {
+            unsafe { (*node).next = head };
+            match self
+                .stack
+                .compare_exchange_weak(head, node, Ordering::Release, Ordering::Relaxed)
+            {
+                Ok(_) => return Ok(()),
+                Err(h) => head = h,
+            }
+        }
+    }
+
+    fn can_push(&self) -> bool {
+        true
+    }
+
+    unsafe fn pop(&self) -> Option<T> {
+        let local = &mut *self.local.get();
+        if local.is_null() {
+            let mut head = self.stack.swap(ptr::null_mut(), Ordering::Acquire);
+            while !head.is_null() {
+                let next = (*head).next;
+                (*head).next = *local;
+                *local = head;
+                head = next;
+            }
+        }
+        if local.is_null() {
+            None
+        } else {
+            let node = Box::from_raw(*local);
+            *local = node.next;
+            Some(node.value)
+        }
+    }
+
+    unsafe fn can_pop(&self) -> bool {
+        !(*self.local.get()).is_null() || !self.stack.load(Ordering::Acquire).is_null()
+    }
+}
+
+impl<T> Drop for ListQueue<T> {
+    fn drop(&mut self) {
+        while unsafe { self.pop() }.is_some() {}
+    }
+}
+
+struct Slot<T> {
+    /// Equals to the position of the slot if it is empty, or the position
+    /// plus one if it is full, where the position grows by the capacity in
+    /// each round.
+    seq: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// A bounded multi-producer multi-consumer queue, based on Dmitry Vyukov's
+/// [bounded MPMC queue](https://www.1024cores.net/home/lock-free-algorithms/queues/bounded-mpmc-queue).
+pub(crate) struct ArrayQueue<T> {
+    slots: Box<[Slot<T>]>,
+    /// The position to pop the next message.
+    head: AtomicUsize,
+    /// The position to push the next message.
+    tail: AtomicUsize,
+}
+
+unsafe impl<T: Send> Send for ArrayQueue<T> {}
+unsafe impl<T: Send> Sync for ArrayQueue<T> {}
+
+impl<T> ArrayQueue<T> {
+    pub fn new(cap: usize) -> Self {
+        assert!(cap > 0, "capacity must be positive");
+        let slots: Vec<_> = (0..cap)
+            .map(|i| Slot {
+                seq: AtomicUsize::new(i),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+            })
+            .collect();
+        Self {
+            slots: slots.into_boxed_slice(),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn slot(&self, pos: usize) -> &Slot<T> {
+        &self.slots[pos % self.slots.len()]
+    }
+}
+
+impl<T> Queue for ArrayQueue<T> {
+    type Item = T;
+    const BOUNDED: bool = true;
+
+    fn push(&self, value: T) -> Result<(), T> {
+        let mut pos = self.tail.load(Ordering::Relaxed);
+        loop {
+            let slot = self.slot(pos);
+            let diff = slot.seq.load(Ordering::Acquire).wrapping_sub(pos) as isize;
+            if diff == 0 {
+                match self.tail.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        unsafe { (*slot.value.get()).write(value) };
+                        slot.seq.store(pos.wrapping_add(1), Ordering::Release);
+                        return Ok(());
+                    }
+                    Err(p) => pos = p,
+                }
+            } else if diff < 0 {
+                // the slot is not popped yet in the previous round.
+                return Err(value);
+            } else {
+                pos = self.tail.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn can_push(&self) -> bool {
+        let pos = self.tail.load(Ordering::Acquire);
+        self.slot(pos).seq.load(Ordering::Acquire).wrapping_sub(pos) as isize >= 0
+    }
+
+    unsafe fn pop(&self) -> Option<T> {
+        let mut pos = self.head.load(Ordering::Relaxed);
+        loop {
+            let slot = self.slot(pos);
+            let diff = slot
+                .seq
+                .load(Ordering::Acquire)
+                .wrapping_sub(pos.wrapping_add(1)) as isize;
+            if diff == 0 {
+                match self.head.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        let value = (*slot.value.get()).assume_init_read();
+                        slot.seq
+                            .store(pos.wrapping_add(self.capacity()), Ordering::Release);
+                        return Some(value);
+                    }
+                    Err(p) => pos = p,
+                }
+            } else if diff < 0 {
+                // the slot is not pushed yet in this round.
+                return None;
+            } else {
+                pos = self.head.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    unsafe fn can_pop(&self) -> bool {
+        let pos = self.head.load(Ordering::Acquire);
+        let seq = self.slot(pos).seq.load(Ordering::Acquire);
+        seq.wrapping_sub(pos.wrapping_add(1)) as isize >= 0
+    }
+}
+
+impl<T> Drop for ArrayQueue<T> {
+    fn drop(&mut self) {
+        while unsafe { self.pop() }.is_some() {}
+    }
+}
+
+/// A task blocked in [`Select`](crate::mpsc::Select), registered to each of
+/// the channels it waits on.
+pub(crate) struct SelectWaiter {
+    wq: WaitQueue,
+}
+
+/// The [`SelectWaiter`]s registered to a channel, woken up when the channel
+/// becomes ready.
+pub struct SelectWaiters {
+    waiters: SpinNoIrq<Vec<Arc<SelectWaiter>>>,
+    /// The number of the waiters, to avoid taking the lock when there are no
+    /// waiters.
+    count: AtomicUsize,
+}
+
+impl SelectWaiters {
+    const fn new() -> Self {
+        Self {
+            waiters: SpinNoIrq::new(Vec::new()),
+            count: AtomicUsize::new(0),
+        }
+    }
+
+    fn register(&self, waiter: &Arc<SelectWaiter>) {
+        self.waiters.lock().push(waiter.clone());
+        self.count.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn unregister(&self, waiter: &Arc<SelectWaiter>) {
+        let mut waiters = self.waiters.lock();
+        if let Some(idx) = waiters.iter().position(|w| Arc::ptr_eq(w, waiter)) {
+            waiters.swap_remove(idx);
+            self.count.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    fn notify(&self) {
+        // pairs with the fence in `select_wait()`: either we see the waiter
+        // here, or it sees the channel ready before blocking.
+        fence(Ordering::SeqCst);
+        if self.count.load(Ordering::Relaxed) > 0 {
+            for waiter in self.waiters.lock().iter() {
+                waiter.wq.notify_one();
+            }
+        }
+    }
+}
+
+/// Blocks the current task until `ready` returns `true`, the deadline is
+/// reached, or the current task is killed, by registering a waiter to each of
+/// the given channels.
+pub(crate) fn select_wait<'a, I, F>(
+    channels: I,
+    deadline: Option<TimeValue>,
+    ready: F,
+) -> WaitResult
+where
+    I: Iterator<Item = &'a SelectWaiters> + Clone,
+    F: Fn() -> bool,
+{
+    let waiter = Arc::new(SelectWaiter {
+        wq: WaitQueue::new(),
+    });
+    for waiters in channels.clone() {
+        waiters.register(&waiter);
+    }
+    fence(Ordering::SeqCst);
+    let res = waiter.wq.wait_until_interruptible(deadline, ready);
+    for waiters in channels {
+        waiters.unregister(&waiter);
+    }
+    res
+}
+
+/// Blocks the current task in `wq` until `condition` becomes true, or the
+/// `deadline` (if any) is reached.
+///
+/// Like std, a blocking call without a deadline only returns when the channel
+/// is ready, while a timed one also returns if the current task is killed.
+fn wait_until<F>(wq: &WaitQueue, deadline: Option<TimeValue>, condition: F) -> WaitResult
+where
+    F: Fn() -> bool,
+{
+    if deadline.is_some() {
+        wq.wait_until_interruptible(deadline, condition)
+    } else {
+        wq.wait_until(condition);
+        WaitResult::Ready
+    }
+}
+
+/// The shared state of a channel.
+pub(crate) struct Channel<Q> {
+    queue: Q,
+    senders: AtomicUsize,
+    receivers: AtomicUsize,
+    /// Receivers waiting for messages.
+    recv_wq: WaitQueue,
+    /// Senders waiting for free slots, only used by bounded channels.
+    send_wq: WaitQueue,
+    /// Tasks waiting on the receivers in [`Select`](crate::mpsc::Select).
+    select_waiters: SelectWaiters,
+}
+
+impl<T, Q: Queue<Item = T>> Channel<Q> {
+    pub fn new(queue: Q) -> Self {
+        Self {
+            queue,
+            senders: AtomicUsize::new(1),
+            receivers: AtomicUsize::new(1),
+            recv_wq: WaitQueue::new(),
+            send_wq: WaitQueue::new(),
+            select_waiters: SelectWaiters::new(),
+        }
+    }
+
+    pub fn queue(&self) -> &Q {
+        &self.queue
+    }
+
+    pub fn select_waiters(&self) -> &SelectWaiters {
+        &self.select_waiters
+    }
+
+    pub fn acquire_sender(&self) {
+        self.senders.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn acquire_receiver(&self) {
+        self.receivers.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Drops a sender, wakes up all receivers if it is the last one.
+    pub fn release_sender(&self) {
+        if self.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.recv_wq.notify_all();
+            self.select_waiters.notify();
+        }
+    }
+
+    /// Drops a receiver, wakes up all senders if it is the last one.
+    pub fn release_receiver(&self) {
+        if self.receivers.fetch_sub(1, Ordering::AcqRel) == 1 && Q::BOUNDED {
+            self.send_wq.notify_all();
+        }
+    }
+
+    fn is_send_disconnected(&self) -> bool {
+        self.senders.load(Ordering::Acquire) == 0
+    }
+
+    fn is_recv_disconnected(&self) -> bool {
+        self.receivers.load(Ordering::Acquire) == 0
+    }
+
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        if self.is_recv_disconnected() {
+            return Err(TrySendError::Disconnected(value));
+        }
+        self.queue.push(value).map_err(TrySendError::Full)?;
+        self.recv_wq.notify_one();
+        self.select_waiters.notify();
+        Ok(())
+    }
+
+    /// Sends a message, blocking until there is a free slot or the deadline is
+    /// reached. A timed send also returns if the current task is killed.
+    pub fn send(
+        &self,
+        mut value: T,
+        deadline: Option<TimeValue>,
+    ) -> Result<(), SendTimeoutError<T>> {
+        loop {
+            match self.try_send(value) {
+                Ok(()) => return Ok(()),
+                Err(TrySendError::Disconnected(v)) => {
+                    return Err(SendTimeoutError::Disconnected(v))
+                }
+                Err(TrySendError::Full(v)) => value = v,
+            }
+            let ready = || self.queue.can_push() || self.is_recv_disconnected();
+            match wait_until(&self.send_wq, deadline, ready) {
+                WaitResult::Ready => {}
+                WaitResult::TimedOut => return Err(SendTimeoutError::Timeout(value)),
+                WaitResult::Interrupted => return Err(SendTimeoutError::Interrupted(value)),
+            }
+        }
+    }
+
+    /// # Safety
+    ///
+    /// Same as [`Queue::pop`].
+    pub unsafe fn try_recv(&self) -> Result<T, TryRecvError> {
+        let disconnected = self.is_send_disconnected();
+        // check the queue after the senders, the last messages may be sent
+        // just before they are dropped.
+        match self.queue.pop() {
+            Some(value) => {
+                if Q::BOUNDED {
+                    self.send_wq.notify_one();
+                }
+                Ok(value)
+            }
+            None if disconnected => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Receives a message, blocking until there is one or the deadline is
+    /// reached. A timed receive also returns if the current task is killed.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Queue::pop`].
+    pub unsafe fn recv(&self, deadline: Option<TimeValue>) -> Result<T, RecvTimeoutError> {
+        loop {
+            match self.try_recv() {
+                Ok(value) => return Ok(value),
+                Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) => {}
+            }
+            match wait_until(&self.recv_wq, deadline, || self.is_ready()) {
+                WaitResult::Ready => {}
+                WaitResult::TimedOut => return Err(RecvTimeoutError::Timeout),
+                WaitResult::Interrupted => return Err(RecvTimeoutError::Interrupted),
+            }
+        }
+    }
+
+    /// Whether [`recv`](Self::recv) will not block, i.e., there is a message
+    /// or all senders are dropped.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Queue::pop`].
+    pub unsafe fn is_ready(&self) -> bool {
+        self.queue.can_pop() || self.is_send_disconnected()
+    }
+}
@@ -0,0 +1,135 @@
+//! A condition variable.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+
+use crate::wait_queue::WaitQueue;
+use crate::MutexGuard;
+
+/// A type indicating whether a timed wait on a condition variable returned due
+/// to a time out or not.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct WaitTimeoutResult(bool);
+
+impl WaitTimeoutResult {
+    /// Returns `true` if the wait was known to have timed out.
+    #[must_use]
+    pub fn timed_out(&self) -> bool {
+        self.0
+    }
+}
+
+/// A condition variable, similar to
+/// [`std::sync::Condvar`](https://doc.rust-lang.org/std/sync/struct.Condvar.html).
+///
+/// It is used with a [`Mutex`](crate::Mutex) to block tasks until some
+/// condition becomes true. Like the one in `std`, a task may be woken up
+/// spuriously, so the condition should always be checked in a loop (or use
+/// [`Condvar::wait_while`]).
+///
+/// If the `multitask` feature is not enabled, the waiting task spins instead
+/// of sleeping.
+pub struct Condvar {
+    /// Incremented by each notification, the waiters wait for it to change.
+    seq: AtomicUsize,
+    wq: WaitQueue,
+}
+
+impl Condvar {
+    /// Creates a new condition variable.
+    #[cfg_attr(feature = "lockdep", track_caller)]
+    pub const fn new() -> Self {
+        Self {
+            seq: AtomicUsize::new(0),
+            wq: WaitQueue::new(),
+        }
+    }
+
+    /// Blocks the current task until this condition variable receives a
+    /// notification.
+    ///
+    /// It atomically unlocks the mutex specified (represented by `guard`) and
+    /// blocks the current task, and re-acquires the mutex before returning.
+    pub fn wait<'a, T>(&self, mut guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        let seq = self.seq.load(Ordering::Acquire);
+        MutexGuard::unlocked(&mut guard, || {
+            self.wq
+                .wait_until(|| self.seq.load(Ordering::Acquire) != seq)
+        });
+        guard
+    }
+
+    /// Blocks the current task while `condition` returns `true`.
+    pub fn wait_while<'a, T, F>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        mut condition: F,
+    ) -> MutexGuard<'a, T>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        while condition(&mut *guard) {
+            guard = self.wait(guard);
+        }
+        guard
+    }
+
+    /// Waits on this condition variable for a notification, timing out after
+    /// the specified duration.
+    pub fn wait_timeout<'a, T>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        dur: Duration,
+    ) -> (MutexGuard<'a, T>, WaitTimeoutResult) {
+        let seq = self.seq.load(Ordering::Acquire);
+        let timed_out = MutexGuard::unlocked(&mut guard, || {
+            self.wq
+                .wait_timeout_until(dur, || self.seq.load(Ordering::Acquire) != seq)
+        });
+        (guard, WaitTimeoutResult(timed_out))
+    }
+
+    /// Waits on this condition variable while `condition` returns `true`,
+    /// timing out after the specified duration.
+    ///
+    /// The returned [`WaitTimeoutResult`] indicates whether the condition is
+    /// still `true` after the timeout.
+    pub fn wait_timeout_while<'a, T, F>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        dur: Duration,
+        mut condition: F,
+    ) -> (MutexGuard<'a, T>, WaitTimeoutResult)
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        let deadline = axhal::time::current_time() + dur;
+        while condition(&mut *guard) {
+            let now = axhal::time::current_time();
+            if now >= deadline {
+                return (guard, WaitTimeoutResult(true));
+            }
+            guard = self.wait_timeout(guard, deadline - now).0;
+        }
+        (guard, WaitTimeoutResult(false))
+    }
+
+    /// Wakes up one blocked task on this condition variable.
+    pub fn notify_one(&self) {
+        self.seq.fetch_add(1, Ordering::Release);
+        self.wq.notify_one();
+    }
+
+    /// Wakes up all blocked tasks on this condition variable.
+    pub fn notify_all(&self) {
+        self.seq.fetch_add(1, Ordering::Release);
+        self.wq.notify_all();
+    }
+}
+
+impl Default for Condvar {
+    #[cfg_attr(feature = "lockdep", track_caller)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -2,23 +2,67 @@
 //!
 //! Currently supported primitives:
 //!
-//! - [`Mutex`]: A mutual exclusion primitive.
+//! - [`Mutex`]: A mutual exclusion primitive, with optional priority
+//!   inheritance.
+//! - [`RwLock`]: A writer-preferring reader-writer lock.
+//! - [`Condvar`]: A condition variable.
+//! - [`Semaphore`]: A counting semaphore.
+//! - [`Barrier`]: A barrier to synchronize a group of tasks.
+//! - [`Once`] and [`OnceLock`]: One-time initialization.
 //! - mod [`spin`](spinlock): spin-locks.
+//! - mod [`mpsc`] and [`mpmc`]: Lock-free channels, and [`Select`](mpsc::Select)
+//!   to wait on several of them.
 //!
 //! # Cargo Features
 //!
 //! - `multitask`: For use in the multi-threaded environments. If the feature is
-//!   not enabled, [`Mutex`] will be an alias of [`spin::SpinNoIrq`]. This
-//!   feature is enabled by default.
+//!   not enabled, [`Mutex`] will be an alias of [`spin::SpinNoIrq`], and other
+//!   primitives spin instead of blocking the current task. This feature is
+//!   enabled by default.
+//! - `irq`: Use timers to wake up the tasks waiting with a timeout, such as
+//!   [`Condvar::wait_timeout`]. Otherwise, the waiting tasks poll the time.
+//! - `alloc`: Enable the channels in [`mpsc`] and [`mpmc`], which need a
+//!   global allocator.
+//! - `lockdep`: Check the lock order of [`Mutex`]es and spin locks, and
+//!   sleeping while holding spin locks, by the `lockdep` validator. It also
+//!   enables the `multitask` feature.
 
 #![cfg_attr(not(test), no_std)]
 #![feature(doc_cfg)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 pub use spinlock as spin;
 
+mod barrier;
+mod condvar;
+mod once;
+mod rwlock;
+mod semaphore;
+mod wait_queue;
+
 #[cfg(feature = "multitask")]
 mod mutex;
 
+#[cfg(feature = "alloc")]
+mod channel;
+#[cfg(feature = "alloc")]
+#[doc(cfg(feature = "alloc"))]
+pub mod mpmc;
+#[cfg(feature = "alloc")]
+#[doc(cfg(feature = "alloc"))]
+pub mod mpsc;
+
+#[cfg(test)]
+mod tests;
+
+pub use self::barrier::{Barrier, BarrierWaitResult};
+pub use self::condvar::{Condvar, WaitTimeoutResult};
+pub use self::once::{Once, OnceLock};
+pub use self::rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+pub use self::semaphore::{Semaphore, SemaphoreGuard};
+
 #[cfg(feature = "multitask")]
 #[doc(cfg(feature = "multitask"))]
 pub use self::mutex::{Mutex, MutexGuard};
@@ -0,0 +1,249 @@
+//! Multi-producer, multi-consumer bounded FIFO queue communication primitives.
+//!
+//! A [`bounded`] channel keeps at most `cap` messages in a lock-free ring
+//! buffer. Both [`Sender`] and [`Receiver`] can be cloned and shared between
+//! tasks, each message is received by exactly one of the receivers. Senders
+//! block when the channel is full, and receivers block when it is empty.
+//!
+//! The channel is disconnected when all senders or all receivers are dropped,
+//! with the same semantics as [`mpsc`](crate::mpsc), whose error types are
+//! also used here.
+//!
+//! # Examples
+//!
+//! ```
+//! use axsync::{mpmc, mpsc};
+//!
+//! # axtask::init_scheduler();
+//! let (tx, rx) = mpmc::bounded(2);
+//! let (done_tx, done_rx) = mpsc::channel();
+//! for _ in 0..4 {
+//!     let rx = rx.clone();
+//!     let done_tx = done_tx.clone();
+//!     axtask::spawn(move || done_tx.send(rx.iter().count()).unwrap());
+//! }
+//! drop(done_tx);
+//! for i in 0..100 {
+//!     tx.send(i).unwrap();
+//! }
+//! drop(tx);
+//!
+//! assert_eq!(done_rx.iter().sum::<usize>(), 100);
+//! ```
+
+use alloc::sync::Arc;
+use core::fmt;
+use core::time::Duration;
+
+use crate::channel::{ArrayQueue, Channel};
+use crate::mpsc::{Selectable, SendError, SendTimeoutError, TrySendError};
+
+pub use crate::mpsc::{RecvError, RecvTimeoutError, TryRecvError};
+
+/// Creates a new bounded channel that holds at most `cap` messages,
+/// returning the sender/receiver halves.
+///
+/// # Panics
+///
+/// Panics if `cap` is zero.
+pub fn bounded<T>(cap: usize) -> (Sender<T>, Receiver<T>) {
+    let chan = Arc::new(Channel::new(ArrayQueue::new(cap)));
+    (Sender { chan: chan.clone() }, Receiver { chan })
+}
+
+/// The sending half of a [`bounded`] channel.
+pub struct Sender<T> {
+    chan: Arc<Channel<ArrayQueue<T>>>,
+}
+
+/// The receiving half of a [`bounded`] channel.
+pub struct Receiver<T> {
+    chan: Arc<Channel<ArrayQueue<T>>>,
+}
+
+impl<T> Sender<T> {
+    /// Sends a message to the channel, blocking the current task until there
+    /// is a free slot.
+    ///
+    /// Returns the message back if all receivers have been dropped. Like std,
+    /// it is not interrupted if the current task is killed, use
+    /// [`send_timeout`](Self::send_timeout) for that.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        self.chan.send(value, None).map_err(|e| match e {
+            SendTimeoutError::Timeout(v)
+            | SendTimeoutError::Disconnected(v)
+            | SendTimeoutError::Interrupted(v) => SendError(v),
+        })
+    }
+
+    /// Sends a message to the channel without blocking.
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        self.chan.try_send(value)
+    }
+
+    /// Sends a message to the channel, blocking the current task until there
+    /// is a free slot, or the given duration has elapsed.
+    pub fn send_timeout(&self, value: T, timeout: Duration) -> Result<(), SendTimeoutError<T>> {
+        let deadline = axhal::time::current_time() + timeout;
+        self.chan.send(value, Some(deadline))
+    }
+
+    /// Returns the maximum number of messages the channel can hold.
+    pub fn capacity(&self) -> usize {
+        self.chan.queue().capacity()
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.chan.acquire_sender();
+        Self {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.chan.release_sender();
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+// Safety: the array queue supports concurrent consumers, so the unsafe
+// methods of the channel can be called from any receiver.
+impl<T> Receiver<T> {
+    /// Receives a message from the channel without blocking.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        unsafe { self.chan.try_recv() }
+    }
+
+    /// Receives a message from the channel, blocking the current task until
+    /// there is one.
+    ///
+    /// Returns an error if the channel is empty and all senders are dropped.
+    /// Like std, it is not interrupted if the current task is killed, use
+    /// [`recv_timeout`](Self::recv_timeout) for that.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        unsafe { self.chan.recv(None) }.map_err(|_| RecvError)
+    }
+
+    /// Receives a message from the channel, blocking the current task until
+    /// there is one, or the given duration has elapsed.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline = axhal::time::current_time() + timeout;
+        unsafe { self.chan.recv(Some(deadline)) }
+    }
+
+    /// Returns an iterator that blocks waiting for messages, until all
+    /// senders are dropped.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { rx: self }
+    }
+
+    /// Returns an iterator that yields the messages currently in the channel
+    /// without blocking.
+    pub fn try_iter(&self) -> TryIter<'_, T> {
+        TryIter { rx: self }
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        self.chan.acquire_receiver();
+        Self {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.chan.release_receiver();
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver").finish_non_exhaustive()
+    }
+}
+
+impl<T> crate::mpsc::private::Sealed for Receiver<T> {
+    fn select_waiters(&self) -> &crate::channel::SelectWaiters {
+        self.chan.select_waiters()
+    }
+}
+
+impl<T> Selectable for Receiver<T> {
+    fn is_ready(&self) -> bool {
+        unsafe { self.chan.is_ready() }
+    }
+}
+
+/// An iterator over messages on a [`Receiver`], created by
+/// [`Receiver::iter`].
+#[derive(Debug)]
+pub struct Iter<'a, T> {
+    rx: &'a Receiver<T>,
+}
+
+/// An iterator that yields the messages currently in a [`Receiver`], created
+/// by [`Receiver::try_iter`].
+#[derive(Debug)]
+pub struct TryIter<'a, T> {
+    rx: &'a Receiver<T>,
+}
+
+/// An owning iterator over messages on a [`Receiver`].
+#[derive(Debug)]
+pub struct IntoIter<T> {
+    rx: Receiver<T>,
+}
+
+impl<T> Iterator for Iter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+}
+
+impl<T> Iterator for TryIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.try_recv().ok()
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Receiver<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for Receiver<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { rx: self }
+    }
+}
@@ -0,0 +1,493 @@
+//! Multi-producer, single-consumer FIFO queue communication primitives.
+//!
+//! It is similar to [`std::sync::mpsc`], a [`channel`] has any number of
+//! [`Sender`]s and one [`Receiver`]. The messages are kept in a lock-free
+//! queue, and the receiver sleeps in a wait queue when the channel is empty
+//! (or spins if the `multitask` feature is not enabled).
+//!
+//! The channel is disconnected when all senders or the receiver are dropped,
+//! [`Receiver::recv`] then returns the remaining messages before an error,
+//! and [`Sender::send`] returns the message back.
+//!
+//! See [`mpmc`](crate::mpmc) for bounded channels with multiple receivers,
+//! and [`Select`] to wait on several receivers at the same time.
+//!
+//! [`std::sync::mpsc`]: https://doc.rust-lang.org/std/sync/mpsc/index.html
+//!
+//! # Examples
+//!
+//! ```
+//! use axsync::mpsc::channel;
+//!
+//! # axtask::init_scheduler();
+//! let (tx, rx) = channel();
+//! for i in 0..10 {
+//!     let tx = tx.clone();
+//!     axtask::spawn(move || tx.send(i).unwrap());
+//! }
+//! drop(tx);
+//!
+//! assert_eq!(rx.iter().sum::<i32>(), 45);
+//! ```
+
+use alloc::{sync::Arc, vec::Vec};
+use core::cell::Cell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::time::Duration;
+
+use axhal::time::TimeValue;
+
+use crate::channel::{select_wait, Channel, ListQueue};
+use crate::wait_queue::WaitResult;
+
+/// An error returned from [`Sender::send`], when the receiver is dropped. The
+/// message is returned back.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct SendError<T>(pub T);
+
+/// An error returned from [`Receiver::recv`], when the channel is empty and
+/// all senders are dropped.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct RecvError;
+
+/// An error returned from [`Receiver::try_recv`].
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TryRecvError {
+    /// The channel is currently empty, but the senders are alive.
+    Empty,
+    /// The channel is empty, and all senders are dropped.
+    Disconnected,
+}
+
+/// An error returned from [`Receiver::recv_timeout`].
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum RecvTimeoutError {
+    /// No message arrived before the timeout, but the senders are alive.
+    Timeout,
+    /// The channel is empty, and all senders are dropped.
+    Disconnected,
+    /// The current task is killed while waiting (see [`axtask::kill`]).
+    Interrupted,
+}
+
+/// An error returned from the `try_send` method of bounded channels.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum TrySendError<T> {
+    /// The channel is full. The message is returned back.
+    Full(T),
+    /// All receivers are dropped. The message is returned back.
+    Disconnected(T),
+}
+
+/// An error returned from the `send_timeout` method of bounded channels.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum SendTimeoutError<T> {
+    /// The channel is still full after the timeout. The message is returned
+    /// back.
+    Timeout(T),
+    /// All receivers are dropped. The message is returned back.
+    Disconnected(T),
+    /// The current task is killed while waiting (see [`axtask::kill`]). The
+    /// message is returned back.
+    Interrupted(T),
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "sending on a closed channel".fmt(f)
+    }
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "receiving on a closed channel".fmt(f)
+    }
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => "receiving on an empty channel".fmt(f),
+            Self::Disconnected => "receiving on a closed channel".fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for RecvTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout => "timed out waiting on channel".fmt(f),
+            Self::Disconnected => "channel is empty and sending half is closed".fmt(f),
+            Self::Interrupted => "interrupted waiting on channel".fmt(f),
+        }
+    }
+}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full(..) => "Full(..)".fmt(f),
+            Self::Disconnected(..) => "Disconnected(..)".fmt(f),
+        }
+    }
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full(..) => "sending on a full channel".fmt(f),
+            Self::Disconnected(..) => "sending on a closed channel".fmt(f),
+        }
+    }
+}
+
+impl<T> fmt::Debug for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout(..) => "Timeout(..)".fmt(f),
+            Self::Disconnected(..) => "Disconnected(..)".fmt(f),
+            Self::Interrupted(..) => "Interrupted(..)".fmt(f),
+        }
+    }
+}
+
+impl<T> fmt::Display for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout(..) => "timed out waiting on send operation".fmt(f),
+            Self::Disconnected(..) => "sending on a closed channel".fmt(f),
+            Self::Interrupted(..) => "interrupted waiting on send operation".fmt(f),
+        }
+    }
+}
+
+impl<T> From<SendError<T>> for TrySendError<T> {
+    fn from(err: SendError<T>) -> Self {
+        Self::Disconnected(err.0)
+    }
+}
+
+impl<T> From<SendError<T>> for SendTimeoutError<T> {
+    fn from(err: SendError<T>) -> Self {
+        Self::Disconnected(err.0)
+    }
+}
+
+impl From<RecvError> for TryRecvError {
+    fn from(_: RecvError) -> Self {
+        Self::Disconnected
+    }
+}
+
+impl From<RecvError> for RecvTimeoutError {
+    fn from(_: RecvError) -> Self {
+        Self::Disconnected
+    }
+}
+
+/// Creates a new unbounded channel, returning the sender/receiver halves.
+///
+/// The [`Sender`] can be cloned to send to the same channel multiple times,
+/// but only one [`Receiver`] is supported. Sending never blocks.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let chan = Arc::new(Channel::new(ListQueue::new()));
+    (
+        Sender { chan: chan.clone() },
+        Receiver {
+            chan,
+            _not_sync: PhantomData,
+        },
+    )
+}
+
+/// The sending half of a [`channel`].
+pub struct Sender<T> {
+    chan: Arc<Channel<ListQueue<T>>>,
+}
+
+/// The receiving half of a [`channel`].
+///
+/// It can be sent to another task, but can not be shared between tasks.
+pub struct Receiver<T> {
+    chan: Arc<Channel<ListQueue<T>>>,
+    /// The queue only supports one consumer at a time.
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+impl<T> Sender<T> {
+    /// Sends a message to the channel, it never blocks.
+    ///
+    /// Returns the message back if the receiver has been dropped.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        self.chan.try_send(value).map_err(|e| match e {
+            TrySendError::Full(v) | TrySendError::Disconnected(v) => SendError(v),
+        })
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.chan.acquire_sender();
+        Self {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.chan.release_sender();
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Receives a message from the channel without blocking.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        // Safety: the receiver is the only consumer, and it is not `Sync`.
+        unsafe { self.chan.try_recv() }
+    }
+
+    /// Receives a message from the channel, blocking the current task until
+    /// there is one.
+    ///
+    /// Returns an error if the channel is empty and all senders are dropped.
+    /// Like std, it is not interrupted if the current task is killed, use
+    /// [`recv_timeout`](Self::recv_timeout) for that.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        unsafe { self.chan.recv(None) }.map_err(|_| RecvError)
+    }
+
+    /// Receives a message from the channel, blocking the current task until
+    /// there is one, or the given duration has elapsed.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline = axhal::time::current_time() + timeout;
+        unsafe { self.chan.recv(Some(deadline)) }
+    }
+
+    /// Returns an iterator that blocks waiting for messages, until all
+    /// senders are dropped.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { rx: self }
+    }
+
+    /// Returns an iterator that yields the messages currently in the channel
+    /// without blocking.
+    pub fn try_iter(&self) -> TryIter<'_, T> {
+        TryIter { rx: self }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.chan.release_receiver();
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver").finish_non_exhaustive()
+    }
+}
+
+/// An iterator over messages on a [`Receiver`], created by
+/// [`Receiver::iter`].
+#[derive(Debug)]
+pub struct Iter<'a, T> {
+    rx: &'a Receiver<T>,
+}
+
+/// An iterator that yields the messages currently in a [`Receiver`], created
+/// by [`Receiver::try_iter`].
+#[derive(Debug)]
+pub struct TryIter<'a, T> {
+    rx: &'a Receiver<T>,
+}
+
+/// An owning iterator over messages on a [`Receiver`].
+#[derive(Debug)]
+pub struct IntoIter<T> {
+    rx: Receiver<T>,
+}
+
+impl<T> Iterator for Iter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+}
+
+impl<T> Iterator for TryIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.try_recv().ok()
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Receiver<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for Receiver<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { rx: self }
+    }
+}
+
+pub(crate) mod private {
+    pub trait Sealed {
+        /// The waiters of [`Select`](super::Select) on the channel.
+        fn select_waiters(&self) -> &crate::channel::SelectWaiters;
+    }
+}
+
+/// Receivers that can be waited on by [`Select`].
+///
+/// It is implemented by [`mpsc::Receiver`](Receiver) and
+/// [`mpmc::Receiver`](crate::mpmc::Receiver), and can not be implemented
+/// outside of this crate.
+pub trait Selectable: private::Sealed {
+    /// Whether receiving from it will not block, i.e., there is a message or
+    /// all senders are dropped.
+    fn is_ready(&self) -> bool;
+}
+
+impl<T> private::Sealed for Receiver<T> {
+    fn select_waiters(&self) -> &crate::channel::SelectWaiters {
+        self.chan.select_waiters()
+    }
+}
+
+impl<T> Selectable for Receiver<T> {
+    fn is_ready(&self) -> bool {
+        unsafe { self.chan.is_ready() }
+    }
+}
+
+/// Waits on several receivers until one of them is ready.
+///
+/// A receiver is ready when receiving from it will not block, i.e., there is
+/// a message or all senders are dropped. The receivers are checked in a
+/// round-robin order, so that a busy one does not starve the others.
+///
+/// If a receiver of an [`mpmc`](crate::mpmc) channel is ready, the message may
+/// still be taken by another receiver before it is received, so use
+/// `try_recv` on it.
+///
+/// # Examples
+///
+/// ```
+/// use axsync::mpsc::{channel, Select};
+///
+/// # axtask::init_scheduler();
+/// let (tx1, rx1) = channel::<i32>();
+/// let (tx2, rx2) = channel::<&str>();
+/// tx2.send("hello").unwrap();
+///
+/// let mut sel = Select::new();
+/// let idx1 = sel.recv(&rx1);
+/// let idx2 = sel.recv(&rx2);
+/// assert_eq!(sel.ready(), Some(idx2));
+/// assert_eq!(rx2.try_recv(), Ok("hello"));
+/// # drop((idx1, tx1));
+/// ```
+#[derive(Default)]
+pub struct Select<'a> {
+    handles: Vec<&'a dyn Selectable>,
+    /// The index to start checking in the next round.
+    next: Cell<usize>,
+}
+
+impl<'a> Select<'a> {
+    /// Creates an empty set of receivers.
+    pub fn new() -> Self {
+        Self {
+            handles: Vec::new(),
+            next: Cell::new(0),
+        }
+    }
+
+    /// Adds a receiver to wait on, and returns its index.
+    pub fn recv<R: Selectable>(&mut self, rx: &'a R) -> usize {
+        self.handles.push(rx);
+        self.handles.len() - 1
+    }
+
+    /// Returns the index of a ready receiver without blocking, or `None` if
+    /// none of them is ready.
+    pub fn try_ready(&self) -> Option<usize> {
+        let n = self.handles.len();
+        let start = self.next.get();
+        let idx = (0..n)
+            .map(|i| (start + i) % n)
+            .find(|&i| self.handles[i].is_ready())?;
+        self.next.set(idx + 1);
+        Some(idx)
+    }
+
+    /// Blocks the current task until one of the receivers is ready, and
+    /// returns its index.
+    ///
+    /// Returns `None` if the current task is killed while waiting (see
+    /// [`axtask::kill`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if no receiver is added.
+    pub fn ready(&self) -> Option<usize> {
+        assert!(!self.handles.is_empty(), "no receiver to select");
+        self.wait(None)
+    }
+
+    /// Blocks the current task until one of the receivers is ready, or the
+    /// given duration has elapsed.
+    ///
+    /// Returns the index of the ready receiver, or `None` if it is timed out
+    /// or the current task is killed while waiting.
+    pub fn ready_timeout(&self, timeout: Duration) -> Option<usize> {
+        let deadline = axhal::time::current_time() + timeout;
+        self.wait(Some(deadline))
+    }
+
+    fn wait(&self, deadline: Option<TimeValue>) -> Option<usize> {
+        let idx = Cell::new(None);
+        let channels = self.handles.iter().map(|rx| rx.select_waiters());
+        match select_wait(channels, deadline, || {
+            idx.set(self.try_ready());
+            idx.get().is_some()
+        }) {
+            WaitResult::Ready => idx.get(),
+            WaitResult::TimedOut | WaitResult::Interrupted => None,
+        }
+    }
+}
@@ -5,7 +5,10 @@ use core::fmt;
 use core::ops::{Deref, DerefMut};
 use core::sync::atomic::{AtomicU64, Ordering};
 
-use axtask::{current, WaitQueue};
+use axtask::{current, AxTaskRef, WaitQueue};
+#[cfg(feature = "lockdep")]
+use lockdep::{LockClass, LockKind};
+use spinlock::SpinNoIrq;
 
 /// A mutual exclusion primitive useful for protecting shared data, similar to
 /// [`std::sync::Mutex`](https://doc.rust-lang.org/std/sync/struct.Mutex.html).
@@ -13,12 +16,39 @@ use axtask::{current, WaitQueue};
 /// When the mutex is locked, the current task will block and be put into the
 /// wait queue. When the mutex is unlocked, all tasks waiting on the queue
 /// will be woken up.
+///
+/// # Priority inheritance
+///
+/// A mutex created by [`Mutex::with_priority_inheritance`] avoids the priority
+/// inversion: while a task with a higher priority is waiting for the mutex,
+/// the priority of the owner is boosted to the waiter's, so that it can not be
+/// preempted by tasks with medium priorities. The waiters are woken up in the
+/// order of their priorities, and the one that acquires the mutex inherits
+/// the highest priority of the remaining waiters. The boost is removed when
+/// the owner releases the mutex, while the boosts from other mutexes it still
+/// holds remain (see [`axtask::boost_task_priority`]).
+///
+/// The boost is not transitive, i.e., it is not propagated to the owner of
+/// another mutex which the boosted owner is waiting for.
 pub struct Mutex<T: ?Sized> {
     wq: WaitQueue,
     owner_id: AtomicU64,
+    pi: Option<PiState>,
+    #[cfg(feature = "lockdep")]
+    class: LockClass,
     data: UnsafeCell<T>,
 }
 
+/// The states of a mutex with priority inheritance.
+struct PiState {
+    /// The task which holds the mutex.
+    owner: SpinNoIrq<Option<AxTaskRef>>,
+    /// Increased each time the mutex is released. Waiters wait for it to
+    /// change rather than `owner_id`, which is the same again if the owner
+    /// relocks the mutex before they run.
+    generation: AtomicU64,
+}
+
 /// A guard that provides mutable data access.
 ///
 /// When the guard falls out of scope it will release the lock.
@@ -34,10 +64,32 @@ unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
 impl<T> Mutex<T> {
     /// Creates a new [`Mutex`] wrapping the supplied data.
     #[inline(always)]
+    #[cfg_attr(feature = "lockdep", track_caller)]
     pub const fn new(data: T) -> Self {
         Self {
             wq: WaitQueue::new(),
             owner_id: AtomicU64::new(0),
+            pi: None,
+            #[cfg(feature = "lockdep")]
+            class: LockClass::new(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Creates a new [`Mutex`] wrapping the supplied data, with the priority
+    /// inheritance enabled.
+    #[inline(always)]
+    #[cfg_attr(feature = "lockdep", track_caller)]
+    pub const fn with_priority_inheritance(data: T) -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            owner_id: AtomicU64::new(0),
+            pi: Some(PiState {
+                owner: SpinNoIrq::new(None),
+                generation: AtomicU64::new(0),
+            }),
+            #[cfg(feature = "lockdep")]
+            class: LockClass::new(),
             data: UnsafeCell::new(data),
         }
     }
@@ -64,11 +116,27 @@ impl<T: ?Sized> Mutex<T> {
         self.owner_id.load(Ordering::Relaxed) != 0
     }
 
+    /// Returns `true` if the priority inheritance is enabled.
+    #[inline(always)]
+    pub fn is_priority_inheritance(&self) -> bool {
+        self.pi.is_some()
+    }
+
     /// Locks the [`Mutex`] and returns a guard that permits access to the inner data.
     ///
     /// The returned value may be dereferenced for data access
     /// and the lock will be dropped when the guard falls out of scope.
+    ///
+    /// It may block, so it must not be called in atomic context (see
+    /// [`axtask::might_sleep`]).
+    #[track_caller]
     pub fn lock(&self) -> MutexGuard<T> {
+        axtask::might_sleep();
+        #[cfg(feature = "lockdep")]
+        self.lockdep_acquire(false);
+        if let Some(pi) = &self.pi {
+            return self.lock_pi(pi);
+        }
         let current_id = current().id().as_u64();
         loop {
             // Can fail to lock even if the spinlock is not locked. May be more efficient than `try_lock`
@@ -98,10 +166,72 @@ impl<T: ?Sized> Mutex<T> {
         }
     }
 
+    fn lock_pi(&self, pi: &PiState) -> MutexGuard<T> {
+        let curr = current();
+        let current_id = curr.id().as_u64();
+        loop {
+            // The owner is recorded together with `owner_id` under the lock of
+            // `pi.owner`, so a waiter always sees the task it should boost.
+            let mut owner = pi.owner.lock();
+            match self.owner_id.compare_exchange(
+                0,
+                current_id,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    self.pi_acquired(&mut owner, curr.as_task_ref());
+                    break;
+                }
+                Err(owner_id) => {
+                    assert_ne!(
+                        owner_id,
+                        current_id,
+                        "{} tried to acquire mutex it already owns.",
+                        curr.id_name()
+                    );
+                    // The owner may have changed since the last try, boost the
+                    // current one with the highest priority of the waiters.
+                    let prio = match self.wq.highest_priority() {
+                        Some(p) => p.min(curr.priority()),
+                        None => curr.priority(),
+                    };
+                    axtask::boost_task_priority(owner.as_ref().unwrap(), self.pi_key(), prio);
+                    let generation = pi.generation.load(Ordering::Relaxed);
+                    drop(owner);
+                    // Wait until the owner releases the mutex before retrying
+                    self.wq
+                        .wait_until(|| pi.generation.load(Ordering::Relaxed) != generation);
+                }
+            }
+        }
+        MutexGuard {
+            lock: self,
+            data: unsafe { &mut *self.data.get() },
+        }
+    }
+
+    /// Records the new owner of a mutex with priority inheritance, which
+    /// inherits the highest priority of the remaining waiters.
+    fn pi_acquired(&self, pi_owner: &mut Option<AxTaskRef>, owner: &AxTaskRef) {
+        *pi_owner = Some(owner.clone());
+        if let Some(prio) = self.wq.highest_priority() {
+            axtask::boost_task_priority(owner, self.pi_key(), prio);
+        }
+    }
+
+    /// The key of the priority boosts caused by this mutex.
+    fn pi_key(&self) -> usize {
+        self as *const Self as *const u8 as usize
+    }
+
     /// Try to lock this [`Mutex`], returning a lock guard if successful.
     #[inline(always)]
+    #[cfg_attr(feature = "lockdep", track_caller)]
     pub fn try_lock(&self) -> Option<MutexGuard<T>> {
-        let current_id = current().id().as_u64();
+        let curr = current();
+        let current_id = curr.id().as_u64();
+        let mut pi_owner = self.pi.as_ref().map(|pi| pi.owner.lock());
         // The reason for using a strong compare_exchange is explained here:
         // https://github.com/Amanieu/parking_lot/pull/207#issuecomment-575869107
         if self
@@ -109,6 +239,11 @@ impl<T: ?Sized> Mutex<T> {
             .compare_exchange(0, current_id, Ordering::Acquire, Ordering::Relaxed)
             .is_ok()
         {
+            if let Some(pi_owner) = &mut pi_owner {
+                self.pi_acquired(pi_owner, curr.as_task_ref());
+            }
+            #[cfg(feature = "lockdep")]
+            self.lockdep_acquire(true);
             Some(MutexGuard {
                 lock: self,
                 data: unsafe { &mut *self.data.get() },
@@ -126,14 +261,28 @@ impl<T: ?Sized> Mutex<T> {
     /// thread. However, this can be useful in some instances for exposing
     /// the lock to FFI that doesn’t know how to deal with RAII.
     pub unsafe fn force_unlock(&self) {
+        #[cfg(feature = "lockdep")]
+        lockdep::lock_release(&self.class);
+        let curr = current();
+        let pi_owner = self.pi.as_ref().map(|pi| (pi, pi.owner.lock()));
         let owner_id = self.owner_id.swap(0, Ordering::Release);
         assert_eq!(
             owner_id,
-            current().id().as_u64(),
+            curr.id().as_u64(),
             "{} tried to release mutex it doesn't own",
-            current().id_name()
+            curr.id_name()
         );
-        self.wq.notify_one(true);
+        if let Some((pi, mut pi_owner)) = pi_owner {
+            *pi_owner = None;
+            pi.generation.fetch_add(1, Ordering::Relaxed);
+            // Remove the boost before waking up the waiter, so that it can
+            // preempt us immediately.
+            axtask::unboost_task_priority(curr.as_task_ref(), self.pi_key());
+            drop(pi_owner);
+            self.wq.notify_highest_priority(true);
+        } else {
+            self.wq.notify_one(true);
+        }
     }
 
     /// Returns a mutable reference to the underlying data.
@@ -147,10 +296,23 @@ impl<T: ?Sized> Mutex<T> {
         // there's no need to lock the inner mutex.
         unsafe { &mut *self.data.get() }
     }
+
+    #[cfg(feature = "lockdep")]
+    #[track_caller]
+    fn lockdep_acquire(&self, trylock: bool) {
+        lockdep::lock_acquire(
+            &self.class,
+            LockKind::Sleep,
+            core::any::type_name::<Self>(),
+            core::panic::Location::caller(),
+            trylock,
+        );
+    }
 }
 
 impl<T: ?Sized + Default> Default for Mutex<T> {
     #[inline(always)]
+    #[cfg_attr(feature = "lockdep", track_caller)]
     fn default() -> Self {
         Self::new(Default::default())
     }
@@ -167,6 +329,25 @@ impl<T: ?Sized + fmt::Debug> fmt::Debug for Mutex<T> {
     }
 }
 
+impl<'a, T: ?Sized> MutexGuard<'a, T> {
+    /// Temporarily unlocks the mutex to execute the given function, then locks
+    /// it again.
+    ///
+    /// This is safe because `&mut` guarantees that there exist no other
+    /// references to the data protected by the mutex.
+    #[cfg_attr(feature = "lockdep", track_caller)]
+    pub fn unlocked<F, U>(guard: &mut Self, f: F) -> U
+    where
+        F: FnOnce() -> U,
+    {
+        unsafe { guard.lock.force_unlock() };
+        let ret = f();
+        // Keep it locked, the data pointer is still valid.
+        core::mem::forget(guard.lock.lock());
+        ret
+    }
+}
+
 impl<'a, T: ?Sized> Deref for MutexGuard<'a, T> {
     type Target = T;
     #[inline(always)]
@@ -199,11 +380,12 @@ impl<'a, T: ?Sized> Drop for MutexGuard<'a, T> {
 
 #[cfg(test)]
 mod tests {
+    use crate::tests::{INIT, SERIAL};
     use crate::Mutex;
     use axtask as thread;
-    use std::sync::Once;
-
-    static INIT: Once = Once::new();
+    use axtask::WaitQueue;
+    use std::sync::atomic::{AtomicBool, AtomicIsize, AtomicUsize, Ordering};
+    use std::sync::Arc;
 
     fn may_interrupt() {
         // simulate interrupts
@@ -214,6 +396,7 @@ mod tests {
 
     #[test]
     fn lots_and_lots() {
+        let _lock = SERIAL.lock();
         INIT.call_once(thread::init_scheduler);
 
         const NUM_TASKS: u32 = 10;
@@ -249,4 +432,148 @@ mod tests {
         assert_eq!(*M.lock(), NUM_ITERS * NUM_TASKS * 3);
         println!("Mutex test OK");
     }
+
+    /// Lowers the priority of the main task, so that it runs only if all other
+    /// tasks are blocked or exited. Returns the original priority, or `None`
+    /// if the scheduler does not support priorities.
+    ///
+    /// The priority tests are skipped in that case, run them with
+    /// `cargo test -p axsync --features axtask/sched_prio`.
+    fn lower_main_priority() -> Option<isize> {
+        let orig_prio = thread::current().priority();
+        if thread::set_priority(99) {
+            Some(orig_prio)
+        } else {
+            println!("Skipped: the scheduler does not support priorities");
+            None
+        }
+    }
+
+    /// Runs a low, a medium and a high priority task, where the low and the
+    /// high ones share the mutex `m`. Returns whether the high priority task
+    /// acquires the mutex before the medium one finishes.
+    fn high_prio_runs_first(m: &'static Mutex<()>, orig_prio: isize) -> bool {
+        const NUM_YIELDS: usize = 10;
+        let seq = Arc::new(AtomicUsize::new(0));
+        let high_order = Arc::new(AtomicUsize::new(usize::MAX));
+        let medium_order = Arc::new(AtomicUsize::new(usize::MAX));
+        let locked = Arc::new(AtomicBool::new(false));
+        let go = Arc::new(AtomicBool::new(false));
+        let go_wq = Arc::new(WaitQueue::new());
+
+        let (l, g, wq) = (locked.clone(), go.clone(), go_wq.clone());
+        let low = thread::spawn(move || {
+            let _guard = m.lock();
+            l.store(true, Ordering::Release);
+            wq.wait_until(|| g.load(Ordering::Acquire));
+            for _ in 0..NUM_YIELDS {
+                thread::yield_now();
+            }
+        });
+        assert!(thread::set_task_priority(&low, 90));
+        while !locked.load(Ordering::Acquire) {
+            thread::yield_now();
+        }
+
+        let (s, order) = (seq.clone(), high_order.clone());
+        let high = thread::spawn(move || {
+            let _guard = m.lock();
+            order.store(s.fetch_add(1, Ordering::Relaxed), Ordering::Release);
+        });
+        assert!(thread::set_task_priority(&high, 10));
+        let (s, order) = (seq.clone(), medium_order.clone());
+        let medium = thread::spawn(move || {
+            for _ in 0..NUM_YIELDS * 2 {
+                thread::yield_now();
+            }
+            order.store(s.fetch_add(1, Ordering::Relaxed), Ordering::Release);
+        });
+        assert!(thread::set_task_priority(&medium, 50));
+
+        go.store(true, Ordering::Release);
+        go_wq.notify_one(false);
+        while seq.load(Ordering::Acquire) < 2 {
+            thread::yield_now();
+        }
+        assert!(thread::set_priority(orig_prio));
+        assert_eq!(low.priority(), 90);
+        high_order.load(Ordering::Acquire) < medium_order.load(Ordering::Acquire)
+    }
+
+    #[test]
+    fn priority_inversion() {
+        let _lock = SERIAL.lock();
+        INIT.call_once(thread::init_scheduler);
+
+        static M: Mutex<()> = Mutex::new(());
+        let Some(orig_prio) = lower_main_priority() else {
+            return;
+        };
+        assert!(!high_prio_runs_first(&M, orig_prio));
+        println!("Priority inversion reproduced");
+    }
+
+    #[test]
+    fn priority_inheritance() {
+        let _lock = SERIAL.lock();
+        INIT.call_once(thread::init_scheduler);
+
+        static M: Mutex<()> = Mutex::with_priority_inheritance(());
+        let Some(orig_prio) = lower_main_priority() else {
+            return;
+        };
+        assert!(high_prio_runs_first(&M, orig_prio));
+        println!("Priority inheritance test OK");
+    }
+
+    #[test]
+    fn nested_priority_inheritance() {
+        let _lock = SERIAL.lock();
+        INIT.call_once(thread::init_scheduler);
+
+        static A: Mutex<()> = Mutex::with_priority_inheritance(());
+        static B: Mutex<()> = Mutex::with_priority_inheritance(());
+        static PRIOS: [AtomicIsize; 3] = [const { AtomicIsize::new(0) }; 3];
+        let Some(orig_prio) = lower_main_priority() else {
+            return;
+        };
+
+        let locked = Arc::new(AtomicBool::new(false));
+        let go = Arc::new(AtomicBool::new(false));
+        let go_wq = Arc::new(WaitQueue::new());
+        let (l, g, wq) = (locked.clone(), go.clone(), go_wq.clone());
+        let low = thread::spawn(move || {
+            let a = A.lock();
+            let b = B.lock();
+            l.store(true, Ordering::Release);
+            wq.wait_until(|| g.load(Ordering::Acquire));
+            let prio = || thread::current().priority();
+            PRIOS[0].store(prio(), Ordering::Release);
+            drop(a);
+            PRIOS[1].store(prio(), Ordering::Release);
+            drop(b);
+            PRIOS[2].store(prio(), Ordering::Release);
+        });
+        assert!(thread::set_task_priority(&low, 90));
+        while !locked.load(Ordering::Acquire) {
+            thread::yield_now();
+        }
+
+        // They block on the mutexes before the main task runs again.
+        let high = thread::spawn(|| drop(A.lock()));
+        assert!(thread::set_task_priority(&high, 10));
+        let medium = thread::spawn(|| drop(B.lock()));
+        assert!(thread::set_task_priority(&medium, 20));
+        thread::yield_now();
+        assert_eq!(low.priority(), 10);
+
+        go.store(true, Ordering::Release);
+        go_wq.notify_one(false);
+        assert_eq!(low.join(), Some(0));
+        assert!(thread::set_priority(orig_prio));
+        // Releasing `A` keeps the boost from `B`, which is removed at last.
+        let prios = PRIOS.each_ref().map(|p| p.load(Ordering::Acquire));
+        assert_eq!(prios, [10, 20, 90]);
+        println!("Nested priority inheritance test OK");
+    }
 }
@@ -0,0 +1,95 @@
+//! A counting semaphore.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+
+use crate::wait_queue::WaitQueue;
+
+/// A counting semaphore.
+///
+/// It maintains a number of permits. [`Semaphore::acquire`] blocks the current
+/// task until a permit is available and takes it, and [`Semaphore::release`]
+/// gives a permit back.
+///
+/// If the `multitask` feature is not enabled, the waiting task spins instead
+/// of sleeping.
+pub struct Semaphore {
+    count: AtomicUsize,
+    wq: WaitQueue,
+}
+
+/// A guard that holds a permit of the [`Semaphore`], which is released when
+/// it falls out of scope.
+pub struct SemaphoreGuard<'a> {
+    sem: &'a Semaphore,
+}
+
+impl Semaphore {
+    /// Creates a new semaphore with the initial number of permits.
+    #[cfg_attr(feature = "lockdep", track_caller)]
+    pub const fn new(count: usize) -> Self {
+        Self {
+            count: AtomicUsize::new(count),
+            wq: WaitQueue::new(),
+        }
+    }
+
+    /// Returns the number of available permits.
+    pub fn available_permits(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Acquires a permit, blocking the current task until one is available.
+    pub fn acquire(&self) {
+        while !self.try_acquire() {
+            self.wq
+                .wait_until(|| self.count.load(Ordering::Relaxed) > 0);
+        }
+    }
+
+    /// Acquires a permit, blocking the current task until one is available or
+    /// the given duration has elapsed.
+    ///
+    /// Returns `false` if it is timed out.
+    pub fn acquire_timeout(&self, dur: Duration) -> bool {
+        let deadline = axhal::time::current_time() + dur;
+        while !self.try_acquire() {
+            let now = axhal::time::current_time();
+            if now >= deadline
+                || self
+                    .wq
+                    .wait_timeout_until(deadline - now, || self.count.load(Ordering::Relaxed) > 0)
+            {
+                return self.try_acquire();
+            }
+        }
+        true
+    }
+
+    /// Tries to acquire a permit without blocking.
+    ///
+    /// Returns `true` if a permit is acquired.
+    pub fn try_acquire(&self) -> bool {
+        self.count
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |c| c.checked_sub(1))
+            .is_ok()
+    }
+
+    /// Releases a permit, and wakes up a waiting task.
+    pub fn release(&self) {
+        self.count.fetch_add(1, Ordering::Release);
+        self.wq.notify_one();
+    }
+
+    /// Acquires a permit, and returns a guard that releases it when dropped.
+    pub fn access(&self) -> SemaphoreGuard<'_> {
+        self.acquire();
+        SemaphoreGuard { sem: self }
+    }
+}
+
+impl Drop for SemaphoreGuard<'_> {
+    fn drop(&mut self) {
+        self.sem.release();
+    }
+}
@@ -0,0 +1,356 @@
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::time::Duration;
+use std::sync::{Arc, Mutex as StdMutex, Once as StdOnce};
+
+use axtask as thread;
+
+use crate::{Barrier, Condvar, Mutex, Once, OnceLock, RwLock, Semaphore};
+
+pub(crate) static INIT: StdOnce = StdOnce::new();
+pub(crate) static SERIAL: StdMutex<()> = StdMutex::new(());
+
+fn wait_for(counter: &AtomicUsize, n: usize) {
+    while counter.load(Ordering::Acquire) < n {
+        thread::yield_now();
+    }
+}
+
+#[test]
+fn rwlock_writer_preferring() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(thread::init_scheduler);
+
+    static RW: RwLock<u32> = RwLock::new(0);
+    static FINISHED: AtomicUsize = AtomicUsize::new(0);
+
+    let r1 = RW.read();
+    let r2 = RW.read();
+    assert!(RW.try_write().is_none());
+
+    thread::spawn(|| {
+        *RW.write() += 1;
+        FINISHED.fetch_add(1, Ordering::Release);
+    });
+    thread::yield_now(); // let the writer block
+
+    // new readers must wait for the waiting writer
+    assert!(RW.try_read().is_none());
+    assert_eq!(*r1 + *r2, 0);
+    drop(r1);
+    drop(r2);
+
+    wait_for(&FINISHED, 1);
+    assert_eq!(*RW.read(), 1);
+    assert!(!RW.is_locked());
+    println!("RwLock test OK");
+}
+
+#[test]
+fn condvar_notify() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(thread::init_scheduler);
+
+    const NUM_TASKS: usize = 5;
+    let pair = Arc::new((Mutex::new(0), Condvar::new()));
+
+    for _ in 0..NUM_TASKS {
+        let pair = pair.clone();
+        thread::spawn(move || {
+            let (m, cv) = &*pair;
+            let mut started = m.lock();
+            *started += 1;
+            cv.notify_all();
+            started = cv.wait_while(started, |n| *n <= NUM_TASKS);
+            *started += 1;
+            cv.notify_one();
+        });
+    }
+
+    let (m, cv) = &*pair;
+    let mut started = cv.wait_while(m.lock(), |n| *n < NUM_TASKS);
+    let (guard, res) = cv.wait_timeout(started, Duration::ZERO);
+    assert!(res.timed_out());
+    started = guard;
+    *started += 1;
+    drop(started);
+    cv.notify_all();
+
+    let (guard, res) =
+        cv.wait_timeout_while(m.lock(), Duration::from_secs(1), |n| *n < NUM_TASKS * 2 + 1);
+    assert!(!res.timed_out());
+    assert_eq!(*guard, NUM_TASKS * 2 + 1);
+    println!("Condvar test OK");
+}
+
+#[test]
+fn semaphore_permits() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(thread::init_scheduler);
+
+    const NUM_TASKS: usize = 10;
+    const PERMITS: usize = 3;
+    static SEM: Semaphore = Semaphore::new(PERMITS);
+    static RUNNING: AtomicUsize = AtomicUsize::new(0);
+    static FINISHED: AtomicUsize = AtomicUsize::new(0);
+
+    for _ in 0..NUM_TASKS {
+        thread::spawn(|| {
+            let guard = SEM.access();
+            assert!(RUNNING.fetch_add(1, Ordering::Relaxed) < PERMITS);
+            thread::yield_now();
+            RUNNING.fetch_sub(1, Ordering::Relaxed);
+            drop(guard);
+            FINISHED.fetch_add(1, Ordering::Release);
+        });
+    }
+
+    wait_for(&FINISHED, NUM_TASKS);
+    assert_eq!(SEM.available_permits(), PERMITS);
+    for _ in 0..PERMITS {
+        assert!(SEM.try_acquire());
+    }
+    assert!(!SEM.try_acquire());
+    assert!(!SEM.acquire_timeout(Duration::ZERO));
+    for _ in 0..PERMITS {
+        SEM.release();
+    }
+    println!("Semaphore test OK");
+}
+
+#[test]
+fn barrier_rounds() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(thread::init_scheduler);
+
+    const NUM_TASKS: usize = 5;
+    const NUM_ROUNDS: usize = 3;
+    static BARRIER: Barrier = Barrier::new(NUM_TASKS);
+    static ARRIVED: AtomicUsize = AtomicUsize::new(0);
+    static LEADERS: AtomicUsize = AtomicUsize::new(0);
+    static FINISHED: AtomicUsize = AtomicUsize::new(0);
+
+    for _ in 0..NUM_TASKS {
+        thread::spawn(|| {
+            for round in 0..NUM_ROUNDS {
+                ARRIVED.fetch_add(1, Ordering::Relaxed);
+                if BARRIER.wait().is_leader() {
+                    LEADERS.fetch_add(1, Ordering::Relaxed);
+                }
+                // nobody passes the barrier before all tasks arrived
+                assert!(ARRIVED.load(Ordering::Relaxed) >= (round + 1) * NUM_TASKS);
+            }
+            FINISHED.fetch_add(1, Ordering::Release);
+        });
+    }
+
+    wait_for(&FINISHED, NUM_TASKS);
+    assert_eq!(LEADERS.load(Ordering::Relaxed), NUM_ROUNDS);
+    println!("Barrier test OK");
+}
+
+#[test]
+fn once_init() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(thread::init_scheduler);
+
+    const NUM_TASKS: usize = 5;
+    static ONCE: Once = Once::new();
+    static CELL: OnceLock<usize> = OnceLock::new();
+    static CALLED: AtomicUsize = AtomicUsize::new(0);
+    static FINISHED: AtomicUsize = AtomicUsize::new(0);
+    static DONE: AtomicBool = AtomicBool::new(false);
+
+    for i in 0..NUM_TASKS {
+        thread::spawn(move || {
+            ONCE.call_once(|| {
+                CALLED.fetch_add(1, Ordering::Relaxed);
+                thread::yield_now(); // others must wait for us
+                DONE.store(true, Ordering::Relaxed);
+            });
+            assert!(DONE.load(Ordering::Relaxed));
+            let v = CELL.get_or_init(|| {
+                thread::yield_now();
+                i
+            });
+            assert_eq!(CELL.get(), Some(v));
+            FINISHED.fetch_add(1, Ordering::Release);
+        });
+    }
+
+    wait_for(&FINISHED, NUM_TASKS);
+    assert!(ONCE.is_completed());
+    assert_eq!(CALLED.load(Ordering::Relaxed), 1);
+    let v = *CELL.get().unwrap();
+    assert!(v < NUM_TASKS);
+    assert_eq!(CELL.set(NUM_TASKS), Err(NUM_TASKS));
+
+    let mut cell = OnceLock::new();
+    assert_eq!(cell.set(1), Ok(()));
+    assert_eq!(cell.take(), Some(1));
+    assert_eq!(cell.get(), None);
+    println!("Once test OK");
+}
+
+#[test]
+fn mpsc_disconnect() {
+    use crate::mpsc::{channel, RecvTimeoutError, TryRecvError};
+
+    let _lock = SERIAL.lock();
+    INIT.call_once(thread::init_scheduler);
+
+    const NUM_TASKS: usize = 5;
+    let (tx, rx) = channel();
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    assert_eq!(
+        rx.recv_timeout(Duration::ZERO),
+        Err(RecvTimeoutError::Timeout)
+    );
+
+    for i in 0..NUM_TASKS {
+        let tx = tx.clone();
+        thread::spawn(move || {
+            for j in 0..10 {
+                tx.send(i * 10 + j).unwrap();
+                thread::yield_now();
+            }
+        });
+    }
+    drop(tx);
+
+    // messages from the same sender arrive in order
+    let mut last = [None; NUM_TASKS];
+    let mut count = 0;
+    for v in &rx {
+        let (i, j) = (v / 10, v % 10);
+        assert!(last[i] < Some(j));
+        last[i] = Some(j);
+        count += 1;
+    }
+    assert_eq!(count, NUM_TASKS * 10);
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+
+    let (tx, rx) = channel();
+    drop(rx);
+    assert_eq!(tx.send(1).unwrap_err().0, 1);
+
+    // the blocking receiver is not interrupted when it is killed, the timed
+    // one returns early
+    let (tx, rx) = channel::<usize>();
+    let task = thread::spawn(move || {
+        let value = rx.recv().unwrap();
+        let res = rx.recv_timeout(Duration::from_secs(3600));
+        assert_eq!(res, Err(RecvTimeoutError::Interrupted));
+        thread::exit(value as i32);
+    });
+    thread::kill(&task);
+    tx.send(5).unwrap();
+    assert_eq!(task.join(), Some(5));
+    println!("mpsc test OK");
+}
+
+#[test]
+fn mpmc_bounded() {
+    use crate::mpmc::bounded;
+    use crate::mpsc::{TryRecvError, TrySendError};
+
+    let _lock = SERIAL.lock();
+    INIT.call_once(thread::init_scheduler);
+
+    const NUM_TASKS: usize = 4;
+    static RECEIVED: AtomicUsize = AtomicUsize::new(0);
+    static FINISHED: AtomicUsize = AtomicUsize::new(0);
+
+    let (tx, rx) = bounded(2);
+    assert_eq!(tx.capacity(), 2);
+    tx.try_send(1).unwrap();
+    tx.try_send(2).unwrap();
+    assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+    assert_eq!(rx.try_recv(), Ok(1));
+    assert_eq!(rx.try_recv(), Ok(2));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+    for _ in 0..NUM_TASKS {
+        let rx = rx.clone();
+        thread::spawn(move || {
+            for v in rx.iter() {
+                RECEIVED.fetch_add(v, Ordering::Relaxed);
+                thread::yield_now();
+            }
+            FINISHED.fetch_add(1, Ordering::Release);
+        });
+    }
+    drop(rx);
+
+    // blocks when the receivers fall behind
+    for i in 1..=100 {
+        tx.send(i).unwrap();
+    }
+    drop(tx);
+    wait_for(&FINISHED, NUM_TASKS);
+    assert_eq!(RECEIVED.load(Ordering::Relaxed), 5050);
+
+    let (tx, rx) = bounded(1);
+    tx.send(1).unwrap();
+    drop(rx);
+    assert_eq!(tx.try_send(2), Err(TrySendError::Disconnected(2)));
+    println!("mpmc test OK");
+}
+
+#[test]
+fn select_ready() {
+    use crate::{mpmc, mpsc};
+
+    let _lock = SERIAL.lock();
+    INIT.call_once(thread::init_scheduler);
+
+    let (tx1, rx1) = mpsc::channel::<u32>();
+    let (tx2, rx2) = mpmc::bounded::<u32>(1);
+    let mut sel = mpsc::Select::new();
+    let i1 = sel.recv(&rx1);
+    let i2 = sel.recv(&rx2);
+    assert_eq!(sel.ready_timeout(Duration::ZERO), None);
+
+    tx1.send(1).unwrap();
+    assert_eq!(sel.ready(), Some(i1));
+    assert_eq!(rx1.recv(), Ok(1));
+
+    thread::spawn(move || {
+        thread::yield_now();
+        tx2.send(2).unwrap();
+    });
+    assert_eq!(sel.ready(), Some(i2));
+    assert_eq!(rx2.try_recv(), Ok(2));
+
+    // a disconnected channel is always ready
+    assert_eq!(sel.ready(), Some(i2));
+    assert!(rx2.try_recv().is_err());
+
+    drop(tx1);
+    assert!(sel.try_ready().is_some());
+    println!("Select test OK");
+}
+
+#[test]
+#[cfg(feature = "lockdep")]
+fn lockdep_inversion() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(thread::init_scheduler);
+
+    let m = Mutex::new(0);
+    let s = crate::spin::SpinNoIrq::new(0);
+    let n = lockdep::nr_reports();
+
+    // mutex -> spin lock, in the same order twice
+    for _ in 0..2 {
+        let _g = m.lock();
+        *s.lock() += 1;
+    }
+    assert_eq!(lockdep::nr_reports(), n);
+
+    // spin lock -> mutex, also sleeping while holding a spin lock
+    let g = s.lock();
+    assert!(m.try_lock().is_some()); // not checked
+    assert_eq!(lockdep::nr_reports(), n);
+    *m.lock() += *g;
+    assert_eq!(lockdep::nr_reports(), n + 2);
+}
@@ -0,0 +1,153 @@
+//! Waiting for conditions in the blocking primitives.
+
+use core::time::Duration;
+
+use axhal::time::TimeValue;
+
+/// The result of [`WaitQueue::wait_until_interruptible`].
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WaitResult {
+    /// The condition becomes true.
+    Ready,
+    /// The deadline is reached.
+    TimedOut,
+    /// The current task is killed (see [`axtask::kill`]).
+    #[cfg_attr(not(feature = "multitask"), allow(dead_code))]
+    Interrupted,
+}
+
+/// A queue of tasks waiting for some conditions.
+///
+/// With the `multitask` feature, the waiting task sleeps in an
+/// [`axtask::WaitQueue`]. Otherwise, it just spins.
+pub(crate) struct WaitQueue {
+    #[cfg(feature = "multitask")]
+    inner: axtask::WaitQueue,
+}
+
+impl WaitQueue {
+    #[cfg_attr(feature = "lockdep", track_caller)]
+    pub const fn new() -> Self {
+        Self {
+            #[cfg(feature = "multitask")]
+            inner: axtask::WaitQueue::new(),
+        }
+    }
+
+    /// Blocks the current task until the given `condition` becomes true.
+    pub fn wait_until<F>(&self, condition: F)
+    where
+        F: Fn() -> bool,
+    {
+        #[cfg(feature = "multitask")]
+        self.inner.wait_until(condition);
+        #[cfg(not(feature = "multitask"))]
+        while !condition() {
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Blocks the current task until the given `condition` becomes true, or
+    /// the given duration has elapsed.
+    ///
+    /// Returns `true` if it is timed out.
+    pub fn wait_timeout_until<F>(&self, dur: Duration, condition: F) -> bool
+    where
+        F: Fn() -> bool,
+    {
+        cfg_if::cfg_if! {
+            if #[cfg(all(feature = "multitask", feature = "irq"))] {
+                self.inner.wait_timeout_until(dur, condition)
+            } else {
+                // No timer to wake us up, poll the condition until the deadline.
+                let deadline = axhal::time::current_time() + dur;
+                loop {
+                    if condition() {
+                        return false;
+                    }
+                    if axhal::time::current_time() >= deadline {
+                        return true;
+                    }
+                    #[cfg(feature = "multitask")]
+                    axtask::yield_now();
+                    #[cfg(not(feature = "multitask"))]
+                    core::hint::spin_loop();
+                }
+            }
+        }
+    }
+
+    /// Blocks the current task until the given `condition` becomes true, or
+    /// the `deadline` (if any) is reached. Unlike the other waits, it also
+    /// returns early if the current task is killed.
+    #[cfg(feature = "alloc")]
+    pub fn wait_until_interruptible<F>(
+        &self,
+        deadline: Option<TimeValue>,
+        condition: F,
+    ) -> WaitResult
+    where
+        F: Fn() -> bool,
+    {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "multitask")] {
+                let res = match deadline {
+                    None => self.inner.wait_until_interruptible(condition).map(|_| false),
+                    #[cfg(feature = "irq")]
+                    Some(deadline) => {
+                        let dur = deadline.saturating_sub(axhal::time::current_time());
+                        self.inner.wait_timeout_until_interruptible(dur, condition)
+                    }
+                    #[cfg(not(feature = "irq"))]
+                    Some(_) => return poll_until(deadline, condition),
+                };
+                match res {
+                    Ok(false) => WaitResult::Ready,
+                    Ok(true) => WaitResult::TimedOut,
+                    Err(_) => WaitResult::Interrupted,
+                }
+            } else {
+                poll_until(deadline, condition)
+            }
+        }
+    }
+
+    /// Wakes up one waiting task to check its condition.
+    pub fn notify_one(&self) {
+        #[cfg(feature = "multitask")]
+        self.inner.notify_one(true);
+    }
+
+    /// Wakes up all waiting tasks to check their conditions.
+    pub fn notify_all(&self) {
+        #[cfg(feature = "multitask")]
+        self.inner.notify_all(true);
+    }
+}
+
+/// Polls the `condition` until it becomes true, or the `deadline` (if any) is
+/// reached, when there is no timer to wake us up.
+#[cfg(all(feature = "alloc", not(all(feature = "multitask", feature = "irq"))))]
+fn poll_until<F>(deadline: Option<TimeValue>, condition: F) -> WaitResult
+where
+    F: Fn() -> bool,
+{
+    loop {
+        if condition() {
+            return WaitResult::Ready;
+        }
+        if deadline.is_some_and(|deadline| axhal::time::current_time() >= deadline) {
+            return WaitResult::TimedOut;
+        }
+        #[cfg(feature = "multitask")]
+        {
+            if axtask::current().is_killed() {
+                return WaitResult::Interrupted;
+            }
+            axtask::yield_now();
+        }
+        #[cfg(not(feature = "multitask"))]
+        core::hint::spin_loop();
+    }
+}
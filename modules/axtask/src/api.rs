@@ -1,28 +1,82 @@
 //! Task APIs for multi-task configuration.
 
 use alloc::{string::String, sync::Arc};
+use core::sync::atomic::{AtomicBool, Ordering};
 
-pub(crate) use crate::run_queue::{AxRunQueue, RUN_QUEUE};
+pub(crate) use crate::run_queue::{current_run_queue, least_loaded_run_queue};
 
+#[doc(cfg(feature = "multitask"))]
+pub use crate::registry::{tasks, TaskInfo};
 #[doc(cfg(feature = "multitask"))]
 pub use crate::task::{CurrentTask, TaskId, TaskInner};
 #[doc(cfg(feature = "multitask"))]
-pub use crate::wait_queue::WaitQueue;
+pub use crate::task_local::LocalKey;
+#[cfg(feature = "tickless")]
+#[doc(cfg(feature = "tickless"))]
+pub use crate::timers::TickIf;
+#[doc(cfg(feature = "multitask"))]
+pub use crate::wait_queue::{Interrupted, WaitQueue};
+#[doc(cfg(feature = "multitask"))]
+pub use axhal::cpu::CpuMask;
 
 /// The reference type of a task.
 pub type AxTaskRef = Arc<AxTask>;
 
+// `sched_fifo` is enabled by default, other schedulers take precedence over it
+// if they are enabled at the same time.
 cfg_if::cfg_if! {
-    if #[cfg(feature = "sched_fifo")] {
-        pub(crate) type AxTask = scheduler::FifoTask<TaskInner>;
-        pub(crate) type Scheduler = scheduler::FifoScheduler<TaskInner>;
-    } else if #[cfg(feature = "sched_rr")] {
+    if #[cfg(feature = "sched_rr")] {
         const MAX_TIME_SLICE: usize = 5;
+        pub(crate) const DEFAULT_PRIO: isize = 0;
         pub(crate) type AxTask = scheduler::RRTask<TaskInner, MAX_TIME_SLICE>;
         pub(crate) type Scheduler = scheduler::RRScheduler<TaskInner, MAX_TIME_SLICE>;
     } else if #[cfg(feature = "sched_cfs")] {
+        pub(crate) const DEFAULT_PRIO: isize = 0;
         pub(crate) type AxTask = scheduler::CFSTask<TaskInner>;
         pub(crate) type Scheduler = scheduler::CFScheduler<TaskInner>;
+    } else if #[cfg(feature = "sched_prio")] {
+        const MAX_TIME_SLICE: usize = 5;
+        pub(crate) const DEFAULT_PRIO: isize = scheduler::DEFAULT_PRIO as isize;
+        pub(crate) type AxTask = scheduler::PrioTask<TaskInner, MAX_TIME_SLICE>;
+        pub(crate) type Scheduler = scheduler::PrioScheduler<TaskInner, MAX_TIME_SLICE>;
+
+        pub use scheduler::RtPolicy;
+
+        /// Set the policy of the current task among the tasks with the same
+        /// priority, i.e., whether it runs in round-robin with time slices or
+        /// in first-in-first-out order until it blocks or yields.
+        pub fn set_rt_policy(policy: RtPolicy) {
+            let curr = current();
+            current_run_queue().with_scheduler(|s| s.set_policy(curr.as_task_ref(), policy));
+        }
+    } else if #[cfg(feature = "sched_edf")] {
+        pub(crate) const DEFAULT_PRIO: isize = 0;
+        pub(crate) type AxTask = scheduler::EDFTask<TaskInner>;
+        pub(crate) type Scheduler = scheduler::EDFScheduler<TaskInner>;
+    } else if #[cfg(feature = "sched_class")] {
+        const MAX_TIME_SLICE: usize = 5;
+        pub(crate) const DEFAULT_PRIO: isize = 0;
+        pub(crate) type AxTask = scheduler::ClassTask<TaskInner, MAX_TIME_SLICE>;
+        pub(crate) type Scheduler = scheduler::ClassScheduler<TaskInner, MAX_TIME_SLICE>;
+
+        pub use scheduler::SchedPolicy;
+
+        /// Set the scheduling policy for current task, which decides the
+        /// scheduling class it belongs to. It takes effect from the next
+        /// rescheduling.
+        ///
+        /// The priority set by [`set_priority`] is kept for each class, e.g., a
+        /// task gets back its previous nice value when it returns to the
+        /// [`SchedPolicy::Normal`] class.
+        pub fn set_sched_policy(policy: SchedPolicy) {
+            let curr = current();
+            current_run_queue().with_scheduler(|s| s.set_policy(curr.as_task_ref(), policy));
+            curr.set_priority(curr.as_task_ref().prio());
+        }
+    } else if #[cfg(feature = "sched_fifo")] {
+        pub(crate) const DEFAULT_PRIO: isize = 0;
+        pub(crate) type AxTask = scheduler::FifoTask<TaskInner>;
+        pub(crate) type Scheduler = scheduler::FifoScheduler<TaskInner>;
     }
 }
 
@@ -45,6 +99,54 @@ impl kernel_guard::KernelGuardIf for KernelGuardIfImpl {
     }
 }
 
+/// Locks held by the IRQ handlers and softirqs on this CPU.
+#[cfg(all(feature = "lockdep", feature = "irq"))]
+#[percpu::def_percpu]
+static IRQ_HELD_LOCKS: lockdep::HeldLocks = lockdep::HeldLocks::new();
+
+#[cfg(feature = "lockdep")]
+struct LockdepIfImpl;
+
+#[cfg(feature = "lockdep")]
+#[crate_interface::impl_interface]
+impl lockdep::LockdepIf for LockdepIfImpl {
+    fn held_locks() -> Option<core::ptr::NonNull<lockdep::HeldLocks>> {
+        // the locks acquired in IRQ context do not belong to the interrupted
+        // task.
+        #[cfg(feature = "irq")]
+        if crate::softirq::in_interrupt() {
+            // Safety: IRQs are disabled by the caller.
+            return Some(unsafe { IRQ_HELD_LOCKS.current_ref_mut_raw() }.into());
+        }
+        current_may_uninit().map(|curr| curr.held_locks())
+    }
+
+    fn backtrace(callers: &mut [usize]) -> usize {
+        #[cfg(target_os = "none")]
+        {
+            // Safety: the current stack is alive during the iteration.
+            let frames =
+                unsafe { axhal::backtrace::Frames::new(axhal::arch::read_frame_pointer()) };
+            let mut depth = 0;
+            for (ra, frame) in callers.iter_mut().zip(frames) {
+                *ra = frame.ra;
+                depth += 1;
+            }
+            depth
+        }
+        // the host stack may not be built with frame pointers.
+        #[cfg(not(target_os = "none"))]
+        {
+            let _ = callers;
+            0
+        }
+    }
+
+    fn lookup_symbol(addr: usize) -> Option<(&'static str, usize)> {
+        axhal::backtrace::lookup_symbol(addr)
+    }
+}
+
 /// Gets the current task, or returns [`None`] if the current task is not
 /// initialized.
 pub fn current_may_uninit() -> Option<CurrentTask> {
@@ -65,8 +167,12 @@ pub fn init_scheduler() {
     info!("Initialize scheduling...");
 
     crate::run_queue::init();
+    crate::workqueue::init();
     #[cfg(feature = "irq")]
-    crate::timers::init();
+    {
+        crate::timers::init();
+        crate::softirq::init();
+    }
 
     info!("  use {} scheduler.", Scheduler::scheduler_name());
 }
@@ -74,6 +180,8 @@ pub fn init_scheduler() {
 /// Initializes the task scheduler for secondary CPUs.
 pub fn init_scheduler_secondary() {
     crate::run_queue::init_secondary();
+    #[cfg(feature = "irq")]
+    crate::softirq::init_percpu();
 }
 
 /// Handles periodic timer ticks for the task manager.
@@ -82,19 +190,79 @@ pub fn init_scheduler_secondary() {
 #[cfg(feature = "irq")]
 #[doc(cfg(feature = "irq"))]
 pub fn on_timer_tick() {
+    TIMER_TICKS.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
     crate::timers::check_events();
-    RUN_QUEUE.lock().scheduler_timer_tick();
+    current_run_queue().scheduler_timer_tick();
+}
+
+#[cfg(feature = "irq")]
+static TIMER_TICKS: core::sync::atomic::AtomicU64 = core::sync::atomic::AtomicU64::new(0);
+
+/// Returns the number of timer ticks handled by [`on_timer_tick`] on all CPUs.
+#[cfg(feature = "irq")]
+#[doc(cfg(feature = "irq"))]
+pub fn timer_ticks() -> u64 {
+    TIMER_TICKS.load(core::sync::atomic::Ordering::Relaxed)
+}
+
+/// Returns the deadline of the earliest pending timer event, such as waking
+/// up a sleeping task or firing a [`timer`](crate::timer).
+#[cfg(feature = "irq")]
+#[doc(cfg(feature = "irq"))]
+pub fn next_timer_deadline() -> Option<axhal::time::TimeValue> {
+    crate::timers::next_deadline()
+}
+
+/// Whether the current CPU is idle, i.e., it is running the idle task and
+/// there is no ready task in its run queue.
+#[cfg(feature = "tickless")]
+#[doc(cfg(feature = "tickless"))]
+pub fn is_cpu_idle() -> bool {
+    current().is_idle() && current_run_queue().nr_tasks() == 0
+}
+
+/// Checks whether a kernel page fault at `vaddr` hits the guard page of the
+/// current task's stack, and panics with the task ID and name if so.
+#[cfg(feature = "paging")]
+#[doc(cfg(feature = "paging"))]
+pub fn check_stack_guard(vaddr: memory_addr::VirtAddr) {
+    if let Some(curr) = current_may_uninit() {
+        curr.check_stack_guard(vaddr);
+    }
 }
 
 /// Spawns a new task with the given parameters.
 ///
 /// Returns the task reference.
 pub fn spawn_raw<F>(f: F, name: String, stack_size: usize) -> AxTaskRef
+where
+    F: FnOnce() + Send + 'static,
+{
+    spawn_raw_with_affinity(f, name, stack_size, CpuMask::full())
+}
+
+/// Spawns a new task with the given parameters, which is only allowed to run
+/// on the CPUs in `cpumask`.
+///
+/// CPUs that do not exist are ignored. If no CPU is left, the task is allowed
+/// to run on all CPUs.
+///
+/// Returns the task reference.
+pub fn spawn_raw_with_affinity<F>(
+    f: F,
+    name: String,
+    stack_size: usize,
+    cpumask: CpuMask,
+) -> AxTaskRef
 where
     F: FnOnce() + Send + 'static,
 {
     let task = TaskInner::new(f, name, stack_size);
-    RUN_QUEUE.lock().add_task(task.clone());
+    let cpumask = cpumask & CpuMask::full();
+    if !cpumask.is_empty() {
+        task.set_affinity(cpumask);
+    }
+    least_loaded_run_queue(task.affinity()).add_task(task.clone());
     task
 }
 
@@ -115,19 +283,93 @@ where
 ///
 /// The range of the priority is dependent on the underlying scheduler. For
 /// example, in the [CFS] scheduler, the priority is the nice value, ranging from
-/// -20 to 19.
+/// -20 to 19. In the fixed-priority scheduler, it ranges from 0 (highest) to 99
+/// (lowest).
+///
+/// If the priority of the task is boosted by [`boost_task_priority`], the
+/// boost still takes effect until it is removed.
 ///
 /// Returns `true` if the priority is set successfully.
 ///
 /// [CFS]: https://en.wikipedia.org/wiki/Completely_Fair_Scheduler
 pub fn set_priority(prio: isize) -> bool {
-    RUN_QUEUE.lock().set_current_priority(prio)
+    crate::run_queue::set_task_priority(current().as_task_ref(), prio)
+}
+
+/// Set the priority for the given task, which may be ready, blocked, or
+/// running on another CPU.
+///
+/// See [`set_priority`] for the range of the priority. Returns `true` if the
+/// priority is set successfully.
+pub fn set_task_priority(task: &AxTaskRef, prio: isize) -> bool {
+    crate::run_queue::set_task_priority(task, prio)
+}
+
+/// Boosts the priority of the given task to `prio` on behalf of `key`, which
+/// replaces the previous boost of the same `key`.
+///
+/// It is used to implement the priority inheritance, where `key` is usually
+/// the address of a lock held by the task, and `prio` is the highest priority
+/// of the tasks waiting for it. The effective priority of the task (see
+/// [`TaskInner::priority`]) is the highest one of the priority set by
+/// [`set_task_priority`] and all its boosts.
+///
+/// Returns `true` if the effective priority is set successfully.
+pub fn boost_task_priority(task: &AxTaskRef, key: usize, prio: isize) -> bool {
+    crate::run_queue::set_task_priority_boost(task, key, Some(prio))
+}
+
+/// Removes the priority boost of `key` from the given task, see
+/// [`boost_task_priority`].
+///
+/// Returns `true` if the effective priority is restored successfully.
+pub fn unboost_task_priority(task: &AxTaskRef, key: usize) -> bool {
+    crate::run_queue::set_task_priority_boost(task, key, None)
+}
+
+/// Sets the CPU affinity mask of the given task, i.e., the set of CPUs it is
+/// allowed to run on. CPUs that do not exist are ignored.
+///
+/// If the task is ready but queued on a CPU not in the new mask, it is moved to
+/// an allowed CPU immediately. If it is the current task, it is moved by
+/// rescheduling. A task running on another CPU is moved when it is
+/// rescheduled next time.
+///
+/// Returns `false` if no CPU in `cpumask` exists, and the affinity is not
+/// changed.
+pub fn set_affinity(task: &AxTaskRef, cpumask: CpuMask) -> bool {
+    let cpumask = cpumask & CpuMask::full();
+    if cpumask.is_empty() {
+        return false;
+    }
+    task.set_affinity(cpumask);
+    #[cfg(feature = "smp")]
+    if current().ptr_eq(task) {
+        if !cpumask.contains(axhal::cpu::this_cpu_id()) {
+            yield_now();
+        }
+    } else {
+        crate::run_queue::migrate_task(task);
+    }
+    true
+}
+
+/// Requests the given task to terminate.
+///
+/// It sets a pending-kill flag on the task (see [`TaskInner::is_killed`]), and
+/// wakes it up if it is blocked in an interruptible wait. The interruptible
+/// blocking operations, e.g., [`WaitQueue::wait_interruptible`] and
+/// [`TaskInner::join_interruptible`], return early in a killed task. Others
+/// (e.g., [`WaitQueue::wait`] and [`sleep`]) are not affected. The task is
+/// expected to check the results, clean up and [`exit`] by itself.
+pub fn kill(task: &AxTaskRef) {
+    crate::run_queue::kill_task(task);
 }
 
 /// Current task gives up the CPU time voluntarily, and switches to another
 /// ready task.
 pub fn yield_now() {
-    RUN_QUEUE.lock().yield_current();
+    current_run_queue().yield_current();
 }
 
 /// Current task is going to sleep for the given duration.
@@ -141,15 +383,74 @@ pub fn sleep(dur: core::time::Duration) {
 ///
 /// If the feature `irq` is not enabled, it uses busy-wait instead.
 pub fn sleep_until(deadline: axhal::time::TimeValue) {
+    might_sleep();
     #[cfg(feature = "irq")]
-    RUN_QUEUE.lock().sleep_until(deadline);
+    while axhal::time::current_time() < deadline {
+        // sleep again if woken up early, e.g., racing with `kill()`.
+        current_run_queue().sleep_until(deadline);
+    }
     #[cfg(not(feature = "irq"))]
     axhal::time::busy_wait_until(deadline);
 }
 
+/// Whether the system has finished booting, see [`set_system_running`].
+static SYSTEM_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Marks that the system has finished booting, called by the runtime before
+/// entering the application.
+///
+/// IRQs are disabled during the boot, so [`might_sleep`] does not check it
+/// before.
+pub fn set_system_running() {
+    SYSTEM_RUNNING.store(true, Ordering::Release);
+}
+
+/// Checks that the current context is allowed to sleep (block). It should be
+/// called at the beginning of the functions that may block.
+///
+/// With debug assertions enabled, it panics if called in IRQ or softirq
+/// context, with IRQs disabled, or with preemption disabled, as blocking
+/// there may deadlock. With the `lockdep` feature, it also reports sleeping
+/// while holding a spin lock.
+#[track_caller]
+pub fn might_sleep() {
+    #[cfg(debug_assertions)]
+    {
+        #[cfg(feature = "irq")]
+        if crate::softirq::in_interrupt() {
+            sleep_in_atomic("in IRQ context");
+        }
+        #[cfg(feature = "irq")]
+        if SYSTEM_RUNNING.load(Ordering::Acquire) && !axhal::arch::irqs_enabled() {
+            sleep_in_atomic("with IRQs disabled");
+        }
+        #[cfg(feature = "preempt")]
+        if current_may_uninit().is_some_and(|curr| !curr.can_preempt(0)) {
+            sleep_in_atomic("with preemption disabled");
+        }
+    }
+    #[cfg(feature = "lockdep")]
+    lockdep::check_sleep();
+}
+
+#[cfg(all(debug_assertions, any(feature = "irq", feature = "preempt")))]
+#[cold]
+#[track_caller]
+fn sleep_in_atomic(reason: &str) -> ! {
+    let task = current_may_uninit().map(|curr| curr.id_name());
+    panic!(
+        "sleeping function called {} by {}",
+        reason,
+        task.as_deref().unwrap_or("<unknown task>")
+    );
+}
+
 /// Exits the current task.
+///
+/// The task-local values (see [`LocalKey`]) are dropped before it exits.
 pub fn exit(exit_code: i32) -> ! {
-    RUN_QUEUE.lock().exit_current(exit_code)
+    crate::task_local::destroy_current();
+    current_run_queue().exit_current(exit_code)
 }
 
 /// The idle task routine.
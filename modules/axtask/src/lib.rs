@@ -10,19 +10,44 @@
 //!   management and scheduling is used, as well as more task-related APIs.
 //!   Otherwise, only a few APIs with naive implementation is available.
 //! - `irq`: Interrupts are enabled. If this feature is enabled, timer-based
-//!    APIs can be used, such as [`sleep`], [`sleep_until`], and
-//!    [`WaitQueue::wait_timeout`].
+//!    APIs can be used, such as [`sleep`], [`sleep_until`],
+//!    [`WaitQueue::wait_timeout`], and the kernel timers in [`timer`]. It also
+//!    enables the bottom halves of IRQ handlers: [`softirq`]s, tasklets and
+//!    [`threaded_irq`]s.
+//! - `tickless`: Stop the periodic timer tick on idle CPUs. It restarts the
+//!   tick when the CPU leaves the idle task. It also enables the `irq`
+//!   and `multitask` features.
+//! - `paging`: Put an unmapped guard page below each task stack, so that a
+//!   stack overflow is reported by the page fault it triggers. Without this
+//!   feature, overflows are only detected by a canary at the bottom of the
+//!   stack, which is checked on each context switch.
+//! - `smp`: Enable SMP (symmetric multiprocessing) support. Each CPU has its
+//!   own run queue, and idle CPUs steal ready tasks from busy ones.
 //! - `preempt`: Enable preemptive scheduling.
+//! - `lockdep`: Check the lock order and sleeping while holding spin locks
+//!   by the `lockdep` validator, each task has its own stack of held locks.
+//!   It also enables the `multitask` feature.
 //! - `sched_fifo`: Use the [FIFO cooperative scheduler][1]. It also enables the
 //!   `multitask` feature if it is enabled. This feature is enabled by default.
 //! - `sched_rr`: Use the [Round-robin preemptive scheduler][2]. It also enables
 //!   the `multitask` and `preempt` features if it is enabled.
 //! - `sched_cfs`: Use the [Completely Fair Scheduler][3]. It also enables the
 //!   the `multitask` and `preempt` features if it is enabled.
+//! - `sched_prio`: Use the [fixed-priority preemptive scheduler][4]. It also
+//!   enables the `multitask` and `preempt` features if it is enabled.
+//! - `sched_edf`: Use the [Earliest Deadline First scheduler][5]. It also
+//!   enables the `multitask` and `preempt` features if it is enabled.
+//! - `sched_class`: Use the [multi-class scheduler][6], where each task is
+//!   put into the real-time, fair or idle class according to its scheduling
+//!   policy. It also enables the `multitask` and `preempt` features if it is
+//!   enabled.
 //!
 //! [1]: scheduler::FifoScheduler
 //! [2]: scheduler::RRScheduler
 //! [3]: scheduler::CFScheduler
+//! [4]: scheduler::PrioScheduler
+//! [5]: scheduler::EDFScheduler
+//! [6]: scheduler::ClassScheduler
 
 #![cfg_attr(not(test), no_std)]
 #![feature(doc_cfg)]
@@ -33,12 +58,22 @@ cfg_if::cfg_if! {
         #[macro_use]
         extern crate log;
         extern crate alloc;
+        mod registry;
         mod run_queue;
         mod task;
+        mod task_local;
         mod wait_queue;
 
+        pub mod workqueue;
+
         #[cfg(feature = "irq")]
         mod timers;
+        #[cfg(feature = "irq")]
+        pub mod softirq;
+        #[cfg(feature = "irq")]
+        pub mod threaded_irq;
+        #[cfg(feature = "irq")]
+        pub mod timer;
     }
 }
 
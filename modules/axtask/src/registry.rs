@@ -0,0 +1,86 @@
+//! The registry of all tasks in the system, for introspection.
+
+use alloc::{collections::BTreeMap, string::String, sync::Weak, vec::Vec};
+use core::time::Duration;
+
+use spinlock::SpinNoIrq;
+
+use crate::task::{TaskId, TaskState};
+use crate::{AxTask, AxTaskRef};
+
+static TASKS: SpinNoIrq<BTreeMap<u64, Weak<AxTask>>> = SpinNoIrq::new(BTreeMap::new());
+
+/// A snapshot of the statistics of a task, returned by [`tasks`].
+#[derive(Debug, Clone)]
+pub struct TaskInfo {
+    /// The task ID.
+    pub id: TaskId,
+    /// The task name.
+    pub name: String,
+    /// The state of the task: `R` for running, `r` for ready, `S` for blocked,
+    /// and `Z` for exited.
+    pub state: char,
+    /// The CPU that the task last ran on.
+    pub cpu_id: usize,
+    /// The priority of the task, see [`set_priority`](crate::set_priority).
+    pub priority: isize,
+    /// The total time the task has been running on CPUs.
+    pub runtime: Duration,
+    /// The number of times the task was switched in.
+    pub context_switches: u64,
+    /// The size of the task's stack, or 0 if it runs on the boot stack.
+    pub stack_size: usize,
+    /// The maximum number of bytes ever used in the task's stack, which is
+    /// only tracked in debug builds.
+    pub stack_high_water_mark: Option<usize>,
+}
+
+impl TaskInfo {
+    fn new(task: &AxTaskRef) -> Self {
+        let (stack_size, stack_high_water_mark) = task.stack_usage().unwrap_or((0, None));
+        Self {
+            id: task.id(),
+            name: String::from(task.name()),
+            state: match task.state() {
+                TaskState::Running => 'R',
+                TaskState::Ready => 'r',
+                TaskState::Blocked => 'S',
+                TaskState::Exited => 'Z',
+            },
+            #[cfg(feature = "smp")]
+            cpu_id: task.cpu_id(),
+            #[cfg(not(feature = "smp"))]
+            cpu_id: 0,
+            priority: task.priority(),
+            runtime: task.runtime(),
+            context_switches: task.context_switches(),
+            stack_size,
+            stack_high_water_mark,
+        }
+    }
+}
+
+pub(crate) fn register(task: &AxTaskRef) {
+    TASKS
+        .lock()
+        .insert(task.id().as_u64(), AxTaskRef::downgrade(task));
+}
+
+pub(crate) fn unregister(id: TaskId) {
+    TASKS.lock().remove(&id.as_u64());
+}
+
+/// Returns an iterator over the statistics of all tasks, ordered by task ID.
+///
+/// The statistics are collected when this function is called, tasks created
+/// or dropped later are not reflected.
+pub fn tasks() -> impl Iterator<Item = TaskInfo> {
+    // Tasks may be dropped when the references are released, so do not hold
+    // the lock at that time.
+    let tasks: Vec<AxTaskRef> = TASKS.lock().values().filter_map(Weak::upgrade).collect();
+    tasks
+        .iter()
+        .map(TaskInfo::new)
+        .collect::<Vec<_>>()
+        .into_iter()
+}
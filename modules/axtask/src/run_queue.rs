@@ -1,72 +1,192 @@
 use alloc::collections::VecDeque;
 use alloc::sync::Arc;
+use core::ops::Deref;
+use core::sync::atomic::{fence, AtomicPtr, AtomicUsize, Ordering};
+
+#[cfg(feature = "irq")]
+use axhal::irq::{IpiKind, IpiTarget};
+use kernel_guard::NoPreemptIrqSave;
 use lazy_init::LazyInit;
 use scheduler::BaseScheduler;
-use spinlock::SpinNoIrq;
+use spinlock::{SpinNoIrq, SpinRaw};
 
 use crate::task::{CurrentTask, TaskState};
-use crate::{AxTaskRef, Scheduler, TaskInner, WaitQueue};
-
-// TODO: per-CPU
-pub(crate) static RUN_QUEUE: LazyInit<SpinNoIrq<AxRunQueue>> = LazyInit::new();
+use crate::{AxTaskRef, CpuMask, Scheduler, TaskInner, WaitQueue};
 
-// TODO: per-CPU
-static EXITED_TASKS: SpinNoIrq<VecDeque<AxTaskRef>> = SpinNoIrq::new(VecDeque::new());
+#[percpu::def_percpu]
+static RUN_QUEUE: LazyInit<AxRunQueue> = LazyInit::new();
 
-static WAIT_FOR_EXIT: WaitQueue = WaitQueue::new();
+/// Pointers to the run queues of all CPUs, used to access remote run queues
+/// (e.g., task placement and work stealing).
+static RUN_QUEUES: [AtomicPtr<AxRunQueue>; axconfig::SMP] = {
+    const EMPTY: AtomicPtr<AxRunQueue> = AtomicPtr::new(core::ptr::null_mut());
+    [EMPTY; axconfig::SMP]
+};
 
 #[percpu::def_percpu]
 static IDLE_TASK: LazyInit<AxTaskRef> = LazyInit::new();
 
+/// The task that was running on this CPU before the last context switch. Its
+/// `on_cpu` flag is cleared after the switch completes.
+#[cfg(feature = "smp")]
+#[percpu::def_percpu]
+static PREV_TASK: usize = 0;
+
+/// The run queue of a CPU.
+///
+/// Each CPU has its own run queue. The local run queue is accessed through
+/// [`current_run_queue`] with IRQs and preemption disabled, other CPUs only
+/// touch the inner scheduler to place new tasks or steal ready tasks.
 pub(crate) struct AxRunQueue {
-    scheduler: Scheduler,
+    cpu_id: usize,
+    nr_tasks: AtomicUsize,
+    scheduler: SpinRaw<Scheduler>, // we already disabled IRQs when getting the run queue
+    /// Tasks exited on this CPU, waiting to be dropped by its GC task.
+    exited_tasks: SpinNoIrq<VecDeque<AxTaskRef>>,
+    wait_for_exit: WaitQueue,
+}
+
+/// A reference to a run queue, with IRQs and preemption disabled on the
+/// current CPU while it is alive.
+pub(crate) struct AxRunQueueRef {
+    inner: &'static AxRunQueue,
+    _guard: NoPreemptIrqSave,
+}
+
+impl Deref for AxRunQueueRef {
+    type Target = AxRunQueue;
+    fn deref(&self) -> &Self::Target {
+        self.inner
+    }
+}
+
+/// Gets the run queue of the current CPU.
+pub(crate) fn current_run_queue() -> AxRunQueueRef {
+    let guard = NoPreemptIrqSave::new();
+    AxRunQueueRef {
+        // Safety: IRQs and preemption are disabled.
+        inner: unsafe { RUN_QUEUE.current_ref_raw().get_unchecked() },
+        _guard: guard,
+    }
+}
+
+/// Selects a run queue to put the given woken up task.
+///
+/// The task goes back to the CPU it last ran on to keep its cache warm, unless
+/// that CPU is no longer in its affinity mask.
+#[cfg_attr(not(feature = "smp"), allow(unused_variables))]
+pub(crate) fn select_run_queue(task: &AxTaskRef) -> AxRunQueueRef {
+    #[cfg(feature = "smp")]
+    {
+        let cpu_id = task.cpu_id();
+        if task.affinity().contains(cpu_id) {
+            if let Some(rq) = remote_run_queue(cpu_id) {
+                return AxRunQueueRef {
+                    inner: rq,
+                    _guard: NoPreemptIrqSave::new(),
+                };
+            }
+        }
+        least_loaded_run_queue(task.affinity())
+    }
+    #[cfg(not(feature = "smp"))]
+    current_run_queue()
+}
+
+/// Selects the least loaded run queue among the CPUs in `cpumask` to put a
+/// new task.
+#[cfg_attr(not(feature = "smp"), allow(unused_variables))]
+pub(crate) fn least_loaded_run_queue(cpumask: CpuMask) -> AxRunQueueRef {
+    #[cfg(feature = "smp")]
+    {
+        let guard = NoPreemptIrqSave::new();
+        let this_cpu = axhal::cpu::this_cpu_id();
+        let mut target: Option<&'static AxRunQueue> = None;
+        // start from the current CPU, so that it is preferred on ties.
+        for i in 0..axconfig::SMP {
+            let cpu_id = (this_cpu + i) % axconfig::SMP;
+            if !cpumask.contains(cpu_id) {
+                continue;
+            }
+            if let Some(rq) = remote_run_queue(cpu_id) {
+                if target.map_or(true, |t| rq.nr_tasks() < t.nr_tasks()) {
+                    target = Some(rq);
+                }
+            }
+        }
+        if let Some(rq) = target {
+            return AxRunQueueRef {
+                inner: rq,
+                _guard: guard,
+            };
+        }
+    }
+    current_run_queue()
+}
+
+#[cfg(feature = "smp")]
+fn remote_run_queue(cpu_id: usize) -> Option<&'static AxRunQueue> {
+    let ptr = RUN_QUEUES[cpu_id].load(Ordering::Acquire);
+    unsafe { ptr.as_ref() }
 }
 
 impl AxRunQueue {
-    pub fn new() -> SpinNoIrq<Self> {
-        let gc_task = TaskInner::new(gc_entry, "gc".into(), axconfig::TASK_STACK_SIZE);
+    fn new(cpu_id: usize) -> Self {
         let mut scheduler = Scheduler::new();
-        scheduler.add_task(gc_task);
-        SpinNoIrq::new(Self { scheduler })
+        scheduler.init();
+        Self {
+            cpu_id,
+            nr_tasks: AtomicUsize::new(0),
+            scheduler: SpinRaw::new(scheduler),
+            exited_tasks: SpinNoIrq::new(VecDeque::new()),
+            wait_for_exit: WaitQueue::new(),
+        }
+    }
+
+    /// Returns the number of ready tasks in this run queue.
+    #[cfg(any(feature = "smp", feature = "tickless"))]
+    pub fn nr_tasks(&self) -> usize {
+        self.nr_tasks.load(Ordering::Relaxed)
     }
 
-    pub fn add_task(&mut self, task: AxTaskRef) {
-        debug!("task spawn: {}", task.id_name());
+    pub fn add_task(&self, task: AxTaskRef) {
+        debug!("task spawn: {} on CPU {}", task.id_name(), self.cpu_id);
         assert!(task.is_ready());
-        self.scheduler.add_task(task);
+        self.enqueue(task, None);
+        self.check_preempt_current(false);
     }
 
     #[cfg(feature = "irq")]
-    pub fn scheduler_timer_tick(&mut self) {
+    pub fn scheduler_timer_tick(&self) {
         let curr = crate::current();
-        if !curr.is_idle() && self.scheduler.task_tick(curr.as_task_ref()) {
+        if !curr.is_idle() && self.scheduler.lock().task_tick(curr.as_task_ref()) {
             #[cfg(feature = "preempt")]
             curr.set_preempt_pending(true);
         }
     }
 
-    pub fn yield_current(&mut self) {
+    pub fn yield_current(&self) {
         let curr = crate::current();
         debug!("task yield: {}", curr.id_name());
         assert!(curr.is_running());
         self.resched(false);
     }
 
-    pub fn set_current_priority(&mut self, prio: isize) -> bool {
-        self.scheduler
-            .set_priority(crate::current().as_task_ref(), prio)
+    /// Runs `f` with the scheduler of this run queue locked.
+    #[cfg(any(feature = "sched_prio", feature = "sched_class"))]
+    pub fn with_scheduler<R>(&self, f: impl FnOnce(&mut Scheduler) -> R) -> R {
+        f(&mut self.scheduler.lock())
     }
 
     #[cfg(feature = "preempt")]
-    pub fn preempt_resched(&mut self) {
+    pub fn preempt_resched(&self) {
         let curr = crate::current();
         assert!(curr.is_running());
 
-        // When we get the mutable reference of the run queue, we must
-        // have held the `SpinNoIrq` lock with both IRQs and preemption
-        // disabled. So we need to set `current_disable_count` to 1 in
-        // `can_preempt()` to obtain the preemption permission before
-        //  locking the run queue.
+        // When we get the reference of the run queue, we must have both IRQs
+        // and preemption disabled. So we need to set `current_disable_count`
+        // to 1 in `can_preempt()` to obtain the preemption permission before
+        // getting the run queue.
         let can_preempt = curr.can_preempt(1);
 
         debug!(
@@ -81,25 +201,32 @@ impl AxRunQueue {
         }
     }
 
-    pub fn exit_current(&mut self, exit_code: i32) -> ! {
+    pub fn exit_current(&self, exit_code: i32) -> ! {
         let curr = crate::current();
         debug!("task exit: {}, exit_code={}", curr.id_name(), exit_code);
         assert!(curr.is_running());
         assert!(!curr.is_idle());
         if curr.is_init() {
-            EXITED_TASKS.lock().clear();
+            self.exited_tasks.lock().clear();
             axhal::misc::terminate();
         } else {
             curr.set_state(TaskState::Exited);
-            curr.notify_exit(exit_code, self);
-            EXITED_TASKS.lock().push_back(curr.clone());
-            WAIT_FOR_EXIT.notify_one_locked(false, self);
+            curr.notify_exit(exit_code);
+            self.exited_tasks.lock().push_back(curr.clone());
+            self.wait_for_exit.notify_one_locked(false);
             self.resched(false);
         }
         unreachable!("task exited!");
     }
 
-    pub fn block_current<F>(&mut self, wait_queue_push: F)
+    /// Blocks the current task, and calls `wait_queue_push` to put it into a
+    /// wait queue.
+    ///
+    /// If `interruptible`, the task is not blocked if it has been killed, and
+    /// it is woken up by [`kill_task`]. Otherwise, it is only woken up by its
+    /// wait queue (or the timer), though the caller should still re-check the
+    /// event it waits for.
+    pub fn block_current<F>(&self, interruptible: bool, wait_queue_push: F)
     where
         F: FnOnce(AxTaskRef),
     {
@@ -108,29 +235,32 @@ impl AxRunQueue {
         assert!(curr.is_running());
         assert!(!curr.is_idle());
 
-        // we must not block current task with preemption disabled.
-        #[cfg(feature = "preempt")]
+        // we must not block current task with preemption disabled. (kernel
+        // guards do nothing on the host, so do not check it in tests)
+        #[cfg(all(feature = "preempt", target_os = "none"))]
         assert!(curr.can_preempt(1));
 
+        curr.set_interruptible(interruptible);
         curr.set_state(TaskState::Blocked);
         wait_queue_push(curr.clone());
+        if interruptible {
+            self.wake_killed_current(&curr);
+        }
         self.resched(false);
     }
 
-    pub fn unblock_task(&mut self, task: AxTaskRef, resched: bool) {
+    pub fn unblock_task(&self, task: AxTaskRef, resched: bool) {
         debug!("task unblock: {}", task.id_name());
-        if task.is_blocked() {
-            task.set_state(TaskState::Ready);
-            self.scheduler.add_task(task); // TODO: priority
-            if resched {
-                #[cfg(feature = "preempt")]
-                crate::current().set_preempt_pending(true);
-            }
+        // The task may be woken up by several events at the same time (e.g.,
+        // timer and `notify()` on different CPUs), only the first one wins.
+        if task.transition_state(TaskState::Blocked, TaskState::Ready) {
+            self.enqueue(task, None);
+            self.check_preempt_current(resched);
         }
     }
 
     #[cfg(feature = "irq")]
-    pub fn sleep_until(&mut self, deadline: axhal::time::TimeValue) {
+    pub fn sleep_until(&self, deadline: axhal::time::TimeValue) {
         let curr = crate::current();
         debug!("task sleep: {}, deadline={:?}", curr.id_name(), deadline);
         assert!(curr.is_running());
@@ -138,32 +268,179 @@ impl AxRunQueue {
 
         let now = axhal::time::current_time();
         if now < deadline {
-            crate::timers::set_alarm_wakeup(deadline, curr.clone());
+            // set the state first, the timer may be fired on other CPUs
+            // immediately.
+            curr.set_interruptible(false);
             curr.set_state(TaskState::Blocked);
+            crate::timers::set_alarm_wakeup(deadline, curr.clone());
             self.resched(false);
+            if curr.in_timer_list() {
+                // woken up before the deadline, the alarm is still pending.
+                crate::timers::cancel_alarm(curr.as_task_ref());
+            }
         }
     }
 }
 
 impl AxRunQueue {
+    /// Wakes up the current task which has just been blocked, if it has been
+    /// killed.
+    fn wake_killed_current(&self, curr: &CurrentTask) {
+        // pairs with the fence in `kill_task()`: either we see the flag here,
+        // or the killer sees the task blocked and wakes it up.
+        fence(Ordering::SeqCst);
+        if curr.is_killed() {
+            self.unblock_task(curr.clone(), false);
+        }
+    }
+
+    /// Marks the current task to be preempted if `resched`, or a ready task
+    /// in this run queue should preempt it (e.g., it has a higher priority).
+    ///
+    /// If this run queue is on another CPU, a reschedule IPI is sent to let
+    /// that CPU check it, which also wakes the CPU up if it is idle.
+    #[cfg_attr(not(feature = "preempt"), allow(unused_variables))]
+    fn check_preempt_current(&self, resched: bool) {
+        if self.is_local() {
+            #[cfg(feature = "preempt")]
+            {
+                let curr = crate::current();
+                if resched
+                    || curr.is_idle()
+                    || self.scheduler.lock().need_preempt(curr.as_task_ref())
+                {
+                    curr.set_preempt_pending(true);
+                }
+            }
+        } else {
+            #[cfg(feature = "irq")]
+            axhal::irq::send_ipi(IpiTarget::Cpu(self.cpu_id), IpiKind::Reschedule);
+        }
+    }
+
+    fn is_local(&self) -> bool {
+        !cfg!(feature = "smp") || self.cpu_id == axhal::cpu::this_cpu_id()
+    }
+
+    /// Whether the given task is allowed to run on this CPU.
+    fn allows(&self, task: &AxTaskRef) -> bool {
+        task.affinity().contains(self.cpu_id)
+    }
+
+    /// Puts a ready task into this run queue. If `prev` is `Some(preempt)`,
+    /// the task is the previous running task on this CPU.
+    fn enqueue(&self, task: AxTaskRef, prev: Option<bool>) {
+        let mut scheduler = self.scheduler.lock();
+        #[cfg(feature = "smp")]
+        {
+            task.set_cpu_id(self.cpu_id);
+            task.set_in_run_queue(true);
+        }
+        self.nr_tasks.fetch_add(1, Ordering::Relaxed);
+        match prev {
+            Some(preempt) => scheduler.put_prev_task(task, preempt),
+            None => scheduler.add_task(task),
+        }
+    }
+
+    /// Bookkeeping after a task is taken out of the scheduler. It must be
+    /// called with the scheduler lock held.
+    fn dequeued(&self, _task: &AxTaskRef) {
+        #[cfg(feature = "smp")]
+        _task.set_in_run_queue(false);
+        self.nr_tasks.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn pick_next_task(&self) -> Option<AxTaskRef> {
+        let mut scheduler = self.scheduler.lock();
+        let next = scheduler.pick_next_task();
+        if let Some(task) = &next {
+            self.dequeued(task);
+        }
+        next
+    }
+
+    /// Moves a ready task in this run queue to another CPU allowed by its
+    /// affinity mask. Does nothing if the task is not in this run queue.
+    #[cfg(feature = "smp")]
+    fn migrate_task(&self, task: &AxTaskRef) {
+        let removed = {
+            let mut scheduler = self.scheduler.lock();
+            if task.in_run_queue() && task.cpu_id() == self.cpu_id {
+                let removed = scheduler.remove_task(task);
+                if let Some(task) = &removed {
+                    self.dequeued(task);
+                }
+                removed
+            } else {
+                None
+            }
+        };
+        if let Some(task) = removed {
+            let rq = least_loaded_run_queue(task.affinity());
+            debug!(
+                "task migrate: {}, CPU {} -> CPU {}",
+                task.id_name(),
+                self.cpu_id,
+                rq.cpu_id
+            );
+            rq.enqueue(task, None);
+        }
+    }
+
+    /// Steals a ready task from the busiest remote run queue.
+    #[cfg(feature = "smp")]
+    fn steal_task(&self) -> Option<AxTaskRef> {
+        let mut busiest: Option<&AxRunQueue> = None;
+        for i in 1..axconfig::SMP {
+            let cpu_id = (self.cpu_id + i) % axconfig::SMP;
+            if let Some(rq) = remote_run_queue(cpu_id) {
+                if rq.nr_tasks() > busiest.map_or(0, |b| b.nr_tasks()) {
+                    busiest = Some(rq);
+                }
+            }
+        }
+        let rq = busiest?;
+        // Do not spin on a remote lock, just try again next time.
+        let mut scheduler = rq.scheduler.try_lock()?;
+        let task = scheduler.steal_task(|t| self.allows(t))?;
+        rq.dequeued(&task);
+        drop(scheduler);
+        debug!(
+            "task migrate: {}, CPU {} -> CPU {}",
+            task.id_name(),
+            rq.cpu_id,
+            self.cpu_id
+        );
+        Some(task)
+    }
+
     /// Common reschedule subroutine. If `preempt`, keep current task's time
     /// slice, otherwise reset it.
-    fn resched(&mut self, preempt: bool) {
+    fn resched(&self, preempt: bool) {
         let prev = crate::current();
         if prev.is_running() {
             prev.set_state(TaskState::Ready);
             if !prev.is_idle() {
-                self.scheduler.put_prev_task(prev.clone(), preempt);
+                if self.allows(prev.as_task_ref()) {
+                    self.enqueue(prev.clone(), Some(preempt));
+                } else {
+                    // the affinity was changed while running, move it away.
+                    select_run_queue(prev.as_task_ref()).enqueue(prev.clone(), None);
+                }
             }
         }
-        let next = self.scheduler.pick_next_task().unwrap_or_else(|| unsafe {
+        let next = self.pick_next_task();
+        #[cfg(feature = "smp")]
+        let next = next.or_else(|| self.steal_task());
+        let next = next.unwrap_or_else(|| unsafe {
             // Safety: IRQs must be disabled at this time.
             IDLE_TASK.current_ref_raw().get_unchecked().clone()
         });
         self.switch_to(prev, next);
     }
 
-    fn switch_to(&mut self, prev_task: CurrentTask, next_task: AxTaskRef) {
+    fn switch_to(&self, prev_task: CurrentTask, next_task: AxTaskRef) {
         trace!(
             "context switch: {} -> {}",
             prev_task.id_name(),
@@ -175,6 +452,29 @@ impl AxRunQueue {
         if prev_task.ptr_eq(&next_task) {
             return;
         }
+        prev_task.check_stack_canary();
+
+        let now_ns = axhal::time::current_time_nanos();
+        prev_task.on_switch_out(now_ns);
+        next_task.on_switch_in(now_ns);
+
+        // The tick may be stopped while the CPU is idle, restart it for the
+        // time slices of the next task.
+        #[cfg(feature = "tickless")]
+        if prev_task.is_idle() {
+            crate::timers::restart_tick();
+        }
+
+        #[cfg(feature = "smp")]
+        {
+            // The next task may be still switching out on a remote CPU (e.g.,
+            // it was just stolen or woken up), wait until its context is saved.
+            while next_task.on_cpu() {
+                core::hint::spin_loop();
+            }
+            next_task.set_on_cpu(true);
+            next_task.set_cpu_id(self.cpu_id);
+        }
 
         unsafe {
             let prev_ctx_ptr = prev_task.ctx_mut_ptr();
@@ -185,19 +485,120 @@ impl AxRunQueue {
             assert!(Arc::strong_count(prev_task.as_task_ref()) > 1);
             assert!(Arc::strong_count(&next_task) >= 1);
 
+            // Keep `prev_task` alive until its `on_cpu` flag is cleared.
+            #[cfg(feature = "smp")]
+            PREV_TASK.write_current_raw(Arc::into_raw(prev_task.clone()) as usize);
+
             CurrentTask::set_current(prev_task, next_task);
             (*prev_ctx_ptr).switch_to(&*next_ctx_ptr);
+
+            #[cfg(feature = "smp")]
+            clear_prev_task_on_cpu();
+        }
+    }
+}
+
+/// Sets the pending-kill flag of the given task, and wakes it up if it is
+/// blocked in an interruptible wait.
+pub(crate) fn kill_task(task: &AxTaskRef) {
+    if task.set_killed() {
+        debug!("task kill: {}", task.id_name());
+        fence(Ordering::SeqCst);
+        if task.state() == TaskState::Blocked && task.is_interruptible() {
+            select_run_queue(task).unblock_task(task.clone(), true);
+        }
+    }
+}
+
+/// Sets the base priority of the given task, and updates its effective
+/// priority with the boosts.
+pub(crate) fn set_task_priority(task: &AxTaskRef, prio: isize) -> bool {
+    let boosts = task.priority_boosts().lock();
+    let ok = set_effective_priority(task, boosted_priority(prio, &boosts));
+    if ok {
+        task.set_base_priority(prio);
+    }
+    ok
+}
+
+/// Replaces the priority boost of `key` of the given task with `prio`, or
+/// removes it if `prio` is `None`, and updates its effective priority.
+pub(crate) fn set_task_priority_boost(task: &AxTaskRef, key: usize, prio: Option<isize>) -> bool {
+    let mut boosts = task.priority_boosts().lock();
+    boosts.retain(|&(k, _)| k != key);
+    if let Some(prio) = prio {
+        boosts.push((key, prio));
+    }
+    let prio = boosted_priority(task.base_priority(), &boosts);
+    prio == task.priority() || set_effective_priority(task, prio)
+}
+
+fn boosted_priority(base: isize, boosts: &[(usize, isize)]) -> isize {
+    boosts
+        .iter()
+        .fold(base, |prio, &(_, boost)| prio.min(boost))
+}
+
+/// Sets the priority of the given task, in the scheduler of the run queue it
+/// belongs to.
+fn set_effective_priority(task: &AxTaskRef, prio: isize) -> bool {
+    let _guard = NoPreemptIrqSave::new();
+    #[cfg(not(feature = "smp"))]
+    let mut scheduler = unsafe { RUN_QUEUE.current_ref_raw().get_unchecked() }
+        .scheduler
+        .lock();
+    #[cfg(feature = "smp")]
+    let mut scheduler = loop {
+        let rq = remote_run_queue(task.cpu_id()).unwrap();
+        let scheduler = rq.scheduler.lock();
+        // The task may be moved to another run queue before we get the lock.
+        if task.cpu_id() == rq.cpu_id {
+            break scheduler;
         }
+    };
+    let ok = scheduler.set_priority(task, prio);
+    if ok {
+        task.set_priority(prio);
+    }
+    ok
+}
+
+/// Moves the given task to a CPU allowed by its affinity mask, if it is
+/// ready and queued on a disallowed CPU.
+///
+/// A running task on a disallowed CPU is moved at its next rescheduling.
+#[cfg(feature = "smp")]
+pub(crate) fn migrate_task(task: &AxTaskRef) {
+    let _guard = NoPreemptIrqSave::new();
+    let cpu_id = task.cpu_id();
+    if task.affinity().contains(cpu_id) {
+        return;
     }
+    if let Some(rq) = remote_run_queue(cpu_id) {
+        rq.migrate_task(task);
+    }
+}
+
+/// Marks the previous task on this CPU as no longer running, so that other
+/// CPUs can switch to it.
+///
+/// # Safety
+///
+/// It must be called exactly once after each context switch, with IRQs
+/// disabled.
+#[cfg(feature = "smp")]
+pub(crate) unsafe fn clear_prev_task_on_cpu() {
+    let prev = AxTaskRef::from_raw(PREV_TASK.read_current_raw() as *const crate::AxTask);
+    prev.set_on_cpu(false);
 }
 
-fn gc_entry() {
+fn gc_entry(rq: &'static AxRunQueue) {
     loop {
         // Drop all exited tasks and recycle resources.
-        let n = EXITED_TASKS.lock().len();
+        let n = rq.exited_tasks.lock().len();
         for _ in 0..n {
             // Do not do the slow drops in the critical section.
-            let task = EXITED_TASKS.lock().pop_front();
+            let task = rq.exited_tasks.lock().pop_front();
             if let Some(task) = task {
                 if Arc::strong_count(&task) == 1 {
                     // If I'm the last holder of the task, drop it immediately.
@@ -205,29 +606,58 @@ fn gc_entry() {
                 } else {
                     // Otherwise (e.g, `switch_to` is not compeleted, held by the
                     // joiner, etc), push it back and wait for them to drop first.
-                    EXITED_TASKS.lock().push_back(task);
+                    rq.exited_tasks.lock().push_back(task);
                 }
             }
         }
-        WAIT_FOR_EXIT.wait();
+        rq.wait_for_exit.wait();
     }
 }
 
+fn init_run_queue(cpu_id: usize) -> &'static AxRunQueue {
+    let ptr = RUN_QUEUE.with_current(|rq| {
+        rq.init_by(AxRunQueue::new(cpu_id));
+        &**rq as *const AxRunQueue as *mut AxRunQueue
+    });
+    RUN_QUEUES[cpu_id].store(ptr, Ordering::Release);
+    // Safety: the per-CPU run queue lives as long as the kernel.
+    unsafe { &*ptr }
+}
+
+/// Spawns the GC task of this CPU, which drops the tasks exited on it.
+fn spawn_gc_task(rq: &'static AxRunQueue) {
+    let gc_task = TaskInner::new(move || gc_entry(rq), "gc".into(), axconfig::TASK_STACK_SIZE);
+    gc_task.set_affinity(CpuMask::one(rq.cpu_id));
+    current_run_queue().add_task(gc_task);
+}
+
 pub(crate) fn init() {
     const IDLE_TASK_STACK_SIZE: usize = 4096;
+    let cpu_id = axhal::cpu::this_cpu_id();
     let idle_task = TaskInner::new(|| crate::run_idle(), "idle".into(), IDLE_TASK_STACK_SIZE);
     IDLE_TASK.with_current(|i| i.init_by(idle_task.clone()));
 
     let main_task = TaskInner::new_init("main".into());
     main_task.set_state(TaskState::Running);
 
-    RUN_QUEUE.init_by(AxRunQueue::new());
+    let rq = init_run_queue(cpu_id);
     unsafe { CurrentTask::init_current(main_task) }
+
+    #[cfg(all(feature = "smp", feature = "irq"))]
+    axhal::irq::register_ipi_handler(IpiKind::Reschedule, || {
+        current_run_queue().check_preempt_current(false)
+    });
+
+    spawn_gc_task(rq);
 }
 
 pub(crate) fn init_secondary() {
+    let cpu_id = axhal::cpu::this_cpu_id();
     let idle_task = TaskInner::new_init("idle".into());
     idle_task.set_state(TaskState::Running);
     IDLE_TASK.with_current(|i| i.init_by(idle_task.clone()));
+
+    let rq = init_run_queue(cpu_id);
     unsafe { CurrentTask::init_current(idle_task) }
+    spawn_gc_task(rq);
 }
@@ -0,0 +1,256 @@
+//! Software interrupts (softirqs) and tasklets, the bottom halves of IRQ
+//! handlers.
+//!
+//! An IRQ handler runs with IRQs disabled, so it should only do the urgent
+//! part of the job, and [`raise`] a softirq for the rest. Pending softirqs are
+//! handled when the outermost IRQ handler returns (see [`handle_pending`]), with
+//! IRQs enabled but preemption still disabled. Their handlers can be
+//! interrupted by other IRQs, but must not block.
+//!
+//! Softirqs raised in task context, or raised again and again by their own
+//! handlers, are handled by the per-CPU `ksoftirqd` task instead, so that they
+//! do not starve the other tasks.
+//!
+//! A softirq is handled on the CPU it is raised on, and the handler of the
+//! same softirq may run on several CPUs at the same time. [`Tasklet`]s are
+//! built on top of [`SoftIrq::Tasklet`], and a tasklet never runs on two CPUs
+//! at the same time.
+//!
+//! Use a [work queue](crate::workqueue) or a
+//! [threaded IRQ](crate::threaded_irq) if the deferred work needs to block.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use axtask::softirq::Tasklet;
+//!
+//! fn rx_bottom_half() {
+//!     println!("process the received packets");
+//! }
+//!
+//! static RX_TASKLET: Tasklet = Tasklet::new(rx_bottom_half);
+//!
+//! // in the IRQ handler:
+//! RX_TASKLET.schedule();
+//! ```
+
+use alloc::{collections::VecDeque, format};
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use handler_table::HandlerTable;
+use kernel_guard::{IrqSave, NoPreemptIrqSave};
+
+use crate::{CpuMask, WaitQueue};
+
+/// Softirq numbers, the lower ones are handled first.
+#[repr(usize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoftIrq {
+    /// Transmitting network packets.
+    NetTx = 0,
+    /// Receiving network packets.
+    NetRx = 1,
+    /// Completing block device requests.
+    Block = 2,
+    /// Running [`Tasklet`]s.
+    Tasklet = 3,
+}
+
+/// The number of softirqs.
+pub const NR_SOFTIRQS: usize = 4;
+
+/// The maximum number of rounds to handle softirqs at IRQ exit, the remaining
+/// ones are left to `ksoftirqd`.
+const MAX_SOFTIRQ_RESTART: usize = 10;
+
+static HANDLERS: HandlerTable<NR_SOFTIRQS> = HandlerTable::new();
+
+/// Bitmap of the pending softirqs on this CPU.
+#[percpu::def_percpu]
+static PENDING: usize = 0;
+
+/// Whether this CPU is handling softirqs.
+#[percpu::def_percpu]
+static IN_SOFTIRQ: bool = false;
+
+/// Wakes up `ksoftirqd` of this CPU.
+#[percpu::def_percpu]
+static KSOFTIRQD_WQ: WaitQueue = WaitQueue::new();
+
+/// Tasklets scheduled on this CPU.
+#[percpu::def_percpu]
+static TASKLETS: VecDeque<&'static Tasklet> = VecDeque::new();
+
+/// Registers the handler of a softirq.
+///
+/// Returns `false` if the softirq already has a handler.
+pub fn register_handler(nr: SoftIrq, handler: fn()) -> bool {
+    HANDLERS.register_handler(nr as usize, handler)
+}
+
+/// Marks a softirq as pending on the current CPU.
+///
+/// In IRQ context, it is handled when the outermost IRQ handler returns.
+/// Otherwise, `ksoftirqd` is woken up to handle it.
+pub fn raise(nr: SoftIrq) {
+    let _guard = NoPreemptIrqSave::new();
+    // Safety: IRQs and preemption are disabled.
+    unsafe {
+        PENDING.write_current_raw(PENDING.read_current_raw() | 1 << nr as usize);
+        if !in_interrupt() {
+            wakeup_ksoftirqd();
+        }
+    }
+}
+
+/// Whether the current CPU is in IRQ context, i.e., running an IRQ handler
+/// (see [`axhal::irq::in_irq`]) or a softirq handler.
+pub fn in_interrupt() -> bool {
+    let _guard = IrqSave::new();
+    // Safety: IRQs are disabled.
+    axhal::irq::in_irq() || unsafe { IN_SOFTIRQ.read_current_raw() }
+}
+
+/// Handles the pending softirqs, called by the trap handler after the IRQ is
+/// dispatched and acknowledged.
+///
+/// It does nothing unless the outermost IRQ handler has returned. Otherwise,
+/// the pending softirqs are handled here, with IRQs enabled. IRQs and
+/// preemption must be disabled when it is called, and IRQs are disabled
+/// again when it returns.
+pub fn handle_pending() {
+    // Safety: IRQs are disabled.
+    unsafe {
+        if !in_interrupt() && PENDING.read_current_raw() != 0 {
+            do_softirq();
+        }
+    }
+}
+
+/// Handles the pending softirqs on the current CPU.
+///
+/// # Safety
+///
+/// IRQs and preemption must be disabled, and it must not be called
+/// recursively.
+unsafe fn do_softirq() {
+    IN_SOFTIRQ.write_current_raw(true);
+    for _ in 0..MAX_SOFTIRQ_RESTART {
+        let mut pending = PENDING.read_current_raw();
+        if pending == 0 {
+            break;
+        }
+        PENDING.write_current_raw(0);
+        axhal::arch::enable_irqs();
+        while pending != 0 {
+            let nr = pending.trailing_zeros() as usize;
+            pending &= pending - 1;
+            HANDLERS.handle(nr);
+        }
+        axhal::arch::disable_irqs();
+    }
+    IN_SOFTIRQ.write_current_raw(false);
+    if PENDING.read_current_raw() != 0 {
+        wakeup_ksoftirqd();
+    }
+}
+
+/// # Safety
+///
+/// IRQs and preemption must be disabled.
+unsafe fn wakeup_ksoftirqd() {
+    KSOFTIRQD_WQ.current_ref_raw().notify_one(true);
+}
+
+fn ksoftirqd_loop() {
+    loop {
+        // Safety: `ksoftirqd` is bound to its CPU.
+        let wq = unsafe { KSOFTIRQD_WQ.current_ref_raw() };
+        wq.wait_until(|| PENDING.read_current() != 0);
+        let guard = NoPreemptIrqSave::new();
+        // Safety: IRQs and preemption are disabled, and it is not in IRQ
+        // context, so no one else is handling softirqs on this CPU.
+        unsafe { do_softirq() };
+        drop(guard);
+        crate::yield_now();
+    }
+}
+
+/// A deferred function that runs in softirq context.
+///
+/// Scheduling a tasklet that is already scheduled has no effect. It is run
+/// on the CPU that schedules it, and never runs on two CPUs at the same time.
+pub struct Tasklet {
+    state: AtomicU8,
+    func: fn(),
+}
+
+const TASKLET_SCHED: u8 = 1 << 0;
+const TASKLET_RUN: u8 = 1 << 1;
+
+impl Tasklet {
+    /// Creates a new tasklet that calls `func` when it runs.
+    pub const fn new(func: fn()) -> Self {
+        Self {
+            state: AtomicU8::new(0),
+            func,
+        }
+    }
+
+    /// Schedules the tasklet to run on the current CPU, returns `false` if it
+    /// has already been scheduled.
+    pub fn schedule(&'static self) -> bool {
+        if self.state.fetch_or(TASKLET_SCHED, Ordering::AcqRel) & TASKLET_SCHED != 0 {
+            return false;
+        }
+        let guard = NoPreemptIrqSave::new();
+        // Safety: IRQs and preemption are disabled.
+        unsafe { TASKLETS.current_ref_mut_raw().push_back(self) };
+        raise(SoftIrq::Tasklet);
+        drop(guard);
+        true
+    }
+
+    /// Whether the tasklet has been scheduled but not started yet.
+    pub fn is_scheduled(&self) -> bool {
+        self.state.load(Ordering::Acquire) & TASKLET_SCHED != 0
+    }
+}
+
+fn tasklet_action() {
+    let list = {
+        let _guard = IrqSave::new();
+        // Safety: IRQs are disabled, and preemption is disabled in softirqs.
+        unsafe { core::mem::take(TASKLETS.current_ref_mut_raw()) }
+    };
+    for tasklet in list {
+        if tasklet.state.fetch_or(TASKLET_RUN, Ordering::Acquire) & TASKLET_RUN != 0 {
+            // running on another CPU, try again later.
+            let _guard = IrqSave::new();
+            // Safety: IRQs are disabled, and preemption is disabled in softirqs.
+            unsafe { TASKLETS.current_ref_mut_raw().push_back(tasklet) };
+            raise(SoftIrq::Tasklet);
+            continue;
+        }
+        // clear the flag first, so that it can be scheduled again while running.
+        tasklet.state.fetch_and(!TASKLET_SCHED, Ordering::AcqRel);
+        (tasklet.func)();
+        tasklet.state.fetch_and(!TASKLET_RUN, Ordering::Release);
+    }
+}
+
+pub(crate) fn init() {
+    register_handler(SoftIrq::Tasklet, tasklet_action);
+    init_percpu();
+}
+
+/// Spawns `ksoftirqd` of the current CPU.
+pub(crate) fn init_percpu() {
+    let cpu_id = axhal::cpu::this_cpu_id();
+    crate::spawn_raw_with_affinity(
+        ksoftirqd_loop,
+        format!("ksoftirqd/{}", cpu_id),
+        axconfig::TASK_STACK_SIZE,
+        CpuMask::one(cpu_id),
+    );
+}
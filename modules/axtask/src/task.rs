@@ -1,15 +1,16 @@
-use alloc::{boxed::Box, string::String, sync::Arc};
+use alloc::{boxed::Box, string::String, sync::Arc, vec::Vec};
 use core::ops::Deref;
-use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, AtomicU8, Ordering};
+use core::sync::atomic::Ordering;
+use core::sync::atomic::{AtomicBool, AtomicI32, AtomicIsize, AtomicU64, AtomicU8, AtomicUsize};
+use core::time::Duration;
 use core::{alloc::Layout, cell::UnsafeCell, fmt, ptr::NonNull};
 
-#[cfg(feature = "preempt")]
-use core::sync::atomic::AtomicUsize;
-
 use axhal::arch::TaskContext;
 use memory_addr::{align_up_4k, VirtAddr};
+use spinlock::SpinNoIrq;
 
-use crate::{AxRunQueue, AxTask, AxTaskRef, WaitQueue};
+use crate::task_local::TaskLocals;
+use crate::{AxTask, AxTaskRef, CpuMask, WaitQueue};
 
 /// A unique identifier for a thread.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -18,10 +19,14 @@ pub struct TaskId(u64);
 /// The possible states of a task.
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
-pub(crate) enum TaskState {
+pub enum TaskState {
+    /// The task is running on a CPU.
     Running = 1,
+    /// The task is ready to run, waiting in a run queue.
     Ready = 2,
+    /// The task is blocked, e.g., sleeping or waiting in a wait queue.
     Blocked = 3,
+    /// The task has exited, but is not dropped yet.
     Exited = 4,
 }
 
@@ -38,15 +43,60 @@ pub struct TaskInner {
     in_wait_queue: AtomicBool,
     #[cfg(feature = "irq")]
     in_timer_list: AtomicBool,
+    /// Whether the task has been requested to terminate by [`kill`].
+    ///
+    /// [`kill`]: crate::kill
+    killed: AtomicBool,
+    /// Whether the task is blocked in an interruptible wait, which is woken up
+    /// by [`kill`].
+    ///
+    /// [`kill`]: crate::kill
+    interruptible: AtomicBool,
+
+    /// The priority last set to the scheduler, including the boosts.
+    priority: AtomicIsize,
+    /// The priority set by [`set_task_priority`], without the boosts.
+    ///
+    /// [`set_task_priority`]: crate::set_task_priority
+    base_priority: AtomicIsize,
+    /// The priority boosts of the task (see [`boost_task_priority`]), and the
+    /// keys of them.
+    ///
+    /// [`boost_task_priority`]: crate::boost_task_priority
+    priority_boosts: SpinNoIrq<Vec<(usize, isize)>>,
+    /// CPUs that the task is allowed to run on.
+    cpumask: AtomicUsize,
+    /// CPU ID that the task last ran on, or the CPU of the run queue it is in.
+    #[cfg(feature = "smp")]
+    cpu_id: AtomicUsize,
+    /// Whether the task is in the run queue of CPU `cpu_id`.
+    #[cfg(feature = "smp")]
+    in_run_queue: AtomicBool,
+    /// Whether the task is running on a CPU, or its context is not saved yet.
+    #[cfg(feature = "smp")]
+    on_cpu: AtomicBool,
 
     #[cfg(feature = "preempt")]
     need_resched: AtomicBool,
     #[cfg(feature = "preempt")]
     preempt_disable_count: AtomicUsize,
 
+    /// The time when the task was last switched in, in nanoseconds.
+    switched_in_at: AtomicU64,
+    /// The accumulated running time before `switched_in_at`, in nanoseconds.
+    runtime_ns: AtomicU64,
+    /// The number of times the task was switched in.
+    nr_switches: AtomicU64,
+
     exit_code: AtomicI32,
     wait_for_exit: WaitQueue,
 
+    /// Task-local values, only accessed by the task itself.
+    task_locals: UnsafeCell<TaskLocals>,
+    /// Locks held by the task, only accessed by the task itself.
+    #[cfg(feature = "lockdep")]
+    held_locks: UnsafeCell<lockdep::HeldLocks>,
+
     kstack: Option<TaskStack>,
     ctx: UnsafeCell<TaskContext>,
 }
@@ -95,6 +145,73 @@ impl TaskInner {
         alloc::format!("Task({}, {:?})", self.id.as_u64(), self.name)
     }
 
+    /// Gets the priority of the task.
+    ///
+    /// Its meaning depends on the scheduler, see [`set_priority`] for
+    /// details. A smaller value means a higher priority.
+    ///
+    /// It is the effective priority, i.e., the highest one of the
+    /// [base priority](Self::base_priority) and the boosts (see
+    /// [`boost_task_priority`]).
+    ///
+    /// [`set_priority`]: crate::set_priority
+    /// [`boost_task_priority`]: crate::boost_task_priority
+    pub fn priority(&self) -> isize {
+        self.priority.load(Ordering::Acquire)
+    }
+
+    /// Gets the priority of the task set by [`set_priority`], without the
+    /// boosts.
+    ///
+    /// [`set_priority`]: crate::set_priority
+    pub fn base_priority(&self) -> isize {
+        self.base_priority.load(Ordering::Acquire)
+    }
+
+    /// Gets the set of CPUs that the task is allowed to run on.
+    pub fn affinity(&self) -> CpuMask {
+        CpuMask::from_raw(self.cpumask.load(Ordering::Acquire))
+    }
+
+    #[inline]
+    pub(crate) fn state(&self) -> TaskState {
+        self.state.load(Ordering::Acquire).into()
+    }
+
+    /// Gets the total time the task has been running on CPUs.
+    pub fn runtime(&self) -> Duration {
+        let mut ns = self.runtime_ns.load(Ordering::Acquire);
+        if self.is_running() {
+            let now_ns = axhal::time::current_time_nanos();
+            ns += now_ns.saturating_sub(self.switched_in_at.load(Ordering::Acquire));
+        }
+        Duration::from_nanos(ns)
+    }
+
+    /// Gets the number of times the task was switched in.
+    pub fn context_switches(&self) -> u64 {
+        self.nr_switches.load(Ordering::Relaxed)
+    }
+
+    /// Gets the size of the task's stack, and the maximum number of bytes
+    /// ever used in it (the high-water mark).
+    ///
+    /// Returns `None` if the task runs on the boot stack, e.g., the main task.
+    /// The high-water mark is only tracked in debug builds, it is `None` in
+    /// release builds.
+    pub fn stack_usage(&self) -> Option<(usize, Option<usize>)> {
+        self.kstack
+            .as_ref()
+            .map(|kstack| (kstack.size(), kstack.high_water_mark()))
+    }
+
+    /// Whether the task has been requested to terminate by [`kill`].
+    ///
+    /// [`kill`]: crate::kill
+    pub fn is_killed(&self) -> bool {
+        self.killed.load(Ordering::Acquire)
+    }
+
     /// Wait for the task to exit, and return the exit code.
     ///
     /// It will return immediately if the task has already exited (but not dropped).
@@ -103,6 +220,46 @@ impl TaskInner {
             .wait_until(|| self.state() == TaskState::Exited);
         Some(self.exit_code.load(Ordering::Acquire))
     }
+
+    /// Same as [`join`](Self::join), but returns `None` if the current task is
+    /// killed while waiting.
+    pub fn join_interruptible(&self) -> Option<i32> {
+        self.wait_for_exit
+            .wait_until_interruptible(|| self.state() == TaskState::Exited)
+            .ok()?;
+        Some(self.exit_code.load(Ordering::Acquire))
+    }
+
+    /// Wait for the task to exit for at most the given duration, and return
+    /// the exit code.
+    ///
+    /// Returns `None` if it is timed out.
+    #[cfg(feature = "irq")]
+    pub fn join_timeout(&self, dur: Duration) -> Option<i32> {
+        let timeout = self
+            .wait_for_exit
+            .wait_timeout_until(dur, || self.state() == TaskState::Exited);
+        if timeout {
+            None
+        } else {
+            Some(self.exit_code.load(Ordering::Acquire))
+        }
+    }
+
+    /// Same as [`join_timeout`](Self::join_timeout), but also returns `None`
+    /// if the current task is killed while waiting.
+    #[cfg(feature = "irq")]
+    pub fn join_timeout_interruptible(&self, dur: Duration) -> Option<i32> {
+        let timeout = self
+            .wait_for_exit
+            .wait_timeout_until_interruptible(dur, || self.state() == TaskState::Exited)
+            .ok()?;
+        if timeout {
+            None
+        } else {
+            Some(self.exit_code.load(Ordering::Acquire))
+        }
+    }
 }
 
 // private methods
@@ -118,12 +275,30 @@ impl TaskInner {
             in_wait_queue: AtomicBool::new(false),
             #[cfg(feature = "irq")]
             in_timer_list: AtomicBool::new(false),
+            killed: AtomicBool::new(false),
+            interruptible: AtomicBool::new(false),
+            priority: AtomicIsize::new(crate::api::DEFAULT_PRIO),
+            base_priority: AtomicIsize::new(crate::api::DEFAULT_PRIO),
+            priority_boosts: SpinNoIrq::new(Vec::new()),
+            cpumask: AtomicUsize::new(CpuMask::full().bits()),
+            #[cfg(feature = "smp")]
+            cpu_id: AtomicUsize::new(0),
+            #[cfg(feature = "smp")]
+            in_run_queue: AtomicBool::new(false),
+            #[cfg(feature = "smp")]
+            on_cpu: AtomicBool::new(false),
             #[cfg(feature = "preempt")]
             need_resched: AtomicBool::new(false),
             #[cfg(feature = "preempt")]
             preempt_disable_count: AtomicUsize::new(0),
+            switched_in_at: AtomicU64::new(0),
+            runtime_ns: AtomicU64::new(0),
+            nr_switches: AtomicU64::new(0),
             exit_code: AtomicI32::new(0),
             wait_for_exit: WaitQueue::new(),
+            task_locals: UnsafeCell::new(TaskLocals::new()),
+            #[cfg(feature = "lockdep")]
+            held_locks: UnsafeCell::new(lockdep::HeldLocks::new()),
             kstack: None,
             ctx: UnsafeCell::new(TaskContext::new()),
         }
@@ -142,22 +317,45 @@ impl TaskInner {
         if t.name == "idle" {
             t.is_idle = true;
         }
-        Arc::new(AxTask::new(t))
+        let task = Arc::new(AxTask::new(t));
+        crate::registry::register(&task);
+        task
     }
 
     pub(crate) fn new_init(name: String) -> AxTaskRef {
         // init_task does not change PC and SP, so `entry` and `kstack` fields are not used.
         let mut t = Self::new_common(TaskId::new(), name);
         t.is_init = true;
+        #[cfg(feature = "smp")]
+        {
+            t.cpu_id = AtomicUsize::new(axhal::cpu::this_cpu_id());
+            t.on_cpu = AtomicBool::new(true);
+        }
         if t.name == "idle" {
             t.is_idle = true;
         }
-        Arc::new(AxTask::new(t))
+        let task = Arc::new(AxTask::new(t));
+        crate::registry::register(&task);
+        task
     }
 
+    /// Gets the task-local values.
+    ///
+    /// # Safety
+    ///
+    /// It must be called by the task itself, and the returned reference must
+    /// not outlive other calls to it.
     #[inline]
-    pub(crate) fn state(&self) -> TaskState {
-        self.state.load(Ordering::Acquire).into()
+    #[allow(clippy::mut_from_ref)]
+    pub(crate) unsafe fn task_locals_mut(&self) -> &mut TaskLocals {
+        &mut *self.task_locals.get()
+    }
+
+    /// Gets the locks held by the task, for the lockdep validator.
+    #[cfg(feature = "lockdep")]
+    #[inline]
+    pub(crate) fn held_locks(&self) -> core::ptr::NonNull<lockdep::HeldLocks> {
+        unsafe { core::ptr::NonNull::new_unchecked(self.held_locks.get()) }
     }
 
     #[inline]
@@ -165,6 +363,20 @@ impl TaskInner {
         self.state.store(state as u8, Ordering::Release)
     }
 
+    /// Changes the state from `current_state` to `new_state` atomically.
+    /// Returns `false` if the task is not in `current_state`.
+    #[inline]
+    pub(crate) fn transition_state(&self, current_state: TaskState, new_state: TaskState) -> bool {
+        self.state
+            .compare_exchange(
+                current_state as u8,
+                new_state as u8,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_ok()
+    }
+
     #[inline]
     pub(crate) fn is_running(&self) -> bool {
         matches!(self.state(), TaskState::Running)
@@ -175,11 +387,6 @@ impl TaskInner {
         matches!(self.state(), TaskState::Ready)
     }
 
-    #[inline]
-    pub(crate) fn is_blocked(&self) -> bool {
-        matches!(self.state(), TaskState::Blocked)
-    }
-
     #[inline]
     pub(crate) const fn is_init(&self) -> bool {
         self.is_init
@@ -212,6 +419,78 @@ impl TaskInner {
         self.in_timer_list.store(in_timer_list, Ordering::Release);
     }
 
+    #[inline]
+    pub(crate) fn is_interruptible(&self) -> bool {
+        self.interruptible.load(Ordering::SeqCst)
+    }
+
+    #[inline]
+    pub(crate) fn set_interruptible(&self, interruptible: bool) {
+        self.interruptible.store(interruptible, Ordering::SeqCst);
+    }
+
+    /// Sets the pending-kill flag, returns `false` if it was already set.
+    #[inline]
+    pub(crate) fn set_killed(&self) -> bool {
+        !self.killed.swap(true, Ordering::SeqCst)
+    }
+
+    #[inline]
+    #[cfg(feature = "smp")]
+    pub(crate) fn cpu_id(&self) -> usize {
+        self.cpu_id.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    #[cfg(feature = "smp")]
+    pub(crate) fn set_cpu_id(&self, cpu_id: usize) {
+        self.cpu_id.store(cpu_id, Ordering::Release);
+    }
+
+    #[inline]
+    pub(crate) fn set_priority(&self, prio: isize) {
+        self.priority.store(prio, Ordering::Release);
+    }
+
+    #[inline]
+    pub(crate) fn set_base_priority(&self, prio: isize) {
+        self.base_priority.store(prio, Ordering::Release);
+    }
+
+    #[inline]
+    pub(crate) const fn priority_boosts(&self) -> &SpinNoIrq<Vec<(usize, isize)>> {
+        &self.priority_boosts
+    }
+
+    #[inline]
+    pub(crate) fn set_affinity(&self, cpumask: CpuMask) {
+        self.cpumask.store(cpumask.bits(), Ordering::Release);
+    }
+
+    #[inline]
+    #[cfg(feature = "smp")]
+    pub(crate) fn in_run_queue(&self) -> bool {
+        self.in_run_queue.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    #[cfg(feature = "smp")]
+    pub(crate) fn set_in_run_queue(&self, in_run_queue: bool) {
+        self.in_run_queue.store(in_run_queue, Ordering::Release);
+    }
+
+    #[inline]
+    #[cfg(feature = "smp")]
+    pub(crate) fn on_cpu(&self) -> bool {
+        self.on_cpu.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    #[cfg(feature = "smp")]
+    pub(crate) fn set_on_cpu(&self, on_cpu: bool) {
+        self.on_cpu.store(on_cpu, Ordering::Release);
+    }
+
     #[inline]
     #[cfg(feature = "preempt")]
     pub(crate) fn set_preempt_pending(&self, pending: bool) {
@@ -243,16 +522,54 @@ impl TaskInner {
     fn current_check_preempt_pending() {
         let curr = crate::current();
         if curr.need_resched.load(Ordering::Acquire) && curr.can_preempt(0) {
-            let mut rq = crate::RUN_QUEUE.lock();
+            let rq = crate::current_run_queue();
             if curr.need_resched.load(Ordering::Acquire) {
                 rq.preempt_resched();
             }
         }
     }
 
-    pub(crate) fn notify_exit(&self, exit_code: i32, rq: &mut AxRunQueue) {
+    pub(crate) fn notify_exit(&self, exit_code: i32) {
         self.exit_code.store(exit_code, Ordering::Release);
-        self.wait_for_exit.notify_all_locked(false, rq);
+        self.wait_for_exit.notify_all_locked(false);
+    }
+
+    /// Updates the runtime statistics when the task is switched out.
+    pub(crate) fn on_switch_out(&self, now_ns: u64) {
+        let start_ns = self.switched_in_at.load(Ordering::Acquire);
+        self.runtime_ns
+            .fetch_add(now_ns.saturating_sub(start_ns), Ordering::AcqRel);
+    }
+
+    /// Updates the runtime statistics when the task is switched in.
+    pub(crate) fn on_switch_in(&self, now_ns: u64) {
+        self.switched_in_at.store(now_ns, Ordering::Release);
+        self.nr_switches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Panics if the canary at the bottom of the task's stack has been
+    /// overwritten.
+    pub(crate) fn check_stack_canary(&self) {
+        if self.kstack.as_ref().is_some_and(|s| s.is_overflowed()) {
+            self.panic_stack_overflow();
+        }
+    }
+
+    /// Panics if `vaddr` is in the guard page of the task's stack.
+    #[cfg(feature = "paging")]
+    pub(crate) fn check_stack_guard(&self, vaddr: VirtAddr) {
+        if self.kstack.as_ref().is_some_and(|s| s.is_guard(vaddr)) {
+            self.panic_stack_overflow();
+        }
+    }
+
+    fn panic_stack_overflow(&self) -> ! {
+        // do not allocate, the overflow may happen with the heap locked
+        panic!(
+            "stack overflow in Task({}, {:?})",
+            self.id.as_u64(),
+            self.name
+        );
     }
 
     #[inline]
@@ -274,6 +591,7 @@ impl fmt::Debug for TaskInner {
 impl Drop for TaskInner {
     fn drop(&mut self) {
         debug!("task drop: {}", self.id_name());
+        crate::registry::unregister(self.id);
     }
 }
 
@@ -282,22 +600,101 @@ struct TaskStack {
     layout: Layout,
 }
 
+/// The byte that an unused stack is filled with in debug builds, to find its
+/// high-water mark.
+const STACK_FILL: u8 = 0xcc;
+
+/// The magic number at the bottom of each stack, which is overwritten when
+/// the stack overflows.
+const STACK_CANARY: u64 = 0x5aa5_c3a5_deaf_beef;
+const CANARY_SIZE: usize = core::mem::size_of::<u64>();
+
+/// The size of the unmapped guard page below each stack.
+#[cfg(feature = "paging")]
+const GUARD_SIZE: usize = axhal::mem::PAGE_SIZE_4K;
+#[cfg(not(feature = "paging"))]
+const GUARD_SIZE: usize = 0;
+
 impl TaskStack {
     pub fn alloc(size: usize) -> Self {
-        let layout = Layout::from_size_align(size, 16).unwrap();
-        Self {
-            ptr: NonNull::new(unsafe { alloc::alloc::alloc(layout) }).unwrap(),
-            layout,
+        let align = if cfg!(feature = "paging") {
+            GUARD_SIZE
+        } else {
+            16
+        };
+        let layout = Layout::from_size_align(GUARD_SIZE + size, align).unwrap();
+        let ptr = NonNull::new(unsafe { alloc::alloc::alloc(layout) }).unwrap();
+        let stack = Self { ptr, layout };
+        unsafe {
+            if cfg!(debug_assertions) {
+                stack.bottom().write_bytes(STACK_FILL, size);
+            }
+            (stack.bottom() as *mut u64).write(STACK_CANARY);
+        }
+        #[cfg(feature = "paging")]
+        if let Err(e) = axhal::paging::protect_page(stack.guard_start()) {
+            panic!(
+                "failed to protect the stack guard page at {:#x}: {:?}",
+                stack.guard_start(),
+                e
+            );
         }
+        stack
+    }
+
+    const fn bottom(&self) -> *mut u8 {
+        unsafe { self.ptr.as_ptr().add(GUARD_SIZE) }
     }
 
     pub const fn top(&self) -> VirtAddr {
         unsafe { core::mem::transmute(self.ptr.as_ptr().add(self.layout.size())) }
     }
+
+    pub const fn size(&self) -> usize {
+        self.layout.size() - GUARD_SIZE
+    }
+
+    /// Returns the maximum number of bytes ever used, by finding the lowest
+    /// byte that has been overwritten.
+    ///
+    /// Returns `None` in release builds, where the stack is not filled.
+    pub fn high_water_mark(&self) -> Option<usize> {
+        if !cfg!(debug_assertions) {
+            return None;
+        }
+        let stack = unsafe {
+            core::slice::from_raw_parts(self.bottom().add(CANARY_SIZE), self.size() - CANARY_SIZE)
+        };
+        let unused = stack.iter().take_while(|&&b| b == STACK_FILL).count();
+        Some(self.size() - unused)
+    }
+
+    /// Whether the canary at the bottom of the stack has been overwritten.
+    pub fn is_overflowed(&self) -> bool {
+        unsafe { (self.bottom() as *const u64).read_volatile() != STACK_CANARY }
+    }
+
+    #[cfg(feature = "paging")]
+    fn guard_start(&self) -> VirtAddr {
+        VirtAddr::from(self.ptr.as_ptr() as usize)
+    }
+
+    /// Whether `vaddr` is in the guard page of the stack.
+    #[cfg(feature = "paging")]
+    pub fn is_guard(&self, vaddr: VirtAddr) -> bool {
+        let start = self.guard_start();
+        vaddr >= start && vaddr < start + GUARD_SIZE
+    }
 }
 
 impl Drop for TaskStack {
     fn drop(&mut self) {
+        #[cfg(feature = "paging")]
+        if let Err(e) = axhal::paging::unprotect_page(self.guard_start()) {
+            // leak the memory rather than giving back an unmapped page
+            warn!("failed to unprotect the stack guard page: {:?}", e);
+            return;
+        }
         unsafe { alloc::alloc::dealloc(self.ptr.as_ptr(), self.layout) }
     }
 }
@@ -355,8 +752,11 @@ impl Deref for CurrentTask {
 }
 
 extern "C" fn task_entry() -> ! {
-    // release the lock that was implicitly held across the reschedule
-    unsafe { crate::RUN_QUEUE.force_unlock() };
+    // finish the context switch that was started by the previous task
+    #[cfg(feature = "smp")]
+    unsafe {
+        crate::run_queue::clear_prev_task_on_cpu()
+    };
     #[cfg(feature = "irq")]
     axhal::arch::enable_irqs();
     let task = crate::current();
@@ -0,0 +1,82 @@
+//! Task-local storage.
+
+use alloc::{boxed::Box, collections::BTreeMap};
+use core::any::Any;
+
+/// The task-local values of a task, indexed by the address of their
+/// [`LocalKey`].
+pub(crate) type TaskLocals = BTreeMap<usize, Box<dyn Any>>;
+
+/// A key for task-local storage, similar to
+/// [`std::thread::LocalKey`](https://doc.rust-lang.org/std/thread/struct.LocalKey.html).
+///
+/// Each task has its own copy of the value, which is lazily initialized by the
+/// init function on the first [`LocalKey::with`] in the task, and dropped when
+/// the task exits.
+///
+/// # Examples
+///
+/// ```no_run
+/// use axtask::LocalKey;
+/// use core::cell::Cell;
+///
+/// static COUNTER: LocalKey<Cell<u32>> = LocalKey::new(|| Cell::new(0));
+///
+/// COUNTER.with(|c| c.set(c.get() + 1));
+/// assert_eq!(COUNTER.with(|c| c.get()), 1);
+/// ```
+pub struct LocalKey<T: 'static> {
+    init: fn() -> T,
+}
+
+impl<T: 'static> LocalKey<T> {
+    /// Creates a new key, whose value is initialized by `init` in each task.
+    pub const fn new(init: fn() -> T) -> Self {
+        Self { init }
+    }
+
+    /// Acquires a reference to the value of the current task.
+    ///
+    /// It initializes the value if it is the first time the current task
+    /// accesses it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the scheduler is not initialized.
+    pub fn with<F, R>(&'static self, f: F) -> R
+    where
+        F: FnOnce(&T) -> R,
+    {
+        let curr = crate::current();
+        let key = self as *const _ as usize;
+        // Safety: the task-local values are only accessed by the current
+        // task, and the borrow does not live across `init` and `f`, which may
+        // access other keys.
+        let value: *const dyn Any = match unsafe { curr.task_locals_mut() }.get(&key) {
+            Some(value) => value.as_ref(),
+            None => {
+                let value = Box::new((self.init)());
+                let locals = unsafe { curr.task_locals_mut() };
+                &**locals.entry(key).or_insert(value)
+            }
+        };
+        // Safety: the value is boxed and only removed when the task exits.
+        f(unsafe { &*value }.downcast_ref::<T>().unwrap())
+    }
+}
+
+/// Drops all task-local values of the current task.
+///
+/// It is called before the task exits, so that the destructors run in the
+/// context of the task itself.
+pub(crate) fn destroy_current() {
+    let curr = crate::current();
+    loop {
+        // destructors may initialize other keys again
+        let locals = core::mem::take(unsafe { curr.task_locals_mut() });
+        if locals.is_empty() {
+            break;
+        }
+        drop(locals);
+    }
+}
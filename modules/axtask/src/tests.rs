@@ -6,6 +6,10 @@ use crate::{self as axtask, current, WaitQueue};
 static INIT: Once = Once::new();
 static SERIAL: Mutex<()> = Mutex::new(());
 
+/// The IRQ numbers used by the tests to run handlers in IRQ context.
+#[cfg(feature = "irq")]
+const TEST_IRQ_NUM: usize = 32;
+
 #[test]
 fn test_sched_fifo() {
     let _lock = SERIAL.lock();
@@ -128,3 +132,282 @@ fn test_task_join() {
         assert_eq!(tasks[i].join(), Some(i as _));
     }
 }
+
+#[test]
+fn test_task_kill() {
+    use crate::task::TaskState;
+    use crate::Interrupted;
+
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    static WQ: WaitQueue = WaitQueue::new();
+
+    // killed while waiting
+    let task = axtask::spawn(|| {
+        assert_eq!(WQ.wait_interruptible(), Err(Interrupted));
+        assert!(current().is_killed());
+        axtask::exit(1);
+    });
+    while task.state() != TaskState::Blocked {
+        axtask::yield_now();
+    }
+    axtask::kill(&task);
+    assert_eq!(task.join(), Some(1));
+    #[cfg(feature = "irq")]
+    assert_eq!(
+        task.join_timeout(core::time::Duration::from_secs(1)),
+        Some(1)
+    );
+
+    // killed before waiting
+    let task = axtask::spawn(|| {
+        let res = WQ.wait_until_interruptible(|| false);
+        axtask::exit(if res == Err(Interrupted) { 2 } else { 0 });
+    });
+    axtask::kill(&task);
+    assert_eq!(task.join(), Some(2));
+    assert!(!current().is_killed());
+
+    // uninterruptible waits are not woken up
+    let task = axtask::spawn(|| {
+        WQ.wait();
+        assert!(current().is_killed());
+        axtask::exit(3);
+    });
+    while task.state() != TaskState::Blocked {
+        axtask::yield_now();
+    }
+    axtask::kill(&task);
+    for _ in 0..10 {
+        axtask::yield_now();
+        assert_eq!(task.state(), TaskState::Blocked);
+    }
+    WQ.notify_one(true);
+    assert_eq!(task.join(), Some(3));
+}
+
+#[test]
+fn test_task_stats() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    const NUM_YIELDS: usize = 5;
+    static STARTED: AtomicUsize = AtomicUsize::new(0);
+
+    let task = axtask::spawn_raw(
+        || {
+            let buf = core::hint::black_box([1u8; 0x2000]);
+            STARTED.store(buf.len(), Ordering::Release);
+            for _ in 0..NUM_YIELDS {
+                axtask::yield_now();
+            }
+        },
+        "stats".into(),
+        0x10000,
+    );
+    while STARTED.load(Ordering::Acquire) == 0 {
+        axtask::yield_now();
+    }
+
+    let info = axtask::tasks().find(|t| t.id == task.id()).unwrap();
+    assert_eq!(info.name, "stats");
+    assert_eq!(info.state, 'r');
+    assert!(info.context_switches >= 1);
+    assert_eq!(info.stack_size, 0x10000);
+    #[cfg(debug_assertions)]
+    {
+        let high_water_mark = info.stack_high_water_mark.unwrap();
+        assert!(high_water_mark >= 0x2000);
+        assert!(high_water_mark < 0x10000);
+    }
+
+    let main = axtask::tasks().find(|t| t.id == current().id()).unwrap();
+    assert_eq!(main.state, 'R');
+    assert_eq!(main.stack_size, 0);
+
+    task.join();
+    assert!(task.context_switches() >= 2);
+}
+
+#[test]
+fn test_task_local() {
+    use core::cell::Cell;
+
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    struct Counter(Cell<usize>);
+
+    impl Drop for Counter {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(self.0.get(), Ordering::Relaxed);
+        }
+    }
+
+    const NUM_TASKS: usize = 5;
+    static COUNTER: crate::LocalKey<Counter> = crate::LocalKey::new(|| Counter(Cell::new(0)));
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    let tasks: Vec<_> = (0..NUM_TASKS)
+        .map(|i| {
+            axtask::spawn(move || {
+                for _ in 0..=i {
+                    COUNTER.with(|c| c.0.set(c.0.get() + 1));
+                    axtask::yield_now();
+                }
+                assert_eq!(COUNTER.with(|c| c.0.get()), i + 1);
+            })
+        })
+        .collect();
+    for t in tasks {
+        t.join();
+    }
+
+    // the values are dropped when the tasks exit
+    assert_eq!(
+        DROPPED.load(Ordering::Relaxed),
+        NUM_TASKS * (NUM_TASKS + 1) / 2
+    );
+    assert_eq!(COUNTER.with(|c| c.0.get()), 0);
+}
+
+#[test]
+#[cfg(feature = "irq")]
+fn test_timer() {
+    use crate::timer::Timer;
+    use core::time::Duration;
+
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    static FIRED: AtomicUsize = AtomicUsize::new(0);
+
+    let now = axhal::time::current_time();
+    let t1 = Timer::oneshot(now, |_| {
+        FIRED.fetch_add(1, Ordering::Relaxed);
+    });
+    let t2 = Timer::oneshot(now, |_| {
+        FIRED.fetch_add(10, Ordering::Relaxed);
+    });
+    let t3 = Timer::periodic(Duration::from_secs(3600), |_| {
+        FIRED.fetch_add(100, Ordering::Relaxed);
+    });
+    assert!(t2.cancel());
+
+    axtask::on_timer_tick();
+    assert_eq!(FIRED.load(Ordering::Relaxed), 1);
+    assert!(!t1.is_active());
+    assert!(!t1.cancel()); // already expired
+    assert!(!t2.cancel()); // already cancelled
+    assert!(t3.is_periodic() && t3.is_active());
+    assert!(t3.cancel());
+}
+
+#[test]
+fn test_workqueue() {
+    use crate::workqueue::{self, Work, WorkQueue};
+
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    static WORK: Work = Work::new(work_fn);
+    static WQ: WorkQueue = WorkQueue::new("test_wq");
+
+    fn work_fn() {
+        COUNTER.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // queued before the workers start, the second request is merged
+    assert!(WQ.queue(&WORK));
+    assert!(!WQ.queue(&WORK));
+    assert!(WORK.is_pending());
+    WQ.queue_fn(|| {
+        axtask::yield_now(); // work items can block
+        COUNTER.fetch_add(10, Ordering::Relaxed);
+    });
+    WQ.start(2);
+    WQ.flush();
+    assert_eq!(COUNTER.load(Ordering::Relaxed), 11);
+    assert!(!WORK.is_pending());
+
+    assert!(workqueue::schedule_work(&WORK));
+    workqueue::system_wq().flush();
+    assert_eq!(COUNTER.load(Ordering::Relaxed), 12);
+}
+
+#[test]
+#[cfg(feature = "irq")]
+fn test_softirq() {
+    use crate::softirq::{self, SoftIrq, Tasklet};
+
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    static TASKLET: Tasklet = Tasklet::new(tasklet_fn);
+
+    fn block_softirq() {
+        COUNTER.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn tasklet_fn() {
+        COUNTER.fetch_add(10, Ordering::Relaxed);
+    }
+
+    assert!(softirq::register_handler(SoftIrq::Block, block_softirq));
+    assert!(!softirq::register_handler(SoftIrq::Block, block_softirq));
+
+    fn irq_handler() {
+        assert!(softirq::in_interrupt());
+        softirq::raise(SoftIrq::Block);
+        assert!(TASKLET.schedule());
+        assert!(!TASKLET.schedule());
+    }
+
+    // raised in IRQ context, handled at IRQ exit
+    assert!(axhal::irq::register_handler(TEST_IRQ_NUM, irq_handler));
+    let guard = kernel_guard::NoPreemptIrqSave::new();
+    axhal::irq::dispatch_irq(TEST_IRQ_NUM);
+    assert_eq!(COUNTER.load(Ordering::Relaxed), 0);
+    softirq::handle_pending();
+    drop(guard);
+    assert_eq!(COUNTER.load(Ordering::Relaxed), 11);
+    assert!(!TASKLET.is_scheduled());
+    assert!(!softirq::in_interrupt());
+
+    // raised in task context, handled by ksoftirqd
+    softirq::raise(SoftIrq::Block);
+    while COUNTER.load(Ordering::Relaxed) < 12 {
+        axtask::yield_now();
+    }
+    assert!(axtask::tasks().any(|t| t.name == "ksoftirqd/0"));
+}
+
+#[test]
+#[cfg(all(feature = "irq", debug_assertions))]
+fn test_might_sleep() {
+    use core::sync::atomic::AtomicBool;
+
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    static WQ: WaitQueue = WaitQueue::new();
+    static BLOCKED: AtomicBool = AtomicBool::new(false);
+
+    WQ.wait_until(|| true);
+
+    fn irq_handler() {
+        let res = std::panic::catch_unwind(|| WQ.wait_until(|| true));
+        BLOCKED.store(res.is_ok(), Ordering::Relaxed);
+    }
+
+    // blocking in IRQ context
+    assert!(axhal::irq::register_handler(TEST_IRQ_NUM + 1, irq_handler));
+    BLOCKED.store(true, Ordering::Relaxed);
+    let guard = kernel_guard::NoPreemptIrqSave::new();
+    axhal::irq::dispatch_irq(TEST_IRQ_NUM + 1);
+    drop(guard);
+    assert!(!BLOCKED.load(Ordering::Relaxed));
+}
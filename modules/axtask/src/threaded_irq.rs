@@ -0,0 +1,133 @@
+//! Threaded IRQ handlers, which run in a dedicated kernel task.
+//!
+//! A threaded IRQ has two parts. The primary handler runs in IRQ context as
+//! usual, it checks whether the device really needs service. Then the IRQ
+//! line is masked, and the thread function runs in the task `irq/{irq_num}`,
+//! where it can block. The IRQ line is unmasked after it returns.
+//!
+//! Each threaded IRQ is described by a `static` [`ThreadedIrq`]. Since the
+//! handlers registered by [`axhal::irq::register_handler`] take no arguments,
+//! it is requested by the [`request_threaded_irq!`] macro, which defines the
+//! handler that refers to the static for you.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use axtask::threaded_irq::ThreadedIrq;
+//!
+//! fn net_irq_thread() {
+//!     println!("process the received packets, may block");
+//! }
+//!
+//! static NET_IRQ: ThreadedIrq = ThreadedIrq::new(net_irq_thread);
+//!
+//! # let irq_num = 0;
+//! axtask::request_threaded_irq!(irq_num, NET_IRQ);
+//! ```
+//!
+//! [`request_threaded_irq!`]: crate::request_threaded_irq
+
+use alloc::format;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use axhal::irq::IrqHandler;
+
+use crate::WaitQueue;
+
+/// The state of a threaded IRQ, see the [module-level documentation](self).
+///
+/// It serves one IRQ line, and can be requested only once.
+pub struct ThreadedIrq {
+    irq_num: AtomicUsize,
+    primary: fn() -> bool,
+    thread_fn: fn(),
+    pending: AtomicBool,
+    wq: WaitQueue,
+}
+
+impl ThreadedIrq {
+    /// Creates a threaded IRQ that always wakes up the thread.
+    pub const fn new(thread_fn: fn()) -> Self {
+        Self::with_primary(always_wake, thread_fn)
+    }
+
+    /// Creates a threaded IRQ with a primary handler.
+    ///
+    /// The primary handler returns `true` if the thread needs to be woken up,
+    /// or `false` if the IRQ is fully handled (or not raised by the device).
+    pub const fn with_primary(primary: fn() -> bool, thread_fn: fn()) -> Self {
+        Self {
+            irq_num: AtomicUsize::new(usize::MAX),
+            primary,
+            thread_fn,
+            pending: AtomicBool::new(false),
+            wq: WaitQueue::new(),
+        }
+    }
+
+    /// Registers `handler` for the IRQ, and spawns the IRQ thread.
+    ///
+    /// `handler` must call [`handle`](Self::handle) of `self`, it is better
+    /// to use [`request_threaded_irq!`](crate::request_threaded_irq) instead.
+    /// Returns `false` if the registration failed, or `self` has already been
+    /// requested.
+    pub fn request(&'static self, irq_num: usize, handler: IrqHandler) -> bool {
+        if self
+            .irq_num
+            .compare_exchange(usize::MAX, irq_num, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return false;
+        }
+        if !axhal::irq::register_handler(irq_num, handler) {
+            self.irq_num.store(usize::MAX, Ordering::Release);
+            return false;
+        }
+        crate::spawn_raw(
+            || self.thread_loop(),
+            format!("irq/{}", irq_num),
+            axconfig::TASK_STACK_SIZE,
+        );
+        true
+    }
+
+    /// The hard IRQ handler, which runs the primary handler, and wakes up the
+    /// IRQ thread with the IRQ line masked if needed.
+    pub fn handle(&self) {
+        if (self.primary)() {
+            let irq_num = self.irq_num.load(Ordering::Acquire);
+            axhal::irq::set_enable(irq_num, false);
+            self.pending.store(true, Ordering::Release);
+            self.wq.notify_one(true);
+        }
+    }
+
+    fn thread_loop(&self) {
+        let irq_num = self.irq_num.load(Ordering::Acquire);
+        loop {
+            self.wq.wait_until(|| self.pending.load(Ordering::Acquire));
+            // the IRQ line is masked, so it can not be set again until we
+            // unmask it.
+            self.pending.store(false, Ordering::Release);
+            (self.thread_fn)();
+            axhal::irq::set_enable(irq_num, true);
+        }
+    }
+}
+
+fn always_wake() -> bool {
+    true
+}
+
+/// Requests a threaded IRQ, returns `false` if the registration failed.
+///
+/// The arguments are the IRQ number, and the path of a `static`
+/// [`ThreadedIrq`](crate::threaded_irq::ThreadedIrq) (see
+/// [`ThreadedIrq::request`](crate::threaded_irq::ThreadedIrq::request)). See
+/// the [`threaded_irq`](crate::threaded_irq) module for details.
+#[macro_export]
+macro_rules! request_threaded_irq {
+    ($irq_num:expr, $irq:path) => {
+        $crate::threaded_irq::ThreadedIrq::request(&$irq, $irq_num, || $irq.handle())
+    };
+}
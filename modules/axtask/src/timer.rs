@@ -0,0 +1,148 @@
+//! Kernel timers that run callbacks at the given time.
+//!
+//! The callbacks are run in the timer interrupt handler (after the sleeping
+//! tasks are woken up), with IRQs disabled. So they must be short and must not
+//! block, e.g., acquire a sleeping mutex or sleep. Spawn a task or notify a
+//! [`WaitQueue`](crate::WaitQueue) in the callback if more work is needed.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use axtask::timer::Timer;
+//! use core::time::Duration;
+//!
+//! let handle = Timer::periodic(Duration::from_millis(10), |now| {
+//!     println!("tick at {:?}", now);
+//! });
+//! axtask::sleep(Duration::from_millis(100));
+//! handle.cancel();
+//! ```
+
+use alloc::{boxed::Box, sync::Arc};
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use axhal::time::{current_time, TimeValue};
+use spinlock::SpinNoIrq;
+
+enum Callback {
+    Oneshot(Option<Box<dyn FnOnce(TimeValue) + Send>>),
+    Periodic(Box<dyn FnMut(TimeValue) + Send>),
+}
+
+/// A kernel timer.
+///
+/// It is created by [`Timer::oneshot`] or [`Timer::periodic`], and controlled
+/// by the returned [`TimerHandle`].
+pub struct Timer {
+    interval: Option<Duration>,
+    active: AtomicBool,
+    callback: SpinNoIrq<Callback>,
+}
+
+/// A handle to a [`Timer`], which can be used to cancel it.
+///
+/// Dropping the handle does not cancel the timer.
+pub struct TimerHandle(Arc<Timer>);
+
+impl Timer {
+    /// Creates a timer that calls `f` once at `deadline`.
+    ///
+    /// The argument of `f` is the current time when it is called. If the
+    /// deadline has already passed, `f` is called at the next timer tick.
+    pub fn oneshot<F>(deadline: TimeValue, f: F) -> TimerHandle
+    where
+        F: FnOnce(TimeValue) + Send + 'static,
+    {
+        Self::start(deadline, None, Callback::Oneshot(Some(Box::new(f))))
+    }
+
+    /// Creates a timer that calls `f` every `interval`, starting from one
+    /// `interval` later.
+    ///
+    /// The deadlines are not affected by the callback latency. If some of them
+    /// are missed (e.g., IRQs are disabled for a long time), the timer skips
+    /// them rather than calling `f` several times in a row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn periodic<F>(interval: Duration, f: F) -> TimerHandle
+    where
+        F: FnMut(TimeValue) + Send + 'static,
+    {
+        assert!(!interval.is_zero(), "the timer interval must be non-zero");
+        let deadline = current_time() + interval;
+        Self::start(deadline, Some(interval), Callback::Periodic(Box::new(f)))
+    }
+
+    fn start(deadline: TimeValue, interval: Option<Duration>, callback: Callback) -> TimerHandle {
+        let timer = Arc::new(Self {
+            interval,
+            active: AtomicBool::new(true),
+            callback: SpinNoIrq::new(callback),
+        });
+        crate::timers::set_timer(deadline, timer.clone());
+        TimerHandle(timer)
+    }
+
+    /// Runs the callback, and re-arms the timer if it is periodic.
+    pub(crate) fn fire(self: Arc<Self>, deadline: TimeValue, now: TimeValue) {
+        if !self.active.load(Ordering::Acquire) {
+            return;
+        }
+        // `active` is checked again under the callback lock, which `cancel`
+        // also holds, so the callback is never called after `cancel` returns.
+        match &mut *self.callback.lock() {
+            Callback::Oneshot(f) => {
+                if self.active.swap(false, Ordering::AcqRel) {
+                    if let Some(f) = f.take() {
+                        f(now);
+                    }
+                }
+            }
+            Callback::Periodic(f) => {
+                if self.active.load(Ordering::Acquire) {
+                    f(now);
+                }
+            }
+        }
+        if let Some(interval) = self.interval {
+            if self.active.load(Ordering::Acquire) {
+                let mut next = deadline + interval;
+                if next <= now {
+                    next = now + interval;
+                }
+                crate::timers::set_timer(next, self);
+            }
+        }
+    }
+}
+
+impl TimerHandle {
+    /// Cancels the timer, the callback will not be called anymore.
+    ///
+    /// If the callback is running on another CPU, it waits for the callback
+    /// to return. So it must not be called in the callback of the same timer.
+    ///
+    /// Returns `false` if the timer has already been cancelled, or it is an
+    /// one-shot timer that has expired.
+    pub fn cancel(&self) -> bool {
+        let callback = self.0.callback.lock();
+        let active = self.0.active.swap(false, Ordering::AcqRel);
+        drop(callback);
+        crate::timers::cancel_timer(&self.0);
+        active
+    }
+
+    /// Whether the timer is still active, i.e., not cancelled and, for an
+    /// one-shot timer, not expired.
+    pub fn is_active(&self) -> bool {
+        self.0.active.load(Ordering::Acquire)
+    }
+
+    /// Whether the timer is periodic.
+    pub fn is_periodic(&self) -> bool {
+        self.0.interval.is_some()
+    }
+}
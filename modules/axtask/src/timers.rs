@@ -4,31 +4,76 @@ use lazy_init::LazyInit;
 use spinlock::SpinNoIrq;
 use timer_list::{TimeValue, TimerEvent, TimerList};
 
-use crate::{AxTaskRef, RUN_QUEUE};
+use crate::run_queue::select_run_queue;
+use crate::timer::Timer;
+use crate::AxTaskRef;
 
 // TODO: per-CPU
-static TIMER_LIST: LazyInit<SpinNoIrq<TimerList<TaskWakeupEvent>>> = LazyInit::new();
+static TIMER_LIST: LazyInit<SpinNoIrq<TimerList<AxTimerEvent>>> = LazyInit::new();
 
-struct TaskWakeupEvent(AxTaskRef);
+enum AxTimerEvent {
+    /// Wakes up a sleeping task.
+    TaskWakeup(AxTaskRef),
+    /// Runs the callback of a [`Timer`] that expired at `deadline`.
+    Callback(Arc<Timer>, TimeValue),
+}
 
-impl TimerEvent for TaskWakeupEvent {
-    fn callback(self, _now: TimeValue) {
-        let mut rq = RUN_QUEUE.lock();
-        self.0.set_in_timer_list(false);
-        rq.unblock_task(self.0, true);
+impl TimerEvent for AxTimerEvent {
+    fn callback(self, now: TimeValue) {
+        match self {
+            Self::TaskWakeup(task) => {
+                let rq = select_run_queue(&task);
+                task.set_in_timer_list(false);
+                rq.unblock_task(task, true);
+            }
+            Self::Callback(timer, deadline) => timer.fire(deadline, now),
+        }
     }
 }
 
 pub fn set_alarm_wakeup(deadline: TimeValue, task: AxTaskRef) {
     let mut timers = TIMER_LIST.lock();
     task.set_in_timer_list(true);
-    timers.set(deadline, TaskWakeupEvent(task));
+    timers.set(deadline, AxTimerEvent::TaskWakeup(task));
 }
 
 pub fn cancel_alarm(task: &AxTaskRef) {
     let mut timers = TIMER_LIST.lock();
     task.set_in_timer_list(false);
-    timers.cancel(|t| Arc::ptr_eq(&t.0, task));
+    timers.cancel(|e| matches!(e, AxTimerEvent::TaskWakeup(t) if Arc::ptr_eq(t, task)));
+}
+
+pub fn set_timer(deadline: TimeValue, timer: Arc<Timer>) {
+    TIMER_LIST
+        .lock()
+        .set(deadline, AxTimerEvent::Callback(timer, deadline));
+}
+
+pub fn cancel_timer(timer: &Arc<Timer>) {
+    TIMER_LIST
+        .lock()
+        .cancel(|e| matches!(e, AxTimerEvent::Callback(t, _) if Arc::ptr_eq(t, timer)));
+}
+
+pub fn next_deadline() -> Option<TimeValue> {
+    TIMER_LIST.lock().next_deadline()
+}
+
+/// The interface to restart the periodic timer tick, which must be implemented
+/// by the runtime that programs the timer.
+#[cfg(feature = "tickless")]
+#[crate_interface::def_interface]
+pub trait TickIf {
+    /// Restarts the periodic tick on the current CPU, which may be stopped
+    /// while the CPU is idle.
+    ///
+    /// It is called with IRQs disabled when the idle task is switched out.
+    fn restart_tick();
+}
+
+#[cfg(feature = "tickless")]
+pub fn restart_tick() {
+    crate_interface::call_interface!(TickIf::restart_tick);
 }
 
 pub fn check_events() {
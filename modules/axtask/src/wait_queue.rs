@@ -2,7 +2,13 @@ use alloc::collections::VecDeque;
 use alloc::sync::Arc;
 use spinlock::SpinRaw;
 
-use crate::{AxRunQueue, AxTaskRef, CurrentTask, RUN_QUEUE};
+use crate::run_queue::{current_run_queue, select_run_queue};
+use crate::{AxTaskRef, CurrentTask};
+
+/// The error returned by the interruptible waits of [`WaitQueue`], when the
+/// current task is killed while waiting (see [`kill`](crate::kill)).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interrupted;
 
 /// A queue to store sleeping tasks.
 ///
@@ -27,11 +33,12 @@ use crate::{AxRunQueue, AxTaskRef, CurrentTask, RUN_QUEUE};
 /// assert_eq!(VALUE.load(Ordering::Relaxed), 1);
 /// ```
 pub struct WaitQueue {
-    queue: SpinRaw<VecDeque<AxTaskRef>>, // we already disabled IRQs when lock the `RUN_QUEUE`
+    queue: SpinRaw<VecDeque<AxTaskRef>>, // we already disabled IRQs when getting the run queue
 }
 
 impl WaitQueue {
     /// Creates an empty wait queue.
+    #[cfg_attr(feature = "lockdep", track_caller)]
     pub const fn new() -> Self {
         Self {
             queue: SpinRaw::new(VecDeque::new()),
@@ -39,6 +46,7 @@ impl WaitQueue {
     }
 
     /// Creates an empty wait queue with space for at least `capacity` elements.
+    #[cfg_attr(feature = "lockdep", track_caller)]
     pub fn with_capacity(capacity: usize) -> Self {
         Self {
             queue: SpinRaw::new(VecDeque::with_capacity(capacity)),
@@ -49,8 +57,8 @@ impl WaitQueue {
         // A task can be wake up only one events (timer or `notify()`), remove
         // the event from another queue.
         if curr.in_wait_queue() {
-            // wake up by timer (timeout).
-            // `RUN_QUEUE` is not locked here, so disable IRQs.
+            // wake up by timer (timeout) or `kill()`.
+            // the run queue is not held here, so disable IRQs.
             let _guard = kernel_guard::IrqSave::new();
             self.queue.lock().retain(|t| !curr.ptr_eq(t));
             curr.set_in_wait_queue(false);
@@ -65,11 +73,45 @@ impl WaitQueue {
     /// Blocks the current task and put it into the wait queue, until other task
     /// notifies it.
     pub fn wait(&self) {
-        RUN_QUEUE.lock().block_current(|task| {
-            task.set_in_wait_queue(true);
-            self.queue.lock().push_back(task)
-        });
-        self.cancel_events(crate::current());
+        self.wait_inner(false).ok();
+    }
+
+    /// Same as [`wait`](Self::wait), but it is also woken up when the current
+    /// task is killed (see [`kill`](crate::kill)).
+    ///
+    /// Returns [`Err(Interrupted)`](Interrupted) if the current task is killed.
+    pub fn wait_interruptible(&self) -> Result<(), Interrupted> {
+        self.wait_inner(true)
+    }
+
+    fn wait_inner(&self, interruptible: bool) -> Result<(), Interrupted> {
+        crate::might_sleep();
+        let curr = crate::current();
+        let mut blocked = false;
+        let mut res = Ok(());
+        loop {
+            let rq = current_run_queue();
+            let mut wq = self.queue.lock();
+            if curr.in_wait_queue() {
+                // woken up by `kill()` rather than `notify()`, still in the queue.
+                wq.retain(|t| !curr.ptr_eq(t));
+            } else if blocked {
+                break;
+            }
+            if interruptible && curr.is_killed() {
+                curr.set_in_wait_queue(false);
+                res = Err(Interrupted);
+                break;
+            }
+            rq.block_current(interruptible, |task| {
+                task.set_in_wait_queue(true);
+                wq.push_back(task);
+                drop(wq);
+            });
+            blocked = true;
+        }
+        self.cancel_events(curr);
+        res
     }
 
     /// Blocks the current task and put it into the wait queue, until the given
@@ -81,23 +123,83 @@ impl WaitQueue {
     where
         F: Fn() -> bool,
     {
+        self.wait_until_inner(false, condition).ok();
+    }
+
+    /// Same as [`wait_until`](Self::wait_until), but it is also woken up when
+    /// the current task is killed (see [`kill`](crate::kill)).
+    ///
+    /// Returns [`Err(Interrupted)`](Interrupted) if the current task is killed
+    /// before the condition becomes true.
+    pub fn wait_until_interruptible<F>(&self, condition: F) -> Result<(), Interrupted>
+    where
+        F: Fn() -> bool,
+    {
+        self.wait_until_inner(true, condition)
+    }
+
+    fn wait_until_inner<F>(&self, interruptible: bool, condition: F) -> Result<(), Interrupted>
+    where
+        F: Fn() -> bool,
+    {
+        crate::might_sleep();
+        let curr = crate::current();
+        let mut res = Ok(());
         loop {
-            let mut rq = RUN_QUEUE.lock();
+            let rq = current_run_queue();
+            // hold the queue lock until we are in the queue, so that other
+            // CPUs can not notify between the check and the block.
+            let mut wq = self.queue.lock();
             if condition() {
                 break;
             }
-            rq.block_current(|task| {
+            if interruptible && curr.is_killed() {
+                res = Err(Interrupted);
+                break;
+            }
+            if curr.in_wait_queue() {
+                // woken up by `kill()` rather than `notify()`, still in the queue.
+                wq.retain(|t| !curr.ptr_eq(t));
+            }
+            rq.block_current(interruptible, |task| {
                 task.set_in_wait_queue(true);
-                self.queue.lock().push_back(task);
+                wq.push_back(task);
+                drop(wq);
             });
         }
-        self.cancel_events(crate::current());
+        self.cancel_events(curr);
+        res
     }
 
     /// Blocks the current task and put it into the wait queue, until other tasks
     /// notify it, or the given duration has elapsed.
+    ///
+    /// Returns `true` if it is timed out.
     #[cfg(feature = "irq")]
     pub fn wait_timeout(&self, dur: core::time::Duration) -> bool {
+        self.wait_timeout_inner(false, dur).unwrap_or(true)
+    }
+
+    /// Same as [`wait_timeout`](Self::wait_timeout), but it is also woken up
+    /// when the current task is killed (see [`kill`](crate::kill)).
+    ///
+    /// Returns `Ok(true)` if it is timed out, or [`Err(Interrupted)`](Interrupted)
+    /// if the current task is killed.
+    #[cfg(feature = "irq")]
+    pub fn wait_timeout_interruptible(
+        &self,
+        dur: core::time::Duration,
+    ) -> Result<bool, Interrupted> {
+        self.wait_timeout_inner(true, dur)
+    }
+
+    #[cfg(feature = "irq")]
+    fn wait_timeout_inner(
+        &self,
+        interruptible: bool,
+        dur: core::time::Duration,
+    ) -> Result<bool, Interrupted> {
+        crate::might_sleep();
         let curr = crate::current();
         let deadline = axhal::time::current_time() + dur;
         debug!(
@@ -105,15 +207,41 @@ impl WaitQueue {
             curr.id_name(),
             deadline
         );
-        crate::timers::set_alarm_wakeup(deadline, curr.clone());
 
-        RUN_QUEUE.lock().block_current(|task| {
-            task.set_in_wait_queue(true);
-            self.queue.lock().push_back(task)
-        });
-        let timeout = curr.in_wait_queue(); // still in the wait queue, must have timed out
+        let mut blocked = false;
+        let mut res = Ok(true);
+        loop {
+            let rq = current_run_queue();
+            let mut wq = self.queue.lock();
+            if curr.in_wait_queue() {
+                // timed out, or woken up by `kill()`, still in the queue.
+                wq.retain(|t| !curr.ptr_eq(t));
+                curr.set_in_wait_queue(false);
+            } else if blocked {
+                res = Ok(false);
+                break;
+            }
+            if interruptible && curr.is_killed() {
+                res = Err(Interrupted);
+                break;
+            }
+            if axhal::time::current_time() >= deadline {
+                break;
+            }
+            rq.block_current(interruptible, |task| {
+                // set the alarm after the task is blocked, the timer may be
+                // fired on other CPUs immediately.
+                if !task.in_timer_list() {
+                    crate::timers::set_alarm_wakeup(deadline, task.clone());
+                }
+                task.set_in_wait_queue(true);
+                wq.push_back(task);
+                drop(wq);
+            });
+            blocked = true;
+        }
         self.cancel_events(curr);
-        timeout
+        res
     }
 
     /// Blocks the current task and put it into the wait queue, until the given
@@ -121,11 +249,45 @@ impl WaitQueue {
     ///
     /// Note that even other tasks notify this task, it will not wake up until
     /// the above conditions are met.
+    ///
+    /// Returns `true` if it is timed out.
     #[cfg(feature = "irq")]
     pub fn wait_timeout_until<F>(&self, dur: core::time::Duration, condition: F) -> bool
     where
         F: Fn() -> bool,
     {
+        self.wait_timeout_until_inner(false, dur, condition)
+            .unwrap_or(true)
+    }
+
+    /// Same as [`wait_timeout_until`](Self::wait_timeout_until), but it is
+    /// also woken up when the current task is killed (see [`kill`](crate::kill)).
+    ///
+    /// Returns `Ok(true)` if it is timed out, or [`Err(Interrupted)`](Interrupted)
+    /// if the current task is killed before the condition becomes true.
+    #[cfg(feature = "irq")]
+    pub fn wait_timeout_until_interruptible<F>(
+        &self,
+        dur: core::time::Duration,
+        condition: F,
+    ) -> Result<bool, Interrupted>
+    where
+        F: Fn() -> bool,
+    {
+        self.wait_timeout_until_inner(true, dur, condition)
+    }
+
+    #[cfg(feature = "irq")]
+    fn wait_timeout_until_inner<F>(
+        &self,
+        interruptible: bool,
+        dur: core::time::Duration,
+        condition: F,
+    ) -> Result<bool, Interrupted>
+    where
+        F: Fn() -> bool,
+    {
+        crate::might_sleep();
         let curr = crate::current();
         let deadline = axhal::time::current_time() + dur;
         debug!(
@@ -133,22 +295,37 @@ impl WaitQueue {
             curr.id_name(),
             deadline
         );
-        crate::timers::set_alarm_wakeup(deadline, curr.clone());
 
-        let mut timeout = true;
-        while axhal::time::current_time() < deadline {
-            let mut rq = RUN_QUEUE.lock();
+        let mut res = Ok(true);
+        loop {
+            let rq = current_run_queue();
+            let mut wq = self.queue.lock();
             if condition() {
-                timeout = false;
+                res = Ok(false);
+                break;
+            }
+            if interruptible && curr.is_killed() {
+                res = Err(Interrupted);
+                break;
+            }
+            if axhal::time::current_time() >= deadline {
                 break;
             }
-            rq.block_current(|task| {
+            if curr.in_wait_queue() {
+                // woken up by `kill()` rather than `notify()`, still in the queue.
+                wq.retain(|t| !curr.ptr_eq(t));
+            }
+            rq.block_current(interruptible, |task| {
+                if !task.in_timer_list() {
+                    crate::timers::set_alarm_wakeup(deadline, task.clone());
+                }
                 task.set_in_wait_queue(true);
-                self.queue.lock().push_back(task);
+                wq.push_back(task);
+                drop(wq);
             });
         }
         self.cancel_events(curr);
-        timeout
+        res
     }
 
     /// Wakes up one task in the wait queue, usually the first one.
@@ -156,28 +333,57 @@ impl WaitQueue {
     /// If `resched` is true, the current task will be preempted when the
     /// preemption is enabled.
     pub fn notify_one(&self, resched: bool) -> bool {
-        let mut rq = RUN_QUEUE.lock();
-        if !self.queue.lock().is_empty() {
-            self.notify_one_locked(resched, &mut rq)
+        let _rq = current_run_queue();
+        self.notify_one_locked(resched)
+    }
+
+    /// Wakes up the task with the highest priority (see
+    /// [`TaskInner::priority`]) in the wait queue. Among the tasks with the
+    /// same priority, the one that waits longest is woken up.
+    ///
+    /// If `resched` is true, the current task will be preempted when the
+    /// preemption is enabled.
+    ///
+    /// [`TaskInner::priority`]: crate::TaskInner::priority
+    pub fn notify_highest_priority(&self, resched: bool) -> bool {
+        let _rq = current_run_queue();
+        let mut wq = self.queue.lock();
+        let index = (0..wq.len()).min_by_key(|&i| wq[i].priority());
+        if let Some(task) = index.and_then(|i| wq.remove(i)) {
+            task.set_in_wait_queue(false);
+            drop(wq);
+            select_run_queue(&task).unblock_task(task, resched);
+            true
         } else {
             false
         }
     }
 
+    /// Returns the highest priority (i.e., the smallest value, see
+    /// [`TaskInner::priority`]) of the tasks in the wait queue, or `None` if
+    /// the queue is empty.
+    ///
+    /// [`TaskInner::priority`]: crate::TaskInner::priority
+    pub fn highest_priority(&self) -> Option<isize> {
+        let _guard = kernel_guard::IrqSave::new();
+        self.queue.lock().iter().map(|t| t.priority()).min()
+    }
+
     /// Wakes all tasks in the wait queue.
     ///
     /// If `resched` is true, the current task will be preempted when the
     /// preemption is enabled.
     pub fn notify_all(&self, resched: bool) {
         loop {
-            let mut rq = RUN_QUEUE.lock();
-            if let Some(task) = self.queue.lock().pop_front() {
+            let rq = current_run_queue();
+            let task = self.queue.lock().pop_front();
+            if let Some(task) = task {
                 task.set_in_wait_queue(false);
-                rq.unblock_task(task, resched);
+                select_run_queue(&task).unblock_task(task, resched);
             } else {
                 break;
             }
-            drop(rq); // we must unlock `RUN_QUEUE` after unlocking `self.queue`.
+            drop(rq); // we must put back the run queue after unlocking `self.queue`.
         }
     }
 
@@ -185,32 +391,44 @@ impl WaitQueue {
     ///
     /// If `resched` is true, the current task will be preempted when the
     /// preemption is enabled.
-    pub fn notify_task(&mut self, resched: bool, task: &AxTaskRef) -> bool {
-        let mut rq = RUN_QUEUE.lock();
+    pub fn notify_task(&self, resched: bool, task: &AxTaskRef) -> bool {
+        let _rq = current_run_queue();
         let mut wq = self.queue.lock();
         if let Some(index) = wq.iter().position(|t| Arc::ptr_eq(t, task)) {
             task.set_in_wait_queue(false);
-            rq.unblock_task(wq.remove(index).unwrap(), resched);
+            let task = wq.remove(index).unwrap();
+            drop(wq);
+            select_run_queue(&task).unblock_task(task, resched);
             true
         } else {
             false
         }
     }
 
-    pub(crate) fn notify_one_locked(&self, resched: bool, rq: &mut AxRunQueue) -> bool {
-        if let Some(task) = self.queue.lock().pop_front() {
+    /// The caller must have disabled IRQs and preemption (e.g., by holding
+    /// the run queue).
+    pub(crate) fn notify_one_locked(&self, resched: bool) -> bool {
+        let task = self.queue.lock().pop_front();
+        if let Some(task) = task {
             task.set_in_wait_queue(false);
-            rq.unblock_task(task, resched);
+            select_run_queue(&task).unblock_task(task, resched);
             true
         } else {
             false
         }
     }
 
-    pub(crate) fn notify_all_locked(&self, resched: bool, rq: &mut AxRunQueue) {
-        while let Some(task) = self.queue.lock().pop_front() {
-            task.set_in_wait_queue(false);
-            rq.unblock_task(task, resched);
+    /// The caller must have disabled IRQs and preemption (e.g., by holding
+    /// the run queue).
+    pub(crate) fn notify_all_locked(&self, resched: bool) {
+        loop {
+            let task = self.queue.lock().pop_front();
+            if let Some(task) = task {
+                task.set_in_wait_queue(false);
+                select_run_queue(&task).unblock_task(task, resched);
+            } else {
+                break;
+            }
         }
     }
 }
@@ -0,0 +1,194 @@
+//! Work queues, which run deferred work in kernel worker tasks.
+//!
+//! Unlike [softirqs](crate::softirq), the work items run in task context, so
+//! they can block, e.g., acquire a sleeping mutex or wait for I/O. An IRQ
+//! handler usually queues a [`Work`] to do the rest of the job after it
+//! returns.
+//!
+//! There is a system-wide work queue, which is used by [`schedule_work`] and
+//! [`schedule_fn`]. Its workers, one for each CPU, are spawned when the
+//! scheduler is initialized. Create a dedicated one with [`WorkQueue::new`] if
+//! the work items may block for a long time.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use axtask::workqueue::{self, Work};
+//!
+//! fn rx_work() {
+//!     println!("process the received packets");
+//! }
+//!
+//! static RX_WORK: Work = Work::new(rx_work);
+//!
+//! // in the IRQ handler:
+//! workqueue::schedule_work(&RX_WORK);
+//! ```
+
+use alloc::{boxed::Box, collections::VecDeque, format};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use spinlock::SpinNoIrq;
+
+use crate::WaitQueue;
+
+/// A work item that can be queued to a [`WorkQueue`].
+///
+/// A work item is pending from being queued until the worker starts to run
+/// it. Queueing a pending work item has no effect, so several requests before
+/// it runs are merged into one.
+pub struct Work {
+    pending: AtomicBool,
+    func: fn(),
+}
+
+impl Work {
+    /// Creates a new work item that calls `func` when it runs.
+    pub const fn new(func: fn()) -> Self {
+        Self {
+            pending: AtomicBool::new(false),
+            func,
+        }
+    }
+
+    /// Whether the work item has been queued but not started yet.
+    pub fn is_pending(&self) -> bool {
+        self.pending.load(Ordering::Acquire)
+    }
+
+    fn run(&self) {
+        // clear the flag first, so that it can be queued again while running.
+        self.pending.store(false, Ordering::Release);
+        (self.func)();
+    }
+}
+
+enum WorkItem {
+    Static(&'static Work),
+    Oneshot(Box<dyn FnOnce() + Send>),
+}
+
+/// A queue of work items, served by one or more worker tasks.
+///
+/// Work items are started in FIFO order. If there are several workers, they
+/// may run concurrently, including the same [`Work`] queued again while it is
+/// running.
+pub struct WorkQueue {
+    name: &'static str,
+    items: SpinNoIrq<VecDeque<WorkItem>>,
+    /// The number of queued and running work items.
+    nr_pending: AtomicUsize,
+    more_work: WaitQueue,
+    idle: WaitQueue,
+}
+
+static SYSTEM_WQ: WorkQueue = WorkQueue::new("kworker");
+
+impl WorkQueue {
+    /// Creates a new work queue without workers.
+    ///
+    /// The work items can be queued before [`start`](Self::start) is called,
+    /// they will run once there are workers.
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            items: SpinNoIrq::new(VecDeque::new()),
+            nr_pending: AtomicUsize::new(0),
+            more_work: WaitQueue::new(),
+            idle: WaitQueue::new(),
+        }
+    }
+
+    /// Spawns `nr_workers` worker tasks for the queue, named `{name}/{i}`.
+    ///
+    /// The workers run forever, which is why the queue must be `'static`.
+    pub fn start(&'static self, nr_workers: usize) {
+        for i in 0..nr_workers {
+            let name = format!("{}/{}", self.name, i);
+            crate::spawn_raw(|| self.worker_loop(), name, axconfig::TASK_STACK_SIZE);
+        }
+    }
+
+    /// Returns the name of the work queue.
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    /// Queues a work item, returns `false` if it is already pending.
+    ///
+    /// It can be called in IRQ handlers.
+    pub fn queue(&self, work: &'static Work) -> bool {
+        if work.pending.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+        self.push(WorkItem::Static(work));
+        true
+    }
+
+    /// Queues a closure which runs once.
+    ///
+    /// It can be called in IRQ handlers, but allocates memory for the closure.
+    pub fn queue_fn<F>(&self, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.push(WorkItem::Oneshot(Box::new(f)));
+    }
+
+    /// Blocks the current task until all queued work items have finished.
+    ///
+    /// Work items queued while waiting are also waited for. It must not be
+    /// called by the work items of the same queue, otherwise it never returns.
+    pub fn flush(&self) {
+        self.idle
+            .wait_until(|| self.nr_pending.load(Ordering::Acquire) == 0);
+    }
+
+    fn push(&self, item: WorkItem) {
+        self.nr_pending.fetch_add(1, Ordering::AcqRel);
+        self.items.lock().push_back(item);
+        self.more_work.notify_one(true);
+    }
+
+    fn worker_loop(&self) {
+        loop {
+            self.more_work.wait_until(|| !self.items.lock().is_empty());
+            let item = self.items.lock().pop_front();
+            match item {
+                Some(WorkItem::Static(work)) => work.run(),
+                Some(WorkItem::Oneshot(f)) => f(),
+                None => continue, // taken by another worker
+            }
+            if self.nr_pending.fetch_sub(1, Ordering::AcqRel) == 1 {
+                self.idle.notify_all(true);
+            }
+        }
+    }
+}
+
+/// Returns the system-wide work queue.
+pub fn system_wq() -> &'static WorkQueue {
+    &SYSTEM_WQ
+}
+
+/// Queues a work item to the system-wide work queue, returns `false` if it is
+/// already pending.
+pub fn schedule_work(work: &'static Work) -> bool {
+    system_wq().queue(work)
+}
+
+/// Queues a closure which runs once to the system-wide work queue.
+pub fn schedule_fn<F>(f: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    system_wq().queue_fn(f)
+}
+
+/// Spawns the workers of the system-wide work queue, one for each CPU.
+///
+/// They are spawned here in task context, rather than on the first use which
+/// may be in an IRQ handler.
+pub(crate) fn init() {
+    SYSTEM_WQ.start(axconfig::SMP);
+}
@@ -16,7 +16,7 @@ _cargo_build:
 	@printf "    $(GREEN_C)Building$(END_C) App: $(APP_NAME), Arch: $(ARCH), Platform: $(PLATFORM), App type: $(APP_TYPE)\n"
 
 ifeq ($(APP_TYPE), rust)
-	$(if $(V), $(info RUSTFLAGS: "$(RUSTFLAGS)"))
+	$(if $(V), $(info RUSTFLAGS: "$(RUSTFLAGS)") $(info RUSTC_FLAGS: "$(RUSTC_FLAGS)"))
 	$(call cargo_rustc,--manifest-path $(APP)/Cargo.toml)
 	@cp $(rust_elf) $(OUT_ELF)
 else ifeq ($(APP_TYPE), c)
@@ -27,7 +27,29 @@ endif
 $(OUT_DIR):
 	$(call run_cmd,mkdir,-p $@)
 
+# Fill the `.ksyms` section reserved in the linker script with the sorted text
+# symbols (`<hex address> <name>` per line, hash suffixes stripped), for
+# symbolizing backtraces at runtime. The section is only reserved with the
+# `ksyms` feature of axhal, otherwise nothing is done. The build fails if the
+# table does not fit.
+define embed_ksyms
+  @max=$$(( $$($(NM) $(1) | sed -nE 's/^([0-9a-f]+) . eksyms$$/0x\1/p') \
+      - $$($(NM) $(1) | sed -nE 's/^([0-9a-f]+) . sksyms$$/0x\1/p') )); \
+    if [ $$max -eq 0 ]; then exit 0; fi; \
+    printf "    $(CYAN_C)Embedding$(END_C) kernel symbols\n"; \
+    $(NM) -n --defined-only -C $(1) \
+      | sed -nE 's/::h[0-9a-f]{16}$$//; s/^([0-9a-f]+) [TtWw] /\1 /p' > $(1).ksyms; \
+    printf '\0' >> $(1).ksyms; \
+    size=$$(wc -c < $(1).ksyms); \
+    if [ $$size -gt $$max ]; then \
+      printf "error: the kernel symbol table ($$size bytes) does not fit in the .ksyms section ($$max bytes), increase KSYMS_SIZE in modules/axhal/build.rs\n"; \
+      exit 1; \
+    fi; \
+    $(OBJCOPY) $(1) --update-section .ksyms=$(1).ksyms
+endef
+
 $(OUT_BIN): _cargo_build $(OUT_ELF)
+	$(call embed_ksyms,$(OUT_ELF))
 	$(call run_cmd,$(OBJCOPY),$(OUT_ELF) --strip-all -O binary $@)
 
 .PHONY: _cargo_build
@@ -14,7 +14,7 @@ out_feat := $(obj_dir)/.features.txt
 ulib_src := $(wildcard $(src_dir)/*.c)
 ulib_obj := $(patsubst $(src_dir)/%.c,$(obj_dir)/%.o,$(ulib_src))
 
-CFLAGS += -nostdinc -static -no-pie -fno-builtin -ffreestanding -Wall
+CFLAGS += -nostdinc -static -no-pie -fno-builtin -ffreestanding -fno-omit-frame-pointer -Wall
 CFLAGS += -I$(inc_dir) -I$(libax_inc_dir)
 LDFLAGS += -nostdlib -static -no-pie --gc-sections -T$(LD_SCRIPT)
 
@@ -60,10 +60,16 @@ ifeq ($(default_features),n)
   build_args += --no-default-features
 endif
 
-RUSTFLAGS := -C link-arg=-T$(LD_SCRIPT) -C link-arg=-no-pie
+# Keep frame pointers in all crates, to unwind the stack on panics. Cargo
+# ignores `CARGO_BUILD_RUSTFLAGS` if `RUSTFLAGS` is set in the environment, so
+# append the flag to the user's `RUSTFLAGS` instead.
+export RUSTFLAGS := $(strip $(RUSTFLAGS) -C force-frame-pointers=yes)
+
+# Flags for the final crate only
+RUSTC_FLAGS := -C link-arg=-T$(LD_SCRIPT) -C link-arg=-no-pie
 
 define cargo_rustc
-  $(call run_cmd,cargo rustc,$(build_args) $(1) $(verbose) -- $(RUSTFLAGS))
+  $(call run_cmd,cargo rustc,$(build_args) $(1) $(verbose) -- $(RUSTC_FLAGS))
 endef
 
 define cargo_clippy
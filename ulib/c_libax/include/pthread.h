@@ -4,6 +4,7 @@
 #include <signal.h>
 #include <stddef.h>
 #include <stdint.h>
+#include <time.h>
 
 #define PTHREAD_CANCEL_ENABLE  0
 #define PTHREAD_CANCEL_DISABLE 1
@@ -55,6 +56,12 @@ pthread_t pthread_self(void);
 int pthread_create(pthread_t *__restrict, const pthread_attr_t *__restrict, void *(*)(void *),
                    void *__restrict);
 int pthread_join(pthread_t t, void **res);
+#if defined(AX_CONFIG_IRQ)
+int pthread_timedjoin_np(pthread_t t, void **res, const struct timespec *at);
+#endif
+
+int pthread_cancel(pthread_t t);
+void pthread_testcancel(void);
 
 int pthread_setcancelstate(int, int *);
 int pthread_setcanceltype(int, int *);
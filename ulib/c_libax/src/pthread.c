@@ -25,6 +25,23 @@ int pthread_join(pthread_t t, void **res)
     return ax_pthread_join(t, res);
 }
 
+#if defined(AX_CONFIG_IRQ)
+int pthread_timedjoin_np(pthread_t t, void **res, const struct timespec *at)
+{
+    return ax_pthread_timedjoin_np(t, res, at);
+}
+#endif
+
+int pthread_cancel(pthread_t t)
+{
+    return ax_pthread_cancel(t);
+}
+
+void pthread_testcancel(void)
+{
+    ax_pthread_testcancel();
+}
+
 int pthread_setcancelstate(int new, int *old)
 {
     unimplemented();
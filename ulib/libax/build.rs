@@ -17,16 +17,27 @@ fn main() {
     }
 
     fn gen_pthread_mutex(out_file: &str) -> std::io::Result<()> {
-        // TODO: generate size and initial content automatically.
-        let (mutex_size, mutex_init) = if cfg!(feature = "multitask") {
-            if cfg!(feature = "smp") {
-                (6, "{0, 8, 0, 0, 0, 0}") // core::mem::transmute::<_, [usize; 6]>(axsync::Mutex::new(()))
-            } else {
-                (5, "{8, 0, 0, 0, 0}") // core::mem::transmute::<_, [usize; 5]>(axsync::Mutex::new(()))
+        // The layout is `core::mem::transmute::<_, [usize; N]>(axsync::Mutex::new(()))`,
+        // which contains a dangling pointer (8) of the empty wait queue. It
+        // can not be computed here as the build script runs on the host, so
+        // both values are checked against `axsync::Mutex` at compile time in
+        // `src/cbindings/pthread/mutex.rs`. With lockdep, the creation sites
+        // of the lock classes are left zero, which `lockdep::LockClass` allows.
+        let (mutex_size, dangling_idx) = if cfg!(feature = "multitask") {
+            match (cfg!(feature = "smp"), cfg!(feature = "lockdep")) {
+                (false, false) => (8, 4),
+                (true, false) => (10, 6),
+                (false, true) => (14, 8),
+                (true, true) => (16, 10),
             }
         } else {
-            (1, "{0}")
+            (1, usize::MAX)
         };
+        let mutex_init = (0..mutex_size)
+            .map(|i| if i == dangling_idx { "8" } else { "0" })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mutex_init = format!("{{{mutex_init}}}");
 
         let mut output = Vec::new();
         writeln!(output, "//! Generated by build.rs, DO NOT edit!")?;
@@ -40,6 +51,12 @@ typedef struct {{
 #define PTHREAD_MUTEX_INITIALIZER {{ __l: {mutex_init}}}
 "#
         )?;
+        if dangling_idx < mutex_size {
+            writeln!(
+                output,
+                "#define AX_PTHREAD_MUTEX_DANGLING_IDX {dangling_idx}"
+            )?;
+        }
         std::fs::write(out_file, output)?;
         Ok(())
     }
@@ -82,6 +99,7 @@ typedef struct {{
             "SOL_.*",
             "EPOLL_CTL_.*",
             "EPOLL.*",
+            "AX_PTHREAD_.*",
         ];
 
         #[derive(Debug)]
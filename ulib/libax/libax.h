@@ -286,6 +286,27 @@ void ax_pthread_exit(void *retval) __attribute__((noreturn));
  */
 int ax_pthread_join(pthread_t thread, void **retval);
 
+/**
+ * Waits for the given thread to exit until the absolute time `abstime`, and
+ * stores the return value in `retval`.
+ *
+ * It fails with `ETIMEDOUT` if the thread does not exit in time.
+ */
+int ax_pthread_timedjoin_np(pthread_t thread, void **retval, const struct timespec *abstime);
+
+/**
+ * Requests the given thread to be canceled.
+ *
+ * The thread exits with `PTHREAD_CANCELED` at the next cancellation point,
+ * i.e., `pthread_join`, `pthread_timedjoin_np` or `pthread_testcancel`.
+ */
+int ax_pthread_cancel(pthread_t thread);
+
+/**
+ * Exits the current thread if it has been canceled.
+ */
+void ax_pthread_testcancel(void);
+
 /**
  * Returns a pointer to the global errno variable.
  */
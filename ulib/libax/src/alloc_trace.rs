@@ -0,0 +1,7 @@
+//! Memory allocation tracing, to find memory leaks.
+//!
+//! Each live allocation is recorded with its size, the ID of the task that
+//! allocates it, and the return addresses on its stack, which are printed
+//! with their symbols in the kernel image.
+
+pub use axalloc::{dump_leaks, dump_usage, start_leak_check, task_usage, AllocUsage};
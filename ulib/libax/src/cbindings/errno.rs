@@ -1,31 +1,31 @@
 use axerrno::LinuxError;
+use core::cell::{Cell, UnsafeCell};
 use core::ffi::{c_char, c_int};
 
-/// The global errno variable.
-///
-/// TODO: this should be thread-local.
-#[no_mangle]
-#[allow(non_upper_case_globals)]
-pub static mut errno: c_int = 0;
+crate::thread_local! {
+    /// The errno variable of each thread.
+    static ERRNO: Cell<c_int> = Cell::new(0);
+
+    /// The buffer for the string returned by [`ax_errno_string`].
+    static STRERROR_BUF: UnsafeCell<[u8; 256]> = UnsafeCell::new([0; 256]);
+}
 
 pub fn set_errno(code: i32) {
-    unsafe {
-        errno = code;
-    }
+    ERRNO.with(|errno| errno.set(code));
 }
 
-/// Returns a pointer to the global errno variable.
+/// Returns a pointer to the errno variable of the current thread.
 #[no_mangle]
 pub unsafe extern "C" fn __errno_location() -> *mut c_int {
-    &mut errno
+    ERRNO.with(|errno| errno.as_ptr())
 }
 
 /// Returns a pointer to the string representation of the given error code.
+///
+/// The string is stored in a thread-local buffer, which is overwritten by the
+/// next call in the same thread.
 #[no_mangle]
 pub unsafe extern "C" fn ax_errno_string(e: c_int) -> *mut c_char {
-    #[allow(non_upper_case_globals)]
-    static mut strerror_buf: [u8; 256] = [0; 256]; // TODO: thread safe
-
     let err_str = if e == 0 {
         "Success"
     } else {
@@ -33,8 +33,10 @@ pub unsafe extern "C" fn ax_errno_string(e: c_int) -> *mut c_char {
             .map(|e| e.as_str())
             .unwrap_or("Unknown error")
     };
-    unsafe {
-        strerror_buf[..err_str.len()].copy_from_slice(err_str.as_bytes());
-        strerror_buf.as_mut_ptr() as *mut c_char
-    }
+    STRERROR_BUF.with(|buf| unsafe {
+        let buf = &mut *buf.get();
+        buf[..err_str.len()].copy_from_slice(err_str.as_bytes());
+        buf[err_str.len()] = 0;
+        buf.as_mut_ptr() as *mut c_char
+    })
 }
@@ -73,12 +73,17 @@ pub use self::socket::{
     ax_recvfrom, ax_resolve_sockaddr, ax_send, ax_sendto, ax_shutdown, ax_socket,
 };
 
+#[cfg(all(feature = "multitask", feature = "irq"))]
+pub use self::pthread::ax_pthread_timedjoin_np;
 #[cfg(feature = "multitask")]
 pub use self::pthread::mutex::{
     ax_pthread_mutex_init, ax_pthread_mutex_lock, ax_pthread_mutex_unlock,
 };
 #[cfg(feature = "multitask")]
-pub use self::pthread::{ax_getpid, ax_pthread_create, ax_pthread_exit, ax_pthread_join};
+pub use self::pthread::{
+    ax_getpid, ax_pthread_cancel, ax_pthread_create, ax_pthread_exit, ax_pthread_join,
+    ax_pthread_testcancel,
+};
 
 #[cfg(feature = "pipe")]
 pub use self::pipe::ax_pipe;
@@ -10,6 +10,9 @@ use super::ctypes;
 
 pub mod mutex;
 
+/// The return value of a canceled thread.
+const PTHREAD_CANCELED: *mut c_void = usize::MAX as _;
+
 lazy_static::lazy_static! {
     static ref TID_TO_PTHREAD: RwLock<BTreeMap<u64, ForceSendSync<ctypes::pthread_t>>> = {
         let mut map = BTreeMap::new();
@@ -88,18 +91,54 @@ impl Pthread {
         axtask::exit(0);
     }
 
+    /// Exits the current thread with `PTHREAD_CANCELED` if it has been
+    /// canceled.
+    fn test_cancel() {
+        if crate::thread::current().is_killed() {
+            Self::exit_current(PTHREAD_CANCELED);
+        }
+    }
+
+    fn cancel(ptr: ctypes::pthread_t) {
+        let thread = unsafe { &*(ptr as *const Pthread) };
+        axtask::kill(&thread.inner);
+    }
+
     fn join(ptr: ctypes::pthread_t) -> LinuxResult<*mut c_void> {
         if core::ptr::eq(ptr, Self::current_ptr() as _) {
             return Err(LinuxError::EDEADLK);
         }
 
-        let thread = unsafe { Box::from_raw(ptr as *mut Pthread) };
-        thread.inner.join();
+        let thread = unsafe { &*(ptr as *const Pthread) };
+        if thread.inner.join_interruptible().is_none() {
+            // the current thread is canceled while joining
+            Self::test_cancel();
+        }
+        Ok(unsafe { Self::release(ptr) })
+    }
+
+    #[cfg(feature = "irq")]
+    fn timed_join(ptr: ctypes::pthread_t, dur: core::time::Duration) -> LinuxResult<*mut c_void> {
+        if core::ptr::eq(ptr, Self::current_ptr() as _) {
+            return Err(LinuxError::EDEADLK);
+        }
+
+        let thread = unsafe { &*(ptr as *const Pthread) };
+        if thread.inner.join_timeout_interruptible(dur).is_none() {
+            Self::test_cancel();
+            return Err(LinuxError::ETIMEDOUT);
+        }
+        Ok(unsafe { Self::release(ptr) })
+    }
+
+    /// Frees an exited thread, and returns its return value.
+    unsafe fn release(ptr: ctypes::pthread_t) -> *mut c_void {
+        let thread = Box::from_raw(ptr as *mut Pthread);
         let tid = thread.inner.id().as_u64();
-        let retval = unsafe { *thread.retval.result.get() };
+        let retval = *thread.retval.result.get();
         TID_TO_PTHREAD.write().remove(&tid);
         drop(thread);
-        Ok(retval)
+        retval
     }
 }
 
@@ -163,6 +202,51 @@ pub unsafe extern "C" fn ax_pthread_join(
     })
 }
 
+/// Waits for the given thread to exit until the absolute time `abstime`, and
+/// stores the return value in `retval`.
+///
+/// It fails with `ETIMEDOUT` if the thread does not exit in time.
+#[cfg(feature = "irq")]
+#[no_mangle]
+pub unsafe extern "C" fn ax_pthread_timedjoin_np(
+    thread: ctypes::pthread_t,
+    retval: *mut *mut c_void,
+    abstime: *const ctypes::timespec,
+) -> c_int {
+    debug!("ax_pthread_timedjoin_np <= {:#x}", retval as usize);
+    ax_call_body!(ax_pthread_timedjoin_np, {
+        if abstime.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let deadline = core::time::Duration::from(unsafe { *abstime });
+        let now = crate::time::Instant::now().as_duration();
+        let ret = Pthread::timed_join(thread, deadline.saturating_sub(now))?;
+        if !retval.is_null() {
+            unsafe { core::ptr::write(retval, ret) };
+        }
+        Ok(0)
+    })
+}
+
+/// Requests the given thread to be canceled.
+///
+/// The thread exits with `PTHREAD_CANCELED` at the next cancellation point,
+/// i.e., `pthread_join`, `pthread_timedjoin_np` or `pthread_testcancel`.
+#[no_mangle]
+pub unsafe extern "C" fn ax_pthread_cancel(thread: ctypes::pthread_t) -> c_int {
+    debug!("ax_pthread_cancel <= {:#x}", thread as usize);
+    ax_call_body!(ax_pthread_cancel, {
+        Pthread::cancel(thread);
+        Ok(0)
+    })
+}
+
+/// Exits the current thread if it has been canceled.
+#[no_mangle]
+pub unsafe extern "C" fn ax_pthread_testcancel() {
+    Pthread::test_cancel();
+}
+
 #[derive(Clone, Copy)]
 struct ForceSendSync<T>(T);
 
@@ -4,10 +4,22 @@ use axerrno::LinuxResult;
 use core::ffi::c_int;
 use core::mem::{size_of, ManuallyDrop};
 
+// `pthread_mutex_t` and `PTHREAD_MUTEX_INITIALIZER` are generated by
+// `build.rs`, check that they still match the layout of `axsync::Mutex`.
 static_assertions::const_assert_eq!(
     size_of::<PthreadMutex>(),
     size_of::<ctypes::pthread_mutex_t>()
 );
+const _: () = {
+    let mutex = ManuallyDrop::new(PthreadMutex::new());
+    let words = &mutex as *const _ as *const usize;
+    let idx = ctypes::AX_PTHREAD_MUTEX_DANGLING_IDX as usize;
+    let dangling = unsafe { words.add(idx).read() };
+    assert!(
+        dangling == 8,
+        "PTHREAD_MUTEX_INITIALIZER does not match the layout of axsync::Mutex"
+    );
+};
 
 #[repr(C)]
 pub struct PthreadMutex(Mutex<()>);
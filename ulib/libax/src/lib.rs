@@ -9,21 +9,41 @@
 //!     - `fp_simd`: Enable floating point and SIMD support.
 //! - Memory
 //!     - `alloc`: Enable dynamic memory allocation.
+//!     - `alloc-tlsf`, `alloc-buddy`, `alloc-first-fit`, `alloc-slab`: Use the
+//!       corresponding byte allocator for dynamic memory allocation (slab by
+//!       default).
+//!     - `page-alloc-buddy`, `page-alloc-bitmap`: Use the corresponding page
+//!       allocator (bitmap by default).
+//!     - `alloc-trace`: Record live allocations to find memory leaks (see
+//!       [`alloc_trace`]).
+//!     - `heap-reclaim`: Give free heap memory back to the page allocator
+//!       (uses the first-fit byte allocator).
 //!     - `paging`: Enable page table manipulation.
 //! - Interrupts:
 //!     - `irq`: Enable interrupt handling support. This feature is required for
 //!       some multitask operations, such as [`sync::WaitQueue::wait_timeout`] and
 //!       non-spinning [`thread::sleep`].
+//!     - `tickless`: Stop the periodic timer tick when the CPU is idle.
 //! - Task management
 //!     - `multitask`: Enable multi-threading support.
 //!     - `sched_fifo`: Use the FIFO cooperative scheduler.
 //!     - `sched_rr`: Use the Round-robin preemptive scheduler.
+//!     - `sched_prio`: Use the fixed-priority preemptive scheduler.
+//!     - `sched_edf`: Use the Earliest Deadline First scheduler.
+//!     - `sched_class`: Use the multi-class scheduler (real-time, fair and idle
+//!       classes).
+//!     - `lockdep`: Check the lock order and sleeping while holding spin locks
+//!       at runtime, and report potential deadlocks in the log.
 //! - Device and upperlayer stack
 //!     - `fs`: Enable file system support.
 //!     - `net`: Enable networking support.
 //!     - `display`: Enable graphics support.
 //!     - `bus-mmio`: Use device tree to probe all MMIO devices.
 //!     - `bus-pci`: Use PCI bus to probe all PCI devices.
+//! - Debugging
+//!     - `ksyms`: Embed the kernel symbol table to symbolize the backtraces.
+//!     - `panic-monitor`: Enter a monitor shell on the console after a panic.
+//!       It also enables the `ksyms` feature.
 //! - Logging
 //!     - `log-level-off`: Disable all logging.
 //!     - `log-level-error`, `log-level-warn`, `log-level-info`, `log-level-debug`,
@@ -69,6 +89,9 @@ pub mod time;
 #[cfg_attr(not(feature = "multitask"), path = "thread/single.rs")]
 pub mod thread;
 
+#[cfg(feature = "alloc-trace")]
+pub mod alloc_trace;
+
 #[cfg(feature = "fs")]
 pub mod fs;
 
@@ -1,12 +1,20 @@
 //! Useful synchronization primitives.
+//!
+//! Without the `multitask` feature, the blocking primitives spin instead of
+//! putting the current task to sleep. The channels in [`mpsc`] and [`mpmc`]
+//! need the `alloc` feature.
 
-#[cfg(feature = "multitask")]
+pub use axsync::{Barrier, BarrierWaitResult};
+pub use axsync::{Condvar, WaitTimeoutResult};
 pub use axsync::{Mutex, MutexGuard};
+pub use axsync::{Once, OnceLock};
+pub use axsync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+pub use axsync::{Semaphore, SemaphoreGuard};
 
 #[cfg(feature = "multitask")]
 pub use axtask::WaitQueue;
 
-pub use spinlock as spin;
+#[cfg(feature = "alloc")]
+pub use axsync::{mpmc, mpsc};
 
-#[cfg(not(feature = "multitask"))]
-pub use spinlock::{SpinNoIrq as Mutex, SpinNoIrqGuard as MutexGuard};
+pub use spinlock as spin;
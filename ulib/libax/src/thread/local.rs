@@ -0,0 +1,83 @@
+//! Thread-local storage.
+
+#[cfg(feature = "multitask")]
+pub use axtask::LocalKey;
+
+#[cfg(not(feature = "multitask"))]
+pub use self::single::LocalKey;
+
+/// Declares a new thread-local storage key of type [`LocalKey`].
+///
+/// The syntax is the same as
+/// [`std::thread_local!`](https://doc.rust-lang.org/std/macro.thread_local.html).
+/// Each thread has its own copy of the value, which is lazily initialized on
+/// the first [`LocalKey::with`] in the thread, and dropped when the thread
+/// exits.
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::cell::RefCell;
+///
+/// libax::thread_local! {
+///     static FOO: RefCell<u32> = RefCell::new(1);
+/// }
+///
+/// FOO.with(|f| *f.borrow_mut() = 2);
+/// assert_eq!(FOO.with(|f| *f.borrow()), 2);
+/// ```
+#[macro_export]
+macro_rules! thread_local {
+    () => {};
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty = $init:expr; $($rest:tt)*) => {
+        $crate::thread_local!($(#[$attr])* $vis static $name: $t = $init);
+        $crate::thread_local!($($rest)*);
+    };
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty = $init:expr) => {
+        $(#[$attr])* $vis static $name: $crate::thread::LocalKey<$t> =
+            $crate::thread::LocalKey::new({
+                fn __init() -> $t {
+                    $init
+                }
+                __init
+            });
+    };
+}
+
+#[cfg(not(feature = "multitask"))]
+mod single {
+    use axsync::OnceLock;
+
+    /// A thread local storage key, similar to
+    /// [`std::thread::LocalKey`](https://doc.rust-lang.org/std/thread/struct.LocalKey.html).
+    ///
+    /// For single-threaded configuration (`multitask` feature is disabled),
+    /// there is only one copy of the value, which is never dropped.
+    pub struct LocalKey<T: 'static> {
+        init: fn() -> T,
+        value: OnceLock<T>,
+    }
+
+    // There is only one thread.
+    unsafe impl<T> Sync for LocalKey<T> {}
+
+    impl<T: 'static> LocalKey<T> {
+        /// Creates a new key, whose value is initialized by `init`.
+        pub const fn new(init: fn() -> T) -> Self {
+            Self {
+                init,
+                value: OnceLock::new(),
+            }
+        }
+
+        /// Acquires a reference to the value of the current thread.
+        ///
+        /// It initializes the value if it is the first time it is accessed.
+        pub fn with<F, R>(&'static self, f: F) -> R
+        where
+            F: FnOnce(&T) -> R,
+        {
+            f(self.value.get_or_init(self.init))
+        }
+    }
+}
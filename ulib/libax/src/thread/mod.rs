@@ -6,8 +6,18 @@ use axerrno::ax_err_type;
 use axtask::AxTaskRef;
 use core::cell::UnsafeCell;
 
+mod local;
+
+pub use self::local::LocalKey;
+
 #[doc(cfg(feature = "multitask"))]
-pub use axtask::{current, set_priority, TaskId as ThreadId};
+pub use axtask::{current, set_affinity, set_priority, CpuMask, TaskId as ThreadId};
+#[doc(cfg(feature = "multitask"))]
+pub use axtask::{tasks, TaskInfo};
+#[cfg(feature = "sched_prio")]
+pub use axtask::{set_rt_policy, RtPolicy};
+#[cfg(feature = "sched_class")]
+pub use axtask::{set_sched_policy, SchedPolicy};
 
 /// Thread factory, which can be used in order to configure the properties of
 /// a new thread.
@@ -20,6 +30,8 @@ pub struct Builder {
     name: Option<String>,
     // The size of the stack for the spawned thread in bytes
     stack_size: Option<usize>,
+    // The CPUs that the spawned thread is allowed to run on
+    affinity: Option<CpuMask>,
 }
 
 impl Builder {
@@ -29,6 +41,7 @@ impl Builder {
         Builder {
             name: None,
             stack_size: None,
+            affinity: None,
         }
     }
 
@@ -44,6 +57,15 @@ impl Builder {
         self
     }
 
+    /// Sets the CPUs that the new thread is allowed to run on.
+    ///
+    /// The thread is placed on one of these CPUs from the start, and never
+    /// migrates to other CPUs.
+    pub fn affinity(mut self, cpumask: CpuMask) -> Builder {
+        self.affinity = Some(cpumask);
+        self
+    }
+
     /// Spawns a new thread by taking ownership of the `Builder`, and returns an
     /// [`io::Result`] to its [`JoinHandle`].
     ///
@@ -68,6 +90,7 @@ impl Builder {
     {
         let name = self.name.unwrap_or_default();
         let stack_size = self.stack_size.unwrap_or(axconfig::TASK_STACK_SIZE);
+        let affinity = self.affinity.unwrap_or_else(CpuMask::full);
 
         let my_packet = Arc::new(Packet {
             result: UnsafeCell::new(None),
@@ -84,7 +107,7 @@ impl Builder {
             drop(their_packet);
         };
 
-        let task = axtask::spawn_raw(main, name, stack_size);
+        let task = axtask::spawn_raw_with_affinity(main, name, stack_size, affinity);
         Ok(JoinHandle {
             task,
             packet: my_packet,
@@ -171,14 +194,46 @@ impl<T> JoinHandle<T> {
         &self.task
     }
 
+    /// Requests the associated thread to terminate.
+    ///
+    /// The interruptible blocking operations of the thread (e.g., joining
+    /// other threads) return early, the thread is expected to exit by itself.
+    /// See [`axtask::kill`] for details.
+    pub fn kill(&self) {
+        axtask::kill(&self.task);
+    }
+
     /// Waits for the associated thread to finish.
     ///
     /// This function will return immediately if the associated thread has
-    /// already finished.
+    /// already finished. Returns [`Interrupted`](io::Error::Interrupted) if
+    /// the current thread is killed while waiting.
     pub fn join(mut self) -> io::Result<T> {
-        self.task.join();
+        self.task
+            .join_interruptible()
+            .ok_or_else(|| ax_err_type!(Interrupted))?;
+        self.take_result()
+    }
+
+    /// Waits for the associated thread to finish for at most the given
+    /// duration.
+    ///
+    /// Returns [`TimedOut`](io::Error::TimedOut) if the thread does not finish
+    /// in time, and it can be joined again later. Returns
+    /// [`Interrupted`](io::Error::Interrupted) if the current thread is killed
+    /// while waiting.
+    #[cfg(feature = "irq")]
+    pub fn join_timeout(&mut self, dur: core::time::Duration) -> io::Result<T> {
+        match self.task.join_timeout_interruptible(dur) {
+            Some(_) => self.take_result(),
+            None if current().is_killed() => Err(ax_err_type!(Interrupted)),
+            None => Err(ax_err_type!(TimedOut)),
+        }
+    }
+
+    fn take_result(&mut self) -> io::Result<T> {
         Arc::get_mut(&mut self.packet)
-            .unwrap()
+            .ok_or_else(|| ax_err_type!(BadState))?
             .result
             .get_mut()
             .take()
@@ -1,5 +1,10 @@
 //! Thread APIs for single-threaded configuration.
 
+#[path = "local.rs"]
+mod local;
+
+pub use self::local::LocalKey;
+
 /// Current thread gives up the CPU time voluntarily, and switches to another
 /// ready thread.
 ///
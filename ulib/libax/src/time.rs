@@ -1,7 +1,13 @@
 //! Temporal quantification.
+//!
+//! With the `multitask` and `irq` features, it also provides timers that run
+//! callbacks at the given time, see [`oneshot_at`] and [`periodic`].
 
 pub use core::time::Duration;
 
+#[cfg(all(feature = "multitask", feature = "irq"))]
+pub use axtask::timer::TimerHandle;
+
 /// A measurement of a monotonically nondecreasing clock.
 /// Opaque and useful only with [`Duration`].
 #[derive(Clone, Copy)]
@@ -40,3 +46,61 @@ impl Instant {
         Instant::now().0 - self.0
     }
 }
+
+impl core::ops::Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, other: Duration) -> Instant {
+        Instant(self.0 + other)
+    }
+}
+
+/// Calls `f` once at `deadline`, with the current time as the argument.
+///
+/// The callback runs in the timer interrupt handler, so it must not block.
+/// Returns a [`TimerHandle`] that can cancel it.
+#[cfg(all(feature = "multitask", feature = "irq"))]
+pub fn oneshot_at<F>(deadline: Instant, f: F) -> TimerHandle
+where
+    F: FnOnce(Instant) + Send + 'static,
+{
+    axtask::timer::Timer::oneshot(deadline.0, move |now| f(Instant(now)))
+}
+
+/// Calls `f` once after `dur` has elapsed, with the current time as the
+/// argument.
+///
+/// The callback runs in the timer interrupt handler, so it must not block.
+/// Returns a [`TimerHandle`] that can cancel it.
+#[cfg(all(feature = "multitask", feature = "irq"))]
+pub fn oneshot_after<F>(dur: Duration, f: F) -> TimerHandle
+where
+    F: FnOnce(Instant) + Send + 'static,
+{
+    oneshot_at(Instant::now() + dur, f)
+}
+
+/// Calls `f` every `interval`, with the current time as the argument.
+///
+/// The callback runs in the timer interrupt handler, so it must not block.
+/// Returns a [`TimerHandle`] that can cancel it.
+///
+/// # Panics
+///
+/// Panics if `interval` is zero.
+#[cfg(all(feature = "multitask", feature = "irq"))]
+pub fn periodic<F>(interval: Duration, mut f: F) -> TimerHandle
+where
+    F: FnMut(Instant) + Send + 'static,
+{
+    axtask::timer::Timer::periodic(interval, move |now| f(Instant(now)))
+}
+
+/// Returns the number of timer ticks handled so far, on all CPUs.
+///
+/// With the `tickless` feature, idle CPUs stop the periodic tick, so it grows
+/// much slower when most tasks are sleeping.
+#[cfg(all(feature = "multitask", feature = "irq"))]
+pub fn timer_ticks() -> u64 {
+    axtask::timer_ticks()
+}